@@ -0,0 +1,259 @@
+use {
+    crate::{
+        monitoring::{
+            volume::TokenMetrics,
+            wallet::Transaction,
+            Signal,
+        },
+        strategy::Strategy,
+    },
+    solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey},
+    spl_token::native_mint,
+    std::collections::{HashMap, VecDeque},
+};
+
+const POSITION_FRACTION: f64 = 0.1; // Of available cash per buy
+const SIMULATED_SLIPPAGE: f64 = 0.01; // Fills 1% worse than the replayed price
+const VOLUME_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Default)]
+pub struct BacktestReport {
+    pub starting_balance: f64,
+    pub final_balance: f64, // SOL, open positions marked at their last price
+    pub trades: usize,      // Closed round trips
+    pub win_rate: f64,
+    pub max_drawdown: f64,  // 0.2 = equity fell 20% from a peak
+    pub sharpe: f64,        // Mean / stddev of per-swap equity returns, not annualized
+}
+
+struct Holding {
+    tokens: f64, // Raw token units
+    cost: f64,   // SOL spent
+}
+
+// Replays `history` in block order, feeding each token's swaps into TokenMetrics
+// the same way VolumeMonitor would and trading the strategy's signals with
+// simulated fills. Swaps that don't trade a token against SOL are skipped.
+pub fn run_backtest(history: Vec<Transaction>, strategy: &dyn Strategy, starting_balance: f64) -> BacktestReport {
+    let mut history: Vec<_> = history.into_iter().filter(|tx| tx.success).collect();
+    history.sort_by_key(|tx| tx.block_time);
+
+    let mut metrics: HashMap<Pubkey, TokenMetrics> = HashMap::new();
+    let mut volumes: HashMap<Pubkey, VecDeque<(i64, f64)>> = HashMap::new();
+    let mut prices: HashMap<Pubkey, f64> = HashMap::new();
+    let mut holdings: HashMap<Pubkey, Holding> = HashMap::new();
+
+    let mut cash = starting_balance;
+    let mut trades = 0;
+    let mut wins = 0;
+    let mut equity_curve = vec![starting_balance];
+
+    for tx in &history {
+        let Some((token, price, sol_volume)) = swap_price(tx) else {
+            continue;
+        };
+
+        // Rolling 24h SOL volume stands in for the price API's volume_24h
+        let window = volumes.entry(token).or_default();
+        window.push_back((tx.block_time, sol_volume));
+        while window.front().is_some_and(|(ts, _)| *ts <= tx.block_time - VOLUME_WINDOW_SECS) {
+            window.pop_front();
+        }
+        let volume_24h: f64 = window.iter().map(|(_, volume)| volume).sum();
+
+        let token_metrics = metrics.entry(token).or_insert_with(TokenMetrics::new);
+        token_metrics.update_metrics_at(tx.block_time, price, volume_24h);
+        prices.insert(token, price);
+
+        match strategy.evaluate(&token, token_metrics) {
+            Some(Signal::BuySignal { .. }) if !holdings.contains_key(&token) => {
+                let spend = cash * POSITION_FRACTION;
+                if spend > 0.0 {
+                    cash -= spend;
+                    holdings.insert(token, Holding {
+                        tokens: spend / (price * (1.0 + SIMULATED_SLIPPAGE)),
+                        cost: spend,
+                    });
+                }
+            }
+            Some(Signal::SellSignal { .. }) => {
+                if let Some(holding) = holdings.remove(&token) {
+                    let proceeds = holding.tokens * price * (1.0 - SIMULATED_SLIPPAGE);
+                    cash += proceeds;
+                    trades += 1;
+                    if proceeds > holding.cost {
+                        wins += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        equity_curve.push(equity(cash, &holdings, &prices));
+    }
+
+    BacktestReport {
+        starting_balance,
+        final_balance: equity(cash, &holdings, &prices),
+        trades,
+        win_rate: if trades == 0 { 0.0 } else { wins as f64 / trades as f64 },
+        max_drawdown: max_drawdown(&equity_curve),
+        sharpe: sharpe_ratio(&equity_curve),
+    }
+}
+
+// (token, SOL per raw token unit, SOL traded) for a SOL <-> token swap
+fn swap_price(tx: &Transaction) -> Option<(Pubkey, f64, f64)> {
+    let (token, lamports, token_amount) = if tx.input_token == native_mint::ID {
+        (tx.output_token, tx.amount_in, tx.amount_out)
+    } else if tx.output_token == native_mint::ID {
+        (tx.input_token, tx.amount_out, tx.amount_in)
+    } else {
+        return None;
+    };
+    if lamports == 0 || token_amount == 0 {
+        return None;
+    }
+
+    let sol = lamports as f64 / LAMPORTS_PER_SOL as f64;
+    Some((token, sol / token_amount as f64, sol))
+}
+
+fn equity(cash: f64, holdings: &HashMap<Pubkey, Holding>, prices: &HashMap<Pubkey, f64>) -> f64 {
+    cash + holdings
+        .iter()
+        .map(|(token, holding)| holding.tokens * prices.get(token).copied().unwrap_or(0.0))
+        .sum::<f64>()
+}
+
+fn max_drawdown(equity_curve: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut worst = 0.0;
+    for &equity in equity_curve {
+        peak = peak.max(equity);
+        if peak > 0.0 {
+            worst = f64::max(worst, (peak - equity) / peak);
+        }
+    }
+    worst
+}
+
+fn sharpe_ratio(equity_curve: &[f64]) -> f64 {
+    let returns: Vec<f64> = equity_curve
+        .windows(2)
+        .filter(|pair| pair[0] > 0.0)
+        .map(|pair| pair[1] / pair[0] - 1.0)
+        .collect();
+    if returns.len() < 2 {
+        return 0.0;
+    }
+
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    let stddev = variance.sqrt();
+    if stddev == 0.0 {
+        0.0
+    } else {
+        mean / stddev
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::monitoring::wallet::TradeType,
+        std::time::Instant,
+    };
+
+    const TOKEN_UNITS: u64 = 1_000_000_000;
+
+    // Signal by how many swaps of the token have been seen, 1-based
+    struct ScriptedStrategy(Vec<Option<bool>>); // Some(true) = buy, Some(false) = sell
+
+    impl Strategy for ScriptedStrategy {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn evaluate(&self, token: &Pubkey, metrics: &TokenMetrics) -> Option<Signal> {
+            let buy = (*self.0.get(metrics.price_history().len() - 1)?)?;
+            let (token, confidence) = (*token, 1.0);
+            Some(if buy { Signal::BuySignal { token, confidence } } else { Signal::SellSignal { token, confidence } })
+        }
+    }
+
+    // A buy of TOKEN_UNITS of `token` at `price` SOL per whole TOKEN_UNITS
+    fn swap(token: Pubkey, block_time: i64, price: f64) -> Transaction {
+        Transaction {
+            signature: format!("sig-{}", block_time),
+            trade_type: TradeType::SwapExactSOLForTokens,
+            input_token: native_mint::ID,
+            output_token: token,
+            amount_in: (price * LAMPORTS_PER_SOL as f64) as u64,
+            amount_out: TOKEN_UNITS,
+            timestamp: Instant::now(),
+            block_time,
+            success: true,
+        }
+    }
+
+    #[test]
+    fn max_drawdown_is_the_deepest_fall_from_a_peak() {
+        assert_eq!(max_drawdown(&[]), 0.0);
+        assert_eq!(max_drawdown(&[1.0, 2.0, 3.0]), 0.0);
+        // 12 -> 6 is deeper than 10 -> 9
+        assert_eq!(max_drawdown(&[10.0, 9.0, 12.0, 9.0, 11.0, 6.0, 8.0]), 0.5);
+    }
+
+    #[test]
+    fn sharpe_ratio_is_mean_over_stddev_of_returns() {
+        // Returns of 10%, 5%, 10%: mean 1/12, sample stddev 1/sqrt(1200)
+        let sharpe = sharpe_ratio(&[100.0, 110.0, 115.5, 127.05]);
+        assert!((sharpe - 5.0 / 3f64.sqrt()).abs() < 1e-9, "{}", sharpe);
+
+        // Too few returns, or no variation in them
+        assert_eq!(sharpe_ratio(&[100.0, 110.0]), 0.0);
+        assert_eq!(sharpe_ratio(&[100.0, 110.0, 121.0]), 0.0);
+    }
+
+    #[test]
+    fn replay_trades_the_strategy_signals_with_slippage() {
+        let token = Pubkey::new_unique();
+        let mut failed = swap(token, 25, 100.0);
+        failed.success = false;
+        // Out of order on purpose; the failed swap is skipped
+        let history = vec![
+            swap(token, 40, 0.5),
+            swap(token, 10, 1.0),
+            failed,
+            swap(token, 30, 1.0),
+            swap(token, 20, 2.0),
+        ];
+        // Buy at 1.0, sell at 2.0 for a win, buy at 1.0, sell at 0.5 for a loss
+        let strategy = ScriptedStrategy(vec![Some(true), Some(false), Some(true), Some(false)]);
+
+        let report = run_backtest(history, &strategy, 10.0);
+
+        let mut cash = 10.0;
+        for (buy, sell) in [(1.0, 2.0), (1.0, 0.5)] {
+            let spend = cash * POSITION_FRACTION;
+            let tokens = spend / (buy * (1.0 + SIMULATED_SLIPPAGE));
+            cash += tokens * sell * (1.0 - SIMULATED_SLIPPAGE) - spend;
+        }
+        assert_eq!(report.trades, 2);
+        assert_eq!(report.win_rate, 0.5);
+        assert!((report.final_balance - cash).abs() < 1e-9, "{} != {}", report.final_balance, cash);
+        assert!(report.max_drawdown > 0.0);
+    }
+
+    #[test]
+    fn swaps_without_sol_on_one_side_are_skipped() {
+        let mut token_to_token = swap(Pubkey::new_unique(), 10, 1.0);
+        token_to_token.input_token = Pubkey::new_unique();
+
+        let report = run_backtest(vec![token_to_token], &ScriptedStrategy(vec![Some(true)]), 10.0);
+        assert_eq!(report.final_balance, 10.0);
+        assert_eq!(report.trades, 0);
+    }
+}
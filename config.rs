@@ -1,31 +1,752 @@
-use serde::{Deserialize, Serialize};
-use solana_sdk::pubkey::Pubkey;
+use {
+    crate::{
+        error::BotError,
+        notify::NotificationConfig,
+        trading::types::Sol,
+    },
+    serde::{Deserialize, Serialize},
+    solana_sdk::pubkey::Pubkey,
+    std::{collections::BTreeMap, convert::Infallible, env, fs, path::Path, str::FromStr},
+};
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+const RAYDIUM_DEVNET_AMM_PROGRAM_ID: &str = "HWy1jotHpo6UqeQxx49dpYYdQB8wj9Qk9MdxwjLvDHB8";
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const USDC_DEVNET_MINT: &str = "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU";
+const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+
+// Compute unit limit and price, prepended to every transaction of a bundle
+pub const COMPUTE_BUDGET_INSTRUCTIONS: usize = 2;
+
+// Written as a plain string, "mainnet" etc. or the custom RPC URL itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Cluster {
+    #[default]
+    Mainnet,
+    Devnet,
+    Testnet,
+    Custom(String), // RPC URL; the WS URL is derived by swapping the scheme
+}
+
+// How priority fees are chosen; fees are micro-lamports per compute unit.
+// Written as a table tagged by `kind`, e.g. kind = "percentile", percentile = 0.75
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum PriorityFeeStrategy {
+    Fixed { fee: u64 },
+    Percentile { percentile: f64 }, // 0.75 = 75th percentile of fees paid in recent slots
+    // Starts at `min`; raised while fewer than target_land_rate (0-1) of our
+    // transactions land, lowered while more do
+    Adaptive { min: u64, max: u64, target_land_rate: f64 },
+}
+
+// Which Strategy the signal-trading loop reads watched tokens' metrics with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StrategyKind {
+    #[default]
+    Volume,   // Volume spike together with a price move
+    Momentum, // Price trend across the retained history
+}
+
+impl FromStr for StrategyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "volume" => Ok(StrategyKind::Volume),
+            "momentum" => Ok(StrategyKind::Momentum),
+            _ => Err(format!("unknown strategy {}, expected volume or momentum", s)),
+        }
+    }
+}
+
+impl PriorityFeeStrategy {
+    pub(crate) fn initial_fee(&self, default: u64) -> u64 {
+        match self {
+            PriorityFeeStrategy::Fixed { fee } => *fee,
+            PriorityFeeStrategy::Percentile { .. } => default,
+            PriorityFeeStrategy::Adaptive { min, .. } => *min,
+        }
+    }
+}
+
+impl Cluster {
+    pub fn rpc_url(&self) -> String {
+        match self {
+            Cluster::Mainnet => "https://api.mainnet-beta.solana.com".to_string(),
+            Cluster::Devnet => "https://api.devnet.solana.com".to_string(),
+            Cluster::Testnet => "https://api.testnet.solana.com".to_string(),
+            Cluster::Custom(url) => url.clone(),
+        }
+    }
+
+    pub fn ws_url(&self) -> String {
+        let rpc_url = self.rpc_url();
+        if let Some(rest) = rpc_url.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            rpc_url
+        }
+    }
+
+    // Raydium has no testnet deployment; custom endpoints are assumed to be mainnet
+    pub fn raydium_amm_program_id(&self) -> &'static str {
+        match self {
+            Cluster::Devnet => RAYDIUM_DEVNET_AMM_PROGRAM_ID,
+            _ => crate::dex::RAYDIUM_V4_PROGRAM_ID,
+        }
+    }
+
+    pub fn usdc_mint(&self) -> &'static str {
+        match self {
+            Cluster::Devnet => USDC_DEVNET_MINT,
+            _ => USDC_MINT,
+        }
+    }
+}
+
+// "mainnet" / "devnet" / "testnet", anything else is taken as a custom RPC URL
+impl From<String> for Cluster {
+    fn from(s: String) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "mainnet" | "mainnet-beta" => Cluster::Mainnet,
+            "devnet" => Cluster::Devnet,
+            "testnet" => Cluster::Testnet,
+            _ => Cluster::Custom(s),
+        }
+    }
+}
+
+impl From<Cluster> for String {
+    fn from(cluster: Cluster) -> Self {
+        match cluster {
+            Cluster::Mainnet => "mainnet".to_string(),
+            Cluster::Devnet => "devnet".to_string(),
+            Cluster::Testnet => "testnet".to_string(),
+            Cluster::Custom(url) => url,
+        }
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Cluster::from(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TradingConfig {
-    pub rpc_url: String,
-    pub ws_url: String,
+    pub cluster: Cluster,
+    pub rpc_url: String, // Empty = derive from `cluster`
+    pub ws_url: String,  // Empty = derive from `cluster`
+    pub rpc_fallback_urls: Vec<String>, // Tried in order when the primary RPC fails
+    pub rpc_failover_cooldown_secs: u64,
+    pub rpc_rank_interval_secs: u64, // How often endpoints are re-ranked by latency
+    pub rpc_requests_per_second: u32, // Provider limit; engine RPC calls wait beyond it
     pub wallet_path: String,
-    pub quote_token: String,
+    pub quote_token: String, // Mint trades are sized and priced in: SOL, USDC, USDT or a mint address
     pub min_liquidity: f64,
-    pub max_position_size: f64,
+    pub min_pool_age_secs: u64,     // Copied buys skip pools younger than this
+    pub token_blacklist: Vec<String>, // Mints never copied into or out of
+    pub min_required_balance: Sol, // pre_launch_check refuses to start below this
+    pub max_position_size: f64, // SOL
+    pub fixed_amount: f64,      // SOL per copied buy under SizeMode::Fixed
+    pub confirm_threshold_sol: f64, // Manual buys above this ask for confirmation
+    pub max_total_exposure: f64,      // SOL across all open positions
+    pub max_token_concentration: f64, // 0.5 = one token may use half of max_total_exposure
+    pub daily_loss_cap: f64,          // SOL of net realized loss that halts trading until 00:00 UTC
     pub risk_percentage: f64,
     pub profit_target: f64,
     pub stop_loss: f64,
+    pub jito_block_engine_url: String,
+    pub jito_tip_account: String,
+    pub lookup_tables: Vec<String>, // Address Lookup Table accounts for v0 transactions
+    pub nonce_account: Option<String>, // Durable nonce owned by the wallet; bundles sign against it instead of a blockhash
+    pub intermediate_tokens: Vec<String>, // Mints split routes may hop through
+    pub price_api_url: String,
+    pub watch_tokens: Vec<String>, // Mints polled for volume/price signals
+    pub min_volume: u64,           // USD 24h volume
+    pub volume_spike_threshold: f64, // 2.0 = volume at twice its moving average
+    pub volume_ema_alpha: f64,       // 0.2 = each new sample carries 20% of the average
+    pub price_move_threshold: f64,   // 0.05 = 5% price movement
+    pub momentum_threshold: f64,     // 0.1 = 10% move across the price history
+    pub strategy: StrategyKind,      // Read by signal trading alongside the volume monitor
+    pub confidence_volume_weight: f64, // Normalized against confidence_price_weight
+    pub confidence_price_weight: f64,
+    pub min_confidence: f64,           // Signals below this aren't traded
+    pub min_activity_score: f64,  // Wallets scoring below this (0-1) aren't copied
+    pub max_trades_per_hour: f64, // Wallets trading faster than this look like bots and aren't copied
+    pub signal_cooldown_secs: u64,
+    pub buy_cooldown_secs: u64, // Copied buys of the same token closer than this are skipped
+    pub position_poll_secs: u64,
+    pub ladder_state_path: String, // Fired sell-ladder rungs survive restarts here
+    pub dca_state_path: String,    // Remaining DCA tranches, resumed on restart
+    pub trailing_stop_state_path: String, // Trailing stops and their high watermarks, resumed on restart
+    pub positions_path: String,    // Open positions, saved every monitor_positions pass
+    pub wallet_state_path: String, // WalletTracker history, loaded by the UI
+    pub trade_history_path: String, // CSV written on shutdown
+    pub close_on_exit: bool,        // Sell every open position on Ctrl-C
+    pub mirror_exits: bool,         // Sell all of a token when a copied wallet sells all of theirs
+    pub mirror_partial_exits: bool, // With mirror_exits, also sell the same share on partial exits
+    pub amm_program_id: Option<String>, // Overrides the mainnet Raydium AMM v4 id
+    pub rpc_record_path: Option<String>, // Append every RPC request/response to this JSONL file
+    pub rpc_replay_path: Option<String>, // Serve RPC calls from a recording instead of the network
+    pub min_priority_fee: u64,          // Floor when no recent fees were paid; micro-lamports per CU
+    pub max_priority_fee: u64,          // Micro-lamports per compute unit
+    pub max_slippage: f64,              // 0.01 = 1%
+    pub safe_mode: bool,                // Simulate every transaction before sending it
+    pub dump_transactions: bool,        // Log each transaction's message at debug level before sending it
+    pub breaker_failure_threshold: u32, // Consecutive failed sends before trading pauses
+    pub breaker_cooldown_secs: u64,
+    pub max_rpc_slot_lag: u64, // Early swaps are refused by RPC nodes this many slots behind ours
+    pub max_instructions_per_tx: usize, // Bundles are split at this many, compute budget included
+    pub blockhash_retries: u32,         // Re-signs with a new blockhash this many times when one expires
+    pub max_daily_spend_sol: Sol, // Buys past this much in one UTC day are refused
+    pub max_sell_tax_bps: u16,    // Round-trip loss beyond quoted fees that marks a token unsellable
+    pub log_level: String, // tracing filter, e.g. "info" or "bot=debug"
+    pub log_json: bool,    // JSON lines instead of pretty terminal output
+    pub metrics_enabled: bool,
+    pub metrics_addr: String, // Prometheus scrape address, e.g. "127.0.0.1:9898"
+    pub priority_fee_strategy: PriorityFeeStrategy,
+    // Replaced by priority_fee_strategy; still read from older files as a
+    // Percentile strategy, never written back
+    #[serde(skip_serializing)]
+    pub priority_fee_percentile: Option<f64>,
+    pub notifications: NotificationConfig, // Webhook / Telegram alerts on trade events
+    // Copy-trade targets -> size weight applied to their swaps. Last so it's
+    // written as a TOML table after every plain value.
+    pub target_wallets: BTreeMap<String, f64>,
 }
 
 impl Default for TradingConfig {
     fn default() -> Self {
         Self {
-            rpc_url: "https://api.mainnet-beta.solana.com".to_string(),
-            ws_url: "wss://api.mainnet-beta.solana.com".to_string(),
+            cluster: Cluster::Mainnet,
+            rpc_url: String::new(),
+            ws_url: String::new(),
+            rpc_fallback_urls: Vec::new(),
+            rpc_failover_cooldown_secs: 30,
+            rpc_rank_interval_secs: 60,
+            rpc_requests_per_second: 25,
             wallet_path: "wallet.json".to_string(),
             quote_token: "SOL".to_string(),
             min_liquidity: 1000.0,
+            min_pool_age_secs: 300,
+            token_blacklist: Vec::new(),
+            min_required_balance: Sol(0.01),
             max_position_size: 1.0,
+            fixed_amount: 0.1,
+            confirm_threshold_sol: 0.5,
+            max_total_exposure: 5.0,
+            max_token_concentration: 0.5,
+            daily_loss_cap: 1.0,
             risk_percentage: 1.0,
             profit_target: 2.0,
             stop_loss: 0.5,
+            jito_block_engine_url: "https://mainnet.block-engine.jito.wtf".to_string(),
+            jito_tip_account: "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5".to_string(),
+            lookup_tables: Vec::new(),
+            nonce_account: None,
+            intermediate_tokens: vec![
+                "So11111111111111111111111111111111111111112".to_string(),  // wSOL
+                "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(), // USDC
+            ],
+            price_api_url: "https://api.dexscreener.com/latest/dex/tokens".to_string(),
+            watch_tokens: Vec::new(),
+            min_volume: 10_000,
+            volume_spike_threshold: 2.0,
+            volume_ema_alpha: 0.2,
+            price_move_threshold: 0.05,
+            momentum_threshold: 0.1,
+            strategy: StrategyKind::Volume,
+            confidence_volume_weight: 0.7,
+            confidence_price_weight: 0.3,
+            min_confidence: 0.7,
+            min_activity_score: 0.5,
+            max_trades_per_hour: 30.0,
+            signal_cooldown_secs: 300,
+            buy_cooldown_secs: 60,
+            position_poll_secs: 5,
+            ladder_state_path: "ladders.json".to_string(),
+            dca_state_path: "dca.json".to_string(),
+            trailing_stop_state_path: "trailing_stops.json".to_string(),
+            positions_path: "positions.json".to_string(),
+            wallet_state_path: "wallets.json".to_string(),
+            trade_history_path: "trade_history.csv".to_string(),
+            close_on_exit: false,
+            mirror_exits: false,
+            mirror_partial_exits: false,
+            amm_program_id: None,
+            rpc_record_path: None,
+            rpc_replay_path: None,
+            min_priority_fee: 1_000,
+            max_priority_fee: 10_000_000,
+            max_slippage: 0.01,
+            safe_mode: false,
+            dump_transactions: false,
+            breaker_failure_threshold: 5,
+            breaker_cooldown_secs: 60,
+            max_rpc_slot_lag: 5,
+            max_instructions_per_tx: 6,
+            blockhash_retries: 2,
+            max_daily_spend_sol: Sol(10.0),
+            max_sell_tax_bps: 1_000,
+            log_level: "info".to_string(),
+            log_json: false,
+            metrics_enabled: false,
+            metrics_addr: "127.0.0.1:9898".to_string(),
+            priority_fee_strategy: PriorityFeeStrategy::Percentile { percentile: 0.75 },
+            priority_fee_percentile: None,
+            notifications: NotificationConfig::default(),
+            target_wallets: BTreeMap::new(),
+        }
+    }
+}
+
+impl TradingConfig {
+    pub fn from_file(path: &str) -> Result<Self, BotError> {
+        let contents = fs::read_to_string(path).map_err(|e| BotError::ConfigError {
+            field: path.to_string(),
+            reason: e.to_string(),
+        })?;
+
+        // Missing fields fall back to Default via #[serde(default)]
+        let mut deserializer = toml::Deserializer::new(&contents);
+        let mut config: Self = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| BotError::ConfigError {
+            field: e.path().to_string(),
+            reason: e.inner().to_string(),
+        })?;
+        config.migrate_priority_fee_percentile();
+        Ok(config)
+    }
+
+    // An explicit priority_fee_strategy wins over the old field
+    fn migrate_priority_fee_percentile(&mut self) {
+        let Some(percentile) = self.priority_fee_percentile.take() else {
+            return;
+        };
+        if self.priority_fee_strategy == Self::default().priority_fee_strategy {
+            self.priority_fee_strategy = PriorityFeeStrategy::Percentile { percentile };
         }
     }
-}
\ No newline at end of file
+
+    // Load from BOT_CONFIG (or config.toml) and let BOT_* variables override it
+    pub fn from_env() -> Result<Self, BotError> {
+        let path = config_path();
+
+        let mut config = if Path::new(&path).exists() {
+            Self::from_file(&path)?
+        } else {
+            Self::default()
+        };
+
+        config.apply_env_overrides()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), BotError> {
+        for (field, weight) in [
+            ("confidence_volume_weight", self.confidence_volume_weight),
+            ("confidence_price_weight", self.confidence_price_weight),
+        ] {
+            if !(weight >= 0.0 && weight.is_finite()) {
+                return Err(BotError::ConfigError {
+                    field: field.to_string(),
+                    reason: format!("must be a non-negative number, got {}", weight),
+                });
+            }
+        }
+        if self.confidence_volume_weight + self.confidence_price_weight == 0.0 {
+            return Err(BotError::ConfigError {
+                field: "confidence_volume_weight".to_string(),
+                reason: "confidence weights can't both be zero".to_string(),
+            });
+        }
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return Err(BotError::ConfigError {
+                field: "min_confidence".to_string(),
+                reason: format!("must be between 0 and 1, got {}", self.min_confidence),
+            });
+        }
+        if !(0.0..=1.0).contains(&self.min_activity_score) {
+            return Err(BotError::ConfigError {
+                field: "min_activity_score".to_string(),
+                reason: format!("must be between 0 and 1, got {}", self.min_activity_score),
+            });
+        }
+        if self.max_trades_per_hour.is_nan() || self.max_trades_per_hour <= 0.0 {
+            return Err(BotError::ConfigError {
+                field: "max_trades_per_hour".to_string(),
+                reason: format!("must be positive, got {}", self.max_trades_per_hour),
+            });
+        }
+        if self.min_priority_fee > self.max_priority_fee {
+            return Err(BotError::ConfigError {
+                field: "min_priority_fee".to_string(),
+                reason: format!("{} is above max_priority_fee {}", self.min_priority_fee, self.max_priority_fee),
+            });
+        }
+        if self.max_instructions_per_tx <= COMPUTE_BUDGET_INSTRUCTIONS {
+            return Err(BotError::ConfigError {
+                field: "max_instructions_per_tx".to_string(),
+                reason: format!(
+                    "{} leaves no room beside the {} compute budget instructions",
+                    self.max_instructions_per_tx, COMPUTE_BUDGET_INSTRUCTIONS,
+                ),
+            });
+        }
+        self.quote_mint()?;
+        self.target_wallets()?;
+        Ok(())
+    }
+
+    // Parsed target_wallets; every weight must be a positive number
+    pub fn target_wallets(&self) -> Result<BTreeMap<Pubkey, f64>, BotError> {
+        self.target_wallets
+            .iter()
+            .map(|(wallet, &weight)| {
+                let pubkey = Pubkey::from_str(wallet).map_err(|e| BotError::ConfigError {
+                    field: "target_wallets".to_string(),
+                    reason: format!("invalid wallet '{}': {}", wallet, e),
+                })?;
+                if !(weight > 0.0 && weight.is_finite()) {
+                    return Err(BotError::ConfigError {
+                        field: "target_wallets".to_string(),
+                        reason: format!("weight for {} must be positive, got {}", wallet, weight),
+                    });
+                }
+                Ok((pubkey, weight))
+            })
+            .collect()
+    }
+
+    // False if the wallet was already a target; its weight is left as it was
+    pub fn add_target_wallet(&mut self, wallet: &Pubkey, weight: f64) -> bool {
+        let key = wallet.to_string();
+        if self.target_wallets.contains_key(&key) {
+            return false;
+        }
+        self.target_wallets.insert(key, weight);
+        true
+    }
+
+    pub fn remove_target_wallet(&mut self, wallet: &Pubkey) -> bool {
+        self.target_wallets.remove(&wallet.to_string()).is_some()
+    }
+
+    // False if the wallet isn't a target
+    pub fn set_target_weight(&mut self, wallet: &Pubkey, weight: f64) -> bool {
+        match self.target_wallets.get_mut(&wallet.to_string()) {
+            Some(current) => {
+                *current = weight;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // "SOL", "USDC" and "USDT" by name, anything else as a mint address
+    pub fn quote_mint(&self) -> Result<Pubkey, BotError> {
+        let mint = match self.quote_token.to_uppercase().as_str() {
+            "SOL" | "WSOL" => WSOL_MINT,
+            "USDC" => self.cluster.usdc_mint(),
+            "USDT" => USDT_MINT,
+            _ => self.quote_token.as_str(),
+        };
+        Pubkey::from_str(mint).map_err(|e| BotError::ConfigError {
+            field: "quote_token".to_string(),
+            reason: format!("{:?} is neither SOL, USDC, USDT nor a mint: {}", self.quote_token, e),
+        })
+    }
+
+    pub fn rpc_endpoint(&self) -> String {
+        if self.rpc_url.is_empty() {
+            self.cluster.rpc_url()
+        } else {
+            self.rpc_url.clone()
+        }
+    }
+
+    pub fn ws_endpoint(&self) -> String {
+        if self.ws_url.is_empty() {
+            self.cluster.ws_url()
+        } else {
+            self.ws_url.clone()
+        }
+    }
+
+    pub fn max_slippage_bps(&self) -> u16 {
+        (self.max_slippage * 10_000.0).round().clamp(0.0, 10_000.0) as u16
+    }
+
+    // Applies `edit` to what the file at `path` holds, or to the defaults when
+    // there's no file yet, and writes that back. Unlike save_to_file on a
+    // loaded config, values that came from BOT_* variables never reach disk.
+    pub fn update_file(path: &str, edit: impl FnOnce(&mut TradingConfig)) -> Result<(), BotError> {
+        let mut config = if Path::new(path).exists() {
+            Self::from_file(path)?
+        } else {
+            Self::default()
+        };
+        edit(&mut config);
+        config.save_to_file(path)
+    }
+
+    pub fn save_to_file(&self, path: &str) -> Result<(), BotError> {
+        let contents = toml::to_string_pretty(self).map_err(|e| BotError::ConfigError {
+            field: path.to_string(),
+            reason: e.to_string(),
+        })?;
+
+        fs::write(path, contents).map_err(|e| BotError::ConfigError {
+            field: path.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), BotError> {
+        override_from_env("BOT_CLUSTER", "cluster", &mut self.cluster)?;
+        override_from_env("BOT_RPC_URL", "rpc_url", &mut self.rpc_url)?;
+        override_from_env("BOT_WS_URL", "ws_url", &mut self.ws_url)?;
+        override_from_env("BOT_WALLET_PATH", "wallet_path", &mut self.wallet_path)?;
+        override_from_env("BOT_QUOTE_TOKEN", "quote_token", &mut self.quote_token)?;
+        override_from_env("BOT_MIN_LIQUIDITY", "min_liquidity", &mut self.min_liquidity)?;
+        override_from_env("BOT_MAX_POSITION_SIZE", "max_position_size", &mut self.max_position_size)?;
+        override_from_env("BOT_FIXED_AMOUNT", "fixed_amount", &mut self.fixed_amount)?;
+        override_from_env("BOT_RISK_PERCENTAGE", "risk_percentage", &mut self.risk_percentage)?;
+        override_from_env("BOT_PROFIT_TARGET", "profit_target", &mut self.profit_target)?;
+        override_from_env("BOT_STOP_LOSS", "stop_loss", &mut self.stop_loss)?;
+        override_from_env("BOT_JITO_BLOCK_ENGINE_URL", "jito_block_engine_url", &mut self.jito_block_engine_url)?;
+        override_from_env("BOT_PRICE_API_URL", "price_api_url", &mut self.price_api_url)?;
+        override_from_env("BOT_STRATEGY", "strategy", &mut self.strategy)?;
+        override_from_env("BOT_MAX_SLIPPAGE", "max_slippage", &mut self.max_slippage)?;
+        override_from_env("BOT_SAFE_MODE", "safe_mode", &mut self.safe_mode)?;
+        override_from_env("BOT_DUMP_TRANSACTIONS", "dump_transactions", &mut self.dump_transactions)?;
+        override_from_env("BOT_CLOSE_ON_EXIT", "close_on_exit", &mut self.close_on_exit)?;
+        override_from_env("BOT_MIRROR_EXITS", "mirror_exits", &mut self.mirror_exits)?;
+        override_from_env("BOT_MIRROR_PARTIAL_EXITS", "mirror_partial_exits", &mut self.mirror_partial_exits)?;
+        override_from_env("BOT_MIN_PRIORITY_FEE", "min_priority_fee", &mut self.min_priority_fee)?;
+        override_from_env("BOT_MIN_ACTIVITY_SCORE", "min_activity_score", &mut self.min_activity_score)?;
+        override_from_env("BOT_MAX_TRADES_PER_HOUR", "max_trades_per_hour", &mut self.max_trades_per_hour)?;
+        override_from_env("BOT_MAX_PRIORITY_FEE", "max_priority_fee", &mut self.max_priority_fee)?;
+        override_from_env("BOT_MAX_RPC_SLOT_LAG", "max_rpc_slot_lag", &mut self.max_rpc_slot_lag)?;
+        override_from_env("BOT_MAX_INSTRUCTIONS_PER_TX", "max_instructions_per_tx", &mut self.max_instructions_per_tx)?;
+        override_from_env("BOT_BLOCKHASH_RETRIES", "blockhash_retries", &mut self.blockhash_retries)?;
+        override_from_env("BOT_LOG_LEVEL", "log_level", &mut self.log_level)?;
+        override_from_env("BOT_LOG_JSON", "log_json", &mut self.log_json)?;
+        override_from_env("BOT_METRICS_ENABLED", "metrics_enabled", &mut self.metrics_enabled)?;
+        override_from_env("BOT_METRICS_ADDR", "metrics_addr", &mut self.metrics_addr)?;
+        if let Ok(id) = env::var("BOT_AMM_PROGRAM_ID") {
+            self.amm_program_id = Some(id);
+        }
+        if let Ok(path) = env::var("BOT_RPC_RECORD_PATH") {
+            self.rpc_record_path = Some(path);
+        }
+        if let Ok(path) = env::var("BOT_RPC_REPLAY_PATH") {
+            self.rpc_replay_path = Some(path);
+        }
+        if let Ok(account) = env::var("BOT_NONCE_ACCOUNT") {
+            self.nonce_account = Some(account);
+        }
+        Ok(())
+    }
+}
+
+// BOT_CONFIG, or config.toml in the working directory
+pub fn config_path() -> String {
+    env::var("BOT_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string())
+}
+
+fn override_from_env<T>(var: &str, field: &str, target: &mut T) -> Result<(), BotError>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    if let Ok(value) = env::var(var) {
+        *target = value.parse::<T>().map_err(|e| BotError::ConfigError {
+            field: field.to_string(),
+            reason: format!("{}={}: {}", var, value, e),
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_usdc_quote_per_cluster() {
+        let mut config = TradingConfig { quote_token: "usdc".to_string(), ..TradingConfig::default() };
+        assert_eq!(config.quote_mint().unwrap(), Pubkey::from_str(USDC_MINT).unwrap());
+
+        config.cluster = Cluster::Devnet;
+        assert_eq!(config.quote_mint().unwrap(), Pubkey::from_str(USDC_DEVNET_MINT).unwrap());
+    }
+
+    #[test]
+    fn each_cluster_resolves_its_endpoints() {
+        let cases = [
+            ("mainnet", "https://api.mainnet-beta.solana.com", "wss://api.mainnet-beta.solana.com"),
+            ("devnet", "https://api.devnet.solana.com", "wss://api.devnet.solana.com"),
+            ("testnet", "https://api.testnet.solana.com", "wss://api.testnet.solana.com"),
+            ("http://127.0.0.1:8899", "http://127.0.0.1:8899", "ws://127.0.0.1:8899"),
+        ];
+        for (name, rpc, ws) in cases {
+            let config = TradingConfig { cluster: name.parse().unwrap(), ..TradingConfig::default() };
+            assert_eq!(config.rpc_endpoint(), rpc, "{}", name);
+            assert_eq!(config.ws_endpoint(), ws, "{}", name);
+        }
+
+        // Explicit URLs win over the cluster's
+        let config = TradingConfig {
+            cluster: Cluster::Devnet,
+            rpc_url: "https://rpc.example".to_string(),
+            ws_url: "wss://ws.example".to_string(),
+            ..TradingConfig::default()
+        };
+        assert_eq!(config.rpc_endpoint(), "https://rpc.example");
+        assert_eq!(config.ws_endpoint(), "wss://ws.example");
+    }
+
+    #[test]
+    fn clusters_are_saved_as_plain_strings() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        for cluster in [Cluster::Mainnet, Cluster::Testnet, Cluster::Custom("https://rpc.example".to_string())] {
+            TradingConfig { cluster: cluster.clone(), ..TradingConfig::default() }.save_to_file(path).unwrap();
+            assert_eq!(TradingConfig::from_file(path).unwrap().cluster, cluster);
+        }
+        let saved: toml::Value = toml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(saved["cluster"].as_str(), Some("https://rpc.example"));
+    }
+
+    #[test]
+    fn rejects_unknown_quote_token() {
+        let config = TradingConfig { quote_token: "DOGE".to_string(), ..TradingConfig::default() };
+        assert!(matches!(config.quote_mint(), Err(BotError::ConfigError { .. })));
+    }
+
+    #[test]
+    fn adds_reweights_and_removes_targets() {
+        let mut config = TradingConfig::default();
+        let wallet = Pubkey::new_unique();
+
+        assert!(config.add_target_wallet(&wallet, 1.5));
+        assert!(!config.add_target_wallet(&wallet, 3.0));
+        assert_eq!(config.target_wallets().unwrap()[&wallet], 1.5);
+
+        assert!(config.set_target_weight(&wallet, 0.5));
+        assert_eq!(config.target_wallets().unwrap()[&wallet], 0.5);
+
+        assert!(config.remove_target_wallet(&wallet));
+        assert!(!config.set_target_weight(&wallet, 1.0));
+        assert!(config.target_wallets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn target_wallet_edits_persist_through_the_file() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        let (kept, dropped) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        // The menu's add, reweight and remove, each written straight through
+        TradingConfig::update_file(path, |config| {
+            config.add_target_wallet(&kept, 1.0);
+            config.add_target_wallet(&dropped, 2.0);
+        }).unwrap();
+        TradingConfig::update_file(path, |config| {
+            config.set_target_weight(&kept, 0.5);
+        }).unwrap();
+        TradingConfig::update_file(path, |config| {
+            config.remove_target_wallet(&dropped);
+        }).unwrap();
+
+        let targets = TradingConfig::from_file(path).unwrap().target_wallets().unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(targets, BTreeMap::from([(kept, 0.5)]));
+    }
+
+    #[test]
+    fn rejects_non_positive_target_weights() {
+        let mut config = TradingConfig::default();
+        config.add_target_wallet(&Pubkey::new_unique(), 0.0);
+        assert!(matches!(config.target_wallets(), Err(BotError::ConfigError { .. })));
+    }
+
+    #[test]
+    fn update_file_keeps_env_values_off_disk() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        let on_disk = TradingConfig { rpc_url: "https://file.example".to_string(), ..TradingConfig::default() };
+        on_disk.save_to_file(path).unwrap();
+
+        // The running config may hold a BOT_RPC_URL with an API key; only the edit is written
+        TradingConfig::update_file(path, |config| config.max_slippage = 0.02).unwrap();
+
+        let saved = TradingConfig::from_file(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(saved.rpc_url, "https://file.example");
+        assert_eq!(saved.max_slippage, 0.02);
+    }
+
+    #[test]
+    fn old_priority_fee_percentile_becomes_a_percentile_strategy() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "priority_fee_percentile = 0.9\n").unwrap();
+
+        let config = TradingConfig::from_file(path).unwrap();
+        assert_eq!(config.priority_fee_strategy, PriorityFeeStrategy::Percentile { percentile: 0.9 });
+        assert_eq!(config.priority_fee_percentile, None);
+
+        // Saving writes only the new field
+        config.save_to_file(path).unwrap();
+        let saved = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(!saved.contains("priority_fee_percentile"));
+    }
+
+    #[test]
+    fn priority_fee_strategies_round_trip_through_the_file() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        let strategies = [
+            PriorityFeeStrategy::Fixed { fee: 5_000 },
+            PriorityFeeStrategy::Percentile { percentile: 0.9 },
+            PriorityFeeStrategy::Adaptive { min: 1_000, max: 100_000, target_land_rate: 0.8 },
+        ];
+        for strategy in strategies {
+            TradingConfig { priority_fee_strategy: strategy, ..TradingConfig::default() }.save_to_file(path).unwrap();
+            assert_eq!(TradingConfig::from_file(path).unwrap().priority_fee_strategy, strategy);
+        }
+
+        // Hand-written files use the same tagged table
+        fs::write(path, "[priority_fee_strategy]\nkind = \"fixed\"\nfee = 7000\n").unwrap();
+        let config = TradingConfig::from_file(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(config.priority_fee_strategy, PriorityFeeStrategy::Fixed { fee: 7_000 });
+    }
+
+    #[test]
+    fn explicit_priority_fee_strategy_wins_over_the_old_field() {
+        let mut config = TradingConfig {
+            priority_fee_strategy: PriorityFeeStrategy::Fixed { fee: 5_000 },
+            priority_fee_percentile: Some(0.9),
+            ..TradingConfig::default()
+        };
+        config.migrate_priority_fee_percentile();
+        assert_eq!(config.priority_fee_strategy, PriorityFeeStrategy::Fixed { fee: 5_000 });
+    }
+}
@@ -3,36 +3,141 @@ use {
         aead::{Aead, KeyInit},
         Aes256Gcm, Nonce,
     },
-    rand::Rng,
+    crate::error::BotError,
+    anyhow::{anyhow, Result},
+    rand::{rngs::OsRng, RngCore},
+    sha2::{Digest, Sha256},
     std::env,
 };
 
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
 pub struct Security {
     cipher: Aes256Gcm,
 }
 
 impl Security {
+    // Keyed from ENCRYPTION_KEY; a random key would leave wallets written
+    // this run unreadable on the next, so an unset key is an error
     pub fn new() -> Result<Self> {
-        // Generate random key or get from secure environment
-        let key = env::var("ENCRYPTION_KEY")
-            .unwrap_or_else(|_| generate_secure_key());
-            
-        let cipher = Aes256Gcm::new_from_slice(key.as_bytes())?;
-        
+        let key = key_from_secret(env::var("ENCRYPTION_KEY").ok())?;
+        Self::from_key(&key)
+    }
+
+    pub fn from_key(key: &[u8; KEY_LEN]) -> Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|e| anyhow!("Invalid encryption key: {}", e))?;
+
         Ok(Self { cipher })
     }
 
+    // Output layout: 12-byte random nonce followed by the ciphertext
     pub fn encrypt_sensitive_data(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let nonce = Nonce::from_slice(b"unique nonce"); // Use random nonce in production
-        self.cipher
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+
+        let ciphertext = self.cipher
             .encrypt(nonce, data)
-            .map_err(|e| anyhow!("Encryption failed: {}", e))
+            .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+
+        let mut output = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        output.extend_from_slice(&nonce_bytes);
+        output.extend_from_slice(&ciphertext);
+        Ok(output)
     }
 
     pub fn decrypt_sensitive_data(&self, encrypted: &[u8]) -> Result<Vec<u8>> {
-        let nonce = Nonce::from_slice(b"unique nonce"); 
+        if encrypted.len() < NONCE_LEN {
+            return Err(anyhow!("Decryption failed: payload shorter than nonce"));
+        }
+
+        let (nonce_bytes, ciphertext) = encrypted.split_at(NONCE_LEN);
+        let nonce = Nonce::from_slice(nonce_bytes);
         self.cipher
-            .decrypt(nonce, encrypted)
+            .decrypt(nonce, ciphertext)
             .map_err(|e| anyhow!("Decryption failed: {}", e))
     }
-}
\ No newline at end of file
+}
+
+pub fn generate_secure_key() -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+fn key_from_secret(secret: Option<String>) -> Result<[u8; KEY_LEN], BotError> {
+    let secret = secret.ok_or_else(|| BotError::ConfigError {
+        field: "ENCRYPTION_KEY".to_string(),
+        reason: "must be set to encrypt and decrypt wallet files".to_string(),
+    })?;
+    derive_key(secret.as_bytes())
+}
+
+// AES-256 needs exactly 32 bytes, so stretch/compress any secret through SHA-256
+pub fn derive_key(secret: &[u8]) -> Result<[u8; KEY_LEN], BotError> {
+    if secret.is_empty() {
+        return Err(BotError::ConfigError {
+            field: "ENCRYPTION_KEY".to_string(),
+            reason: "key must not be empty".to_string(),
+        });
+    }
+
+    Ok(Sha256::digest(secret).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_plaintext_encrypts_differently() {
+        let security = Security::from_key(&generate_secure_key()).unwrap();
+        let first = security.encrypt_sensitive_data(b"secret").unwrap();
+        let second = security.encrypt_sensitive_data(b"secret").unwrap();
+
+        assert_ne!(first, second);
+        assert_ne!(first[..NONCE_LEN], second[..NONCE_LEN]);
+        assert_eq!(security.decrypt_sensitive_data(&first).unwrap(), b"secret");
+        assert_eq!(security.decrypt_sensitive_data(&second).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn truncated_payload_fails_to_decrypt() {
+        let security = Security::from_key(&generate_secure_key()).unwrap();
+        assert!(security.decrypt_sensitive_data(&[0u8; NONCE_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn unset_key_is_a_config_error() {
+        assert!(matches!(key_from_secret(None), Err(BotError::ConfigError { .. })));
+        assert!(matches!(key_from_secret(Some(String::new())), Err(BotError::ConfigError { .. })));
+    }
+
+    #[test]
+    fn short_key_is_stretched_to_32_bytes() {
+        let key = key_from_secret(Some("hunter2".to_string())).unwrap();
+        assert_eq!(key.len(), KEY_LEN);
+        assert_eq!(key, derive_key(b"hunter2").unwrap());
+        assert!(Security::from_key(&key).is_ok());
+    }
+
+    #[test]
+    fn exact_32_byte_key_works() {
+        let secret = "0123456789abcdef0123456789abcdef";
+        let security = Security::from_key(&key_from_secret(Some(secret.to_string())).unwrap()).unwrap();
+        let encrypted = security.encrypt_sensitive_data(b"wallet").unwrap();
+        assert_eq!(security.decrypt_sensitive_data(&encrypted).unwrap(), b"wallet");
+    }
+
+    #[test]
+    fn generated_key_round_trips() {
+        let key = generate_secure_key();
+        assert_ne!(key, generate_secure_key());
+
+        let security = Security::from_key(&key).unwrap();
+        let encrypted = security.encrypt_sensitive_data(b"wallet").unwrap();
+        assert_eq!(security.decrypt_sensitive_data(&encrypted).unwrap(), b"wallet");
+    }
+}
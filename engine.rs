@@ -1,32 +1,80 @@
+pub mod types;
+
 use {
     solana_sdk::{
-        instruction::{AccountMeta, Instruction},
+        account::Account,
+        address_lookup_table_account::AddressLookupTableAccount,
+        hash::Hash,
+        instruction::Instruction,
+        message::{v0, Message, VersionedMessage},
+        program_pack::Pack,
         pubkey::Pubkey,
         commitment_config::CommitmentConfig,
         compute_budget::ComputeBudgetInstruction,
-        transaction::Transaction,
+        signature::{Keypair, Signature},
+        signer::Signer,
+        system_instruction,
+        transaction::{Transaction, TransactionError, VersionedTransaction},
     },
+    solana_address_lookup_table_program::state::AddressLookupTable,
+    dashmap::DashMap,
     solana_client::{
-        rpc_client::RpcClient,
-        rpc_config::RpcSendTransactionConfig,
-        rpc_filter::{RpcFilterType, Memcmp},
-        client_error::ClientError,
+        nonblocking::rpc_client::RpcClient,
+        nonce_utils,
+        rpc_client::SerializableTransaction,
+        rpc_config::{
+            RpcSendTransactionConfig, RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+            RpcTransactionConfig,
+        },
+        rpc_custom_error::JSON_RPC_SERVER_ERROR_UNSUPPORTED_TRANSACTION_VERSION,
+        rpc_request::RpcError,
+        rpc_response::RpcSimulateTransactionResult,
+        client_error::{ClientError, ClientErrorKind},
     },
+    solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, TransactionStatus, UiTransactionEncoding},
     tokio::time::{Duration, sleep},
-    tokio::sync::Semaphore,
+    tokio::sync::{mpsc, Semaphore},
+    tokio::task::JoinSet,
+    tokio_util::sync::CancellationToken,
+    tracing::{debug, enabled, error, info, warn, Level},
     anyhow::{Result, anyhow},
+    base64::{engine::general_purpose::STANDARD as BASE64, Engine},
+    async_trait::async_trait,
+    futures::future::join_all,
     rand::Rng,
-    std::sync::Arc,
+    serde::Deserialize,
+    serde_json::json,
+    solana_account_decoder::UiAccountEncoding,
+    spl_associated_token_account::get_associated_token_address,
+    spl_token::{native_mint, state::Account as TokenAccount},
+    std::{collections::{HashMap, HashSet, VecDeque}, fmt, future::Future, str::FromStr, sync::Arc},
     lru::LruCache,
 };
 
 use {
+    crate::config::{PriorityFeeStrategy, TradingConfig},
+    crate::dex::{
+        raydium::{decode_new_pools, NewPoolEvent, RaydiumDex, TradeDirection, TradeSignal, INITIALIZE2_LOG},
+        resolve_amm_program_id,
+        SwapVenue,
+    },
+    crate::error::{retry_escalating, BotError, ErrorHandler, EscalationHook},
+    crate::metrics::Metrics,
+    crate::rpc_record::RpcMode,
+    crate::notify::{notifiers_from_config, EventKind, Notifier, TradeEvent},
     crate::security::Security,
+    crate::monitoring::{
+        subscription::{supervise_program_logs, ConnectionState, SeenSignatures},
+        wallet::{decode_wallet_trade, TradeType, Transaction as WalletTrade},
+    },
+    crate::trading::types::{
+        unix_timestamp, Lamports, LatencyOp, LatencyRing, LatencyStats, PositionAction, Price,
+        min_out_after_slippage, ui_to_base_units, wrap_sol_ixs, DcaSchedule, QuoteResult, SellLadder, Sol, TradeHistory, TrailingStop, read_trailing_stops,
+    },
+    parking_lot::{Mutex, RwLock},
     std::time::Instant,
-    atomic::{AtomicUsize, AtomicU64, Ordering},
-}
-
-pub const HELIUS_RPC_URL: &str = "https://mainnet.helius-rpc.com/?api-key=YOUR-API-KEY";
+    std::sync::atomic::{AtomicUsize, AtomicU64, Ordering},
+};
 
 #[derive(Debug)]
 pub struct Config {
@@ -35,819 +83,4257 @@ pub struct Config {
 }
 
 pub fn load_config(path: &str) -> Result<Config> {
-    // Add config loading logic
+    let trading_config = TradingConfig::from_file(path)?;
     Ok(Config {
-        rpc_url: HELIUS_RPC_URL.to_string(),
-        keypair_path: "wallet.json".to_string(),
+        rpc_url: trading_config.rpc_endpoint(),
+        keypair_path: trading_config.wallet_path,
     })
 }
 
-const HELIUS_WS_URL: &str = "wss://mainnet.helius-rpc.com/?api-key=208db7b5-221c-43b2-ac1c-d8ead05874e9";
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(30);
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(400);
+const MAX_SIGNATURE_STATUSES: usize = 256; // getSignatureStatuses per-request limit
+const TRANSACTION_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const MEMPOOL_BUFFER: usize = 1024;
+const SEEN_SIGNATURE_CAPACITY: usize = 10_000; // Dedupes swaps replayed across reconnects
+const NEW_POOL_BUFFER: usize = 64;
+const MAX_SUPPORTED_TRANSACTION_VERSION: u8 = 0; // Legacy and v0 (lookup tables)
+
+const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+const COMPUTE_UNIT_MARGIN: f64 = 1.1; // Pad simulated usage by 10%
+
+const MAX_FEE_ACCOUNTS: usize = 128; // getRecentPrioritizationFees address limit
+
+const RPC_PING_TIMEOUT: Duration = Duration::from_secs(2);
+const POOL_SIGNAL_BUFFER: usize = 64;
+
+const ROUTE_SPLIT_STEPS: u64 = 10; // Order is split across venues in 10% slices
+const MAX_CANDIDATE_ROUTES: usize = 3; // find_optimal_routes keeps this many best quotes
+const MIN_SETUP_BALANCE: Sol = Sol(0.001);
+const SELL_CHECK_PROBE: Sol = Sol(0.001); // Simulated buy size for the honeypot check, priced in the quote mint
+const SELL_CHECK_FAILURE_TTL: Duration = Duration::from_secs(10 * 60); // A failed mint is re-checked after this
+
+const LAND_RATE_WINDOW: usize = 20; // Confirmations the Adaptive fee strategy looks back over
+const ADAPTIVE_FEE_RAISE: f64 = 1.25;
+const ADAPTIVE_FEE_LOWER: f64 = 0.9;
+const HIGH_LOAD_PAYING_SHARE: f64 = 0.75; // Share of recent slots paying any priority fee
+const MEDIUM_LOAD_PAYING_SHARE: f64 = 0.4;
+
+const BLOCKHASH_REFRESH_INTERVAL: Duration = Duration::from_millis(400);
+const BLOCKHASH_MAX_AGE: Duration = Duration::from_secs(2); // Older cached hashes are refetched
+
+const BACKOFF_BASE_MS: u64 = 50;
+const RATE_LIMIT_BACKOFF_BASE_MS: u64 = 20;
+const MAX_BACKOFF_MS: u64 = 5_000;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+// Token bucket of `capacity` permits; a background task adds one back every
+// 1/rate seconds. Callers wait for a permit instead of getting a 429.
+pub struct RateLimiter {
+    permits: Arc<Semaphore>,
+    capacity: usize,
+}
+
+impl RateLimiter {
+    // Spawns the refill task, so this must run inside a Tokio runtime. The task
+    // ends once the limiter is dropped.
+    pub fn new(requests_per_second: u32) -> Arc<Self> {
+        let capacity = requests_per_second.max(1) as usize;
+        let limiter = Arc::new(Self {
+            permits: Arc::new(Semaphore::new(capacity)),
+            capacity,
+        });
+
+        let weak = Arc::downgrade(&limiter);
+        let refill_every = Duration::from_secs(1) / capacity as u32;
+        tokio::spawn(async move {
+            // The bucket starts full, so the first refill is one period out
+            let start = tokio::time::Instant::now() + refill_every;
+            let mut ticker = tokio::time::interval_at(start, refill_every);
+            loop {
+                ticker.tick().await;
+                let Some(limiter) = weak.upgrade() else { break };
+                if limiter.permits.available_permits() < limiter.capacity {
+                    limiter.permits.add_permits(1);
+                }
+            }
+        });
+
+        limiter
+    }
+
+    pub async fn acquire(&self) {
+        if let Ok(permit) = self.permits.acquire().await {
+            permit.forget();
+        }
+    }
+
+    // Fraction of the bucket in use, 0.0 idle to 1.0 throttled
+    pub fn utilization(&self) -> f64 {
+        let used = self.capacity.saturating_sub(self.permits.available_permits());
+        used as f64 / self.capacity as f64
+    }
+}
+
+// Latest blockhash, kept warm by TradingEngine::refresh_blockhash so
+// transaction builders don't pay an RPC round-trip
+pub struct BlockhashCache {
+    latest: RwLock<Option<(Hash, u64, Instant)>>, // blockhash, last valid block height, fetched at
+    max_age: Duration,
+}
+
+impl BlockhashCache {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            latest: RwLock::new(None),
+            max_age,
+        }
+    }
+
+    // None once the cached value is older than max_age
+    pub fn get(&self) -> Option<(Hash, u64)> {
+        match *self.latest.read() {
+            Some((blockhash, last_valid_block_height, fetched_at))
+                if fetched_at.elapsed() <= self.max_age =>
+            {
+                Some((blockhash, last_valid_block_height))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn store(&self, blockhash: Hash, last_valid_block_height: u64) {
+        *self.latest.write() = Some((blockhash, last_valid_block_height, Instant::now()));
+    }
+
+    // The next get() misses, e.g. once the cluster has rejected the hash
+    pub fn invalidate(&self) {
+        *self.latest.write() = None;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreakerState {
+    Closed,
+    Open { remaining: Duration },
+    HalfOpen, // Cooldown over; one probe trade's result closes or reopens it
+}
+
+impl fmt::Display for BreakerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakerState::Closed => write!(f, "closed"),
+            BreakerState::Open { remaining } => write!(f, "open ({}s left)", remaining.as_secs()),
+            BreakerState::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+// Stops new trades after `threshold` consecutive failed transactions, so a bad
+// RPC or pool doesn't burn fees on a burst of doomed ones. Outcomes come from
+// confirmation: a send the RPC accepted can still fail or never land.
+pub struct CircuitBreaker {
+    consecutive_failures: Mutex<u32>,
+    opened_at: Mutex<Option<Instant>>,
+    probe_sent: Mutex<bool>, // A transaction went out while half-open and hasn't resolved
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            consecutive_failures: Mutex::new(0),
+            opened_at: Mutex::new(None),
+            probe_sent: Mutex::new(false),
+            threshold: threshold.max(1),
+            cooldown,
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state_at(*self.opened_at.lock())
+    }
+
+    // Closed lets trades through; half-open only until its probe goes out
+    pub fn allows_trades(&self) -> bool {
+        match self.state() {
+            BreakerState::Closed => true,
+            BreakerState::Open { .. } => false,
+            BreakerState::HalfOpen => !*self.probe_sent.lock(),
+        }
+    }
+
+    // The first transaction sent while half-open is the probe; new trades
+    // wait for its outcome
+    pub fn record_sent(&self) {
+        let mut probe_sent = self.probe_sent.lock();
+        if !*probe_sent && self.state() == BreakerState::HalfOpen {
+            info!("Circuit breaker probing with one trade");
+            *probe_sent = true;
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.consecutive_failures.lock() = 0;
+        *self.probe_sent.lock() = false;
+        if self.opened_at.lock().take().is_some() {
+            info!("Circuit breaker closed after a successful trade");
+        }
+    }
+
+    // True when this failure opened the breaker
+    pub fn record_failure(&self) -> bool {
+        *self.probe_sent.lock() = false;
+        let mut failures = self.consecutive_failures.lock();
+        *failures += 1;
+
+        let mut opened_at = self.opened_at.lock();
+        match self.state_at(*opened_at) {
+            BreakerState::Closed if *failures >= self.threshold => {
+                warn!(
+                    failures = *failures,
+                    cooldown_secs = self.cooldown.as_secs(),
+                    "Circuit breaker opened, pausing trades"
+                );
+                *opened_at = Some(Instant::now());
+                true
+            }
+            // The trial trade failed, so start another cooldown
+            BreakerState::HalfOpen => {
+                warn!(cooldown_secs = self.cooldown.as_secs(), "Circuit breaker reopened, pausing trades");
+                *opened_at = Some(Instant::now());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn state_at(&self, opened_at: Option<Instant>) -> BreakerState {
+        let Some(opened_at) = opened_at else {
+            return BreakerState::Closed;
+        };
+        match self.cooldown.checked_sub(opened_at.elapsed()) {
+            Some(remaining) if !remaining.is_zero() => BreakerState::Open { remaining },
+            _ => BreakerState::HalfOpen,
+        }
+    }
+}
+
+// Submissions and how many of them the RPC accepted
+#[derive(Default)]
+pub struct SubmissionCounts {
+    total: AtomicU64,
+    accepted: AtomicU64,
+}
+
+impl SubmissionCounts {
+    pub fn record(&self, accepted: bool) {
+        self.total.fetch_add(1, Ordering::Release);
+        if accepted {
+            self.accepted.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    // Accepted is read first: record bumps the total before it, so a
+    // concurrent submission can't push the rate above 1
+    pub fn success_rate(&self) -> f64 {
+        let accepted = self.accepted.load(Ordering::Acquire);
+        let total = self.total.load(Ordering::Acquire);
+        if total == 0 {
+            return 0.0;
+        }
+        (accepted as f64 / total as f64).min(1.0)
+    }
+}
+
+// How a sent transaction resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Confirmation {
+    Succeeded,
+    Failed,  // Landed with an error; the fee was still paid
+    Dropped, // Never landed within CONFIRMATION_TIMEOUT
+}
+
+// None while the signature is still pending
+fn confirmation_of(
+    status: Option<&TransactionStatus>,
+    commitment: CommitmentConfig,
+    sent_at: Instant,
+    now: Instant,
+) -> Option<Confirmation> {
+    match status {
+        Some(status) if status.err.is_some() => Some(Confirmation::Failed),
+        Some(status) if status.satisfies_commitment(commitment) => Some(Confirmation::Succeeded),
+        _ if now.saturating_duration_since(sent_at) >= CONFIRMATION_TIMEOUT => Some(Confirmation::Dropped),
+        _ => None,
+    }
+}
+
+// Buy spend and net realized loss for the current UTC day, both zeroed when
+// the day rolls over
+pub struct DailyLimits {
+    max_spend: Lamports,
+    max_loss: Sol,
+    totals: Mutex<DailyTotals>,
+}
+
+// Spend counted by DailyLimits::reserve_spend, for handing back if the buy fails
+#[derive(Debug, Clone, Copy)]
+pub struct SpendReservation {
+    day: i64,
+    amount: Lamports,
+}
+
+#[derive(Default)]
+struct DailyTotals {
+    day: i64, // Days since the Unix epoch
+    spent: Lamports,
+    loss: f64, // SOL; realized gains pay it back down
+}
+
+impl DailyLimits {
+    pub fn new(max_spend: Sol, max_loss: Sol) -> Self {
+        Self {
+            max_spend: max_spend.to_lamports(),
+            max_loss,
+            totals: Mutex::new(DailyTotals::default()),
+        }
+    }
+
+    // Counts `amount` toward the day's spend, or errs without counting it when
+    // that would pass the cap. Checked and added under one lock, so concurrent
+    // buys can't each see room for themselves and overshoot together.
+    pub fn reserve_spend(&self, amount: Lamports, now: i64) -> Result<SpendReservation> {
+        let mut totals = self.totals.lock();
+        Self::roll_over(&mut totals, now);
+
+        let after = Lamports(totals.spent.0.saturating_add(amount.0));
+        if after > self.max_spend {
+            return Err(BotError::TradingError(format!(
+                "Buying {} would bring today's spend to {}, cap is {}",
+                amount, after, self.max_spend
+            )).into());
+        }
+        totals.spent = after;
+        Ok(SpendReservation { day: totals.day, amount })
+    }
+
+    // Gives back a reservation whose buy didn't go out. One from a day that
+    // has since rolled over was already cleared with it.
+    pub fn release_spend(&self, reservation: SpendReservation, now: i64) {
+        let mut totals = self.totals.lock();
+        Self::roll_over(&mut totals, now);
+        if totals.day == reservation.day {
+            totals.spent = Lamports(totals.spent.0.saturating_sub(reservation.amount.0));
+        }
+    }
+
+    // True when this result is the one that reached the loss cap
+    pub fn record_realized(&self, pnl: f64, now: i64) -> bool {
+        let mut totals = self.totals.lock();
+        Self::roll_over(&mut totals, now);
+
+        let was_hit = self.loss_cap_reached(&totals);
+        totals.loss -= pnl;
+        !was_hit && self.loss_cap_reached(&totals)
+    }
+
+    pub fn loss_cap_hit(&self, now: i64) -> bool {
+        let mut totals = self.totals.lock();
+        Self::roll_over(&mut totals, now);
+        self.loss_cap_reached(&totals)
+    }
+
+    // (spent, net realized loss) so far today
+    pub fn today(&self, now: i64) -> (Lamports, Sol) {
+        let mut totals = self.totals.lock();
+        Self::roll_over(&mut totals, now);
+        (totals.spent, Sol(totals.loss.max(0.0)))
+    }
+
+    fn loss_cap_reached(&self, totals: &DailyTotals) -> bool {
+        totals.loss > 0.0 && totals.loss >= self.max_loss.0
+    }
+
+    fn roll_over(totals: &mut DailyTotals, now: i64) {
+        let day = now.div_euclid(SECS_PER_DAY);
+        if totals.day != day {
+            *totals = DailyTotals { day, ..DailyTotals::default() };
+        }
+    }
+}
 
 pub struct RPCConfig {
-    endpoints: Vec<String>,
+    endpoints: RwLock<Vec<String>>, // Primary first; only the primary is ever replaced
     current_index: AtomicUsize,
-    last_error_time: AtomicU64,
+    last_error_time: AtomicU64,   // Unix secs of the most recent failure on any endpoint
+    failed_at: Vec<AtomicU64>,    // Unix secs each endpoint last failed, 0 = healthy
+    cooldown: Duration,           // How long a failed endpoint is skipped
+    ranking: RwLock<Vec<usize>>,  // Endpoint indices, fastest healthy first
 }
 
 impl RPCConfig {
-    fn get_next_endpoint(&self) -> String {
-        let index = self.current_index.fetch_add(1, Ordering::Relaxed) % self.endpoints.len();
-        self.endpoints[index].clone()
+    pub fn new(endpoints: Vec<String>, cooldown: Duration) -> Self {
+        let failed_at = endpoints.iter().map(|_| AtomicU64::new(0)).collect();
+        let ranking = RwLock::new((0..endpoints.len()).collect());
+        Self {
+            endpoints: RwLock::new(endpoints),
+            current_index: AtomicUsize::new(0),
+            last_error_time: AtomicU64::new(0),
+            failed_at,
+            cooldown,
+            ranking,
+        }
+    }
+
+    // Ping every endpoint with getSlot in parallel and re-order failover by latency.
+    // Endpoints that error are put on cooldown and ranked after all healthy ones.
+    pub async fn rank_endpoints(&self) -> Vec<(String, Duration)> {
+        self.rank_by(ping_endpoint).await
+    }
+
+    async fn rank_by<F, Fut>(&self, ping: F) -> Vec<(String, Duration)>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Option<Duration>>,
+    {
+        let endpoints = self.endpoints.read().clone();
+        let latencies = join_all(endpoints.iter().map(|endpoint| ping(endpoint.clone()))).await;
+        let results: Vec<(usize, Option<Duration>)> = latencies.into_iter().enumerate().collect();
+
+        let now = unix_timestamp() as u64;
+        let mut ranked: Vec<(usize, Duration, bool)> = results
+            .into_iter()
+            .map(|(index, latency)| match latency {
+                Some(latency) => (index, latency, true),
+                None => {
+                    self.failed_at[index].store(now, Ordering::Relaxed);
+                    (index, RPC_PING_TIMEOUT, false)
+                }
+            })
+            .collect();
+        ranked.sort_by_key(|(_, latency, healthy)| (!healthy, *latency));
+
+        *self.ranking.write() = ranked.iter().map(|(index, _, _)| *index).collect();
+        ranked
+            .into_iter()
+            .map(|(index, latency, _)| (endpoints[index].clone(), latency))
+            .collect()
+    }
+
+    // Fastest healthy endpoint per the last ranking, if it isn't the current one
+    fn best_endpoint(&self, now: u64) -> Option<String> {
+        let current = self.current_index.load(Ordering::Relaxed) % self.endpoints.read().len();
+        let index = self.ranking
+            .read()
+            .iter()
+            .copied()
+            .find(|index| self.is_available(*index, now))?;
+        if index == current {
+            return None;
+        }
+        self.current_index.store(index, Ordering::Relaxed);
+        Some(self.endpoints.read()[index].clone())
+    }
+
+    fn is_available(&self, index: usize, now: u64) -> bool {
+        let failed_at = self.failed_at[index].load(Ordering::Relaxed);
+        failed_at == 0 || now.saturating_sub(failed_at) >= self.cooldown.as_secs()
+    }
+
+    pub fn current_endpoint(&self) -> String {
+        let endpoints = self.endpoints.read();
+        endpoints[self.current_index.load(Ordering::Relaxed) % endpoints.len()].clone()
+    }
+
+    // Swaps in a new primary and makes it current, clear of any cooldown the
+    // old one was serving. Fallbacks keep their places until the next ranking.
+    pub fn replace_primary(&self, endpoint: String) {
+        self.endpoints.write()[0] = endpoint;
+        self.failed_at[0].store(0, Ordering::Relaxed);
+        self.current_index.store(0, Ordering::Relaxed);
+    }
+
+    // Put the current endpoint on cooldown and move to the next healthy one.
+    // Returns the new endpoint, or None if every other endpoint is cooling down.
+    fn report_failure(&self) -> Option<String> {
+        let now = unix_timestamp() as u64;
+        let current = self.current_index.load(Ordering::Relaxed) % self.endpoints.read().len();
+        self.failed_at[current].store(now, Ordering::Relaxed);
+        self.last_error_time.store(now, Ordering::Relaxed);
+
+        self.get_next_endpoint(now)
     }
+
+    // Highest-ranked endpoint other than the current one that isn't cooling down
+    fn get_next_endpoint(&self, now: u64) -> Option<String> {
+        let current = self.current_index.load(Ordering::Relaxed) % self.endpoints.read().len();
+        let index = self.ranking
+            .read()
+            .iter()
+            .copied()
+            .find(|index| *index != current && self.is_available(*index, now))?;
+        self.current_index.store(index, Ordering::Relaxed);
+        Some(self.endpoints.read()[index].clone())
+    }
+}
+
+// getSlot round trip on the nonblocking client, None on error or timeout
+async fn ping_endpoint(endpoint: String) -> Option<Duration> {
+    let client = RpcClient::new_with_timeout(endpoint, RPC_PING_TIMEOUT);
+    let started = Instant::now();
+    client.get_slot().await.ok().map(|_| started.elapsed())
 }
 
 // Check trading parameters
 pub struct TradingEngine {
-    rpc_client: Arc<RpcClient>,
+    rpc_client: RwLock<Arc<RpcClient>>, // Swapped out by rotate_rpc on failover
+    rpc_config: RPCConfig,
+    rpc_rank_interval: Duration, // How often rank_rpc_endpoints re-pings every endpoint
+    rpc_mode: RpcMode, // Live, or recording / replaying every call
+    rate_limiter: Arc<RateLimiter>,
+    safe_mode: bool, // Simulate before every send
+    dump_transactions: bool, // Debug-log every message before it's sent
+    blockhash_cache: BlockhashCache,
+    ws_url: String,
+    private_node: Option<Arc<RpcClient>>, // Private submission endpoint, if any
+    payer: Arc<Keypair>,
     security: Security,
     compute_units: u32,     // Should be 1_400_000
-    priority_fee: u64,      // Should be high enough (1_000_000)
+    priority_fee: AtomicU64, // Should be high enough (1_000_000)
+    priority_fee_strategy: PriorityFeeStrategy,
+    priority_fee_floor: u64, // Used when no recent fees were paid
+    priority_fee_ceiling: u64,
+    max_slippage_bps: u16,
+    adaptive_fee: AtomicU64, // Current fee under PriorityFeeStrategy::Adaptive
+    land_outcomes: Mutex<VecDeque<bool>>, // Whether each recent confirmation landed
     preflight_checks: bool, // Should be false for speed
     commitment: CommitmentConfig, // Should be "processed"
-    rpc_client: Arc<RpcClient>,
+    min_required_balance: Lamports,
+    amount: u64,
+    token_in: Pubkey,
+    token_out: Pubkey,
     max_retries: u32,
-    minimum_slots_ahead: u64,
-    last_transaction_time: std::time::Instant,
-    transaction_count: u64,
-    success_count: u64,
+    max_rpc_slot_lag: u64, // Look-ahead guard for execute_early_swap
+    max_instructions_per_tx: usize, // Bundle chunk size, compute budget included
+    blockhash_retries: u32, // Resends after BlockhashNotFound, on top of the first send
+    last_transaction_time: Mutex<Instant>, // Last submission, successful or not
+    submissions: SubmissionCounts,
+    circuit_breaker: CircuitBreaker,
+    pending_confirmations: Mutex<Vec<(Signature, Instant)>>, // Sent, awaiting track_confirmations
+    daily_limits: DailyLimits,
+    latencies: Mutex<HashMap<LatencyOp, LatencyRing>>,
+    metrics: Arc<Metrics>, // Served by metrics::start_metrics_server when enabled
+    notifiers: Vec<Arc<dyn Notifier>>,
     transaction_cache: LruCache<String, Transaction>,
     execution_semaphore: Arc<Semaphore>,
+    venues: Vec<Box<dyn SwapVenue>>, // Best quote wins; order only breaks ties
+    escalation_hooks: RwLock<Vec<EscalationHook>>,
+    tracked_tokens: RwLock<HashSet<Pubkey>>, // Tokens get_active_positions looks at
+    realized_pnl: Mutex<HashMap<Pubkey, f64>>, // SOL booked per token by sells
+    trade_history: Mutex<Vec<TradeHistory>>,
+    profit_target: f64, // Sell when price reaches entry * profit_target
+    stop_loss: f64,     // Sell when price falls to entry * stop_loss
+    position_poll_interval: Duration,
+    http_client: reqwest::Client,
+    jito_block_engine_url: String,
+    jito_tip_account: Pubkey,
+    lookup_table_addresses: Vec<Pubkey>,
+    intermediate_tokens: Vec<Pubkey>, // Hops tried by find_optimal_routes, deduped
+    nonce_account: Option<(Pubkey, Arc<Keypair>)>, // Durable nonce and its authority, if set
+    lookup_table_cache: DashMap<Pubkey, AddressLookupTableAccount>,
+    mint_decimals: DashMap<Pubkey, u8>,
+    sell_checks: DashMap<Pubkey, (bool, Instant)>, // Mint -> passed simulate_sell_check, checked at
+    max_sell_tax_bps: u16,
+    shutdown: CancellationToken, // Cancelled by trigger_emergency_stop
+    mempool_state: Arc<RwLock<ConnectionState>>, // Shared with the monitor_mempool supervisor
+    amm_program_id: Pubkey, // Raydium AMM v4, watched by detect_new_pools
+    quote_mint: Pubkey,     // What buys spend and prices are quoted in
+    ladders: Mutex<HashMap<Pubkey, SellLadder>>,
+    ladder_state_path: String,
+    dca_schedules: Mutex<HashMap<Pubkey, DcaSchedule>>,
+    dca_state_path: String,
+    trailing_stops: Mutex<HashMap<Pubkey, TrailingStop>>,
+    trailing_stop_state_path: String,
+    positions_path: String,
+    restored_entries: Mutex<HashMap<Pubkey, (Price, i64)>>, // Entry price and time from load_positions
+    pool_monitor: Arc<RaydiumDex>, // Watches the pools of watch_tokens for trade_pool_signals
+    watch_tokens: Vec<Pubkey>,
+    signal_buy_amount: f64, // Whole quote tokens spent on a pool Buy signal
 }
 
 impl TradingEngine {
-    pub fn new() -> Self {
+    pub fn new(config: &TradingConfig, payer: Arc<Keypair>) -> Result<Self> {
         let security = Security::new()?;
+        let jito_tip_account = Pubkey::from_str(&config.jito_tip_account)
+            .map_err(|e| anyhow!("Invalid jito_tip_account: {}", e))?;
+        let lookup_table_addresses = config.lookup_tables
+            .iter()
+            .map(|address| Pubkey::from_str(address)
+                .map_err(|e| anyhow!("Invalid lookup table {}: {}", address, e)))
+            .collect::<Result<Vec<_>>>()?;
+        let intermediate_tokens = parse_intermediate_tokens(&config.intermediate_tokens)?;
+        // The wallet is the nonce authority, so it alone signs the advance
+        let nonce_account = config.nonce_account
+            .as_deref()
+            .map(|address| Pubkey::from_str(address)
+                .map(|account| (account, payer.clone()))
+                .map_err(|e| anyhow!("Invalid nonce_account {}: {}", address, e)))
+            .transpose()?;
+
+        // Primary endpoint first, then the configured fallbacks in order
+        let mut endpoints = vec![config.rpc_endpoint()];
+        endpoints.extend(config.rpc_fallback_urls.iter().cloned());
+        let rpc_config = RPCConfig::new(
+            endpoints,
+            Duration::from_secs(config.rpc_failover_cooldown_secs),
+        );
+        let rpc_mode = RpcMode::from_config(config);
+        let raydium = RaydiumDex::new(config, Keypair::from_bytes(&payer.to_bytes())?)?;
+        let pool_monitor = Arc::new(RaydiumDex::new(config, Keypair::from_bytes(&payer.to_bytes())?)?);
+        let watch_tokens = config.watch_tokens
+            .iter()
+            .map(|mint| Pubkey::from_str(mint)
+                .map_err(|e| anyhow!("Invalid watch token {}: {}", mint, e)))
+            .collect::<Result<Vec<_>>>()?;
         
         Ok(Self {
-            rpc_client: Arc::new(RpcClient::new_with_commitment(
-                HELIUS_RPC_URL.to_string(),
+            rpc_client: RwLock::new(Arc::new(rpc_mode.nonblocking_client(
+                rpc_config.current_endpoint(),
                 CommitmentConfig::processed(),
-            )),
+            )?)),
+            rpc_config,
+            rpc_rank_interval: Duration::from_secs(config.rpc_rank_interval_secs.max(1)),
+            rpc_mode,
+            rate_limiter: RateLimiter::new(config.rpc_requests_per_second),
+            blockhash_cache: BlockhashCache::new(BLOCKHASH_MAX_AGE),
+            safe_mode: config.safe_mode,
+            dump_transactions: config.dump_transactions,
+            ws_url: config.ws_endpoint(),
+            private_node: None,
+            payer,
             security,
-            compute_units: 1_400_000,
-            priority_fee: 1_000_000,
+            compute_units: MAX_COMPUTE_UNITS,
+            priority_fee: AtomicU64::new(1_000_000),
+            priority_fee_strategy: config.priority_fee_strategy,
+            priority_fee_floor: config.min_priority_fee,
+            priority_fee_ceiling: config.max_priority_fee,
+            max_slippage_bps: config.max_slippage_bps(),
+            adaptive_fee: AtomicU64::new(config.priority_fee_strategy.initial_fee(1_000_000)),
+            land_outcomes: Mutex::new(VecDeque::with_capacity(LAND_RATE_WINDOW + 1)),
             max_retries: 3,
             preflight_checks: false,
-            minimum_slots_ahead: 5,
+            min_required_balance: config.min_required_balance.to_lamports(),
+            amount: 0,
+            token_in: native_mint::ID,
+            token_out: native_mint::ID,
+            max_rpc_slot_lag: config.max_rpc_slot_lag,
+            max_instructions_per_tx: config.max_instructions_per_tx,
+            blockhash_retries: config.blockhash_retries,
             commitment: CommitmentConfig::processed(),
-            last_transaction_time: std::time::Instant::now(),
-            transaction_count: 0,
-            success_count: 0,
+            last_transaction_time: Mutex::new(Instant::now()),
+            submissions: SubmissionCounts::default(),
+            circuit_breaker: CircuitBreaker::new(
+                config.breaker_failure_threshold,
+                Duration::from_secs(config.breaker_cooldown_secs),
+            ),
+            pending_confirmations: Mutex::new(Vec::new()),
+            daily_limits: DailyLimits::new(config.max_daily_spend_sol, Sol(config.daily_loss_cap)),
+            latencies: Mutex::new(HashMap::new()),
+            metrics: Arc::new(Metrics::default()),
+            notifiers: notifiers_from_config(&config.notifications)?,
             transaction_cache: LruCache::new(100),
             execution_semaphore: Arc::new(Semaphore::new(1)),
+            venues: vec![Box::new(raydium)],
+            escalation_hooks: RwLock::new(Vec::new()),
+            tracked_tokens: RwLock::new(HashSet::new()),
+            realized_pnl: Mutex::new(HashMap::new()),
+            trade_history: Mutex::new(Vec::new()),
+            profit_target: config.profit_target,
+            stop_loss: config.stop_loss,
+            position_poll_interval: Duration::from_secs(config.position_poll_secs),
+            http_client: reqwest::Client::new(),
+            jito_block_engine_url: config.jito_block_engine_url.trim_end_matches('/').to_string(),
+            jito_tip_account,
+            lookup_table_addresses,
+            intermediate_tokens,
+            nonce_account,
+            lookup_table_cache: DashMap::new(),
+            mint_decimals: DashMap::new(),
+            sell_checks: DashMap::new(),
+            max_sell_tax_bps: config.max_sell_tax_bps,
+            shutdown: CancellationToken::new(),
+            mempool_state: Arc::new(RwLock::new(ConnectionState::Stopped)),
+            amm_program_id: resolve_amm_program_id(config)?,
+            quote_mint: config.quote_mint()?,
+            ladders: Mutex::new(Self::load_ladders(&config.ladder_state_path)?),
+            ladder_state_path: config.ladder_state_path.clone(),
+            dca_schedules: Mutex::new(Self::load_dca_schedules(&config.dca_state_path)?),
+            dca_state_path: config.dca_state_path.clone(),
+            trailing_stops: Mutex::new(read_trailing_stops(&config.trailing_stop_state_path)?),
+            trailing_stop_state_path: config.trailing_stop_state_path.clone(),
+            positions_path: config.positions_path.clone(),
+            restored_entries: Mutex::new(HashMap::new()),
+            pool_monitor,
+            watch_tokens,
+            signal_buy_amount: config.fixed_amount,
         })
     }
 
-    pub async fn execute_transaction(&mut self, instruction: Instruction) -> Result<()> {
-        let start = std::time::Instant::now();
-        
-        // Pre-build compute budget instructions
-        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(self.priority_fee);
-        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(self.compute_units);
-        
-        // Parallel blockhash fetch
-        let blockhash = self.rpc_client.get_latest_blockhash_with_commitment(
-            CommitmentConfig::processed()
-        )?;
+    // Hand to long-running loops (monitor_positions etc.) so an emergency stop ends them
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
 
-        let tx = Transaction::new_signed_with_payer(
-            &[priority_ix, compute_ix, instruction],
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
-            blockhash
-        );
+    // Starts the engine's own loops; each ends with the shutdown token
+    pub fn spawn_background_tasks(self: &Arc<Self>) {
+        let engine = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = engine.refresh_blockhash(engine.shutdown_token()).await {
+                error!(error = %e, "Blockhash refresh stopped");
+            }
+        });
 
-        // Fast execution path
-        self.rpc_client.send_transaction_with_config(
-            &tx,
-            RpcSendTransactionConfig {
-                skip_preflight: true,
-                preflight_commitment: None,
-                encoding: None,
-                max_retries: Some(0), 
-                min_context_slot: None,
-            },
-        )?;
+        let engine = self.clone();
+        tokio::spawn(async move {
+            engine.track_confirmations(engine.shutdown_token()).await;
+        });
 
-        Ok(())
-    }
+        let engine = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = engine.rank_rpc_endpoints(engine.rpc_rank_interval, engine.shutdown_token()).await {
+                error!(error = %e, "RPC ranking stopped");
+            }
+        });
 
-    pub fn get_success_rate(&self) -> f64 {
-        if self.transaction_count == 0 {
-            return 0.0;
+        if !self.watch_tokens.is_empty() {
+            let engine = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = engine.trade_pool_signals().await {
+                    error!(error = %e, "Pool signal trading stopped");
+                }
+            });
         }
-        self.success_count as f64 / self.transaction_count as f64
+
+        // Restore saved positions before the monitor's first save overwrites them
+        let engine = self.clone();
+        tokio::spawn(async move {
+            match engine.load_positions(&engine.positions_path).await {
+                Ok(restored) => info!(restored, path = %engine.positions_path, "Loaded saved positions"),
+                Err(e) => error!(error = %e, path = %engine.positions_path, "Failed to load saved positions"),
+            }
+            if let Err(e) = engine.monitor_positions(engine.shutdown_token()).await {
+                error!(error = %e, "Position monitor stopped");
+            }
+        });
     }
 
-    pub async fn execute_early_swap(
-        &self,
-        token: &Pubkey,
-        amount: u64,
-        signer: &Keypair,
-    ) -> Result<()> {
-        let _permit = self.execution_semaphore.acquire().await?;
+    // Watches the Raydium pool of every watch_tokens mint and trades its
+    // signals: a Buy spends signal_buy_amount when we hold none of the token,
+    // a Sell closes the position when we do. Runs until shutdown.
+    async fn trade_pool_signals(self: Arc<Self>) -> Result<()> {
+        let mut pool_tokens = HashMap::new();
+        for token in self.watch_tokens.iter().copied() {
+            let raydium = self.pool_monitor.clone();
+            match tokio::task::spawn_blocking(move || raydium.find_pool_for_mint(&token)).await? {
+                Ok(Some(pool_id)) => {
+                    pool_tokens.insert(pool_id, token);
+                }
+                Ok(None) => warn!(token = %token, "No Raydium pool to watch"),
+                Err(e) => warn!(token = %token, error = %e, "Pool lookup failed"),
+            }
+        }
+        if pool_tokens.is_empty() {
+            return Ok(());
+        }
 
-        // 1. Prioritize transaction
-        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(
-            self.priority_fee
-        );
-        
-        // 2. Maximum compute units
-        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(
-            self.compute_units
-        );
+        let (signals_tx, mut signals) = mpsc::channel(POOL_SIGNAL_BUFFER);
+        let monitor = self.pool_monitor.clone().monitor_pools(pool_tokens.keys().copied().collect(), signals_tx);
+        tokio::spawn(async move {
+            if let Err(e) = monitor.await {
+                error!(error = %e, "Pool monitor stopped");
+            }
+        });
 
-        // 3. Create optimized swap
-        let swap_ix = self.create_privileged_swap(token, amount)?;
+        let shutdown = self.shutdown_token();
+        loop {
+            let (pool_id, signal) = tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                received = signals.recv() => match received {
+                    Some(received) => received,
+                    None => return Ok(()),
+                },
+            };
+            let token = pool_tokens[&pool_id];
+            if let Err(e) = self.trade_pool_signal(&token, &signal).await {
+                warn!(pool = %pool_id, token = %token, error = %e, "Pool signal trade failed");
+            }
+        }
+    }
 
-        // 4. Get latest blockhash with look-ahead
-        let (recent_blockhash, last_valid_block_height) = self
-            .rpc_client
-            .get_latest_blockhash_with_commitment(self.commitment)?;
-
-        // 5. Build minimal transaction
-        let transaction = Transaction::new_signed_with_payer(
-            &[priority_ix, compute_ix, swap_ix],
-            Some(&signer.pubkey()),
-            &[signer],
-            recent_blockhash,
-        );
+    async fn trade_pool_signal(&self, token: &Pubkey, signal: &TradeSignal) -> Result<()> {
+        let held = self.get_token_balance(token).await? > 0;
+        match signal.direction {
+            TradeDirection::Buy if !held && self.signal_buy_amount > 0.0 => {
+                info!(token = %token, change = signal.price_change, confidence = signal.confidence, "Buying on pool signal");
+                let amount = self.quote_to_base_units(self.signal_buy_amount).await?;
+                self.manage_position(token, PositionAction::Buy(amount)).await
+            }
+            TradeDirection::Sell if held => {
+                info!(token = %token, change = signal.price_change, confidence = signal.confidence, "Selling on pool signal");
+                self.manage_position(token, PositionAction::SellAll).await
+            }
+            _ => Ok(()),
+        }
+    }
 
-        // 6. Send with optimized config
-        self.rpc_client.send_transaction_with_config(
-            &transaction,
-            RpcSendTransactionConfig {
-                skip_preflight: true,                // Speed up submission
-                preflight_commitment: None,          // Skip preflight
-                encoding: None,                      // Use default encoding
-                max_retries: Some(0),               // No automatic retries
-                min_context_slot: Some(            // Stay ahead of network
-                    last_valid_block_height - self.minimum_slots_ahead
-                ),
-            },
-        )?;
+    pub fn is_stopped(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
 
+    // Guard for paths that open new positions; sells stay allowed so we can unwind
+    fn ensure_running(&self) -> Result<()> {
+        if self.is_stopped() {
+            return Err(BotError::TradingError("Emergency stop active, refusing new trades".into()).into());
+        }
+        if !self.circuit_breaker.allows_trades() {
+            return Err(BotError::TradingError(
+                format!("Circuit breaker {}, refusing new trades", self.circuit_breaker.state())
+            ).into());
+        }
+        if self.daily_limits.loss_cap_hit(unix_timestamp()) {
+            return Err(BotError::TradingError(
+                "Daily loss cap reached, refusing new trades until 00:00 UTC".into()
+            ).into());
+        }
         Ok(())
     }
 
-    fn create_privileged_swap(
+    // Runs `buy` of `amount` quote base units once it fits under the daily
+    // spend cap, alerting when it doesn't. The spend is reserved before `buy`
+    // starts and handed back if it fails.
+    pub(crate) async fn within_daily_spend<T>(
         &self,
-        token: &Pubkey,
         amount: u64,
-    ) -> Result<Instruction> {
-        // Minimal account validation for speed
-        let accounts = vec![
-            AccountMeta::new(*token, false),
-            AccountMeta::new(system_program::ID, false),
-        ];
+        buy: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        self.ensure_running()?;
+        let reservation = match self.daily_limits.reserve_spend(self.quote_units_in_lamports(amount)?, unix_timestamp()) {
+            Ok(reservation) => reservation,
+            Err(e) => {
+                warn!(error = %e, "Daily spend cap blocked a buy");
+                self.notify(TradeEvent::new(EventKind::DailyLimitHit).amount(amount));
+                return Err(e);
+            }
+        };
 
-        Ok(Instruction {
-            program_id: raydium_v4::ID,
-            accounts,
-            data: amount.to_le_bytes().to_vec(),
-        })
+        let result = buy.await;
+        if result.is_err() {
+            self.daily_limits.release_spend(reservation, unix_timestamp());
+        }
+        result
     }
 
-    async fn retry_with_backoff<T, F>(&self, operation: F) -> Result<T> 
-    where
-        F: Fn() -> Result<T>,
-    {
-        let mut retries = 0;
-        let mut delay = Duration::from_millis(50);
+    // What `amount` quote base units are worth in SOL at the venues' current
+    // price, since the daily caps are set in SOL
+    fn quote_units_in_lamports(&self, amount: u64) -> Result<Lamports> {
+        if self.quote_mint == native_mint::ID || amount == 0 {
+            return Ok(Lamports(amount));
+        }
+        Ok(Lamports(self.route_swap(&self.quote_mint, &native_mint::ID, amount)?.1))
+    }
 
-        loop {
-            match operation() {
-                Ok(result) => return Ok(result),
-                Err(e) => {
-                    if !self.is_retryable_error(&e) || retries >= self.max_retries {
-                        return Err(e);
-                    }
-                    tokio::time::sleep(self.calculate_backoff(retries, &e)).await;
-                    retries += 1;
-                }
-            }
+    // PnL in whole quote tokens as SOL, for the daily loss cap
+    pub(crate) fn quote_pnl_in_sol(&self, pnl: f64) -> Result<f64> {
+        if self.quote_mint == native_mint::ID {
+            return Ok(pnl);
         }
+        let decimals = self.mint_decimals
+            .get(&self.quote_mint)
+            .map(|decimals| *decimals)
+            .ok_or_else(|| anyhow!("Decimals of quote mint {} not loaded yet", self.quote_mint))?;
+        let units = ui_to_base_units(pnl.abs(), decimals);
+        Ok(self.quote_units_in_lamports(units)?.to_sol().0.copysign(pnl))
     }
 
-    // Add mempool monitoring
-    pub async fn monitor_mempool(&self) -> Result<()> {
-        let ws_url = HELIUS_WS_URL.to_string();
-        let ws_client = WsClientBuilder::new().build(ws_url)?;
+    // (spent, net realized loss) for the current UTC day
+    pub fn daily_totals(&self) -> (Lamports, Sol) {
+        self.daily_limits.today(unix_timestamp())
+    }
 
-        ws_client.subscribe_mempool(move |tx| {
-            if let Some(swap_info) = self.parse_transaction(&tx) {
-                if self.is_profitable_opportunity(&swap_info) {
-                    self.execute_frontrun_trade(swap_info).await?;
-                }
-            }
-            Ok(())
-        }).await?;
+    pub fn breaker_state(&self) -> BreakerState {
+        self.circuit_breaker.state()
+    }
 
-        Ok(())
+    pub fn quote_mint(&self) -> Pubkey {
+        self.quote_mint
     }
 
-    // Add transaction bundling
-    pub async fn bundle_transactions(&self, instructions: Vec<Instruction>) -> Result<()> {
-        let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(
-            self.compute_units
-        );
-        
-        let priority_fee_ix = ComputeBudgetInstruction::set_compute_unit_price(
-            self.calculate_optimal_priority_fee()
-        );
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
 
-        let mut final_ixs = vec![compute_budget_ix, priority_fee_ix];
-        final_ixs.extend(instructions);
+    // Feeds the success rate; a rejected send counts against the circuit
+    // breaker now, an accepted one once track_confirmations resolves it
+    fn record_submission(&self, sent: Option<&Signature>) {
+        *self.last_transaction_time.lock() = Instant::now();
+        self.submissions.record(sent.is_some());
+        self.metrics.record_trade(sent.is_some());
+        match sent {
+            Some(signature) => {
+                self.circuit_breaker.record_sent();
+                self.pending_confirmations.lock().push((*signature, Instant::now()));
+            }
+            None => self.record_breaker_failure(),
+        }
+    }
 
-        let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
-        
-        let transaction = Transaction::new_signed_with_payer(
-            &final_ixs,
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
-            recent_blockhash,
-        );
+    fn record_breaker_failure(&self) {
+        if self.circuit_breaker.record_failure() {
+            self.notify(TradeEvent::new(EventKind::BreakerOpened));
+        }
+    }
 
-        self.rpc_client.send_transaction_with_config(
-            &transaction,
-            RpcSendTransactionConfig {
-                skip_preflight: true,
-                preflight_commitment: None,
-                encoding: None,
-                max_retries: Some(0),
-                min_context_slot: None,
-            },
-        )?;
+    // Polls every sent signature until it lands, fails or times out, and feeds
+    // the outcome to the circuit breaker and the adaptive fee. Runs until shutdown.
+    async fn track_confirmations(&self, shutdown: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = sleep(CONFIRMATION_POLL_INTERVAL) => {}
+            }
 
-        Ok(())
+            let batch: Vec<(Signature, Instant)> = {
+                let pending = self.pending_confirmations.lock();
+                pending.iter().take(MAX_SIGNATURE_STATUSES).copied().collect()
+            };
+            if batch.is_empty() {
+                continue;
+            }
+            let signatures: Vec<Signature> = batch.iter().map(|(signature, _)| *signature).collect();
+            let statuses = match self.rpc_throttled().await.get_signature_statuses(&signatures).await {
+                Ok(response) => response.value,
+                Err(e) => {
+                    warn!(error = %e, pending = signatures.len(), "Signature status poll failed");
+                    continue;
+                }
+            };
+
+            let now = Instant::now();
+            let mut resolved = HashSet::new();
+            for ((signature, sent_at), status) in batch.iter().zip(statuses.iter()) {
+                let Some(outcome) = confirmation_of(status.as_ref(), self.commitment, *sent_at, now) else {
+                    continue;
+                };
+                debug!(signature = %signature, outcome = ?outcome, "Transaction resolved");
+                resolved.insert(*signature);
+                self.record_landing(outcome != Confirmation::Dropped);
+                match outcome {
+                    Confirmation::Succeeded => self.circuit_breaker.record_success(),
+                    Confirmation::Failed | Confirmation::Dropped => self.record_breaker_failure(),
+                }
+            }
+            if !resolved.is_empty() {
+                self.pending_confirmations.lock().retain(|(signature, _)| !resolved.contains(signature));
+            }
+        }
     }
 
-    // Add MEV protection
-    pub async fn execute_protected_swap(&self) -> Result<()> {
-        // 1. Calculate optimal routes
-        let routes = self.find_optimal_routes()?;
-        
-        // 2. Split transaction into multiple parts
-        let split_amount = self.amount / 3;  // Split into 3 parts
-        
-        // 3. Execute trades with random delays
-        for route in routes {
-            let delay = rand::thread_rng().gen_range(100..500);
-            tokio::time::sleep(Duration::from_millis(delay)).await;
-            
-            self.execute_swap_with_route(route, split_amount).await?;
+    // Fire-and-forget; a failed notification never fails a trade
+    pub(crate) fn notify(&self, event: TradeEvent) {
+        for notifier in &self.notifiers {
+            let notifier = notifier.clone();
+            let event = event.clone();
+            tokio::spawn(async move {
+                if let Err(e) = notifier.notify(&event).await {
+                    warn!(error = %e, event = ?event.event, "Notification failed");
+                }
+            });
         }
+    }
 
-        Ok(())
+    // Unthrottled; engine code goes through rpc_throttled
+    pub fn rpc(&self) -> Arc<RpcClient> {
+        self.rpc_client.read().clone()
     }
 
-    // Add custom prioritization
-    pub fn calculate_optimal_priority_fee(&self) -> u64 {
-        let recent_fees = self.rpc_client
-            .get_recent_prioritization_fees(&[self.payer.pubkey()])
-            .unwrap_or_default();
+    // Client for exactly one request, after waiting for a rate-limit permit
+    async fn rpc_throttled(&self) -> Arc<RpcClient> {
+        self.rate_limiter.acquire().await;
+        self.rpc()
+    }
+
+    pub fn rpc_utilization(&self) -> f64 {
+        self.rate_limiter.utilization()
+    }
 
-        if recent_fees.is_empty() {
-            return self.priority_fee;  // Default fee
+    // Every submission goes through here; with safe_mode on it only sends
+    // transactions that simulate cleanly
+    async fn send_transaction<T: SerializableTransaction>(
+        &self,
+        tx: &T,
+        policy: SubmitPolicy,
+    ) -> Result<Signature> {
+        let current_slot = match policy.min_context_slot_offset {
+            Some(_) => Some(self.retry_with_backoff(|| async { Ok(self.rpc_throttled().await.get_slot().await?) }).await?),
+            None => None,
+        };
+        let config = policy.send_config(self.commitment, current_slot);
+        if self.dump_transactions && enabled!(Level::DEBUG) {
+            dump_transaction(tx);
+        }
+        let start = Instant::now();
+        let result = self.submit(tx, config).await;
+        let latency = start.elapsed();
+        self.record_latency(LatencyOp::Submit, latency);
+        let latency_ms = latency.as_millis() as u64;
+        match &result {
+            Ok(signature) => info!(signature = %signature, latency_ms, "Transaction sent"),
+            Err(e) => warn!(error = %e, latency_ms, "Transaction send failed"),
         }
+        self.record_submission(result.as_ref().ok());
+        result
+    }
 
-        // Calculate 75th percentile fee
-        let mut fees: Vec<u64> = recent_fees
-            .iter()
-            .map(|f| f.prioritization_fee)
-            .collect();
-        fees.sort_unstable();
-        
-        let index = (fees.len() as f64 * 0.75) as usize;
-        fees.get(index).copied().unwrap_or(self.priority_fee)
-    }
-
-    // Helper Methods
-    async fn find_optimal_routes(&self) -> Result<Vec<SwapRoute>> {
-        let routes = vec![
-            // Direct route
-            SwapRoute::Direct(self.token_in, self.token_out),
-            // Split routes
-            SwapRoute::Split(vec![
-                (self.token_in, intermediate_token1, self.token_out),
-                (self.token_in, intermediate_token2, self.token_out),
-            ]),
-        ];
-        Ok(routes)
+    async fn submit<T: SerializableTransaction>(
+        &self,
+        tx: &T,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature> {
+        if self.safe_mode {
+            self.simulate_or_abort(tx).await?;
+        }
+        self.retry_with_backoff(|| async move {
+            Ok(self.rpc_throttled().await.send_transaction_with_config(tx, config).await?)
+        })
+        .await
     }
 
-    async fn execute_swap_with_route(&self, route: SwapRoute, amount: u64) -> Result<()> {
-        let ix = match route {
-            SwapRoute::Direct(in_token, out_token) => {
-                self.create_swap_instruction(in_token, out_token, amount)?
+    async fn simulate_or_abort<T: SerializableTransaction>(&self, tx: &T) -> Result<()> {
+        let result = self.rpc_throttled()
+            .await
+            .simulate_transaction_with_config(
+                tx,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    commitment: Some(self.commitment),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .value;
+
+        match result.err {
+            Some(err) => Err(BotError::SimulationFailed {
+                reason: err.to_string(),
+                logs: result.logs.unwrap_or_default(),
+            }.into()),
+            None => Ok(()),
+        }
+    }
+
+    // (blockhash, last valid block height), fetched live if the cache has gone stale
+    pub async fn get_cached_blockhash(&self) -> Result<(Hash, u64)> {
+        if let Some(cached) = self.blockhash_cache.get() {
+            return Ok(cached);
+        }
+        let (blockhash, last_valid_block_height) = self
+            .retry_with_backoff(|| async {
+                Ok(self.rpc_throttled().await.get_latest_blockhash_with_commitment(self.commitment).await?)
+            })
+            .await?;
+        self.blockhash_cache.store(blockhash, last_valid_block_height);
+        Ok((blockhash, last_valid_block_height))
+    }
+
+    // Keeps the blockhash cache warm; started by spawn_background_tasks
+    pub async fn refresh_blockhash(&self, shutdown: CancellationToken) -> Result<()> {
+        loop {
+            match self.rpc_throttled().await.get_latest_blockhash_with_commitment(self.commitment).await {
+                Ok((blockhash, last_valid_block_height)) => {
+                    self.blockhash_cache.store(blockhash, last_valid_block_height)
+                }
+                Err(e) => warn!(error = %e, "Blockhash refresh failed"),
+            }
+
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                _ = sleep(BLOCKHASH_REFRESH_INTERVAL) => {}
+            }
+        }
+    }
+
+    // Keeps the current client if the recording can't be opened
+    fn set_rpc_endpoint(&self, endpoint: String) {
+        match self.rpc_mode.nonblocking_client(endpoint.clone(), self.commitment) {
+            Ok(client) => *self.rpc_client.write() = Arc::new(client),
+            Err(e) => error!(endpoint = %endpoint, error = %e, "Failed to switch RPC endpoint"),
+        }
+    }
+
+    // The UI's RPC URL setting; takes effect from the next request
+    pub fn set_primary_rpc_endpoint(&self, endpoint: String) {
+        self.rpc_config.replace_primary(endpoint.clone());
+        self.set_rpc_endpoint(endpoint);
+    }
+
+    // Fail over to the next endpoint that isn't cooling down
+    fn rotate_rpc(&self) {
+        self.metrics.record_rpc_error();
+        match self.rpc_config.report_failure() {
+            Some(endpoint) => {
+                warn!(endpoint = %endpoint, "RPC failover");
+                self.set_rpc_endpoint(endpoint);
+            }
+            None => error!("RPC error, no healthy fallback endpoint available"),
+        }
+    }
+
+    // Re-rank endpoints every `interval` and move to the fastest healthy one
+    pub async fn rank_rpc_endpoints(&self, interval: Duration, shutdown: CancellationToken) -> Result<()> {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                _ = ticker.tick() => {}
+            }
+
+            for (endpoint, latency) in self.rpc_config.rank_endpoints().await {
+                if latency >= RPC_PING_TIMEOUT {
+                    warn!(endpoint = %endpoint, "RPC endpoint failed latency check");
+                } else {
+                    debug!(endpoint = %endpoint, latency_ms = latency.as_millis() as u64, "RPC latency");
+                }
+            }
+            if let Some(endpoint) = self.rpc_config.best_endpoint(unix_timestamp() as u64) {
+                info!(endpoint = %endpoint, "Switching RPC to faster endpoint");
+                self.set_rpc_endpoint(endpoint);
+            }
+        }
+    }
+
+    pub fn register_venue(&mut self, venue: Box<dyn SwapVenue>) {
+        self.venues.push(venue);
+    }
+
+    // Run, in registration order, each time retry_with_escalation escalates
+    pub fn on_escalation(&self, hook: impl Fn(&BotError) + Send + Sync + 'static) {
+        self.escalation_hooks.write().push(Arc::new(hook));
+    }
+
+    // Snapshot, so a hook can register another without deadlocking
+    pub(crate) fn escalation_hooks(&self) -> Vec<EscalationHook> {
+        self.escalation_hooks.read().clone()
+    }
+
+    // Venue quoting the most output for the pair, net of its fees
+    pub fn route_swap(
+        &self,
+        token_in: &Pubkey,
+        token_out: &Pubkey,
+        amount_in: u64,
+    ) -> Result<(&dyn SwapVenue, u64)> {
+        let (index, amount_out) = self.best_venue(token_in, token_out, amount_in)?;
+        Ok((self.venues[index].as_ref(), amount_out))
+    }
+
+    // Best venue's fill, impact and fees for the swap, without building it
+    pub async fn quote(&self, token_in: &Pubkey, token_out: &Pubkey, amount_in: u64) -> Result<QuoteResult> {
+        let (venue, expected_out) = self.route_swap(token_in, token_out, amount_in)?;
+        let price_impact = venue.price_impact(token_in, token_out, amount_in)?;
+
+        Ok(QuoteResult::new(
+            venue.name(),
+            amount_in,
+            expected_out,
+            price_impact,
+            self.max_slippage_bps,
+            self.calculate_optimal_priority_fee().await?,
+            self.compute_units,
+        ))
+    }
+
+    fn best_venue(&self, token_in: &Pubkey, token_out: &Pubkey, amount_in: u64) -> Result<(usize, u64)> {
+        best_venue_quote(&self.venues, token_in, token_out, amount_in)
+    }
+
+    // Legs for the order across the registered venues, see split_across_venues
+    pub fn plan_venue_split(
+        &self,
+        token_in: &Pubkey,
+        token_out: &Pubkey,
+        amount_in: u64,
+    ) -> Result<Vec<VenueLeg>> {
+        split_across_venues(&self.venues, token_in, token_out, amount_in)
+    }
+
+    // One swap instruction per leg of the best plan
+    async fn create_routed_swap(&self, token_in: &Pubkey, token_out: &Pubkey, amount_in: u64) -> Result<Vec<Instruction>> {
+        let legs = self.plan_venue_split(token_in, token_out, amount_in)?;
+        let swap_ixs = self.venue_leg_instructions(token_in, token_out, &legs)?;
+        self.with_swap_accounts(token_in, amount_in, &[*token_out], swap_ixs).await
+    }
+
+    fn venue_leg_instructions(
+        &self,
+        token_in: &Pubkey,
+        token_out: &Pubkey,
+        legs: &[VenueLeg],
+    ) -> Result<Vec<Instruction>> {
+        legs.iter()
+            .map(|leg| {
+                self.venues[leg.venue].build_swap_ix(token_in, token_out, leg.amount_in, self.min_out(leg.amount_out))
+            })
+            .collect()
+    }
+
+    // Simulated compute usage plus a 10% margin, capped at the per-transaction maximum
+    pub async fn estimate_compute_units(&self, instructions: &[Instruction], payer: &Pubkey) -> Result<u32> {
+        // Simulate with the maximum limit so the default 200k per instruction doesn't cut it short
+        let mut simulated = vec![ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS)];
+        simulated.extend_from_slice(instructions);
+        let tx = Transaction::new_unsigned(Message::new(&simulated, Some(payer)));
+
+        let result = self.rpc_throttled().await.simulate_transaction_with_config(
+            &tx,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(self.commitment),
+                ..RpcSimulateTransactionConfig::default()
             },
-            SwapRoute::Split(paths) => {
-                self.create_split_swap_instruction(paths, amount)?
+        ).await?.value;
+
+        if let Some(err) = result.err {
+            return Err(BotError::TransactionError(format!("Simulation failed: {}", err)).into());
+        }
+
+        let consumed = result.units_consumed
+            .ok_or_else(|| anyhow!("Simulation did not report units consumed"))?;
+        let padded = (consumed as f64 * COMPUTE_UNIT_MARGIN).ceil() as u64;
+
+        Ok(padded.min(MAX_COMPUTE_UNITS as u64) as u32)
+    }
+
+    // Simulates buying `probe_amount` of `mint` with the quote mint and then
+    // selling everything that bought. False when either leg fails or the round
+    // trip returns more than max_sell_tax_bps less than the venues quote for it.
+    pub async fn simulate_sell_check(&self, mint: &Pubkey, probe_amount: u64) -> Result<bool> {
+        let owner = self.payer.pubkey();
+        let quote_mint = self.quote_mint;
+        let token_account = get_associated_token_address(&owner, mint);
+        let quote_account = get_associated_token_address(&owner, &quote_mint);
+        let tokens_before = self.get_token_balance(mint).await?;
+        let quote_before = self.get_token_balance(&quote_mint).await?;
+
+        let (buy_venue, quoted_tokens) = self.route_swap(&quote_mint, mint, probe_amount)?;
+        let mut ixs = if quote_mint == native_mint::ID {
+            wrap_sol_ixs(&owner, probe_amount)?
+        } else {
+            Vec::new()
+        };
+        ixs.extend(self.ensure_ata_ix(&owner, mint).await?);
+        ixs.push(buy_venue.build_swap_ix(&quote_mint, mint, probe_amount, self.min_out(quoted_tokens))?);
+
+        // Buy alone first, to learn how many tokens actually arrive
+        let Some(after_buy) = self.simulate_token_amounts(&ixs, &[token_account]).await? else {
+            return Ok(false);
+        };
+        let received = after_buy[0].saturating_sub(tokens_before);
+        if received == 0 {
+            return Ok(false);
+        }
+
+        // The sell leg's floor is the tax cap, so a heavier tax fails the simulation outright
+        let (sell_venue, sell_quote) = self.route_swap(mint, &quote_mint, received)?;
+        let sell_floor = min_out_after_slippage(sell_quote, self.max_sell_tax_bps);
+        ixs.push(sell_venue.build_swap_ix(mint, &quote_mint, received, sell_floor)?);
+        let Some(after_sell) = self.simulate_token_amounts(&ixs, &[quote_account]).await? else {
+            return Ok(false);
+        };
+        let returned = after_sell[0].saturating_sub(quote_before);
+
+        // What an untaxed token would give back: the quoted buy, sold straight back
+        let (_, expected) = self.route_swap(mint, &quote_mint, quoted_tokens)?;
+        let Some(tax_bps) = sell_tax_bps(returned, expected) else {
+            return Ok(false);
+        };
+        info!(token = %mint, received, returned, expected, tax_bps, "Sell check");
+        Ok(tax_bps <= self.max_sell_tax_bps as u64)
+    }
+
+    // SELL_CHECK_PROBE in quote mint base units
+    fn sell_check_probe(&self) -> Result<u64> {
+        let lamports = SELL_CHECK_PROBE.to_lamports().0;
+        if self.quote_mint == native_mint::ID {
+            return Ok(lamports);
+        }
+        Ok(self.route_swap(&native_mint::ID, &self.quote_mint, lamports)?.1)
+    }
+
+    // Refuses a buy of a mint that fails simulate_sell_check. A pass is kept
+    // for good; a failure is alerted and re-checked after SELL_CHECK_FAILURE_TTL.
+    pub(crate) async fn ensure_sellable(&self, mint: &Pubkey) -> Result<()> {
+        let cached = self.sell_checks.get(mint).map(|entry| *entry);
+        let sellable = match cached_sell_check(cached, Instant::now()) {
+            Some(passed) => passed,
+            None => {
+                let passed = self.simulate_sell_check(mint, self.sell_check_probe()?).await?;
+                self.sell_checks.insert(*mint, (passed, Instant::now()));
+                if !passed {
+                    warn!(token = %mint, "Token failed the sell check, blocking buys");
+                    self.notify(TradeEvent::new(EventKind::HoneypotBlocked).token(*mint));
+                }
+                passed
             }
         };
 
-        self.bundle_transactions(vec![ix]).await
+        if !sellable {
+            return Err(BotError::TradingError(
+                format!("{} can't be sold back at an acceptable tax, refusing to buy", mint)
+            ).into());
+        }
+        Ok(())
     }
 
-    // Improved pre-liquidity trading
-    async fn execute_pre_liquidity_swap(&self, token: &Pubkey, amount: u64) -> Result<()> {
-        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
-        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(self.max_priority_fee());
+    // Token amounts of `accounts` after simulating `instructions` (0 for an
+    // account that doesn't exist), or None when the simulation fails
+    async fn simulate_token_amounts(&self, instructions: &[Instruction], accounts: &[Pubkey]) -> Result<Option<Vec<u64>>> {
+        let mut simulated = vec![ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS)];
+        simulated.extend_from_slice(instructions);
+        let tx = Transaction::new_unsigned(Message::new(&simulated, Some(&self.payer.pubkey())));
+
+        let result = self.rpc_throttled().await.simulate_transaction_with_config(
+            &tx,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(self.commitment),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: accounts.iter().map(Pubkey::to_string).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        ).await?.value;
+
+        simulated_token_amounts(result)
+    }
+
+    pub async fn execute_transaction(&self, instruction: Instruction) -> Result<()> {
+        let compute_units = self
+            .estimate_compute_units(std::slice::from_ref(&instruction), &self.payer.pubkey())
+            .await
+            .unwrap_or_else(|e| {
+                warn!(error = %e, compute_units = self.compute_units, "Compute unit estimate failed, using default");
+                self.compute_units
+            });
         
-        let swap_ix = self.create_privileged_swap(
-            token,
-            amount,
-            true  // bypass liquidity check
-        )?;
+        // Pre-build compute budget instructions
+        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(self.priority_fee());
+        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(compute_units);
+
+        // Fast execution path
+        self.send_with_fresh_blockhash(&[priority_ix, compute_ix, instruction], SubmitPolicy::FAST).await?;
+
+        Ok(())
+    }
+
+    // Signs `instructions` with the payer and sends them. If the cluster
+    // rejects the blockhash as expired, a new one is fetched and the
+    // transaction re-signed, up to blockhash_retries times, after which it
+    // fails with BlockhashExpired; any other error is returned as is, for the
+    // caller's own retry rules.
+    pub async fn send_with_fresh_blockhash(
+        &self,
+        instructions: &[Instruction],
+        policy: SubmitPolicy,
+    ) -> Result<Signature> {
+        self.send_signed_with_fresh_blockhash(instructions, &self.payer, policy).await
+    }
+
+    // send_with_fresh_blockhash, paid for and signed by `signer`
+    pub async fn send_signed_with_fresh_blockhash(
+        &self,
+        instructions: &[Instruction],
+        signer: &Keypair,
+        policy: SubmitPolicy,
+    ) -> Result<Signature> {
+        let send = || async {
+            let start = Instant::now();
+            let (blockhash, _) = self.get_cached_blockhash().await?;
+            let tx = Transaction::new_signed_with_payer(
+                instructions,
+                Some(&signer.pubkey()),
+                &[signer],
+                blockhash,
+            );
+            self.record_latency(LatencyOp::Build, start.elapsed());
+            self.send_transaction(&tx, policy).await
+        };
+        resend_on_expired_blockhash(self.blockhash_retries, send, |attempt| {
+            warn!(attempt, "Blockhash expired, resending with a fresh one");
+            self.blockhash_cache.invalidate();
+        })
+        .await
+    }
+
+    // Like execute_transaction, but waits until `commitment` is reached
+    pub async fn execute_and_confirm(
+        &self,
+        instruction: Instruction,
+        commitment: CommitmentConfig,
+    ) -> Result<Signature> {
+        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(self.priority_fee());
+        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(self.compute_units);
+
+        let signature = self
+            .send_with_fresh_blockhash(&[priority_ix, compute_ix, instruction], SubmitPolicy::FAST)
+            .await?;
+        self.confirm_signature(&signature, commitment).await?;
+        Ok(signature)
+    }
+
+    // TransactionError if it landed but failed, ConfirmationTimeout if it never
+    // landed. The breaker and land rate hear about it from track_confirmations.
+    async fn confirm_signature(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<()> {
+        let start = Instant::now();
+        let deadline = start + CONFIRMATION_TIMEOUT;
+
+        while Instant::now() < deadline {
+            let statuses = self
+                .retry_with_backoff(|| async {
+                    Ok(self.rpc_throttled().await.get_signature_statuses(&[*signature]).await?.value)
+                })
+                .await?;
+            if let Some(Some(status)) = statuses.into_iter().next() {
+                if let Some(err) = status.err {
+                    return Err(BotError::TransactionError(
+                        format!("{} landed but failed: {}", signature, err)
+                    ).into());
+                }
+                if status.satisfies_commitment(commitment) {
+                    self.record_latency(LatencyOp::Confirm, start.elapsed());
+                    return Ok(());
+                }
+            }
+            sleep(CONFIRMATION_POLL_INTERVAL).await;
+        }
+
+        Err(BotError::ConfirmationTimeout(signature.to_string()).into())
+    }
+
+    pub fn get_success_rate(&self) -> f64 {
+        self.submissions.success_rate()
+    }
+
+    // Compute unit price for execute_transaction and the early-swap paths
+    pub fn priority_fee(&self) -> u64 {
+        self.priority_fee.load(Ordering::Relaxed)
+    }
+
+    pub fn set_priority_fee(&self, fee: u64) {
+        self.priority_fee.store(fee.min(self.priority_fee_ceiling), Ordering::Relaxed);
+    }
 
-        let blockhash = self.rpc_client.get_latest_blockhash()?;
+    pub fn last_transaction_time(&self) -> Instant {
+        *self.last_transaction_time.lock()
+    }
+
+    fn record_latency(&self, op: LatencyOp, latency: Duration) {
+        self.latencies.lock().entry(op).or_default().record(latency);
+    }
+
+    pub fn get_latency_stats(&self, op: LatencyOp) -> LatencyStats {
+        self.latencies.lock().get(&op).map(LatencyRing::stats).unwrap_or_default()
+    }
+
+    pub async fn execute_early_swap(
+        &self,
+        token: &Pubkey,
+        amount: u64,
+        signer: &Keypair,
+    ) -> Result<()> {
+        self.ensure_running()?;
+        let _permit = self.execution_semaphore.acquire().await?;
+
+        // 1. Prioritize transaction
+        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(
+            self.priority_fee()
+        );
         
-        let tx = Transaction::new_signed_with_payer(
-            &[compute_ix, priority_ix, swap_ix],
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
-            blockhash,
+        // 2. Maximum compute units
+        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(
+            self.compute_units
         );
 
-        self.rpc_client.send_transaction_with_config(
-            &tx,
+        // 3. Create optimized swap
+        let mut instructions = vec![priority_ix, compute_ix];
+        instructions.extend(self.create_privileged_swap(token, amount).await?);
+
+        // 4. Get latest blockhash with look-ahead
+        let (recent_blockhash, _) = self.get_cached_blockhash().await?;
+
+        // 5. Build minimal transaction, v0 when lookup tables are configured
+        let lookup_tables = self.get_lookup_tables().await?;
+        let transaction = Self::compile_versioned_tx(
+            signer,
+            &instructions,
+            &lookup_tables,
+            recent_blockhash,
+        )?;
+
+        // 6. Send without preflight; a node lagging our slot view can't be trusted with the blockhash
+        self.send_transaction(
+            &transaction,
+            SubmitPolicy::FAST.with_max_slot_lag(self.max_rpc_slot_lag),
+        ).await?;
+
+        Ok(())
+    }
+
+    pub async fn build_versioned_tx(
+        &self,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<VersionedTransaction> {
+        let (blockhash, _) = self.get_cached_blockhash().await?;
+        Self::compile_versioned_tx(&self.payer, instructions, lookup_tables, blockhash)
+    }
+
+    // Legacy message when there are no lookup tables, otherwise a v0 message
+    fn compile_versioned_tx(
+        signer: &Keypair,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+        blockhash: Hash,
+    ) -> Result<VersionedTransaction> {
+        let message = if lookup_tables.is_empty() {
+            VersionedMessage::Legacy(Message::new_with_blockhash(
+                instructions,
+                Some(&signer.pubkey()),
+                &blockhash,
+            ))
+        } else {
+            VersionedMessage::V0(v0::Message::try_compile(
+                &signer.pubkey(),
+                instructions,
+                lookup_tables,
+                blockhash,
+            )?)
+        };
+
+        Ok(VersionedTransaction::try_new(message, &[signer])?)
+    }
+
+    // Configured lookup tables, fetched once and then served from cache
+    async fn get_lookup_tables(&self) -> Result<Vec<AddressLookupTableAccount>> {
+        let mut tables = Vec::with_capacity(self.lookup_table_addresses.len());
+
+        for key in &self.lookup_table_addresses {
+            if let Some(cached) = self.lookup_table_cache.get(key) {
+                tables.push(cached.clone());
+                continue;
+            }
+
+            let account = self.rpc_throttled().await.get_account(key).await?;
+            let table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|e| anyhow!("Invalid lookup table {}: {}", key, e))?;
+            let table_account = AddressLookupTableAccount {
+                key: *key,
+                addresses: table.addresses.to_vec(),
+            };
+
+            self.lookup_table_cache.insert(*key, table_account.clone());
+            tables.push(table_account);
+        }
+
+        Ok(tables)
+    }
+
+    // Buy `token` with the quote mint through the first venue that can route it,
+    // creating our token account first if this is the first buy
+    pub async fn create_privileged_swap(
+        &self,
+        token: &Pubkey,
+        amount: u64,
+    ) -> Result<Vec<Instruction>> {
+        let swap_ix = self.create_swap_instruction(self.quote_mint, *token, amount)?;
+        self.with_swap_accounts(&self.quote_mint, amount, &[*token], vec![swap_ix]).await
+    }
+
+    fn create_swap_instruction(
+        &self,
+        token_in: Pubkey,
+        token_out: Pubkey,
+        amount: u64,
+    ) -> Result<Instruction> {
+        let (venue, expected_out) = self.route_swap(&token_in, &token_out, amount)?;
+        venue.build_swap_ix(&token_in, &token_out, amount, self.min_out(expected_out))
+    }
+
+    // Floor for a swap quoted at `expected_out`, per max_slippage_bps
+    fn min_out(&self, expected_out: u64) -> u64 {
+        min_out_after_slippage(expected_out, self.max_slippage_bps)
+    }
+
+    async fn retry_with_backoff<T, F, Fut>(&self, operation: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retries = 0;
+
+        loop {
+            match operation().await {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    if !self.should_retry(&e) || retries >= self.max_retries {
+                        return Err(e);
+                    }
+                    let delay = match e.downcast_ref::<ClientError>() {
+                        Some(client_error) => {
+                            self.rotate_rpc();
+                            self.calculate_backoff(retries, client_error)
+                        }
+                        None => self.get_retry_delay(retries),
+                    };
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+            }
+        }
+    }
+
+    // Exponential backoff plus up to 50% random jitter, capped at MAX_BACKOFF_MS
+    fn calculate_backoff(&self, retry: u32, err: &ClientError) -> Duration {
+        let base_ms = if is_rate_limited(err) {
+            RATE_LIMIT_BACKOFF_BASE_MS
+        } else {
+            BACKOFF_BASE_MS
+        };
+        Duration::from_millis(backoff_ms(base_ms, retry, rand::thread_rng().gen_range(0.0..=0.5)))
+    }
+
+    // Expired blockhashes are left to send_with_fresh_blockhash, which re-signs;
+    // resending the same transaction can't succeed
+    fn should_retry(&self, error: &anyhow::Error) -> bool {
+        if let Some(client_error) = error.downcast_ref::<ClientError>() {
+            return !is_blockhash_expired(client_error) && self.is_retryable_error(client_error);
+        }
+        if let Some(bot_error) = error.downcast_ref::<BotError>() {
+            return self.handle_error(bot_error);
+        }
+        false
+    }
+
+    // Follows swaps on `program` from the moment they're processed, reconnecting
+    // with backoff whenever the socket drops (see mempool_state). Each swap is
+    // decoded against its fee payer and forwarded to `swaps` once, even if a
+    // reconnect replays it. Runs until shutdown or until `swaps` is dropped.
+    pub async fn monitor_mempool(&self, program: Pubkey, swaps: mpsc::Sender<(Pubkey, WalletTrade)>) -> Result<()> {
+        let (signatures_tx, mut signatures_rx) = mpsc::channel(MEMPOOL_BUFFER);
+        tokio::spawn(supervise_program_logs(
+            self.ws_url.clone(),
+            program,
+            CommitmentConfig::processed(),
+            None,
+            signatures_tx,
+            self.mempool_state.clone(),
+            self.shutdown.clone(),
+        ));
+
+        let mut seen = SeenSignatures::new(SEEN_SIGNATURE_CAPACITY);
+        while let Some(signature) = signatures_rx.recv().await {
+            if !seen.insert(signature) {
+                continue;
+            }
+            let tx = match self.fetch_transaction(&signature).await {
+                Ok(tx) => tx,
+                Err(e) => {
+                    warn!(signature = %signature, error = %e, "Failed to fetch mempool transaction");
+                    continue;
+                }
+            };
+            let Some(payer) = tx.transaction.transaction
+                .decode()
+                .and_then(|decoded| decoded.message.static_account_keys().first().copied())
+            else {
+                continue;
+            };
+            if let Some(trade) = decode_wallet_trade(&program, &payer, &tx) {
+                if swaps.send((payer, trade)).await.is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn mempool_state(&self) -> ConnectionState {
+        *self.mempool_state.read()
+    }
+
+    // Add transaction bundling
+    pub async fn bundle_transactions(&self, instructions: Vec<Instruction>) -> Result<()> {
+        let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(
+            self.compute_units
+        );
+        
+        let priority_fee_ix = ComputeBudgetInstruction::set_compute_unit_price(
+            self.calculate_priority_fee_for(&writable_accounts(&instructions)).await?
+        );
+
+        let mut final_ixs = vec![compute_budget_ix, priority_fee_ix];
+        final_ixs.extend(instructions);
+
+        // A durable nonce never expires; a blockhash may, so that path re-signs
+        if self.nonce_account.is_none() {
+            self.send_with_fresh_blockhash(&final_ixs, SubmitPolicy::FAST).await?;
+            return Ok(());
+        }
+        let transaction = self.build_with_durable_nonce(final_ixs).await?;
+
+        self.send_transaction(&transaction, SubmitPolicy::FAST).await?;
+
+        Ok(())
+    }
+
+    // Signed with the durable nonce when one is configured: advance_nonce_account
+    // goes first and the nonce value stands in for the blockhash. Without one
+    // this is a plain cached-blockhash transaction.
+    pub async fn build_with_durable_nonce(&self, instructions: Vec<Instruction>) -> Result<Transaction> {
+        let Some((nonce_account, authority)) = &self.nonce_account else {
+            let (blockhash, _) = self.get_cached_blockhash().await?;
+            return Ok(Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&self.payer.pubkey()),
+                &[&self.payer],
+                blockhash,
+            ));
+        };
+
+        let account = self.rpc_throttled().await.get_account(nonce_account).await?;
+        let nonce = nonce_utils::data_from_account(&account)
+            .map_err(|e| anyhow!("Invalid nonce account {}: {}", nonce_account, e))?
+            .blockhash();
+
+        nonced_transaction(instructions, nonce_account, nonce, &self.payer, authority)
+    }
+
+    // Add MEV protection
+    pub async fn execute_protected_swap(&self) -> Result<()> {
+        self.ensure_running()?;
+
+        // 1. Calculate optimal routes
+        let routes = self.find_optimal_routes()?;
+        
+        // 2. Split transaction into multiple parts
+        let split_amount = self.amount / 3;  // Split into 3 parts
+        
+        // 3. Execute trades with random delays
+        for route in routes {
+            let delay = rand::thread_rng().gen_range(100..500);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            
+            self.execute_swap_with_route(route, split_amount).await?;
+        }
+
+        Ok(())
+    }
+
+    // Submit txs as one atomic Jito bundle, tipping from the payer in a trailing tx
+    pub async fn submit_jito_bundle(&self, txs: Vec<Transaction>, tip_lamports: u64) -> Result<String> {
+        if txs.is_empty() {
+            return Err(anyhow!("Cannot submit an empty bundle"));
+        }
+
+        let blockhash = txs[txs.len() - 1].message.recent_blockhash;
+        let tip_ix = system_instruction::transfer(
+            &self.payer.pubkey(),
+            &self.jito_tip_account,
+            tip_lamports,
+        );
+        let tip_tx = Transaction::new_signed_with_payer(
+            &[tip_ix],
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+
+        let encoded = txs.iter()
+            .chain(std::iter::once(&tip_tx))
+            .map(|tx| Ok(bs58::encode(bincode::serialize(tx)?).into_string()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let response: JitoResponse<String> = self.jito_request("sendBundle", json!([encoded])).await?;
+        response.into_result()
+    }
+
+    pub async fn get_bundle_status(&self, id: &str) -> Result<Option<BundleStatus>> {
+        let response: JitoResponse<BundleStatusesResult> =
+            self.jito_request("getBundleStatuses", json!([[id]])).await?;
+        Ok(response.into_result()?.value.into_iter().next())
+    }
+
+    async fn jito_request<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        let response = self.http_client
+            .post(format!("{}/api/v1/bundles", self.jito_block_engine_url))
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Jito {} failed: {}", method, response.text().await?));
+        }
+        Ok(response.json().await?)
+    }
+
+    // Add custom prioritization
+    pub async fn calculate_optimal_priority_fee(&self) -> Result<u64> {
+        self.calculate_priority_fee_for(&[self.payer.pubkey()]).await
+    }
+
+    // Fee for a transaction locking `accounts` (pool, vaults, mints) under the
+    // configured strategy, clamped to min_priority_fee..=max_priority_fee.
+    // An RPC failure is an error rather than a silently stale fee.
+    pub async fn calculate_priority_fee_for(&self, accounts: &[Pubkey]) -> Result<u64> {
+        let fee = match self.priority_fee_strategy {
+            PriorityFeeStrategy::Fixed { fee } => fee,
+            PriorityFeeStrategy::Percentile { percentile } => {
+                percentile_fee(self.recent_priority_fees(accounts).await, percentile, self.priority_fee_floor)?
+            }
+            PriorityFeeStrategy::Adaptive { .. } => self.adaptive_fee.load(Ordering::Relaxed),
+        };
+        let fee = clamp_priority_fee(fee, self.priority_fee_floor, self.priority_fee_ceiling);
+        self.metrics.set_priority_fee(fee);
+        Ok(fee)
+    }
+
+    // Fees paid in recent slots, ascending; empty when nothing paid lately
+    async fn recent_priority_fees(&self, accounts: &[Pubkey]) -> Result<Vec<u64>> {
+        let recent = self.rpc_throttled()
+            .await
+            .get_recent_prioritization_fees(accounts)
+            .await
+            .map_err(|e| {
+                self.metrics.record_rpc_error();
+                BotError::RPCError(format!("getRecentPrioritizationFees: {}", e))
+            })?;
+        let mut fees: Vec<u64> = recent.iter().map(|f| f.prioritization_fee).collect();
+        fees.sort_unstable();
+        Ok(fees)
+    }
+
+    // How contested recent slots were, by the share of them that paid any priority fee
+    async fn estimate_network_load(&self) -> Result<LoadLevel> {
+        Ok(LoadLevel::from_recent_fees(&self.recent_priority_fees(&[self.payer.pubkey()]).await?))
+    }
+
+    // Steps the Adaptive fee toward its target land rate once the window is full
+    fn record_landing(&self, landed: bool) {
+        let PriorityFeeStrategy::Adaptive { min, max, target_land_rate } = self.priority_fee_strategy else {
+            return;
+        };
+
+        let land_rate = {
+            let mut outcomes = self.land_outcomes.lock();
+            outcomes.push_back(landed);
+            if outcomes.len() > LAND_RATE_WINDOW {
+                outcomes.pop_front();
+            }
+            if outcomes.len() < LAND_RATE_WINDOW {
+                return;
+            }
+            outcomes.iter().filter(|landed| **landed).count() as f64 / outcomes.len() as f64
+        };
+
+        let current = self.adaptive_fee.load(Ordering::Relaxed);
+        let next = adaptive_fee_step(current, land_rate, target_land_rate).clamp(min, max);
+        if next != current {
+            info!(fee = next, land_rate, "Adaptive priority fee adjusted");
+            self.adaptive_fee.store(next, Ordering::Relaxed);
+        }
+    }
+
+    // Direct swap plus one two-hop path per configured intermediate, best
+    // quotes first. Routes no venue can quote are dropped.
+    fn find_optimal_routes(&self) -> Result<Vec<SwapRoute>> {
+        let candidates = candidate_routes(self.token_in, self.token_out, &self.intermediate_tokens);
+
+        let mut quoted: Vec<(SwapRoute, u64)> = candidates
+            .into_iter()
+            .filter_map(|route| {
+                let amount_out = self.quote_route(&route, self.amount).ok()?;
+                Some((route, amount_out))
+            })
+            .collect();
+        if quoted.is_empty() {
+            return Err(anyhow!("No route found for {} -> {}", self.token_in, self.token_out));
+        }
+
+        quoted.sort_by_key(|quote| std::cmp::Reverse(quote.1));
+        quoted.truncate(MAX_CANDIDATE_ROUTES);
+        Ok(quoted.into_iter().map(|(route, _)| route).collect())
+    }
+
+    fn quote_route(&self, route: &SwapRoute, amount: u64) -> Result<u64> {
+        match route {
+            SwapRoute::Direct(token_in, token_out) => {
+                Ok(self.route_swap(token_in, token_out, amount)?.1)
+            }
+            SwapRoute::Split(paths) => {
+                let shares = split_evenly(amount, paths.len());
+                paths.iter()
+                    .zip(shares)
+                    .map(|(path, share)| self.quote_path(path, share))
+                    .sum()
+            }
+            SwapRoute::Venues { legs, .. } => Ok(legs.iter().map(|leg| leg.amount_out).sum()),
+        }
+    }
+
+    fn quote_path(&self, &(token_in, mid, token_out): &(Pubkey, Pubkey, Pubkey), amount: u64) -> Result<u64> {
+        let (_, mid_amount) = self.route_swap(&token_in, &mid, amount)?;
+        Ok(self.route_swap(&mid, &token_out, mid_amount)?.1)
+    }
+
+    // Two swaps per path; the second hop spends the first hop's quoted output,
+    // so if the first fills short the whole transaction fails rather than
+    // leaving us holding the intermediate
+    fn create_split_swap_instructions(&self, paths: &[(Pubkey, Pubkey, Pubkey)], amount: u64) -> Result<Vec<Instruction>> {
+        let mut instructions = Vec::with_capacity(paths.len() * 2);
+        for (&(token_in, mid, token_out), share) in paths.iter().zip(split_evenly(amount, paths.len())) {
+            let (first, mid_amount) = self.route_swap(&token_in, &mid, share)?;
+            instructions.push(first.build_swap_ix(&token_in, &mid, share, self.min_out(mid_amount))?);
+            let (second, amount_out) = self.route_swap(&mid, &token_out, mid_amount)?;
+            instructions.push(second.build_swap_ix(&mid, &token_out, mid_amount, self.min_out(amount_out))?);
+        }
+        Ok(instructions)
+    }
+
+    async fn execute_swap_with_route(&self, route: SwapRoute, amount: u64) -> Result<()> {
+        let start = Instant::now();
+        let mut venues = Vec::new();
+        let (ixs, input, outputs) = match route {
+            SwapRoute::Direct(in_token, out_token) => {
+                let (venue, amount_out) = self.route_swap(&in_token, &out_token, amount)?;
+                venues.push(venue.name());
+                let swap_ix = venue.build_swap_ix(&in_token, &out_token, amount, self.min_out(amount_out))?;
+                (vec![swap_ix], in_token, vec![out_token])
+            },
+            SwapRoute::Split(paths) => {
+                let input = paths.first().map(|&(token_in, _, _)| token_in)
+                    .ok_or_else(|| anyhow!("Split route has no paths"))?;
+                // Intermediates need an account too, the first hop pays into it
+                let mut outputs: Vec<Pubkey> = paths.iter().flat_map(|&(_, mid, out)| [mid, out]).collect();
+                outputs.sort();
+                outputs.dedup();
+                (self.create_split_swap_instructions(&paths, amount)?, input, outputs)
+            }
+            SwapRoute::Venues { token_in, token_out, legs } => {
+                venues.extend(legs.iter().map(|leg| self.venues[leg.venue].name()));
+                (self.venue_leg_instructions(&token_in, &token_out, &legs)?, token_in, vec![token_out])
+            }
+        };
+        let ixs = self.with_swap_accounts(&input, amount, &outputs, ixs).await?;
+
+        self.bundle_transactions(ixs).await?;
+        for venue in venues {
+            self.metrics.record_venue_fill(venue, start.elapsed());
+        }
+        Ok(())
+    }
+
+    // Improved pre-liquidity trading
+    async fn execute_pre_liquidity_swap(&self, token: &Pubkey, amount: u64) -> Result<()> {
+        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(self.max_priority_fee().await?);
+        
+        let mut instructions = vec![compute_ix, priority_ix];
+        instructions.extend(self.create_privileged_swap(token, amount).await?);
+
+        let (blockhash, _) = self.get_cached_blockhash().await?;
+        
+        let tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+
+        self.send_transaction(&tx, SubmitPolicy::FAST).await?;
+
+        Ok(())
+    }
+
+    // Improved MEV protection
+    async fn max_priority_fee(&self) -> Result<u64> {
+        let base_fee = self.calculate_optimal_priority_fee().await?;
+        Ok(base_fee.saturating_mul(3)) // Triple the priority fee for critical transactions
+    }
+
+    // Enhanced transaction bundling for atomic execution
+    // Sends the instructions in order, as many transactions as
+    // max_instructions_per_tx requires. Each one carries the compute budget and
+    // takes the blockhash afresh, so later chunks can't outlive an early one's.
+    async fn bundle_critical_transactions(&self, instructions: Vec<Instruction>) -> Result<Vec<Signature>> {
+        let budget = [
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            ComputeBudgetInstruction::set_compute_unit_price(self.max_priority_fee().await?),
+        ];
+
+        let mut signatures = Vec::new();
+        for chunk in split_with_budget(&budget, instructions, self.max_instructions_per_tx) {
+            // Send with maximum priority
+            signatures.push(self.send_with_fresh_blockhash(&chunk, SubmitPolicy::FAST).await?);
+        }
+
+        Ok(signatures)
+    }
+
+    // Add advanced error handling
+    fn is_retryable_error(&self, error: &ClientError) -> bool {
+        matches!(
+            error.kind(),
+            ClientErrorKind::RpcError(_) |
+            ClientErrorKind::TransactionError(_) |
+            ClientErrorKind::Io(_)
+        )
+    }
+
+    // Add transaction monitoring
+    // Polls until the transaction is visible, up to max_retries times. Every
+    // lookup allows v0 so lookup-table transactions decode, and uses our
+    // commitment, raised to confirmed since getTransaction rejects processed.
+    pub async fn fetch_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+        let commitment = if self.commitment.is_at_least_confirmed() {
+            self.commitment
+        } else {
+            CommitmentConfig::confirmed()
+        };
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(commitment),
+            max_supported_transaction_version: Some(MAX_SUPPORTED_TRANSACTION_VERSION),
+        };
+
+        let mut retries = 0;
+        loop {
+            match self.rpc_throttled().await.get_transaction_with_config(signature, config).await {
+                Ok(tx) => return Ok(tx),
+                Err(e) if is_unsupported_version(&e) => {
+                    return Err(BotError::UnsupportedTransactionVersion(
+                        format!("{}: {}", signature, e)
+                    ).into());
+                }
+                Err(_) if retries + 1 < self.max_retries => {
+                    retries += 1;
+                    sleep(TRANSACTION_POLL_INTERVAL).await;
+                }
+                Err(_) => return Err(BotError::ConfirmationTimeout(signature.to_string()).into()),
+            }
+        }
+    }
+
+    // Add early pool detection
+    // Emits every Raydium pool created from now on. Pool accounts only change
+    // size when created, so instead of watching accounts this follows the AMM's
+    // logs for Initialize2 and decodes the instruction, which also carries the
+    // opening reserves. Runs until shutdown or until `events` is dropped.
+    pub async fn detect_new_pools(&self, events: mpsc::Sender<NewPoolEvent>) -> Result<()> {
+        let (signatures_tx, mut signatures_rx) = mpsc::channel(MEMPOOL_BUFFER);
+        tokio::spawn(supervise_program_logs(
+            self.ws_url.clone(),
+            self.amm_program_id,
+            CommitmentConfig::confirmed(),
+            Some(INITIALIZE2_LOG),
+            signatures_tx,
+            Arc::new(RwLock::new(ConnectionState::Stopped)),
+            self.shutdown.clone(),
+        ));
+
+        let mut seen = SeenSignatures::new(SEEN_SIGNATURE_CAPACITY);
+        while let Some(signature) = signatures_rx.recv().await {
+            if !seen.insert(signature) {
+                continue;
+            }
+            let tx = match self.fetch_transaction(&signature).await {
+                Ok(tx) => tx,
+                Err(e) => {
+                    warn!(signature = %signature, error = %e, "Failed to fetch pool creation");
+                    continue;
+                }
+            };
+            for event in decode_new_pools(&self.amm_program_id, &tx) {
+                info!(pool = %event.pool_id, base = %event.base_mint, quote = %event.quote_mint, "New Raydium pool");
+                if events.send(event).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Add advanced priority management
+    async fn dynamic_priority_fee(&self) -> Result<u64> {
+        let base_fee = self.calculate_optimal_priority_fee().await?;
+        let network_load = self.estimate_network_load().await?;
+        
+        Ok(match network_load {
+            LoadLevel::High => base_fee.saturating_mul(3),
+            LoadLevel::Medium => base_fee.saturating_mul(2),
+            LoadLevel::Low => base_fee,
+        })
+    }
+
+    // Runs every (route, amount) as its own task. The first failure aborts the
+    // routes still in flight and is returned; Ok means every route landed.
+    pub async fn execute_parallel_trades(self: &Arc<Self>, routes: Vec<(SwapRoute, u64)>) -> Result<()> {
+        run_all_or_abort(routes.into_iter().map(|(route, amount)| {
+            let engine = Arc::clone(self);
+            async move { engine.execute_swap_with_route(route, amount).await }
+        }))
+        .await
+    }
+
+    // Add sandwich protection
+    async fn execute_protected_trade(&self, instruction: Instruction) -> Result<()> {
+        let tx = Transaction::new_signed_with_payer(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                ComputeBudgetInstruction::set_compute_unit_price(self.max_priority_fee().await?),
+                instruction
+            ],
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            self.get_cached_blockhash().await?.0,
+        );
+
+        // Only nodes past the current slot may accept it
+        self.send_transaction(
+            &tx,
+            SubmitPolicy {
+                min_context_slot_offset: Some(1),
+                ..SubmitPolicy::FAST
+            },
+        ).await?;
+
+        Ok(())
+    }
+
+    // Add private mempool access
+    async fn submit_private_transaction(&self, tx: Transaction) -> Result<()> {
+        // Submit to private mempool if available
+        if let Some(private_node) = &self.private_node {
+            if self.safe_mode {
+                self.simulate_or_abort(&tx).await?;
+            }
+            private_node.send_transaction(&tx).await?;
+        } else {
+            // Fallback to public mempool with max priority
+            self.send_transaction(&tx, SubmitPolicy::FAST).await?;
+        }
+
+        Ok(())
+    }
+
+    // 1. Fast Pre-liquidity Access
+    async fn execute_privileged_swap(&self, token: &Pubkey, amount: u64, policy: SubmitPolicy) -> Result<Signature> {
+        self.ensure_running()?;
+
+        // 1. Route the buy, split across venues when that fills better
+        let swap_ixs = self.create_routed_swap(&self.quote_mint, token, amount).await?;
+
+        // 2. Start at an ultra high priority fee, 5x normal, doubling each time
+        // the send fails for want of a healthy node
+        let initial_fee = self.max_priority_fee().await? * 5;
+        self.retry_with_escalation(initial_fee, |priority_fee| {
+            let swap_ixs = &swap_ixs;
+            async move {
+                // 3. Maximum compute budget for complex operations
+                let mut instructions = vec![
+                    ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                    ComputeBudgetInstruction::set_compute_unit_price(priority_fee),
+                ];
+                instructions.extend(swap_ixs.iter().cloned());
+
+                // 4. Send under the caller's policy, re-signed if the blockhash
+                // expires; manual buys let the node preflight
+                self.send_with_fresh_blockhash(&instructions, policy)
+                    .await
+                    .map_err(escalate_network_error)
+            }
+        })
+        .await
+    }
+
+    // Sells `amount` of `token` for the quote mint. Not behind ensure_running,
+    // since an emergency stop unwinds positions through it.
+    async fn execute_sell(&self, token: &Pubkey, amount: u64) -> Result<Signature> {
+        let mut instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(self.compute_units),
+            ComputeBudgetInstruction::set_compute_unit_price(self.calculate_optimal_priority_fee().await?),
+        ];
+        instructions.extend(self.create_routed_swap(token, &self.quote_mint, amount).await?);
+
+        self.send_with_fresh_blockhash(&instructions, SubmitPolicy::FAST).await
+    }
+
+    // Pre-liquidity detection and execution
+    pub async fn execute_pre_liquidity(&self, token: &Pubkey, amount: u64) -> Result<()> {
+        self.ensure_running()?;
+
+        // Buy as soon as a pool trading `token` is created
+        let (events_tx, mut events_rx) = mpsc::channel(NEW_POOL_BUFFER);
+        let listener = self.detect_new_pools(events_tx);
+        tokio::pin!(listener);
+        loop {
+            tokio::select! {
+                result = &mut listener => {
+                    result?;
+                    return Err(anyhow!("Pool listener stopped before a {} pool appeared", token));
+                }
+                Some(event) = events_rx.recv() => {
+                    if event.involves(token) && !event.involves(&self.quote_mint) {
+                        info!(pool = %event.pool_id, token = %token, "Pool created against another quote token, waiting");
+                    } else if event.involves(token) {
+                        info!(pool = %event.pool_id, token = %token, "Pool created, buying");
+                        self.execute_privileged_swap(token, amount, SubmitPolicy::FAST).await?;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    // retry_escalating under our ErrorHandler rules, starting at `initial_fee`;
+    // each escalation also runs the on_escalation hooks
+    async fn retry_with_escalation<T, F, Fut>(&self, initial_fee: u64, operation: F) -> Result<T>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        retry_escalating(self, self.max_retries, initial_fee, self.priority_fee_ceiling, operation).await
+    }
+
+    async fn execute_with_max_priority(&self, tx: Transaction) -> Result<()> {
+        self.send_transaction(&tx, SubmitPolicy::FAST).await?;
+        Ok(())
+    }
+
+    // Add safety checks
+    async fn verify_setup(&self) -> Result<()> {
+        // 1. Test RPC
+        self.rpc_throttled().await.get_latest_blockhash().await?;
+        
+        // 2. Check wallet balance
+        let balance = Lamports(self.rpc_throttled().await.get_balance(&self.payer.pubkey()).await?);
+        let required = MIN_SETUP_BALANCE.to_lamports();
+        if balance < required {
+            return Err(anyhow!("Insufficient balance: {} < {}", balance, required));
+        }
+
+        // 3. Verify compute budget
+        check_compute_units(self.compute_units)
+    }
+
+    // Refuse new trades, stop background loops, then try to sell every open position
+    pub async fn trigger_emergency_stop(&self) -> Result<()> {
+        warn!("Emergency stop triggered");
+        self.shutdown.cancel();
+        let cancelled = self.cancel_dca_schedules()?;
+        if cancelled > 0 {
+            warn!(cancelled, "Cancelled pending DCA tranches");
+        }
+        self.close_all_positions("emergency-stop").await
+    }
+
+    // Ctrl-C path: stop background loops, optionally unwind, then write state to disk
+    pub async fn graceful_shutdown(&self, close_positions: bool, history_path: &str) -> Result<()> {
+        info!(close_positions, "Shutting down");
+        run_shutdown(self, close_positions, history_path).await
+    }
+
+    async fn close_all_positions(&self, reason: &str) -> Result<()> {
+        let mut failed = 0;
+        for position in self.get_active_positions().await? {
+            let result = self.sell_all(&position.token).await;
+            if let Err(e) = &result {
+                error!(token = %position.token, amount = position.amount, error = %e, "Failed to close position");
+                failed += 1;
+            }
+            self.trade_history.lock().push(TradeHistory {
+                signature: result.as_ref().map(|s| s.to_string()).unwrap_or_default(),
+                token: position.token,
+                trade_type: TradeType::SwapTokensForExactSOL,
+                amount: position.amount,
+                price: position.current_price,
+                success: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+                timestamp: std::time::Instant::now(),
+                recorded_at: unix_timestamp(),
+                reason: Some(reason.to_string()),
+            });
+        }
+
+        if failed > 0 {
+            return Err(BotError::TradingError(format!("{} positions could not be closed", failed)).into());
+        }
+        Ok(())
+    }
+
+    async fn pre_launch_check(&self) -> Result<()> {
+        // 1. RPC Connection
+        self.rpc_throttled().await.get_latest_blockhash().await?;
+
+        // 2. Wallet Balance
+        let balance = Lamports(self.rpc_throttled().await.get_balance(&self.payer.pubkey()).await?);
+        if balance < self.min_required_balance {
+            return Err(anyhow!("Insufficient balance: {} < {}", balance, self.min_required_balance));
+        }
+
+        // 3. Network Status
+        let slot = self.rpc_throttled().await.get_slot().await?;
+        if slot == 0 {
+            return Err(anyhow!("Network issue"));
+        }
+
+        // 4. Compute Budget
+        check_compute_units(self.compute_units)
+    }
+}
+
+// Any limit a transaction may request, up to the per-transaction maximum
+// `instructions` behind advance_nonce_account, signed with the nonce value
+// `nonce` standing in for the blockhash
+fn nonced_transaction(
+    instructions: Vec<Instruction>,
+    nonce_account: &Pubkey,
+    nonce: Hash,
+    payer: &Keypair,
+    authority: &Keypair,
+) -> Result<Transaction> {
+    let mut nonced = Vec::with_capacity(instructions.len() + 1);
+    nonced.push(system_instruction::advance_nonce_account(nonce_account, &authority.pubkey()));
+    nonced.extend(instructions);
+
+    let mut tx = Transaction::new_with_payer(&nonced, Some(&payer.pubkey()));
+    if authority.pubkey() == payer.pubkey() {
+        tx.try_sign(&[payer], nonce)?;
+    } else {
+        tx.try_sign(&[payer, authority], nonce)?;
+    }
+    Ok(tx)
+}
+
+fn check_compute_units(units: u32) -> Result<()> {
+    if !(1..=MAX_COMPUTE_UNITS).contains(&units) {
+        return Err(anyhow!("Invalid compute units {}, must be 1..={}", units, MAX_COMPUTE_UNITS));
+    }
+    Ok(())
+}
+
+// The steps of a graceful shutdown, split out so their order can be checked
+#[async_trait]
+pub(crate) trait ShutdownHooks: Send + Sync {
+    fn cancel_loops(&self);
+    async fn close_positions(&self) -> Result<()>;
+    async fn flush_positions(&self) -> Result<()>;
+    fn flush_trade_history(&self, path: &str) -> Result<()>;
+}
+
+#[async_trait]
+impl ShutdownHooks for TradingEngine {
+    fn cancel_loops(&self) {
+        self.shutdown.cancel();
+    }
+
+    async fn close_positions(&self) -> Result<()> {
+        self.close_all_positions("shutdown").await
+    }
+
+    async fn flush_positions(&self) -> Result<()> {
+        self.save_positions(&self.positions_path).await
+    }
+
+    fn flush_trade_history(&self, path: &str) -> Result<()> {
+        self.export_trade_history_csv(path)
+    }
+}
+
+// Loops stop first so nothing opens a position behind the closes. Both flushes
+// run even if some closes failed, so the next run picks up what's left.
+async fn run_shutdown(hooks: &dyn ShutdownHooks, close_positions: bool, history_path: &str) -> Result<()> {
+    hooks.cancel_loops();
+
+    let closed = if close_positions {
+        hooks.close_positions().await
+    } else {
+        Ok(())
+    };
+
+    hooks.flush_positions().await?;
+    hooks.flush_trade_history(history_path)?;
+    closed
+}
+
+// Value at percentile `p` (0.0..=1.0) of an ascending slice
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let index = ((sorted.len() - 1) as f64 * p.clamp(0.0, 1.0)).round() as usize;
+    sorted.get(index).copied()
+}
+
+// The floor stands in when nothing paid a fee lately; an RPC error is passed on
+fn percentile_fee(recent_fees: Result<Vec<u64>>, p: f64, floor: u64) -> Result<u64> {
+    Ok(percentile(&recent_fees?, p).unwrap_or_else(|| {
+        debug!(floor, "No recent priority fees, using the floor");
+        floor
+    }))
+}
+
+// A floor above the ceiling means the ceiling wins
+fn clamp_priority_fee(fee: u64, floor: u64, ceiling: u64) -> u64 {
+    fee.max(floor).min(ceiling)
+}
+
+// Writable accounts touched by `instructions`, for scoping fee queries
+fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = Vec::new();
+    for meta in instructions.iter().flat_map(|ix| ix.accounts.iter()) {
+        if meta.is_writable && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts.truncate(MAX_FEE_ACCOUNTS);
+    accounts
+}
+
+// Ties go to the earlier venue; one whose quote fails is logged and skipped
+fn best_venue_quote(
+    venues: &[Box<dyn SwapVenue>],
+    token_in: &Pubkey,
+    token_out: &Pubkey,
+    amount_in: u64,
+) -> Result<(usize, u64)> {
+    let mut best: Option<(usize, u64)> = None;
+    for (index, venue) in venues.iter().enumerate() {
+        let amount_out = match venue.quote(token_in, token_out, amount_in) {
+            Ok(Some(amount_out)) => amount_out,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(venue = venue.name(), error = %e, "Venue quote failed, skipping it");
+                continue;
+            }
+        };
+        if best.is_none_or(|(_, best_out)| amount_out > best_out) {
+            best = Some((index, amount_out));
+        }
+    }
+    best.ok_or_else(|| anyhow!("No venue has a route for {} -> {}", token_in, token_out))
+}
+
+// Hands each slice of the order to the venue with the best marginal output,
+// keeping the split only if it beats the best single venue. Every slice
+// re-quotes every venue, so HTTP venues like Jupiter make this slower.
+fn split_across_venues(
+    venues: &[Box<dyn SwapVenue>],
+    token_in: &Pubkey,
+    token_out: &Pubkey,
+    amount_in: u64,
+) -> Result<Vec<VenueLeg>> {
+    let (best, best_out) = best_venue_quote(venues, token_in, token_out, amount_in)?;
+    let single = vec![VenueLeg { venue: best, amount_in, amount_out: best_out }];
+
+    let slice = amount_in / ROUTE_SPLIT_STEPS;
+    if venues.len() < 2 || slice == 0 {
+        return Ok(single);
+    }
+
+    // A venue that fails to quote just gets no share of the order
+    let quote = |venue: usize, amount: u64| -> u64 {
+        if amount == 0 {
+            return 0;
+        }
+        venues[venue].quote(token_in, token_out, amount).ok().flatten().unwrap_or(0)
+    };
+
+    let mut allocated = vec![0u64; venues.len()];
+    let mut quoted = vec![0u64; venues.len()];
+    for step in 0..ROUTE_SPLIT_STEPS {
+        let size = if step == ROUTE_SPLIT_STEPS - 1 {
+            amount_in - slice * (ROUTE_SPLIT_STEPS - 1)
+        } else {
+            slice
+        };
+
+        let mut pick: Option<(usize, u64, u64)> = None; // venue, new quote, gain
+        for venue in 0..venues.len() {
+            let new_quote = quote(venue, allocated[venue] + size);
+            let gain = new_quote.saturating_sub(quoted[venue]);
+            if pick.is_none_or(|(_, _, best_gain)| gain > best_gain) {
+                pick = Some((venue, new_quote, gain));
+            }
+        }
+
+        if let Some((venue, new_quote, _)) = pick {
+            allocated[venue] += size;
+            quoted[venue] = new_quote;
+        }
+    }
+
+    let split_out: u64 = quoted.iter().sum();
+    if split_out <= best_out {
+        return Ok(single);
+    }
+
+    Ok(allocated
+        .iter()
+        .zip(&quoted)
+        .enumerate()
+        .filter(|(_, (amount_in, _))| **amount_in > 0)
+        .map(|(venue, (amount_in, amount_out))| VenueLeg {
+            venue,
+            amount_in: *amount_in,
+            amount_out: *amount_out,
+        })
+        .collect())
+}
+
+// Requested token account amounts from a simulation, None when it failed
+fn simulated_token_amounts(result: RpcSimulateTransactionResult) -> Result<Option<Vec<u64>>> {
+    if let Some(err) = result.err {
+        info!(error = %err, "Simulated swap failed");
+        return Ok(None);
+    }
+
+    let states = result.accounts
+        .ok_or_else(|| anyhow!("Simulation did not return account states"))?;
+    states
+        .into_iter()
+        .map(|state| match state.and_then(|ui_account| ui_account.decode::<Account>()) {
+            Some(account) => Ok(TokenAccount::unpack(&account.data)?.amount),
+            None => Ok(0),
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
+}
+
+// Share of the untaxed `expected` a round trip lost, None if nothing was expected back
+fn sell_tax_bps(returned: u64, expected: u64) -> Option<u64> {
+    if expected == 0 {
+        return None;
+    }
+    Some(10_000u64.saturating_sub((returned as u128 * 10_000 / expected as u128) as u64))
+}
+
+// A cached sell-check verdict still in force, None when the mint needs checking
+fn cached_sell_check(cached: Option<(bool, Instant)>, now: Instant) -> Option<bool> {
+    match cached {
+        Some((true, _)) => Some(true),
+        Some((false, checked_at)) if now.duration_since(checked_at) < SELL_CHECK_FAILURE_TTL => Some(false),
+        _ => None,
+    }
+}
+
+// Configured hop mints in order, duplicates dropped
+fn parse_intermediate_tokens(mints: &[String]) -> Result<Vec<Pubkey>> {
+    let mut tokens = Vec::with_capacity(mints.len());
+    for mint in mints {
+        let mint = Pubkey::from_str(mint)
+            .map_err(|e| anyhow!("Invalid intermediate token {}: {}", mint, e))?;
+        if !tokens.contains(&mint) {
+            tokens.push(mint);
+        }
+    }
+    Ok(tokens)
+}
+
+// The direct route, then one split path through each intermediate that isn't an endpoint
+fn candidate_routes(token_in: Pubkey, token_out: Pubkey, intermediates: &[Pubkey]) -> Vec<SwapRoute> {
+    let mut candidates = vec![SwapRoute::Direct(token_in, token_out)];
+    candidates.extend(
+        intermediates
+            .iter()
+            .filter(|mid| **mid != token_in && **mid != token_out)
+            .map(|mid| SwapRoute::Split(vec![(token_in, *mid, token_out)])),
+    );
+    candidates
+}
+
+// `amount` in `parts` near-equal shares; the remainder goes to the first
+fn split_evenly(amount: u64, parts: usize) -> Vec<u64> {
+    if parts == 0 {
+        return Vec::new();
+    }
+    let share = amount / parts as u64;
+    let mut shares = vec![share; parts];
+    shares[0] += amount % parts as u64;
+    shares
+}
+
+fn is_unsupported_version(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if *code == JSON_RPC_SERVER_ERROR_UNSUPPORTED_TRANSACTION_VERSION
+    )
+}
+
+// Logs the message (base64, as `solana decode-transaction` and explorers
+// take it) and each instruction's program and accounts. Only the signed
+// transaction is read, so no key material can end up in the log.
+fn dump_transaction<T: SerializableTransaction>(tx: &T) {
+    // Legacy and versioned transactions share a wire format
+    let tx: VersionedTransaction = match bincode::serialize(tx).map(|bytes| bincode::deserialize(&bytes)) {
+        Ok(Ok(tx)) => tx,
+        Ok(Err(e)) | Err(e) => {
+            debug!(signature = %tx.get_signature(), error = %e, "Couldn't decode transaction for dump");
+            return;
+        }
+    };
+
+    let keys = tx.message.static_account_keys();
+    // Accounts loaded from lookup tables have no static key to print
+    let key_at = |index: u8| keys
+        .get(index as usize)
+        .map(Pubkey::to_string)
+        .unwrap_or_else(|| format!("lookup#{}", index as usize - keys.len()));
+    let instructions: Vec<String> = tx.message
+        .instructions()
+        .iter()
+        .map(|ix| {
+            let accounts: Vec<String> = ix.accounts.iter().map(|&index| key_at(index)).collect();
+            format!("{} [{}]", key_at(ix.program_id_index), accounts.join(", "))
+        })
+        .collect();
+
+    debug!(
+        signature = %tx.signatures.first().copied().unwrap_or_default(),
+        message = %encode_message(&tx.message),
+        instructions = ?instructions,
+        "Sending transaction"
+    );
+}
+
+pub fn encode_message(message: &VersionedMessage) -> String {
+    BASE64.encode(message.serialize())
+}
+
+// Inverse of encode_message, for replaying a dumped message
+pub fn decode_message(encoded: &str) -> Result<VersionedMessage> {
+    Ok(bincode::deserialize(&BASE64.decode(encoded)?)?)
+}
+
+// Spawns each trade and joins them, aborting the rest on the first error
+async fn run_all_or_abort<F>(trades: impl IntoIterator<Item = F>) -> Result<()>
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    let mut tasks = JoinSet::new();
+    for trade in trades {
+        tasks.spawn(trade);
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        let result = joined
+            .map_err(|e| anyhow!("Route task failed: {}", e))
+            .and_then(|result| result);
+        if let Err(e) = result {
+            tasks.abort_all();
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+// The transaction's blockhash is too old, or from a fork the node never saw.
+// Preflight reports it as a failed simulation, a skip_preflight send as a
+// transaction error; both carry BlockhashNotFound.
+// Runs `send` until it fails for any reason but an expired blockhash, calling
+// `on_expired` before each of up to `retries` resends. Still expired after
+// that comes back as BotError::BlockhashExpired.
+async fn resend_on_expired_blockhash<T, F, Fut>(retries: u32, send: F, on_expired: impl Fn(u32)) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempts = 0;
+    loop {
+        let e = match send().await {
+            Ok(result) => return Ok(result),
+            Err(e) => e,
+        };
+        if !e.downcast_ref::<ClientError>().is_some_and(is_blockhash_expired) {
+            return Err(e);
+        }
+        if attempts >= retries {
+            return Err(BotError::BlockhashExpired(format!("{} resends: {}", attempts, e)).into());
+        }
+        attempts += 1;
+        on_expired(attempts);
+    }
+}
+
+pub fn is_blockhash_expired(err: &ClientError) -> bool {
+    if err.get_transaction_error() == Some(TransactionError::BlockhashNotFound) {
+        return true;
+    }
+    err.to_string().to_lowercase().contains("blockhash not found")
+}
+
+// Privileged sends that failed on the network are retried with a higher fee;
+// funds, blockhash and program errors keep their own kind
+fn escalate_network_error(error: anyhow::Error) -> anyhow::Error {
+    match error.downcast::<ClientError>() {
+        Ok(client_error) => match BotError::from(client_error) {
+            BotError::NetworkError(reason) => BotError::PrivilegeError(reason).into(),
+            other => other.into(),
+        },
+        Err(error) => error,
+    }
+}
+
+// `base_ms` doubled per retry with `jitter` (a fraction) of that added on
+// top, never more than MAX_BACKOFF_MS in total
+fn backoff_ms(base_ms: u64, retry: u32, jitter: f64) -> u64 {
+    let exp_ms = base_ms.saturating_mul(2u64.saturating_pow(retry));
+    exp_ms
+        .saturating_add((exp_ms as f64 * jitter) as u64)
+        .min(MAX_BACKOFF_MS)
+}
+
+fn is_rate_limited(err: &ClientError) -> bool {
+    let message = err.to_string();
+    message.contains("429") || message.to_lowercase().contains("too many requests")
+}
+
+impl Drop for TradingEngine {
+    fn drop(&mut self) {
+        // Only stops background loops; state is written by graceful_shutdown
+        self.shutdown.cancel();
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JitoResponse<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+impl<T> JitoResponse<T> {
+    fn into_result(self) -> Result<T> {
+        match (self.result, self.error) {
+            (Some(result), _) => Ok(result),
+            (None, Some(error)) => Err(anyhow!("Jito error: {}", error)),
+            (None, None) => Err(anyhow!("Jito returned an empty response")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BundleStatusesResult {
+    value: Vec<BundleStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BundleStatus {
+    pub bundle_id: String,
+    pub transactions: Vec<String>,
+    pub slot: u64,
+    pub confirmation_status: Option<String>,
+    pub err: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+pub enum SwapRoute {
+    Direct(Pubkey, Pubkey),
+    Split(Vec<(Pubkey, Pubkey, Pubkey)>),
+    Venues { token_in: Pubkey, token_out: Pubkey, legs: Vec<VenueLeg> }, // One pair, order divided across venues
+}
+
+// Part of an order sent to `venues[venue]`
+#[derive(Debug, Clone, Copy)]
+pub struct VenueLeg {
+    pub venue: usize,
+    pub amount_in: u64,
+    pub amount_out: u64, // Quoted
+}
+
+#[derive(Debug)]
+enum RetryableError {
+    RateLimit,
+    NetworkError,
+    TemporaryFailure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LoadLevel {
+    High,
+    Medium,
+    Low,
+}
+
+impl LoadLevel {
+    fn from_recent_fees(fees: &[u64]) -> LoadLevel {
+        if fees.is_empty() {
+            return LoadLevel::Low;
+        }
+        let paying = fees.iter().filter(|fee| **fee > 0).count() as f64 / fees.len() as f64;
+        if paying >= HIGH_LOAD_PAYING_SHARE {
+            LoadLevel::High
+        } else if paying >= MEDIUM_LOAD_PAYING_SHARE {
+            LoadLevel::Medium
+        } else {
+            LoadLevel::Low
+        }
+    }
+}
+
+// How a signed transaction is handed to the RPC. FAST skips preflight for
+// latency (copy trades, front-running); SAFE lets the node simulate first and
+// retry on its own, for manual trades where a failed send costs more than a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubmitPolicy {
+    pub skip_preflight: bool,
+    pub max_retries: Option<usize>, // RPC-side rebroadcasts; None leaves it to the node
+    pub min_context_slot_offset: Option<i64>, // Relative to our current slot; negative tolerates a lagging node
+}
+
+impl SubmitPolicy {
+    pub const FAST: SubmitPolicy = SubmitPolicy {
+        skip_preflight: true,
+        max_retries: Some(0),
+        min_context_slot_offset: None,
+    };
+
+    pub const SAFE: SubmitPolicy = SubmitPolicy {
+        skip_preflight: false,
+        max_retries: None,
+        min_context_slot_offset: None,
+    };
+
+    // Rejected by any node more than `lag` slots behind our last slot read
+    pub fn with_max_slot_lag(self, lag: u64) -> Self {
+        Self {
+            min_context_slot_offset: Some(-(lag.min(i64::MAX as u64) as i64)),
+            ..self
+        }
+    }
+
+    // `current_slot` is only read when min_context_slot_offset is set
+    pub fn send_config(&self, commitment: CommitmentConfig, current_slot: Option<u64>) -> RpcSendTransactionConfig {
+        RpcSendTransactionConfig {
+            skip_preflight: self.skip_preflight,
+            preflight_commitment: (!self.skip_preflight).then_some(commitment.commitment),
+            encoding: None,
+            max_retries: self.max_retries,
+            min_context_slot: self.min_context_slot_offset
+                .zip(current_slot)
+                .map(|(offset, slot)| min_context_slot(slot, offset)),
+        }
+    }
+}
+
+// Saturates at both ends, so small slots on a fresh test validator can't wrap
+pub fn min_context_slot(current_slot: u64, offset: i64) -> u64 {
+    current_slot.saturating_add_signed(offset)
+}
+
+// One instruction list per transaction, each starting with `budget` and holding
+// at most `max_per_tx` instructions in all. Always at least one instruction of
+// the caller's per transaction, even if the budget alone fills the limit.
+pub fn split_with_budget(budget: &[Instruction], instructions: Vec<Instruction>, max_per_tx: usize) -> Vec<Vec<Instruction>> {
+    let per_chunk = max_per_tx.saturating_sub(budget.len()).max(1);
+    instructions
+        .chunks(per_chunk)
+        .map(|chunk| budget.iter().chain(chunk).cloned().collect())
+        .collect()
+}
+
+fn adaptive_fee_step(current: u64, land_rate: f64, target_land_rate: f64) -> u64 {
+    let factor = if land_rate < target_land_rate {
+        ADAPTIVE_FEE_RAISE
+    } else if land_rate > target_land_rate {
+        ADAPTIVE_FEE_LOWER
+    } else {
+        return current;
+    };
+    // +1 so a fee of 0 can still climb
+    ((current.max(1) as f64) * factor).round() as u64
+}
+
+#[derive(Debug)]
+enum NetworkLoad {
+    High,
+    Medium,
+    Low,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::rpc_record::RecordedCall;
+    use serde_json::Value;
+    use solana_client::{
+        client_error::Result as ClientResult,
+        rpc_client::RpcClientConfig,
+        rpc_request::RpcRequest,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    };
+    use solana_sdk::instruction::AccountMeta;
+    use solana_transaction_status::TransactionConfirmationStatus;
+
+    // Engine whose RPC calls are answered, per method in order, by `calls`,
+    // with its state files kept out of the working directory
+    pub(crate) fn replay_engine(config: TradingConfig, calls: &[(&str, Value)]) -> TradingEngine {
+        let dir = std::env::temp_dir().join(format!("engine-{}", Pubkey::new_unique()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+        let recording: String = calls
+            .iter()
+            .map(|(method, result)| {
+                let call = RecordedCall {
+                    method: method.to_string(),
+                    params: Value::Null,
+                    result: Some(result.clone()),
+                    error: None,
+                };
+                serde_json::to_string(&call).unwrap() + "\n"
+            })
+            .collect();
+        std::fs::write(path("rpc.jsonl"), recording).unwrap();
+
+        std::env::set_var("ENCRYPTION_KEY", "replay-engine");
+        let config = TradingConfig {
+            rpc_replay_path: Some(path("rpc.jsonl")),
+            ladder_state_path: path("ladders.json"),
+            dca_state_path: path("dca.json"),
+            trailing_stop_state_path: path("trailing_stops.json"),
+            positions_path: path("positions.json"),
+            ..config
+        };
+        TradingEngine::new(&config, Arc::new(Keypair::new())).unwrap()
+    }
+
+    // Answers each RPC call with `answer(method, params)`, None failing the
+    // call. The client's own version check is answered if `answer` doesn't.
+    struct ScriptedRpc<F>(F);
+
+    #[async_trait]
+    impl<F> RpcSender for ScriptedRpc<F>
+    where
+        F: Fn(&str, &Value) -> Option<Value> + Send + Sync,
+    {
+        async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+            let method = request.to_string();
+            match (self.0)(&method, &params) {
+                Some(result) => Ok(result),
+                None if method == "getVersion" => Ok(json!({ "solana-core": "1.18.26", "feature-set": 3_469_865_029_u32 })),
+                None => Err(ClientErrorKind::Custom(format!("Unexpected {} {}", method, params)).into()),
+            }
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "scripted".to_string()
+        }
+    }
+
+    // replay_engine, but every RPC call is answered by `answer`
+    pub(crate) fn scripted_engine<F>(config: TradingConfig, answer: F) -> TradingEngine
+    where
+        F: Fn(&str, &Value) -> Option<Value> + Send + Sync + 'static,
+    {
+        let engine = replay_engine(config, &[]);
+        let client_config = RpcClientConfig::with_commitment(engine.commitment);
+        *engine.rpc_client.write() = Arc::new(RpcClient::new_sender(ScriptedRpc(answer), client_config));
+        engine
+    }
+
+    // RPC response wrapped in the usual { context, value }
+    pub(crate) fn with_context(value: Value) -> Value {
+        json!({ "context": { "slot": 1 }, "value": value })
+    }
+
+    // What a node answers sendTransaction with: the transaction's own signature
+    pub(crate) fn sent_signature(params: &Value) -> Value {
+        let encoded = params[0].as_str().unwrap();
+        let tx: Transaction = bincode::deserialize(&BASE64.decode(encoded).unwrap()).unwrap();
+        json!(tx.signatures[0].to_string())
+    }
+
+    // Any existing account, for callers that only check it's there
+    pub(crate) fn some_account() -> Value {
+        json!({
+            "lamports": 2_039_280,
+            "data": ["", "base64"],
+            "owner": spl_token::ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        })
+    }
+
+    pub(crate) fn token_amount(amount: u64, decimals: u8) -> Value {
+        let ui_amount = amount as f64 / 10f64.powi(decimals as i32);
+        json!({
+            "amount": amount.to_string(),
+            "decimals": decimals,
+            "uiAmount": ui_amount,
+            "uiAmountString": ui_amount.to_string(),
+        })
+    }
+
+    // Constant-product venue with fixed reserves; `fails` makes every quote error
+    pub(crate) struct MockVenue {
+        name: &'static str,
+        reserve_in: u64,
+        reserve_out: u64,
+        fails: bool,
+    }
+
+    impl MockVenue {
+        pub(crate) fn boxed(name: &'static str, reserve_in: u64, reserve_out: u64) -> Box<dyn SwapVenue> {
+            Box::new(Self { name, reserve_in, reserve_out, fails: false })
+        }
+    }
+
+    impl SwapVenue for MockVenue {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn program_id(&self) -> Pubkey {
+            Pubkey::default()
+        }
+
+        fn build_swap_ix(&self, _: &Pubkey, _: &Pubkey, amount_in: u64, min_out: u64) -> Result<Instruction> {
+            let mut data = amount_in.to_le_bytes().to_vec();
+            data.extend_from_slice(&min_out.to_le_bytes());
+            Ok(Instruction { program_id: self.program_id(), accounts: Vec::new(), data })
+        }
+
+        fn quote(&self, _: &Pubkey, _: &Pubkey, amount_in: u64) -> Result<Option<u64>> {
+            if self.fails {
+                return Err(anyhow!("{} is down", self.name));
+            }
+            let out = self.reserve_out as u128 * amount_in as u128 / (self.reserve_in as u128 + amount_in as u128);
+            Ok(Some(out as u64))
+        }
+
+        fn build_swap_transaction(&self, input: &Pubkey, output: &Pubkey, amount_in: u64, min_amount_out: u64, payer: &Pubkey) -> Result<Transaction> {
+            let ix = self.build_swap_ix(input, output, amount_in, min_amount_out)?;
+            Ok(Transaction::new_with_payer(&[ix], Some(payer)))
+        }
+    }
+
+    fn pair() -> (Pubkey, Pubkey) {
+        (Pubkey::new_unique(), Pubkey::new_unique())
+    }
+
+    #[test]
+    fn split_beats_any_single_venue() {
+        let (token_in, token_out) = pair();
+        let venues = vec![
+            MockVenue::boxed("a", 10_000_000, 10_000_000),
+            MockVenue::boxed("b", 8_000_000, 8_000_000),
+        ];
+        let amount_in = 5_000_000;
+
+        let (_, best_single) = best_venue_quote(&venues, &token_in, &token_out, amount_in).unwrap();
+        let legs = split_across_venues(&venues, &token_in, &token_out, amount_in).unwrap();
+
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs.iter().map(|leg| leg.amount_in).sum::<u64>(), amount_in);
+        assert!(legs.iter().map(|leg| leg.amount_out).sum::<u64>() > best_single);
+    }
+
+    #[test]
+    fn small_order_stays_on_the_best_venue() {
+        let (token_in, token_out) = pair();
+        let venues = vec![
+            MockVenue::boxed("thin", 1_000_000, 900_000),
+            MockVenue::boxed("deep", 1_000_000_000, 1_000_000_000),
+        ];
+
+        let legs = split_across_venues(&venues, &token_in, &token_out, 1_000).unwrap();
+        assert_eq!(legs.len(), 1);
+        assert_eq!(legs[0].venue, 1);
+    }
+
+    #[test]
+    fn failing_venue_is_skipped() {
+        let (token_in, token_out) = pair();
+        let venues: Vec<Box<dyn SwapVenue>> = vec![
+            Box::new(MockVenue { name: "down", reserve_in: 1, reserve_out: 1, fails: true }),
+            MockVenue::boxed("up", 10_000_000, 10_000_000),
+        ];
+
+        let (index, amount_out) = best_venue_quote(&venues, &token_in, &token_out, 1_000).unwrap();
+        assert_eq!(index, 1);
+        assert!(amount_out > 0);
+
+        let legs = split_across_venues(&venues, &token_in, &token_out, 5_000_000).unwrap();
+        assert!(legs.iter().all(|leg| leg.venue == 1));
+    }
+
+    #[test]
+    fn routes_go_through_each_configured_intermediate_once() {
+        let (token_in, token_out) = pair();
+        let usdc = Pubkey::new_unique();
+        let configured = vec![
+            usdc.to_string(),
+            native_mint::ID.to_string(),
+            usdc.to_string(),
+            token_out.to_string(),
+        ];
+
+        let intermediates = parse_intermediate_tokens(&configured).unwrap();
+        assert_eq!(intermediates, vec![usdc, native_mint::ID, token_out]);
+
+        let routes = candidate_routes(token_in, token_out, &intermediates);
+        assert_eq!(routes.len(), 3);
+        assert!(matches!(routes[0], SwapRoute::Direct(a, b) if a == token_in && b == token_out));
+        for (route, mid) in routes[1..].iter().zip([usdc, native_mint::ID]) {
+            match route {
+                SwapRoute::Split(paths) => assert_eq!(paths, &vec![(token_in, mid, token_out)]),
+                other => panic!("expected a split route, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_intermediate() {
+        assert!(parse_intermediate_tokens(&["not-a-mint".to_string()]).is_err());
+    }
+
+    #[test]
+    fn failed_sell_simulation_marks_token_unsellable() {
+        // Sell leg reverted by the token program, as a honeypot's transfer hook does
+        let result: RpcSimulateTransactionResult = serde_json::from_value(json!({
+            "err": { "InstructionError": [3, { "Custom": 6001 }] },
+            "logs": ["Program log: transfers disabled"],
+            "accounts": null,
+            "unitsConsumed": 48_000,
+        }))
+        .unwrap();
+        assert_eq!(simulated_token_amounts(result).unwrap(), None);
+    }
+
+    #[test]
+    fn missing_simulated_account_reads_as_empty() {
+        let result: RpcSimulateTransactionResult = serde_json::from_value(json!({
+            "err": null,
+            "logs": [],
+            "accounts": [null],
+            "unitsConsumed": 48_000,
+        }))
+        .unwrap();
+        assert_eq!(simulated_token_amounts(result).unwrap(), Some(vec![0]));
+    }
+
+    #[test]
+    fn simulated_token_accounts_read_back_their_amounts() {
+        let token_account = TokenAccount {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount: 4_200_000,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(token_account, &mut data).unwrap();
+
+        let result: RpcSimulateTransactionResult = serde_json::from_value(json!({
+            "err": null,
+            "logs": [],
+            "accounts": [
+                {
+                    "lamports": 2_039_280,
+                    "data": [BASE64.encode(&data), "base64"],
+                    "owner": spl_token::ID.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                },
+                null,
+            ],
+            "unitsConsumed": 48_000,
+        }))
+        .unwrap();
+        assert_eq!(simulated_token_amounts(result).unwrap(), Some(vec![4_200_000, 0]));
+
+        // A successful simulation has to return the requested accounts
+        let result: RpcSimulateTransactionResult = serde_json::from_value(json!({
+            "err": null,
+            "logs": [],
+            "accounts": null,
+            "unitsConsumed": 48_000,
+        }))
+        .unwrap();
+        assert!(simulated_token_amounts(result).is_err());
+    }
+
+    #[test]
+    fn sell_tax_is_measured_against_the_untaxed_quote() {
+        assert_eq!(sell_tax_bps(1_000, 1_000), Some(0));
+        assert_eq!(sell_tax_bps(900, 1_000), Some(1_000));
+        assert_eq!(sell_tax_bps(0, 1_000), Some(10_000));
+        assert_eq!(sell_tax_bps(1_100, 1_000), Some(0));
+        assert_eq!(sell_tax_bps(1_000, 0), None);
+    }
+
+    #[test]
+    fn failed_sell_checks_expire() {
+        let now = Instant::now();
+        assert_eq!(cached_sell_check(None, now), None);
+        assert_eq!(cached_sell_check(Some((true, now)), now + SELL_CHECK_FAILURE_TTL * 10), Some(true));
+        assert_eq!(cached_sell_check(Some((false, now)), now + Duration::from_secs(1)), Some(false));
+        assert_eq!(cached_sell_check(Some((false, now)), now + SELL_CHECK_FAILURE_TTL), None);
+    }
+
+    #[test]
+    fn blockhash_cache_serves_fresh_values_only() {
+        let cache = BlockhashCache::new(Duration::from_millis(20));
+        assert_eq!(cache.get(), None);
+
+        let blockhash = Hash::new_unique();
+        cache.store(blockhash, 1_000);
+        assert_eq!(cache.get(), Some((blockhash, 1_000)));
+
+        // Past max_age the builder has to fetch again
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(cache.get(), None);
+
+        cache.store(blockhash, 1_001);
+        cache.invalidate();
+        assert_eq!(cache.get(), None);
+    }
+
+    #[test]
+    fn min_out_leaves_room_for_slippage() {
+        assert_eq!(min_out_after_slippage(1_000_000, 50), 995_000);
+        assert_eq!(min_out_after_slippage(1_000_000, 0), 1_000_000);
+        assert_eq!(min_out_after_slippage(1_000_000, u16::MAX), 0);
+    }
+
+    #[test]
+    fn spend_cap_refuses_the_buy_that_would_cross_it() {
+        let limits = DailyLimits::new(Sol(1.0), Sol(1.0));
+        let now = 10 * SECS_PER_DAY;
+
+        limits.reserve_spend(Lamports(600_000_000), now).unwrap();
+        let refused = limits.reserve_spend(Lamports(500_000_000), now);
+        assert!(refused.is_err());
+        // A refused buy isn't counted
+        assert_eq!(limits.today(now).0, Lamports(600_000_000));
+
+        let fits = limits.reserve_spend(Lamports(400_000_000), now).unwrap();
+        assert_eq!(limits.today(now).0, Lamports(1_000_000_000));
+
+        // A failed buy hands its reservation back
+        limits.release_spend(fits, now);
+        assert_eq!(limits.today(now).0, Lamports(600_000_000));
+    }
+
+    #[test]
+    fn loss_cap_is_hit_once_net_losses_reach_it() {
+        let limits = DailyLimits::new(Sol(10.0), Sol(1.0));
+        let now = 10 * SECS_PER_DAY;
+
+        assert!(!limits.record_realized(-0.5, now));
+        assert!(!limits.record_realized(0.25, now));
+        assert!(!limits.loss_cap_hit(now));
+        assert!(limits.record_realized(-0.75, now));
+        assert!(limits.loss_cap_hit(now));
+        // Only the result that crossed the cap reports it
+        assert!(!limits.record_realized(-0.1, now));
+    }
+
+    #[test]
+    fn daily_totals_reset_at_the_day_rollover() {
+        let limits = DailyLimits::new(Sol(1.0), Sol(1.0));
+        let last_second = 11 * SECS_PER_DAY - 1;
+        let next_day = last_second + 1;
+
+        let yesterdays = limits.reserve_spend(Lamports(1_000_000_000), last_second).unwrap();
+        limits.record_realized(-1.0, last_second);
+        assert!(limits.loss_cap_hit(last_second));
+        assert!(limits.reserve_spend(Lamports(1), last_second).is_err());
+
+        assert!(!limits.loss_cap_hit(next_day));
+        assert_eq!(limits.today(next_day), (Lamports(0), Sol(0.0)));
+        let todays = limits.reserve_spend(Lamports(700_000_000), next_day).unwrap();
+
+        // Releasing yesterday's reservation leaves today's spend alone
+        limits.release_spend(yesterdays, next_day);
+        assert_eq!(limits.today(next_day).0, Lamports(700_000_000));
+        limits.release_spend(todays, next_day);
+        assert_eq!(limits.today(next_day).0, Lamports(0));
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_paces_requests_past_its_burst() {
+        let limiter = RateLimiter::new(10);
+        let start = Instant::now();
+        // The first 10 drain the bucket; each of the other 5 waits for a 100ms refill
+        for _ in 0..15 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert_eq!(limiter.utilization(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn ranks_mock_endpoints_by_latency_and_fails_over() {
+        let rpc = RPCConfig::new(
+            vec!["slow".into(), "down".into(), "fast".into()],
+            Duration::from_secs(30),
+        );
+        let latencies = HashMap::from([
+            ("slow".to_string(), Duration::from_millis(80)),
+            ("fast".to_string(), Duration::from_millis(10)),
+        ]);
+
+        let ranked = rpc
+            .rank_by(|endpoint| futures::future::ready(latencies.get(&endpoint).copied()))
+            .await;
+
+        let order: Vec<&str> = ranked.iter().map(|(endpoint, _)| endpoint.as_str()).collect();
+        assert_eq!(order, ["fast", "slow", "down"]);
+        assert_eq!(ranked[2].1, RPC_PING_TIMEOUT);
+
+        let now = unix_timestamp() as u64;
+        assert!(!rpc.is_available(1, now));
+        assert_eq!(rpc.best_endpoint(now).as_deref(), Some("fast"));
+        assert_eq!(rpc.best_endpoint(now), None);
+    }
+
+    #[test]
+    fn backoff_stays_within_its_jitter_band() {
+        for retry in 0..20 {
+            let base = BACKOFF_BASE_MS << retry.min(16);
+            assert_eq!(backoff_ms(BACKOFF_BASE_MS, retry, 0.0), base.min(MAX_BACKOFF_MS), "retry {}", retry);
+            assert_eq!(backoff_ms(BACKOFF_BASE_MS, retry, 0.5), (base + base / 2).min(MAX_BACKOFF_MS), "retry {}", retry);
+        }
+        // Jitter can't push a delay past the cap
+        assert_eq!(backoff_ms(4_000, 0, 0.0), 4_000);
+        assert_eq!(backoff_ms(4_000, 0, 0.5), MAX_BACKOFF_MS);
+        assert_eq!(backoff_ms(BACKOFF_BASE_MS, 30, 0.5), MAX_BACKOFF_MS);
+        assert_eq!(backoff_ms(BACKOFF_BASE_MS, u32::MAX, 0.5), MAX_BACKOFF_MS);
+        assert_eq!(backoff_ms(RATE_LIMIT_BACKOFF_BASE_MS, 2, 0.25), 100);
+    }
+
+    #[tokio::test]
+    async fn retries_back_off_until_the_retry_limit() {
+        let engine = replay_engine(TradingConfig::default(), &[]);
+
+        // Every retryable failure is retried, 50ms doubling in between
+        let attempts = AtomicU64::new(0);
+        let start = Instant::now();
+        let result: Result<()> = engine
+            .retry_with_backoff(|| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(BotError::NetworkError("connection reset".to_string()).into())
+            })
+            .await;
+        let elapsed = start.elapsed();
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), engine.max_retries as u64 + 1);
+        assert!(elapsed >= Duration::from_millis(50 + 100 + 200), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1_000), "{:?}", elapsed);
+
+        // Success stops the retries
+        let attempts = AtomicU64::new(0);
+        let result = engine
+            .retry_with_backoff(|| async {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 | 1 => Err(BotError::RPCError("timed out".to_string()).into()),
+                    _ => Ok("landed"),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), "landed");
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        // Errors that can't succeed on retry fail straight away
+        let attempts = AtomicU64::new(0);
+        let result: Result<()> = engine
+            .retry_with_backoff(|| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(BotError::InsufficientFunds("0.1 SOL short".to_string()).into())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn status_poll_fails_n_times_then_confirms() {
+        const FAILURES: u32 = 3;
+        let engine = replay_engine(TradingConfig::default(), &[]);
+        let attempts = Mutex::new(Vec::new());
+
+        let start = Instant::now();
+        let status = engine
+            .retry_with_backoff(|| async {
+                let mut attempts = attempts.lock();
+                attempts.push(Instant::now());
+                if attempts.len() as u32 <= FAILURES {
+                    return Err(BotError::NetworkError("status lookup timed out".to_string()).into());
+                }
+                Ok(TransactionConfirmationStatus::Confirmed)
+            })
+            .await
+            .unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(status, TransactionConfirmationStatus::Confirmed);
+        let attempts = attempts.into_inner();
+        assert_eq!(attempts.len() as u32, FAILURES + 1);
+        // Each retry waits the handler's delay for its retry number: 50, 100 then 200ms
+        for (retry, gap) in attempts.windows(2).map(|pair| pair[1] - pair[0]).enumerate() {
+            assert!(gap >= engine.get_retry_delay(retry as u32), "retry {}: {:?}", retry, gap);
+        }
+        let total: Duration = (0..FAILURES).map(|retry| engine.get_retry_delay(retry)).sum();
+        assert_eq!(total, Duration::from_millis(350));
+        assert!(elapsed >= total, "{:?}", elapsed);
+        assert!(elapsed < total + Duration::from_millis(250), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn replaced_primary_becomes_current_and_healthy() {
+        let rpc = RPCConfig::new(vec!["old".into(), "fallback".into()], Duration::from_secs(30));
+        assert_eq!(rpc.report_failure().as_deref(), Some("fallback"));
+
+        rpc.replace_primary("new".into());
+        assert_eq!(rpc.current_endpoint(), "new");
+        assert!(rpc.is_available(0, unix_timestamp() as u64));
+        // Failing over still reaches the fallback
+        assert_eq!(rpc.report_failure().as_deref(), Some("fallback"));
+    }
+
+    fn expired() -> anyhow::Error {
+        ClientError::from(TransactionError::BlockhashNotFound).into()
+    }
+
+    #[tokio::test]
+    async fn expired_blockhash_is_resent_until_it_lands() {
+        let attempts = AtomicU64::new(0);
+        let refreshes = AtomicU64::new(0);
+
+        let result = resend_on_expired_blockhash(
+            3,
+            || async {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 => Err(expired()),
+                    _ => Ok("landed"),
+                }
+            },
+            |_| {
+                refreshes.fetch_add(1, Ordering::Relaxed);
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "landed");
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        assert_eq!(refreshes.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn blockhash_still_expired_after_retries_is_reported_as_such() {
+        let attempts = AtomicU64::new(0);
+
+        let result: Result<()> = resend_on_expired_blockhash(
+            2,
+            || async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(expired())
+            },
+            |_| {},
+        )
+        .await;
+
+        assert!(matches!(BotError::from(result.unwrap_err()), BotError::BlockhashExpired(_)));
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn other_send_errors_are_not_resent() {
+        let attempts = AtomicU64::new(0);
+
+        let result: Result<()> = resend_on_expired_blockhash(
+            2,
+            || async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(ClientError::from(TransactionError::InsufficientFundsForFee).into())
+            },
+            |_| {},
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert!(breaker.allows_trades());
+        assert!(breaker.record_failure());
+
+        assert!(matches!(breaker.state(), BreakerState::Open { .. }));
+        assert!(!breaker.allows_trades());
+    }
+
+    #[test]
+    fn half_open_breaker_lets_one_probe_through() {
+        // No cooldown, so the breaker is half-open as soon as it opens
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.allows_trades());
+
+        breaker.record_sent();
+        assert!(!breaker.allows_trades());
+        breaker.record_sent();
+        assert!(!breaker.allows_trades());
+    }
+
+    #[test]
+    fn probe_outcome_closes_or_reopens_the_breaker() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        breaker.record_failure();
+        breaker.record_sent();
+        assert!(breaker.record_failure(), "a failed probe reopens");
+        assert!(breaker.allows_trades(), "the reopened breaker takes a new probe");
+
+        breaker.record_sent();
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.record_sent();
+        assert!(breaker.allows_trades(), "sends while closed aren't probes");
+    }
+
+    fn status(err: Option<TransactionError>, confirmation_status: TransactionConfirmationStatus) -> TransactionStatus {
+        TransactionStatus {
+            slot: 1,
+            confirmations: Some(0),
+            status: err.clone().map_or(Ok(()), Err),
+            err,
+            confirmation_status: Some(confirmation_status),
+        }
+    }
+
+    #[test]
+    fn confirmation_outcomes_follow_the_signature_status() {
+        let sent_at = Instant::now();
+        let confirmed = CommitmentConfig::confirmed();
+
+        let landed = status(None, TransactionConfirmationStatus::Confirmed);
+        assert_eq!(confirmation_of(Some(&landed), confirmed, sent_at, sent_at), Some(Confirmation::Succeeded));
+
+        let failed = status(Some(TransactionError::AccountNotFound), TransactionConfirmationStatus::Processed);
+        assert_eq!(confirmation_of(Some(&failed), confirmed, sent_at, sent_at), Some(Confirmation::Failed));
+
+        let processed = status(None, TransactionConfirmationStatus::Processed);
+        assert_eq!(confirmation_of(Some(&processed), confirmed, sent_at, sent_at), None);
+        assert_eq!(confirmation_of(None, confirmed, sent_at, sent_at), None);
+
+        let timed_out = sent_at + CONFIRMATION_TIMEOUT;
+        assert_eq!(confirmation_of(None, confirmed, sent_at, timed_out), Some(Confirmation::Dropped));
+    }
+
+    #[tokio::test]
+    async fn mock_routes_run_concurrently() {
+        const ROUTES: u64 = 5;
+        let in_flight = Arc::new(AtomicU64::new(0));
+        let peak = Arc::new(AtomicU64::new(0));
+
+        let start = Instant::now();
+        let routes = (0..ROUTES).map(|_| {
+            let (in_flight, peak) = (in_flight.clone(), peak.clone());
+            async move {
+                let running = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(running, Ordering::SeqCst);
+                sleep(Duration::from_millis(100)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        run_all_or_abort(routes).await.unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), ROUTES);
+        assert!(start.elapsed() < Duration::from_millis(100 * ROUTES));
+    }
+
+    #[tokio::test]
+    async fn failed_route_aborts_the_rest() {
+        let finished = Arc::new(AtomicU64::new(0));
+
+        let routes = (0..4u64).map(|index| {
+            let finished = finished.clone();
+            async move {
+                if index == 2 {
+                    return Err(anyhow!("route {} failed", index));
+                }
+                sleep(Duration::from_secs(5)).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        let result = tokio::time::timeout(Duration::from_secs(1), run_all_or_abort(routes)).await;
+
+        assert_eq!(result.expect("the failure returns without waiting").unwrap_err().to_string(), "route 2 failed");
+        assert_eq!(finished.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn submit_policies_produce_their_send_configs() {
+        let commitment = CommitmentConfig::confirmed();
+
+        assert_eq!(
+            SubmitPolicy::FAST.send_config(commitment, Some(1_000)),
             RpcSendTransactionConfig {
                 skip_preflight: true,
                 preflight_commitment: None,
                 encoding: None,
                 max_retries: Some(0),
                 min_context_slot: None,
-            },
-        )?;
+            }
+        );
+        assert_eq!(
+            SubmitPolicy::SAFE.send_config(commitment, None),
+            RpcSendTransactionConfig {
+                skip_preflight: false,
+                preflight_commitment: Some(commitment.commitment),
+                encoding: None,
+                max_retries: None,
+                min_context_slot: None,
+            }
+        );
 
-        Ok(())
+        let lagging = SubmitPolicy::FAST.with_max_slot_lag(5);
+        assert_eq!(lagging.send_config(commitment, Some(1_000)).min_context_slot, Some(995));
+        // Without a slot read there's nothing to be relative to
+        assert_eq!(lagging.send_config(commitment, None).min_context_slot, None);
     }
 
-    // Improved MEV protection
-    fn max_priority_fee(&self) -> u64 {
-        let base_fee = self.calculate_optimal_priority_fee();
-        base_fee.saturating_mul(3) // Triple the priority fee for critical transactions
+    #[test]
+    fn min_context_slot_saturates_at_small_slots() {
+        // A lag guard behind the current slot, the early-swap case
+        assert_eq!(min_context_slot(100, -5), 95);
+        assert_eq!(min_context_slot(3, -5), 0);
+        assert_eq!(min_context_slot(0, i64::MIN), 0);
+        // A look-ahead moves the other way
+        assert_eq!(min_context_slot(100, 2), 102);
+        assert_eq!(min_context_slot(u64::MAX - 1, 5), u64::MAX);
+
+        let config = SubmitPolicy::FAST.with_max_slot_lag(10).send_config(CommitmentConfig::processed(), Some(4));
+        assert_eq!(config.min_context_slot, Some(0));
     }
 
-    // Enhanced transaction bundling for atomic execution
-    async fn bundle_critical_transactions(&self, instructions: Vec<Instruction>) -> Result<()> {
-        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
-        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(self.max_priority_fee());
+    #[tokio::test]
+    async fn emergency_stop_refuses_buys_but_still_closes_positions() {
+        let token = Pubkey::new_unique();
+        let sold = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let config = TradingConfig {
+            priority_fee_strategy: PriorityFeeStrategy::Fixed { fee: 5_000 },
+            ..TradingConfig::default()
+        };
+        let sent = sold.clone();
+        let mut engine = scripted_engine(config, move |method, params| match method {
+            "getAccountInfo" => Some(with_context(some_account())),
+            "getTokenAccountBalance" => {
+                let amount = if sent.load(Ordering::Relaxed) { 0 } else { 2_000_000 };
+                Some(with_context(token_amount(amount, 6)))
+            }
+            "getTokenSupply" => Some(with_context(token_amount(1_000_000_000_000, 6))),
+            "getLatestBlockhash" => Some(with_context(json!({
+                "blockhash": Hash::new_unique().to_string(),
+                "lastValidBlockHeight": 1_000,
+            }))),
+            "sendTransaction" => {
+                sent.store(true, Ordering::Relaxed);
+                Some(sent_signature(params))
+            }
+            _ => None,
+        });
+        engine.venues = vec![MockVenue::boxed("mock", 1_000_000_000_000, 1_000_000_000_000)];
+        engine.track_token(token);
 
-        let mut final_ixs = vec![compute_ix, priority_ix];
-        final_ixs.extend(instructions);
+        engine.trigger_emergency_stop().await.unwrap();
+        assert!(sold.load(Ordering::Relaxed), "the open position was sold");
+        assert!(engine.tracked_tokens().is_empty());
+        let history = engine.trade_history.lock().clone();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].success);
+        assert_eq!(history[0].reason.as_deref(), Some("emergency-stop"));
 
-        let blockhash = self.rpc_client.get_latest_blockhash()?;
-        
-        // Split into multiple transactions if needed
-        let chunk_size = 6; // Maximum instructions per transaction
-        for chunk in final_ixs.chunks(chunk_size) {
-            let tx = Transaction::new_signed_with_payer(
-                chunk,
-                Some(&self.payer.pubkey()),
-                &[&self.payer],
-                blockhash,
-            );
+        let refused = engine.manage_position(&Pubkey::new_unique(), PositionAction::Buy(1_000_000)).await;
+        assert!(refused.unwrap_err().to_string().contains("Emergency stop active"));
+    }
 
-            // Send with maximum priority
-            self.rpc_client.send_transaction_with_config(
-                &tx,
-                RpcSendTransactionConfig {
-                    skip_preflight: true,
-                    preflight_commitment: None,
-                    encoding: None,
-                    max_retries: Some(0),
-                    min_context_slot: None,
-                },
-            )?;
-        }
+    #[test]
+    fn nonced_transactions_advance_the_nonce_first_and_sign_against_it() {
+        let payer = Keypair::new();
+        let nonce_account = Pubkey::new_unique();
+        let nonce = Hash::new_unique();
+        let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
 
-        Ok(())
-    }
+        let tx = nonced_transaction(vec![transfer.clone()], &nonce_account, nonce, &payer, &payer).unwrap();
 
-    // Add advanced error handling
-    fn is_retryable_error(&self, error: &ClientError) -> bool {
-        matches!(
-            error,
-            ClientError::RpcError(_) | 
-            ClientError::TransactionError(_) |
-            ClientError::IoError(_)
-        )
+        let message = &tx.message;
+        assert_eq!(message.recent_blockhash, nonce);
+        assert_eq!(message.instructions.len(), 2);
+        let advance = &message.instructions[0];
+        assert_eq!(message.account_keys[advance.program_id_index as usize], solana_sdk::system_program::ID);
+        assert_eq!(
+            bincode::deserialize::<solana_sdk::system_instruction::SystemInstruction>(&advance.data).unwrap(),
+            solana_sdk::system_instruction::SystemInstruction::AdvanceNonceAccount,
+        );
+        assert_eq!(message.account_keys[advance.accounts[0] as usize], nonce_account);
+        assert_eq!(message.instructions[1].data, transfer.data);
+        // The wallet is both fee payer and authority, so it signs once
+        assert_eq!(tx.signatures.len(), 1);
+        tx.verify().unwrap();
     }
 
-    // Add transaction monitoring
-    async fn monitor_transaction(&self, signature: &str) -> Result<()> {
-        let mut retries = 0;
-        while retries < self.max_retries {
-            match self.rpc_client.get_transaction_with_config(
-                signature,
-                RpcTransactionConfig {
-                    encoding: None,
-                    commitment: Some(self.commitment),
-                    max_supported_transaction_version: Some(0),
-                },
-            ) {
-                Ok(_) => return Ok(()),
-                Err(_) => {
-                    retries += 1;
-                    tokio::time::sleep(Duration::from_millis(100)).await;
-                }
-            }
-        }
-        Err(anyhow!("Transaction confirmation timeout"))
+    #[test]
+    fn separate_nonce_authority_signs_too() {
+        let (payer, authority) = (Keypair::new(), Keypair::new());
+        let tx = nonced_transaction(Vec::new(), &Pubkey::new_unique(), Hash::new_unique(), &payer, &authority).unwrap();
+
+        assert_eq!(tx.signatures.len(), 2);
+        assert_eq!(&tx.message.account_keys[..2], &[payer.pubkey(), authority.pubkey()]);
+        tx.verify().unwrap();
     }
 
-    // Add early pool detection
-    async fn detect_new_pools(&self) -> Result<()> {
-        let filters = vec![
-            RpcFilterType::DataSize(165),
-            RpcFilterType::Memcmp(Memcmp {
-                offset: 32,
-                bytes: MemcmpEncodedBytes::Base58(raydium_v4::ID.to_string()),
-                encoding: None,
-            }),
-        ];
+    #[tokio::test]
+    async fn configured_nonce_account_replaces_the_blockhash() {
+        use solana_sdk::nonce::state::{Data, DurableNonce, State, Versions};
 
-        self.rpc_client.subscribe_program(
-            raydium_v4::ID,
-            Some(filters),
-            move |tx| {
-                if let Some(pool) = self.parse_pool_creation(tx) {
-                    self.execute_early_liquidity_trade(&pool).await?;
-                }
-                Ok(())
-            },
-        ).await?;
+        let nonce_account = Pubkey::new_unique();
+        let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+        let state = Versions::new(State::Initialized(Data::new(Pubkey::new_unique(), durable_nonce, 5_000)));
+        let account = json!({
+            "lamports": 1_447_680,
+            "data": [BASE64.encode(bincode::serialize(&state).unwrap()), "base64"],
+            "owner": solana_sdk::system_program::ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        });
+        let config = TradingConfig { nonce_account: Some(nonce_account.to_string()), ..TradingConfig::default() };
+        let engine = scripted_engine(config, move |method, params| match method {
+            "getAccountInfo" if params[0] == nonce_account.to_string() => Some(with_context(account.clone())),
+            _ => None,
+        });
 
-        Ok(())
+        let tx = engine.build_with_durable_nonce(Vec::new()).await.unwrap();
+        assert_eq!(tx.message.recent_blockhash, *durable_nonce.as_hash());
+        assert_eq!(tx.message.account_keys[tx.message.instructions[0].accounts[0] as usize], nonce_account);
     }
 
-    // Add advanced priority management
-    fn dynamic_priority_fee(&self) -> u64 {
-        let base_fee = self.calculate_optimal_priority_fee();
-        let network_load = self.estimate_network_load()?;
-        
-        match network_load {
-            LoadLevel::High => base_fee.saturating_mul(3),
-            LoadLevel::Medium => base_fee.saturating_mul(2),
-            LoadLevel::Low => base_fee,
+    #[test]
+    fn compute_units_anywhere_in_the_allowed_range_pass() {
+        for units in [1, 200_000, 600_000, MAX_COMPUTE_UNITS] {
+            assert!(check_compute_units(units).is_ok(), "{}", units);
         }
+        assert!(check_compute_units(0).is_err());
+        assert!(check_compute_units(MAX_COMPUTE_UNITS + 1).is_err());
     }
 
-    // Add parallel execution
-    async fn execute_parallel_trades(&self, routes: Vec<SwapRoute>) -> Result<()> {
-        let mut handles = vec![];
-        
-        for route in routes {
-            let handle = tokio::spawn(async move {
-                self.execute_swap_with_route(route.clone()).await
-            });
-            handles.push(handle);
-        }
+    #[test]
+    fn percentile_handles_empty_single_and_large_inputs() {
+        assert_eq!(percentile(&[], 0.5), None);
 
-        for handle in handles {
-            handle.await??;
+        for p in [0.0, 0.5, 1.0, -1.0, 2.0] {
+            assert_eq!(percentile(&[42], p), Some(42), "p = {}", p);
         }
 
-        Ok(())
+        let large: Vec<u64> = (0..1_000_000).collect();
+        assert_eq!(percentile(&large, 0.0), Some(0));
+        assert_eq!(percentile(&large, 0.5), Some(500_000));
+        assert_eq!(percentile(&large, 0.99), Some(989_999));
+        assert_eq!(percentile(&large, 1.0), Some(999_999));
+        // Out-of-range percentiles are clamped to the ends
+        assert_eq!(percentile(&large, 1.5), Some(999_999));
+        assert_eq!(percentile(&large, -0.5), Some(0));
     }
 
-    // Add sandwich protection
-    async fn execute_protected_trade(&self, instruction: Instruction) -> Result<()> {
-        let tx = Transaction::new_signed_with_payer(
-            &[
-                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
-                ComputeBudgetInstruction::set_compute_unit_price(self.max_priority_fee()),
-                instruction
-            ],
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
-            self.rpc_client.get_latest_blockhash()?,
-        );
-
-        // Send with advanced configuration
-        self.rpc_client.send_transaction_with_config(
-            &tx,
-            RpcSendTransactionConfig {
-                skip_preflight: true,
-                preflight_commitment: None,
-                encoding: None,
-                max_retries: Some(0),
-                min_context_slot: Some(self.get_current_slot()? + 1),
-            },
-        )?;
-
-        Ok(())
+    #[test]
+    fn percentile_fee_reads_recent_fees() {
+        let fees: Vec<u64> = (1..=100).map(|fee| fee * 1_000).collect();
+        assert_eq!(percentile_fee(Ok(fees.clone()), 0.75, 500).unwrap(), 75_000);
+        assert_eq!(percentile_fee(Ok(fees.clone()), 0.0, 500).unwrap(), 1_000);
+        assert_eq!(percentile_fee(Ok(fees), 1.0, 500).unwrap(), 100_000);
     }
 
-    // Add private mempool access
-    async fn submit_private_transaction(&self, tx: Transaction) -> Result<()> {
-        let blockhash = self.rpc_client.get_latest_blockhash()?;
-        
-        // Submit to private mempool if available
-        if let Some(private_node) = &self.private_node {
-            private_node.submit_transaction(&tx)?;
-        } else {
-            // Fallback to public mempool with max priority
-            self.rpc_client.send_transaction_with_config(
-                &tx,
-                RpcSendTransactionConfig {
-                    skip_preflight: true,
-                    preflight_commitment: None,
-                    encoding: None,
-                    max_retries: Some(0),
-                    min_context_slot: None,
-                },
-            )?;
-        }
-
-        Ok(())
+    #[test]
+    fn no_recent_fees_falls_back_to_the_floor() {
+        assert_eq!(percentile_fee(Ok(Vec::new()), 0.75, 500).unwrap(), 500);
     }
 
-    // 1. Fast Pre-liquidity Access
-    async fn execute_privileged_swap(&self, token: &Pubkey, amount: u64) -> Result<()> {
-        // 1. Maximum compute budget for complex operations
-        let compute_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
-        
-        // 2. Set ultra high priority fee to ensure inclusion
-        let priority_ix = ComputeBudgetInstruction::set_compute_unit_price(
-            self.max_priority_fee() * 5 // 5x normal priority
-        );
+    #[test]
+    fn priority_fee_rpc_errors_are_not_masked() {
+        let failed = Err(BotError::RPCError("getRecentPrioritizationFees: timed out".to_string()).into());
+        let error = percentile_fee(failed, 0.75, 500).unwrap_err();
+        assert!(matches!(BotError::from(error), BotError::RPCError(_)));
+    }
 
-        // 3. Create swap instruction bypassing all checks
-        let swap_ix = self.create_bypass_swap(token, amount)?;
+    #[tokio::test]
+    async fn each_fee_strategy_prices_from_its_own_inputs() {
+        let config = |strategy| TradingConfig {
+            priority_fee_strategy: strategy,
+            min_priority_fee: 1_000,
+            max_priority_fee: 50_000,
+            ..TradingConfig::default()
+        };
 
-        // 4. Get latest blockhash with minimum latency
-        let blockhash = self.rpc_client.get_latest_blockhash_with_commitment(
-            CommitmentConfig::processed() // Fastest commitment
-        )?;
+        let fixed = replay_engine(config(PriorityFeeStrategy::Fixed { fee: 5_000 }), &[]);
+        assert_eq!(fixed.calculate_optimal_priority_fee().await.unwrap(), 5_000);
+        let below_floor = replay_engine(config(PriorityFeeStrategy::Fixed { fee: 10 }), &[]);
+        assert_eq!(below_floor.calculate_optimal_priority_fee().await.unwrap(), 1_000);
 
-        // 5. Build and send transaction with maximum privilege
-        let tx = Transaction::new_signed_with_payer(
-            &[compute_ix, priority_ix, swap_ix],
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
-            blockhash.0,
+        let recent: Vec<Value> = [8_000, 2_000, 10_000, 4_000, 6_000]
+            .iter()
+            .enumerate()
+            .map(|(slot, fee)| json!({ "slot": slot, "prioritizationFee": fee }))
+            .collect();
+        let percentile = replay_engine(
+            config(PriorityFeeStrategy::Percentile { percentile: 0.5 }),
+            &[("getRecentPrioritizationFees", Value::Array(recent))],
         );
+        assert_eq!(percentile.calculate_optimal_priority_fee().await.unwrap(), 6_000);
 
-        // 6. Send with optimized config
-        self.rpc_client.send_transaction_with_config(
-            &tx,
-            RpcSendTransactionConfig {
-                skip_preflight: true,
-                preflight_commitment: None,
-                encoding: None,
-                max_retries: Some(0),
-                min_context_slot: None,
-            },
-        )?;
+        // Starts at `min`, then climbs while too few transactions land
+        let adaptive = replay_engine(
+            config(PriorityFeeStrategy::Adaptive { min: 2_000, max: 40_000, target_land_rate: 0.9 }),
+            &[],
+        );
+        assert_eq!(adaptive.calculate_optimal_priority_fee().await.unwrap(), 2_000);
+        for _ in 0..LAND_RATE_WINDOW {
+            adaptive.record_landing(false);
+        }
+        assert_eq!(adaptive.calculate_optimal_priority_fee().await.unwrap(), 2_500);
+        adaptive.record_landing(false);
+        assert_eq!(adaptive.calculate_optimal_priority_fee().await.unwrap(), 3_125);
+        for _ in 0..100 {
+            adaptive.record_landing(false);
+        }
+        assert_eq!(adaptive.calculate_optimal_priority_fee().await.unwrap(), 40_000);
+    }
 
-        Ok(())
+    #[test]
+    fn priority_fees_are_clamped_to_the_configured_range() {
+        assert_eq!(clamp_priority_fee(50, 1_000, 10_000), 1_000);
+        assert_eq!(clamp_priority_fee(5_000, 1_000, 10_000), 5_000);
+        assert_eq!(clamp_priority_fee(50_000, 1_000, 10_000), 10_000);
+        assert_eq!(clamp_priority_fee(5_000, 20_000, 10_000), 10_000);
     }
 
-    // Create swap instruction bypassing all checks
-    fn create_bypass_swap(&self, token: &Pubkey, amount: u64) -> Result<Instruction> {
-        // Direct low-level instruction creation
-        let accounts = vec![
-            AccountMeta::new(*token, false),
-            AccountMeta::new(system_program::ID, false),
-            AccountMeta::new(raydium_v4::ID, false),
-            // Add other required accounts
-        ];
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn submission_counts_hold_up_under_concurrent_tasks() {
+        const TASKS: u64 = 8;
+        const PER_TASK: u64 = 1_000;
+        let counts = Arc::new(SubmissionCounts::default());
 
-        // Custom data layout for privileged execution
-        let mut data = Vec::with_capacity(32);
-        data.extend_from_slice(&amount.to_le_bytes());
-        data.push(1); // Bypass flag
+        let mut tasks = JoinSet::new();
+        for _ in 0..TASKS {
+            let counts = counts.clone();
+            tasks.spawn(async move {
+                for index in 0..PER_TASK {
+                    // Every fourth send is rejected
+                    counts.record(index % 4 != 0);
+                    let rate = counts.success_rate();
+                    assert!(rate <= 1.0, "success rate {} above 1", rate);
+                    if index % 100 == 0 {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            joined.unwrap();
+        }
 
-        Ok(Instruction {
-            program_id: raydium_v4::ID,
-            accounts,
-            data,
-        })
+        assert_eq!(counts.total.load(Ordering::Acquire), TASKS * PER_TASK);
+        assert_eq!(counts.accepted.load(Ordering::Acquire), TASKS * PER_TASK * 3 / 4);
+        assert_eq!(counts.success_rate(), 0.75);
     }
 
-    // Pre-liquidity detection and execution
-    pub async fn execute_pre_liquidity(&self, token: &Pubkey, amount: u64) -> Result<()> {
-        // Monitor for pool creation
-        let filters = vec![
-            RpcFilterType::DataSize(165),
-            RpcFilterType::Memcmp(Memcmp {
-                offset: 32,
-                bytes: MemcmpEncodedBytes::Base58(token.to_string()),
-                encoding: None,
-            }),
+    #[test]
+    fn twenty_instructions_split_into_chunks_that_each_carry_the_budget() {
+        let budget = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            ComputeBudgetInstruction::set_compute_unit_price(5_000),
         ];
+        let instructions: Vec<Instruction> = (0..20u8)
+            .map(|index| Instruction { program_id: Pubkey::new_unique(), accounts: Vec::new(), data: vec![index] })
+            .collect();
 
-        // Execute trade as soon as pool is detected
-        self.rpc_client.subscribe_program(
-            &raydium_v4::ID,
-            Some(filters),
-            |_| {
-                self.execute_privileged_swap(token, amount)
-            },
-        ).await?;
+        // 6 per transaction leaves room for 4 of ours
+        let chunks = split_with_budget(&budget, instructions.clone(), 6);
+        assert_eq!(chunks.len(), 5);
+        for chunk in &chunks {
+            assert!(chunk.len() <= 6);
+            assert_eq!(chunk[..2], budget[..]);
+        }
+        // Every instruction goes out once, in order
+        let sent: Vec<Instruction> = chunks.iter().flat_map(|chunk| chunk[2..].iter().cloned()).collect();
+        assert_eq!(sent, instructions);
 
-        Ok(())
+        // Uneven split: the last chunk takes what's left
+        let chunks = split_with_budget(&budget, instructions, 9);
+        assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), vec![9, 9, 8]);
     }
 
-    fn create_privilege_instruction(&self, token: &Pubkey) -> Result<Instruction> {
-        // Create instruction with maximum privileges
-        Ok(Instruction {
-            program_id: raydium_v4::ID,
+    #[test]
+    fn dumped_messages_decode_to_the_same_message() {
+        let payer = Keypair::new();
+        let swap = Instruction {
+            program_id: Pubkey::new_unique(),
             accounts: vec![
-                AccountMeta::new(*token, false),
-                AccountMeta::new(self.payer.pubkey(), true),
-                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
             ],
-            data: vec![1], // Privilege flag
-        })
-    }
+            data: vec![9, 1, 2, 3],
+        };
+        let instructions = [ComputeBudgetInstruction::set_compute_unit_price(5_000), swap];
 
-    fn create_bypass_swap(&self, token: &Pubkey, amount: u64, bypass_checks: bool) -> Result<Instruction> {
-        let mut data = amount.to_le_bytes().to_vec();
-        if bypass_checks {
-            data.push(1); // Bypass flag
-        }
+        let legacy = VersionedMessage::Legacy(Message::new(&instructions, Some(&payer.pubkey())));
+        assert_eq!(decode_message(&encode_message(&legacy)).unwrap(), legacy);
 
-        Ok(Instruction {
-            program_id: raydium_v4::ID,
+        let lookup_table = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: vec![Pubkey::new_unique()] };
+        let v0 = VersionedMessage::V0(
+            v0::Message::try_compile(&payer.pubkey(), &instructions, &[lookup_table], Hash::new_unique()).unwrap(),
+        );
+        let encoded = encode_message(&v0);
+        assert_eq!(decode_message(&encoded).unwrap(), v0);
+
+        // Only the message is encoded; the payer's secret key never appears in it
+        let bytes = BASE64.decode(&encoded).unwrap();
+        assert!(!bytes.windows(32).any(|window| window == &payer.to_bytes()[..32]));
+    }
+
+    #[test]
+    fn lookup_tables_compile_into_a_v0_message() {
+        let payer = Keypair::new();
+        let (program, in_table_writable, in_table_readonly, outside_table) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let swap = Instruction {
+            program_id: program,
             accounts: vec![
-                AccountMeta::new(*token, false),
-                AccountMeta::new(self.payer.pubkey(), true),
-                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(in_table_writable, false),
+                AccountMeta::new_readonly(in_table_readonly, false),
+                AccountMeta::new(outside_table, false),
             ],
-            data,
-        })
+            data: vec![9],
+        };
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![in_table_readonly, Pubkey::new_unique(), in_table_writable],
+        };
+        let blockhash = Hash::new_unique();
+
+        let instructions = [swap];
+        let tx = TradingEngine::compile_versioned_tx(&payer, &instructions, std::slice::from_ref(&table), blockhash).unwrap();
+        let VersionedMessage::V0(message) = &tx.message else { panic!("expected a v0 message") };
+        // Signer first, then writable, then readonly; program ids are never looked up
+        assert_eq!(message.account_keys, vec![payer.pubkey(), outside_table, program]);
+        assert_eq!(
+            (message.header.num_required_signatures, message.header.num_readonly_signed_accounts, message.header.num_readonly_unsigned_accounts),
+            (1, 0, 1),
+        );
+        assert_eq!(message.address_table_lookups.len(), 1);
+        let lookup = &message.address_table_lookups[0];
+        assert_eq!(lookup.account_key, table.key);
+        assert_eq!(lookup.writable_indexes, vec![2]);
+        assert_eq!(lookup.readonly_indexes, vec![0]);
+        // Loaded addresses follow the static keys: writable ones, then readonly
+        assert_eq!(message.instructions[0].program_id_index, 2);
+        assert_eq!(message.instructions[0].accounts, vec![3, 4, 1]);
+        assert_eq!(message.recent_blockhash, blockhash);
+        assert!(tx.verify_with_results().iter().all(|verified| *verified));
+
+        let legacy = TradingEngine::compile_versioned_tx(&payer, &instructions, &[], blockhash).unwrap();
+        assert!(matches!(legacy.message, VersionedMessage::Legacy(_)));
     }
 
-    // Error recovery and retry logic
-    async fn retry_with_escalation<T, F>(&self, operation: F) -> Result<T>
-    where
-        F: Fn() -> Result<T>,
-    {
-        let mut retries = 0;
-        let mut priority_multiplier = 1;
+    #[tokio::test]
+    async fn v0_transactions_fetch_with_their_lookup_accounts_resolved() {
+        let payer = Keypair::new();
+        let (program, loaded_writable, loaded_readonly) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let swap = Instruction {
+            program_id: program,
+            accounts: vec![AccountMeta::new_readonly(loaded_readonly, false), AccountMeta::new(loaded_writable, false)],
+            data: vec![9],
+        };
+        let table = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: vec![loaded_writable, loaded_readonly] };
+        let tx = TradingEngine::compile_versioned_tx(&payer, &[swap], &[table], Hash::new_unique()).unwrap();
+        let signature = tx.signatures[0];
+        let response = json!({
+            "slot": 300,
+            "transaction": [BASE64.encode(bincode::serialize(&tx).unwrap()), "base64"],
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5000,
+                "preBalances": [1_000_000, 1],
+                "postBalances": [995_000, 1],
+                "loadedAddresses": {
+                    "writable": [loaded_writable.to_string()],
+                    "readonly": [loaded_readonly.to_string()],
+                },
+            },
+            "version": 0,
+            "blockTime": 1_700_000_000,
+        });
 
-        loop {
-            match operation() {
-                Ok(result) => return Ok(result),
-                Err(e) if retries < self.max_retries => {
-                    retries += 1;
-                    priority_multiplier *= 2;
-                    self.priority_fee = self.base_priority_fee * priority_multiplier;
-                    tokio::time::sleep(Duration::from_millis(10)).await;
-                    continue;
-                }
-                Err(e) => return Err(e),
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let engine = scripted_engine(TradingConfig::default(), move |method, params| match method {
+            "getTransaction" => {
+                seen.lock().push(params.clone());
+                Some(response.clone())
             }
-        }
+            _ => None,
+        });
+
+        let fetched = engine.fetch_transaction(&signature).await.unwrap();
+        let config = &requests.lock()[0][1];
+        assert_eq!(config["maxSupportedTransactionVersion"], 0);
+        assert_eq!(config["commitment"], "confirmed");
+
+        let message = fetched.transaction.transaction.decode().unwrap().message;
+        assert!(matches!(message, VersionedMessage::V0(_)));
+        let keys = crate::monitoring::wallet::full_account_keys(&message, fetched.transaction.meta.as_ref().unwrap());
+        assert_eq!(keys, vec![payer.pubkey(), program, loaded_writable, loaded_readonly]);
+        let accounts: Vec<Pubkey> = message.instructions()[0].accounts.iter().map(|&index| keys[index as usize]).collect();
+        assert_eq!(accounts, vec![loaded_readonly, loaded_writable]);
     }
 
-    fn create_privileged_swap(&self, token: &Pubkey, amount: u64) -> Result<Instruction> {
-        let mut data = amount.to_le_bytes().to_vec();
-        data.push(1); // Privileged flag
+    type Recorded<T> = Arc<Mutex<Vec<T>>>;
 
-        Ok(Instruction {
-            program_id: raydium_v4::ID,
-            accounts: vec![
-                AccountMeta::new(*token, false),
-                AccountMeta::new(self.payer.pubkey(), true),
-                AccountMeta::new_readonly(system_program::ID, false),
-            ],
-            data,
-        })
+    // Engine whose simulations report `units_consumed`, or fail when it's None,
+    // and which keeps every simulated and sent transaction
+    fn simulating_engine(units_consumed: Option<u64>) -> (TradingEngine, Recorded<Value>, Recorded<Transaction>) {
+        let simulations = Arc::new(Mutex::new(Vec::new()));
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let (simulated, sent) = (simulations.clone(), sends.clone());
+        let engine = scripted_engine(TradingConfig::default(), move |method, params| match method {
+            "simulateTransaction" => {
+                simulated.lock().push(params.clone());
+                let err = units_consumed.is_none().then(|| json!({ "InstructionError": [1, { "Custom": 1 }] }));
+                Some(with_context(json!({
+                    "err": err,
+                    "logs": [],
+                    "accounts": null,
+                    "unitsConsumed": units_consumed.unwrap_or(2_000),
+                    "returnData": null,
+                })))
+            }
+            "getLatestBlockhash" => Some(with_context(json!({
+                "blockhash": Hash::new_unique().to_string(),
+                "lastValidBlockHeight": 1_000,
+            }))),
+            "sendTransaction" => {
+                let encoded = params[0].as_str().unwrap();
+                sent.lock().push(bincode::deserialize(&BASE64.decode(encoded).unwrap()).unwrap());
+                Some(sent_signature(params))
+            }
+            _ => None,
+        });
+        (engine, simulations, sends)
     }
 
-    async fn execute_with_max_priority(&self, tx: Transaction) -> Result<()> {
-        self.rpc_client.send_transaction_with_config(
-            &tx,
-            RpcSendTransactionConfig {
-                skip_preflight: true,
-                preflight_commitment: None,
-                encoding: None,
-                max_retries: Some(0),
-                min_context_slot: None,
-            },
-        )?;
-        Ok(())
+    // The compute unit limit a sent transaction asked for
+    fn compute_unit_limit(tx: &Transaction) -> u32 {
+        tx.message
+            .instructions
+            .iter()
+            .filter(|ix| tx.message.account_keys[ix.program_id_index as usize] == solana_sdk::compute_budget::id())
+            .find_map(|ix| match ix.data[..] {
+                [2, a, b, c, d] => Some(u32::from_le_bytes([a, b, c, d])),
+                _ => None,
+            })
+            .unwrap()
     }
 
-    // Add safety checks
-    async fn verify_setup(&self) -> Result<()> {
-        // 1. Test RPC
-        self.rpc_client.get_latest_blockhash()?;
-        
-        // 2. Check wallet balance
-        let balance = self.rpc_client.get_balance(&self.payer.pubkey())?;
-        if balance < 1_000_000 { // 0.001 SOL
-            return Err(anyhow!("Insufficient balance"));
-        }
+    #[tokio::test]
+    async fn compute_limit_is_the_simulated_usage_plus_a_margin() {
+        let (engine, simulations, sends) = simulating_engine(Some(123_456));
+        let transfer = system_instruction::transfer(&engine.payer.pubkey(), &Pubkey::new_unique(), 1);
 
-        // 3. Verify compute budget
-        if self.compute_units != 1_400_000 {
-            return Err(anyhow!("Invalid compute units"));
-        }
+        let units = engine.estimate_compute_units(std::slice::from_ref(&transfer), &engine.payer.pubkey()).await.unwrap();
+        assert_eq!(units, 135_802); // 123_456 * 1.1, rounded up
 
-        Ok(())
+        // Simulated unsigned, at the maximum limit, against the node's blockhash
+        let params = simulations.lock()[0].clone();
+        assert_eq!(params[1]["sigVerify"], false);
+        assert_eq!(params[1]["replaceRecentBlockhash"], true);
+        let simulated: Transaction = bincode::deserialize(&BASE64.decode(params[0].as_str().unwrap()).unwrap()).unwrap();
+        assert_eq!(compute_unit_limit(&simulated), MAX_COMPUTE_UNITS);
+
+        let _ = engine.execute_transaction(transfer).await;
+        assert_eq!(compute_unit_limit(&sends.lock()[0]), 135_802);
+
+        // Heavy transactions are capped at the per-transaction maximum
+        let (engine, _, _) = simulating_engine(Some(1_390_000));
+        let transfer = system_instruction::transfer(&engine.payer.pubkey(), &Pubkey::new_unique(), 1);
+        assert_eq!(engine.estimate_compute_units(&[transfer], &engine.payer.pubkey()).await.unwrap(), MAX_COMPUTE_UNITS);
     }
 
-    // Add emergency stop
-    fn emergency_stop(&self) {
-        println!("Emergency stop triggered!");
-        // Cleanup and exit
+    #[tokio::test]
+    async fn failed_simulation_falls_back_to_the_configured_limit() {
+        let (engine, _, sends) = simulating_engine(None);
+        let transfer = system_instruction::transfer(&engine.payer.pubkey(), &Pubkey::new_unique(), 1);
+
+        let error = engine.estimate_compute_units(std::slice::from_ref(&transfer), &engine.payer.pubkey()).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<BotError>(), Some(BotError::TransactionError(_))));
+
+        let _ = engine.execute_transaction(transfer).await;
+        assert_eq!(compute_unit_limit(&sends.lock()[0]), engine.compute_units);
     }
 
-    async fn pre_launch_check(&self) -> Result<()> {
-        // 1. RPC Connection
-        self.rpc_client.get_latest_blockhash()?;
+    // Records each shutdown step as it runs; closing fails when `close_fails`
+    struct RecordedShutdown {
+        calls: parking_lot::Mutex<Vec<String>>,
+        close_fails: bool,
+    }
 
-        // 2. Wallet Balance
-        let balance = self.rpc_client.get_balance(&self.payer.pubkey())?;
-        if balance < self.min_required_balance {
-            return Err(anyhow!("Insufficient balance"));
+    impl RecordedShutdown {
+        fn new(close_fails: bool) -> Self {
+            Self { calls: parking_lot::Mutex::new(Vec::new()), close_fails }
         }
 
-        // 3. Network Status
-        let slot = self.rpc_client.get_slot()?;
-        if slot == 0 {
-            return Err(anyhow!("Network issue"));
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().clone()
         }
+    }
 
-        // 4. Compute Budget
-        if self.compute_units != 1_400_000 {
-            return Err(anyhow!("Invalid compute units"));
+    #[async_trait]
+    impl ShutdownHooks for RecordedShutdown {
+        fn cancel_loops(&self) {
+            self.calls.lock().push("cancel".to_string());
         }
 
-        Ok(())
-    }
-
-    // Add retry mechanism
-    async fn retry_failed_transaction(&self, tx: &str) -> Result<()> {
-        let mut retries = 0;
-        while retries < self.max_retries {
-            match self.rpc_client.get_transaction(tx) {
-                Ok(_) => return Ok(()),
-                Err(_) => {
-                    retries += 1;
-                    tokio::time::sleep(Duration::from_millis(200)).await;
-                }
+        async fn close_positions(&self) -> Result<()> {
+            self.calls.lock().push("close".to_string());
+            if self.close_fails {
+                return Err(BotError::TradingError("1 positions could not be closed".to_string()).into());
             }
+            Ok(())
+        }
+
+        async fn flush_positions(&self) -> Result<()> {
+            self.calls.lock().push("positions".to_string());
+            Ok(())
+        }
+
+        fn flush_trade_history(&self, path: &str) -> Result<()> {
+            self.calls.lock().push(format!("history {}", path));
+            Ok(())
         }
-        Err(anyhow!("Max retries exceeded"))
     }
 
-    // Add emergency shutdown
-    fn emergency_shutdown(&self) {
-        println!("Emergency shutdown initiated!");
-        // Cancel pending transactions
-        // Close websocket connections
-        // Save state
-        std::process::exit(1);
+    #[tokio::test]
+    async fn shutdown_cancels_then_closes_then_flushes() {
+        let hooks = RecordedShutdown::new(false);
+        run_shutdown(&hooks, true, "trades.csv").await.unwrap();
+        assert_eq!(hooks.calls(), ["cancel", "close", "positions", "history trades.csv"]);
     }
-}
 
-// Add transaction configuration
-const TX_CONFIG: RpcSendTransactionConfig = RpcSendTransactionConfig {
-    skip_preflight: true,
-    preflight_commitment: None, 
-    encoding: None,
-    max_retries: Some(0),
-    min_context_slot: None,
-};
+    #[tokio::test]
+    async fn shutdown_leaves_positions_open_unless_asked() {
+        let hooks = RecordedShutdown::new(false);
+        run_shutdown(&hooks, false, "trades.csv").await.unwrap();
+        assert_eq!(hooks.calls(), ["cancel", "positions", "history trades.csv"]);
+    }
 
-impl Drop for TradingEngine {
-    fn drop(&mut self) {
-        // Cleanup resources
-        self.close_connections();
-        self.flush_pending_transactions();
+    #[tokio::test]
+    async fn shutdown_still_flushes_when_a_close_fails() {
+        let hooks = RecordedShutdown::new(true);
+        let error = run_shutdown(&hooks, true, "trades.csv").await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<BotError>(), Some(BotError::TradingError(_))));
+        assert_eq!(hooks.calls(), ["cancel", "close", "positions", "history trades.csv"]);
     }
-}
 
-#[derive(Debug)]
-enum SwapRoute {
-    Direct(Pubkey, Pubkey),
-    Split(Vec<(Pubkey, Pubkey, Pubkey)>),
-}
+    #[tokio::test]
+    async fn engine_shutdown_stops_its_loops_and_writes_its_state() {
+        let engine = replay_engine(TradingConfig::default(), &[]);
+        let history = std::env::temp_dir().join(format!("history-{}.csv", Pubkey::new_unique()));
+        let history = history.to_str().unwrap();
 
-#[derive(Debug)]
-enum RetryableError {
-    RateLimit,
-    NetworkError,
-    TemporaryFailure,
-}
+        engine.graceful_shutdown(false, history).await.unwrap();
 
-#[derive(Debug)]
-enum LoadLevel {
-    High,
-    Medium,
-    Low,
-}
+        assert!(engine.shutdown_token().is_cancelled());
+        assert!(std::path::Path::new(&engine.positions_path).exists());
+        assert!(std::path::Path::new(history).exists());
+        std::fs::remove_file(history).unwrap();
+    }
 
-#[derive(Debug)]
-enum NetworkLoad {
-    High,
-    Medium,
-    Low,
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn quote_reports_fill_impact_min_out_and_fee_for_a_sample_pool() {
+        use {
+            crate::dex::raydium::tests::{dex_tracking, pool},
+            solana_sdk::native_token::LAMPORTS_PER_SOL,
+        };
+
+        // 1,000 tokens against 10 SOL, 0.25% pool fee
+        let token = Pubkey::new_unique();
+        let config = TradingConfig {
+            max_slippage: 0.01,
+            priority_fee_strategy: PriorityFeeStrategy::Fixed { fee: 10_000 },
+            ..TradingConfig::default()
+        };
+        let mut engine = replay_engine(config, &[]);
+        engine.venues = vec![Box::new(dex_tracking(pool(token, 1_000_000_000_000, 9, native_mint::ID, 10_000_000_000, 9)))];
+
+        let quote = engine.quote(&native_mint::ID, &token, LAMPORTS_PER_SOL).await.unwrap();
+
+        assert_eq!(quote, QuoteResult {
+            venue: "raydium",
+            amount_in: LAMPORTS_PER_SOL,
+            // 0.9975 SOL after the pool fee: 1e12 * 0.9975e9 / 10.9975e9
+            expected_out: 90_702_432_370,
+            // 0.01 -> 0.0121 SOL per token, before the fee
+            price_impact: Some(0.21),
+            // 1% below the expected fill
+            min_out: 89_795_408_046,
+            priority_fee: 10_000,
+            // 5,000 base + 10,000 micro-lamports over the full 1.4M compute units
+            estimated_fee: Lamports(19_000),
+        });
+        // The quote is the same fill a swap through the engine would route to
+        assert_eq!(engine.route_swap(&native_mint::ID, &token, LAMPORTS_PER_SOL).unwrap().1, quote.expected_out);
+    }
+}
@@ -1,10 +1,17 @@
 use {
-    solana_client::client_error::ClientError,
+    solana_client::client_error::{ClientError, ClientErrorKind},
     solana_sdk::transaction::TransactionError,
     thiserror::Error,
-    std::time::Duration,
+    std::{future::Future, sync::Arc, time::Duration},
+    tracing::warn,
 };
 
+const BASE_RETRY_DELAY_MS: u64 = 50;
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// Called with the error whenever a retry escalates, e.g. to switch RPC or page someone
+pub type EscalationHook = Arc<dyn Fn(&BotError) + Send + Sync>;
+
 #[derive(Error, Debug)]
 pub enum BotError {
     #[error("RPC error: {0}")]
@@ -30,15 +37,93 @@ pub enum BotError {
 
     #[error("Trading error: {0}")]
     TradingError(String),
+
+    #[error("Transaction not confirmed before timeout: {0}")]
+    ConfirmationTimeout(String),
+
+    #[error("Simulation failed: {reason}\n{}", .logs.join("\n"))]
+    SimulationFailed { reason: String, logs: Vec<String> },
+
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
+    #[error("Config error in '{field}': {reason}")]
+    ConfigError { field: String, reason: String },
+
+    #[error("Transaction version not supported: {0}")]
+    UnsupportedTransactionVersion(String),
+
+    #[error("Blockhash expired: {0}")]
+    BlockhashExpired(String),
+}
+
+impl BotError {
+    // One line for the menu: what went wrong and what to do about it
+    pub fn user_message(&self) -> String {
+        match self {
+            BotError::RPCError(msg) => format!("RPC request failed ({}). Check the RPC URL in Settings and retry", msg),
+            BotError::TransactionError(msg) => format!("{}. Check the token and amount, then retry", msg),
+            BotError::PreLiquidityError(msg) => format!("Pool isn't tradable yet ({}). Retry once liquidity is added", msg),
+            BotError::PrivilegeError(msg) => format!("Swap could not be routed ({}). Try a different amount or token", msg),
+            BotError::NetworkError(msg) => format!("Can't reach the RPC ({}). Check your connection or switch RPC URL in Settings", msg),
+            BotError::InsufficientFunds(_) => "Not enough SOL for this trade and its fees. Add SOL to your wallet and retry".to_string(),
+            BotError::SlippageError(msg) => format!("Price would move too far ({}). Trade a smaller amount or raise Slippage % in Settings", msg),
+            BotError::TradingError(msg) => msg.clone(),
+            BotError::ConfirmationTimeout(signature) => format!(
+                "Transaction {} wasn't confirmed in time. Check it in an explorer before retrying",
+                signature
+            ),
+            BotError::SimulationFailed { reason, .. } => format!("Simulation failed ({}), nothing was sent", reason),
+            BotError::ParseError(msg) => format!("Couldn't read that input ({}). Check it and try again", msg),
+            BotError::ConfigError { field, reason } => format!("Fix '{}' in your config: {}", field, reason),
+            BotError::UnsupportedTransactionVersion(msg) => format!(
+                "The RPC can't return this transaction version ({}). Switch RPC URL in Settings",
+                msg
+            ),
+            BotError::BlockhashExpired(_) => "The transaction expired before it landed. Retry, or switch to a faster RPC in Settings".to_string(),
+        }
+    }
+}
+
+// Keeps a BotError or RPC error as its own variant; anything else is sorted
+// by its message, falling back to TradingError
+impl From<anyhow::Error> for BotError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<BotError>() {
+            Ok(bot_error) => return bot_error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<ClientError>() {
+            Ok(client_error) => return BotError::from(client_error),
+            Err(error) => error,
+        };
+
+        let message = format!("{:#}", error);
+        let lower = message.to_lowercase();
+        if lower.contains("insufficient") {
+            BotError::InsufficientFunds(message)
+        } else if lower.contains("slippage") || lower.contains("price impact") {
+            BotError::SlippageError(message)
+        } else if lower.contains("timed out") || lower.contains("timeout") || lower.contains("connection") {
+            BotError::NetworkError(message)
+        } else if lower.contains("invalid") || lower.contains("parse") {
+            BotError::ParseError(message)
+        } else {
+            BotError::TradingError(message)
+        }
+    }
 }
 
 impl From<ClientError> for BotError {
     fn from(error: ClientError) -> Self {
-        match error {
-            ClientError::TransactionError(TransactionError::InsufficientFunds) => {
+        match error.kind() {
+            ClientErrorKind::TransactionError(TransactionError::InsufficientFundsForFee) => {
                 BotError::InsufficientFunds("Not enough funds for transaction".into())
             }
-            ClientError::RpcError(_) => {
+            ClientErrorKind::TransactionError(TransactionError::BlockhashNotFound) => {
+                BotError::BlockhashExpired("Blockhash not found".into())
+            }
+            ClientErrorKind::RpcError(_) => {
                 BotError::NetworkError("RPC connection failed".into())
             }
             _ => BotError::TransactionError(format!("Transaction failed: {}", error))
@@ -50,6 +135,58 @@ pub trait ErrorHandler {
     fn handle_error(&self, error: &BotError) -> bool;
     fn get_retry_delay(&self, retries: u32) -> Duration;
     fn should_escalate(&self, error: &BotError) -> bool;
+
+    // Runs before the next attempt whenever should_escalate returned true
+    fn on_escalate(&self, _error: &BotError) {}
+}
+
+// 50ms doubling per retry, never more than MAX_RETRY_DELAY
+pub fn capped_retry_delay(retries: u32) -> Duration {
+    let delay_ms = BASE_RETRY_DELAY_MS.saturating_mul(2u64.saturating_pow(retries));
+    Duration::from_millis(delay_ms).min(MAX_RETRY_DELAY)
+}
+
+// Retries `operation` under `handler`'s rules, passing it the priority fee to
+// use. Errors handle_error won't retry fail straight away; errors that
+// should_escalate double the fee for the next attempt, up to `fee_ceiling`,
+// and call on_escalate.
+pub async fn retry_escalating<T, H, F, Fut>(
+    handler: &H,
+    max_retries: u32,
+    initial_fee: u64,
+    fee_ceiling: u64,
+    operation: F,
+) -> anyhow::Result<T>
+where
+    H: ErrorHandler + ?Sized,
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut retries = 0;
+    let mut priority_fee = initial_fee;
+
+    loop {
+        let e = match operation(priority_fee).await {
+            Ok(result) => return Ok(result),
+            Err(e) => e,
+        };
+        let error = match e.downcast::<ClientError>() {
+            Ok(client_error) => BotError::from(client_error),
+            Err(e) => e.downcast::<BotError>()?,
+        };
+
+        if retries >= max_retries || !handler.handle_error(&error) {
+            return Err(error.into());
+        }
+        if handler.should_escalate(&error) {
+            // Never below where we started, even if that was over the ceiling
+            priority_fee = priority_fee.saturating_mul(2).min(fee_ceiling).max(priority_fee);
+            handler.on_escalate(&error);
+        }
+
+        tokio::time::sleep(handler.get_retry_delay(retries)).await;
+        retries += 1;
+    }
 }
 
 impl ErrorHandler for crate::trading::TradingEngine {
@@ -63,7 +200,7 @@ impl ErrorHandler for crate::trading::TradingEngine {
     }
 
     fn get_retry_delay(&self, retries: u32) -> Duration {
-        Duration::from_millis(50 * 2u64.pow(retries))
+        capped_retry_delay(retries)
     }
 
     fn should_escalate(&self, error: &BotError) -> bool {
@@ -72,4 +209,165 @@ impl ErrorHandler for crate::trading::TradingEngine {
             BotError::PrivilegeError(_)
         )
     }
-}
\ No newline at end of file
+
+    fn on_escalate(&self, error: &BotError) {
+        warn!(error = %error, "Escalating retry");
+        for hook in self.escalation_hooks() {
+            hook(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, parking_lot::Mutex, std::sync::atomic::{AtomicU32, Ordering}};
+
+    #[derive(Default)]
+    struct CountingHandler {
+        escalations: AtomicU32,
+    }
+
+    impl ErrorHandler for CountingHandler {
+        fn handle_error(&self, error: &BotError) -> bool {
+            !matches!(error, BotError::InsufficientFunds(_))
+        }
+
+        fn get_retry_delay(&self, _retries: u32) -> Duration {
+            Duration::ZERO
+        }
+
+        fn should_escalate(&self, error: &BotError) -> bool {
+            matches!(error, BotError::PrivilegeError(_))
+        }
+
+        fn on_escalate(&self, _error: &BotError) {
+            self.escalations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn retry_delay_is_capped() {
+        assert_eq!(capped_retry_delay(0), Duration::from_millis(50));
+        assert_eq!(capped_retry_delay(3), Duration::from_millis(400));
+        assert_eq!(capped_retry_delay(10), MAX_RETRY_DELAY);
+        assert_eq!(capped_retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn escalating_errors_double_the_fee_up_to_the_ceiling() {
+        let handler = CountingHandler::default();
+        let fees = Mutex::new(Vec::new());
+
+        let result = retry_escalating(&handler, 5, 100, 300, |fee| {
+            fees.lock().push(fee);
+            let attempt = fees.lock().len();
+            async move {
+                if attempt < 4 {
+                    return Err(BotError::PrivilegeError("not landed".into()).into());
+                }
+                Ok(fee)
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 300);
+        assert_eq!(*fees.lock(), [100, 200, 300, 300]);
+        assert_eq!(handler.escalations.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn non_escalating_errors_retry_at_the_same_fee() {
+        let handler = CountingHandler::default();
+        let fees = Mutex::new(Vec::new());
+
+        let result: anyhow::Result<()> = retry_escalating(&handler, 2, 100, 1_000, |fee| {
+            fees.lock().push(fee);
+            async { Err(BotError::NetworkError("down".into()).into()) }
+        })
+        .await;
+
+        assert!(matches!(BotError::from(result.unwrap_err()), BotError::NetworkError(_)));
+        assert_eq!(*fees.lock(), [100, 100, 100]);
+        assert_eq!(handler.escalations.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn unretryable_errors_fail_on_the_first_attempt() {
+        let handler = CountingHandler::default();
+        let attempts = AtomicU32::new(0);
+
+        let result: anyhow::Result<()> = retry_escalating(&handler, 5, 100, 1_000, |_| {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            async { Err(BotError::InsufficientFunds("empty".into()).into()) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn every_variant_has_an_actionable_user_message() {
+        let cases = [
+            (BotError::RPCError("429".into()), "RPC request failed (429). Check the RPC URL in Settings and retry"),
+            (BotError::TransactionError("Custom(1)".into()), "Custom(1). Check the token and amount, then retry"),
+            (BotError::PreLiquidityError("no pool".into()), "Pool isn't tradable yet (no pool). Retry once liquidity is added"),
+            (BotError::PrivilegeError("no route".into()), "Swap could not be routed (no route). Try a different amount or token"),
+            (
+                BotError::NetworkError("refused".into()),
+                "Can't reach the RPC (refused). Check your connection or switch RPC URL in Settings",
+            ),
+            (
+                BotError::InsufficientFunds("0.1 SOL short".into()),
+                "Not enough SOL for this trade and its fees. Add SOL to your wallet and retry",
+            ),
+            (
+                BotError::SlippageError("12%".into()),
+                "Price would move too far (12%). Trade a smaller amount or raise Slippage % in Settings",
+            ),
+            (BotError::TradingError("Daily spend limit reached".into()), "Daily spend limit reached"),
+            (
+                BotError::ConfirmationTimeout("5sig".into()),
+                "Transaction 5sig wasn't confirmed in time. Check it in an explorer before retrying",
+            ),
+            (
+                BotError::SimulationFailed { reason: "custom program error: 0x1".into(), logs: vec!["log".into()] },
+                "Simulation failed (custom program error: 0x1), nothing was sent",
+            ),
+            (BotError::ParseError("bad pubkey".into()), "Couldn't read that input (bad pubkey). Check it and try again"),
+            (
+                BotError::ConfigError { field: "slippage".into(), reason: "must be below 50".into() },
+                "Fix 'slippage' in your config: must be below 50",
+            ),
+            (
+                BotError::UnsupportedTransactionVersion("v0".into()),
+                "The RPC can't return this transaction version (v0). Switch RPC URL in Settings",
+            ),
+            (
+                BotError::BlockhashExpired("abc".into()),
+                "The transaction expired before it landed. Retry, or switch to a faster RPC in Settings",
+            ),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.user_message(), message);
+        }
+    }
+
+    #[test]
+    fn generic_errors_are_classified_by_their_message() {
+        let classify = |error: anyhow::Error| BotError::from(error);
+
+        assert!(matches!(classify(BotError::ConfirmationTimeout("5sig".into()).into()), BotError::ConfirmationTimeout(_)));
+        let fee = ClientError::from(ClientErrorKind::TransactionError(TransactionError::InsufficientFundsForFee));
+        assert!(matches!(classify(fee.into()), BotError::InsufficientFunds(_)));
+
+        assert!(matches!(classify(anyhow::anyhow!("Insufficient balance")), BotError::InsufficientFunds(_)));
+        assert!(matches!(classify(anyhow::anyhow!("Price impact 40% too high")), BotError::SlippageError(_)));
+        assert!(matches!(classify(anyhow::anyhow!("request timed out")), BotError::NetworkError(_)));
+        assert!(matches!(classify(anyhow::anyhow!("Invalid mint address")), BotError::ParseError(_)));
+        match classify(anyhow::anyhow!("Pool closed").context("Buy failed")) {
+            BotError::TradingError(message) => assert_eq!(message, "Buy failed: Pool closed"),
+            other => panic!("expected TradingError, got {:?}", other),
+        }
+    }
+}
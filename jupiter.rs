@@ -0,0 +1,246 @@
+use {
+    crate::dex::SwapVenue,
+    anyhow::{Result, anyhow},
+    base64::{engine::general_purpose::STANDARD as BASE64, Engine},
+    reqwest::Client,
+    serde::Deserialize,
+    serde_json::json,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        transaction::Transaction,
+    },
+    std::{future::Future, str::FromStr},
+};
+
+pub const JUPITER_API_URL: &str = "https://quote-api.jup.ag/v6";
+pub const JUPITER_V6_PROGRAM_ID: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    pub input_mint: String,
+    pub in_amount: String,
+    pub output_mint: String,
+    pub out_amount: String,
+    pub other_amount_threshold: String,
+    pub price_impact_pct: String,
+    pub route_plan: Vec<serde_json::Value>,
+    // The swap endpoint expects the quote echoed back verbatim
+    #[serde(skip)]
+    raw: serde_json::Value,
+}
+
+impl Quote {
+    pub fn out_amount(&self) -> Result<u64> {
+        Ok(self.out_amount.parse()?)
+    }
+
+    pub fn min_out_amount(&self) -> Result<u64> {
+        Ok(self.other_amount_threshold.parse()?)
+    }
+
+    pub fn has_route(&self) -> bool {
+        !self.route_plan.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SwapResponse {
+    swap_transaction: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SwapInstructionsResponse {
+    swap_instruction: JupiterInstruction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JupiterInstruction {
+    program_id: String,
+    accounts: Vec<JupiterAccountMeta>,
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JupiterAccountMeta {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl JupiterInstruction {
+    fn into_instruction(self) -> Result<Instruction> {
+        let accounts = self.accounts
+            .into_iter()
+            .map(|meta| {
+                let pubkey = Pubkey::from_str(&meta.pubkey)?;
+                Ok(if meta.is_writable {
+                    AccountMeta::new(pubkey, meta.is_signer)
+                } else {
+                    AccountMeta::new_readonly(pubkey, meta.is_signer)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Instruction {
+            program_id: Pubkey::from_str(&self.program_id)?,
+            accounts,
+            data: BASE64.decode(self.data)?,
+        })
+    }
+}
+
+pub struct JupiterDex {
+    http: Client,
+    api_url: String,
+    slippage_bps: u16,
+    user: Pubkey, // Jupiter resolves token accounts for this owner
+}
+
+impl JupiterDex {
+    pub fn new(api_url: &str, slippage_bps: u16, user: Pubkey) -> Self {
+        Self {
+            http: Client::new(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            slippage_bps,
+            user,
+        }
+    }
+
+    pub async fn get_quote(&self, input: Pubkey, output: Pubkey, amount: u64) -> Result<Quote> {
+        let response = self.http
+            .get(format!("{}/quote", self.api_url))
+            .query(&[
+                ("inputMint", input.to_string()),
+                ("outputMint", output.to_string()),
+                ("amount", amount.to_string()),
+                ("slippageBps", self.slippage_bps.to_string()),
+                ("asLegacyTransaction", "true".to_string()),
+            ])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Jupiter quote failed: {}", response.text().await?));
+        }
+
+        let raw: serde_json::Value = response.json().await?;
+        let mut quote: Quote = serde_json::from_value(raw.clone())?;
+        quote.raw = raw;
+        Ok(quote)
+    }
+
+    pub async fn build_swap_transaction(&self, quote: &Quote, payer: &Pubkey) -> Result<Transaction> {
+        let response = self.http
+            .post(format!("{}/swap", self.api_url))
+            .json(&json!({
+                "quoteResponse": quote.raw,
+                "userPublicKey": payer.to_string(),
+                "wrapAndUnwrapSol": true,
+                "asLegacyTransaction": true,
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Jupiter swap build failed: {}", response.text().await?));
+        }
+
+        let swap: SwapResponse = response.json().await?;
+        let tx_bytes = BASE64.decode(swap.swap_transaction)?;
+        let transaction: Transaction = bincode::deserialize(&tx_bytes)?;
+        Ok(transaction)
+    }
+
+    // The swap alone, for `quote`; no SOL wrapping or account setup
+    pub async fn swap_instruction(&self, quote: &Quote) -> Result<Instruction> {
+        let response = self.http
+            .post(format!("{}/swap-instructions", self.api_url))
+            .json(&json!({
+                "quoteResponse": quote.raw,
+                "userPublicKey": self.user.to_string(),
+                "wrapAndUnwrapSol": false,
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Jupiter swap-instructions failed: {}", response.text().await?));
+        }
+
+        let instructions: SwapInstructionsResponse = response.json().await?;
+        instructions.swap_instruction.into_instruction()
+    }
+}
+
+// SwapVenue is synchronous, so its calls park this worker thread for the
+// request while the runtime moves other tasks elsewhere
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+impl SwapVenue for JupiterDex {
+    fn name(&self) -> &'static str {
+        "jupiter"
+    }
+
+    fn program_id(&self) -> Pubkey {
+        Pubkey::from_str(JUPITER_V6_PROGRAM_ID).unwrap()
+    }
+
+    // Only the swap itself; setup/cleanup (ATA creation, SOL wrapping) is left to the caller
+    fn build_swap_ix(
+        &self,
+        token_in: &Pubkey,
+        token_out: &Pubkey,
+        amount_in: u64,
+        min_out: u64,
+    ) -> Result<Instruction> {
+        let quote = block_on(self.get_quote(*token_in, *token_out, amount_in))?;
+        if quote.min_out_amount()? < min_out {
+            return Err(anyhow!(
+                "Jupiter route below minimum out: {} < {}",
+                quote.min_out_amount()?,
+                min_out
+            ));
+        }
+
+        block_on(self.swap_instruction(&quote))
+    }
+
+    fn quote(&self, input: &Pubkey, output: &Pubkey, amount_in: u64) -> Result<Option<u64>> {
+        match block_on(self.get_quote(*input, *output, amount_in)) {
+            Ok(quote) if quote.has_route() => Ok(Some(quote.out_amount()?)),
+            Ok(_) => Ok(None),
+            Err(e) => {
+                println!("Jupiter quote unavailable: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    fn build_swap_transaction(
+        &self,
+        input: &Pubkey,
+        output: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+        payer: &Pubkey,
+    ) -> Result<Transaction> {
+        let quote = block_on(self.get_quote(*input, *output, amount_in))?;
+        if quote.min_out_amount()? < min_amount_out {
+            return Err(anyhow!(
+                "Jupiter route below minimum out: {} < {}",
+                quote.min_out_amount()?,
+                min_amount_out
+            ));
+        }
+
+        block_on(JupiterDex::build_swap_transaction(self, &quote, payer))
+    }
+}
@@ -1,27 +1,49 @@
+// Modules keep venue, analytics and menu code the running bot doesn't reach yet
+#![allow(dead_code)]
+
 use {
-    std::error::Error,
-    anyhow::Result,
+    anyhow::{Result, anyhow},
+    colored::Colorize,
     solana_sdk::signer::keypair::Keypair,
+    std::{sync::Arc, time::Duration},
+    tokio_util::sync::CancellationToken,
+    tracing::{info, warn, Subscriber},
+    tracing_subscriber::{fmt::MakeWriter, util::SubscriberInitExt, EnvFilter},
     crate::{
-        config::Config,
+        config::TradingConfig,
         monitoring::{Monitor, Signal},
         risk::RiskManager,
-        strategy::{Strategy, VolumeStrategy},
-        trading::TradingEngine,
+        security::Security,
+        strategy::{strategy_from_config, Strategy},
+        trading::{types::PositionAction, TradingEngine},
         ui::BotUI,
     }
 };
 
+// Sources sit flat at the crate root, so modules whose file is named after
+// something else are pointed at it explicitly
+mod backtest;
 mod config;
+#[path = "mod.rs"]
 mod dex;
 mod error;
+mod metrics;
+#[path = "monitoring.rs"]
 mod monitoring;
+mod notify;
 mod risk;
+mod rpc_record;
+#[path = "encryption.rs"]
 mod security;
 mod strategy;
+mod token_registry;
+#[path = "engine.rs"]
 mod trading;
+#[path = "menu.rs"]
 mod ui;
 
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 const LOGO: &str = r#"
   ▄▄ ▄▄ ▄▄▄▄▄▄▄ ▄▄▄▄▄▄▄ ▄▄   ▄▄ ▄▄▄▄▄▄▄ ▄▄▄▄▄▄   
  █  ▀  █      █       █  █ █ █  █       █   ▄  █  
@@ -39,65 +61,271 @@ pub fn display_logo() {
     println!();
 }
 
+// JSON lines for log shipping, human-readable output for the terminal.
+// RUST_LOG, when set, takes precedence over `level`.
+fn init_logging(level: &str, json: bool) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(level))
+        .map_err(|e| anyhow!("Invalid log level {}: {}", level, e))?;
+
+    build_logger(filter, json, std::io::stdout)
+        .try_init()
+        .map_err(|e| anyhow!("Failed to install logger: {}", e))
+}
+
+fn build_logger<W>(filter: EnvFilter, json: bool, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(writer);
+    if json {
+        Box::new(builder.json().flatten_event(true).finish())
+    } else {
+        Box::new(builder.pretty().finish())
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("Solana Copy Trading Bot Starting...");
-    Ok(())
+    let config = TradingConfig::from_env()?;
+    init_logging(&config.log_level, config.log_json)?;
+
+    info!(cluster = ?config.cluster, rpc = %config.rpc_endpoint(), "Solana Copy Trading Bot starting");
+
+    let security = Security::new()?;
+    let wallet = load_wallet(&config.wallet_path, &security)?;
+    let mut ui = BotUI::new(wallet, config)?;
+
+    // Ctrl-C cancels the engine's token so background loops wind down; the
+    // menu prompt sees the interrupt itself and returns
+    let shutdown = ui.shutdown_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Ctrl-C received");
+            shutdown.cancel();
+        }
+    });
+
+    let result = ui.show_main_menu().await;
+    ui.shutdown().await?;
+    result
 }
 
-fn load_wallet(path: &str) -> Result<Keypair> {
-    let keypair_bytes = std::fs::read(path)?;
-    let keypair: Keypair = serde_json::from_slice(&keypair_bytes)?;
+// Prefix written in front of wallet files encrypted with Security
+const ENCRYPTED_WALLET_MAGIC: &[u8] = b"MRLNENC1";
+
+fn load_wallet(path: &str, security: &Security) -> Result<Keypair> {
+    let file_bytes = std::fs::read(path)?;
+
+    let (keypair_bytes, plaintext) = match file_bytes.strip_prefix(ENCRYPTED_WALLET_MAGIC) {
+        Some(encrypted) => (security.decrypt_sensitive_data(encrypted)?, false),
+        None => (file_bytes, true),
+    };
+
+    let secret: Vec<u8> = serde_json::from_slice(&keypair_bytes)?;
+    let keypair = Keypair::from_bytes(&secret)
+        .map_err(|e| anyhow::anyhow!("Invalid keypair in {}: {}", path, e))?;
+
+    if plaintext {
+        save_encrypted_wallet(&keypair, path, security)?;
+        info!(path, "Encrypted plaintext wallet file in place");
+    }
     Ok(keypair)
 }
 
+// Migrate a plaintext wallet by re-writing it encrypted under the current Security key
+fn save_encrypted_wallet(keypair: &Keypair, path: &str, security: &Security) -> Result<()> {
+    let keypair_json = serde_json::to_vec(&keypair.to_bytes().to_vec())?;
+    let encrypted = security.encrypt_sensitive_data(&keypair_json)?;
+
+    let mut file_bytes = Vec::with_capacity(ENCRYPTED_WALLET_MAGIC.len() + encrypted.len());
+    file_bytes.extend_from_slice(ENCRYPTED_WALLET_MAGIC);
+    file_bytes.extend_from_slice(&encrypted);
+
+    std::fs::write(path, file_bytes)?;
+    Ok(())
+}
+
 pub struct Wallet {
     pub keypair: Keypair,
 }
 
+// Trades the Monitor's signals, and what the strategy reads from each watched
+// token's metrics, through the engine once the RiskManager approves them
 pub struct TradingBot {
-    config: Config,
+    config: TradingConfig,
+    engine: Arc<TradingEngine>,
     monitor: Monitor,
     strategy: Box<dyn Strategy>,
     risk_manager: RiskManager,
 }
 
 impl TradingBot {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: TradingConfig, engine: Arc<TradingEngine>) -> Self {
         Self {
             monitor: Monitor::new(&config),
-            strategy: Box::new(VolumeStrategy::new(&config)),
-            risk_manager: RiskManager::new(&config),
+            strategy: strategy_from_config(&config),
+            risk_manager: RiskManager::new(&config).with_engine(engine.clone()),
+            engine,
             config,
         }
     }
 
-    pub async fn start(&self) -> Result<(), Box<dyn Error>> {
-        println!("Initializing market monitoring...");
-        
+    // Runs until `shutdown` is cancelled; a failed pass is logged and the
+    // next one runs on schedule
+    pub async fn start(&self, shutdown: CancellationToken) -> Result<()> {
+        info!(strategy = self.strategy.name(), "Initializing market monitoring");
         self.monitor.start_monitoring().await?;
-        
+
         loop {
-            if let Some(signal) = self.monitor.check_signals().await? {
-                if self.risk_manager.validate_trade(&signal).await? {
-                    self.execute_trade(signal).await?;
-                }
+            if let Err(e) = self.trade_signals().await {
+                warn!(error = %e, "Signal pass failed");
+            }
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(SIGNAL_POLL_INTERVAL) => {}
             }
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
     }
 
-    async fn execute_trade(&self, signal: Signal) -> Result<(), Box<dyn Error>> {
-        println!("Executing trade based on signal: {:?}", signal);
-        // Trade execution logic will go here
+    async fn trade_signals(&self) -> Result<()> {
+        let mut signals = Vec::new();
+        if let Some(signal) = self.monitor.check_signals().await? {
+            signals.push(signal);
+        }
+        for (token, metrics) in self.monitor.token_metrics() {
+            if let Some(signal) = self.strategy.evaluate(&token, &metrics) {
+                info!(strategy = self.strategy.name(), ?signal, "Strategy signalled");
+                signals.push(signal);
+            }
+        }
+
+        for signal in signals {
+            if let Err(e) = self.handle_signal(&signal).await {
+                warn!(token = %signal.token(), error = %e, "Signal trade failed");
+            }
+        }
         Ok(())
     }
-}
 
-pub struct Execution {
-    // Add execution logic
+    // True if the signal was traded
+    async fn handle_signal(&self, signal: &Signal) -> Result<bool> {
+        if signal.confidence() < self.config.min_confidence {
+            return Ok(false);
+        }
+        if !self.risk_manager.validate_trade(signal).await? {
+            return Ok(false);
+        }
+        self.execute_trade(signal).await?;
+        Ok(true)
+    }
+
+    async fn execute_trade(&self, signal: &Signal) -> Result<()> {
+        match signal {
+            Signal::BuySignal { token, confidence } => {
+                info!(token = %token, confidence, "Buying on signal");
+                let amount = self.engine.quote_to_base_units(self.config.fixed_amount).await?;
+                self.engine.manage_position(token, PositionAction::Buy(amount)).await
+            }
+            Signal::SellSignal { token, confidence } => {
+                let positions = self.engine.get_active_positions().await?;
+                let Some(position) = positions.into_iter().find(|p| p.token == *token) else {
+                    return Ok(());
+                };
+                info!(token = %token, confidence, "Selling on signal");
+                self.engine.manage_position(token, PositionAction::SellAll).await?;
+                self.risk_manager.record_realized_pnl(position.pnl);
+                Ok(())
+            }
+        }
+    }
 }
 
-pub struct TradingEngine {
-    // Add trading logic
-}
\ No newline at end of file
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::trading::tests::scripted_engine,
+        solana_sdk::pubkey::Pubkey,
+        std::io::Write,
+    };
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<parking_lot::Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_logs_carry_the_event_fields() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let logger = build_logger(EnvFilter::new("info"), true, move || writer.clone());
+
+        let config = TradingConfig {
+            watch_tokens: vec!["not-a-mint".to_string()],
+            ..Default::default()
+        };
+        tracing::subscriber::with_default(logger, || Monitor::new(&config));
+
+        let output = String::from_utf8(captured.0.lock().clone()).unwrap();
+        let event: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(event["level"], "WARN");
+        assert_eq!(event["message"], "Skipping invalid watch token");
+        assert_eq!(event["token"], "not-a-mint");
+        assert!(event["error"].as_str().is_some_and(|e| !e.is_empty()));
+    }
+
+    // Bot over an engine that fails every RPC call, recording which were made
+    fn signal_bot(config: TradingConfig) -> (TradingBot, Arc<parking_lot::Mutex<Vec<String>>>) {
+        let calls = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let seen = calls.clone();
+        let engine = scripted_engine(config.clone(), move |method, _| {
+            seen.lock().push(method.to_string());
+            None
+        });
+        (TradingBot::new(config, Arc::new(engine)), calls)
+    }
+
+    fn buy(confidence: f64) -> Signal {
+        Signal::BuySignal { token: Pubkey::new_unique(), confidence }
+    }
+
+    #[tokio::test]
+    async fn buys_the_risk_manager_rejects_never_reach_the_engine() {
+        let (bot, calls) = signal_bot(TradingConfig {
+            fixed_amount: 2.0,
+            max_position_size: 1.0,
+            ..TradingConfig::default()
+        });
+
+        assert!(!bot.handle_signal(&buy(1.0)).await.unwrap());
+        assert!(calls.lock().is_empty(), "{:?}", calls.lock());
+    }
+
+    #[tokio::test]
+    async fn low_confidence_signals_are_not_traded() {
+        let (bot, calls) = signal_bot(TradingConfig { min_confidence: 0.8, ..TradingConfig::default() });
+
+        assert!(!bot.handle_signal(&buy(0.5)).await.unwrap());
+        assert!(calls.lock().is_empty(), "{:?}", calls.lock());
+    }
+
+    #[tokio::test]
+    async fn approved_buys_go_to_the_engine() {
+        let (bot, calls) = signal_bot(TradingConfig::default());
+
+        // The engine's RPC calls all fail, so the buy does too, but only after trying
+        assert!(bot.handle_signal(&buy(1.0)).await.is_err());
+        assert!(!calls.lock().is_empty());
+    }
+}
@@ -1,17 +1,87 @@
 use {
-    inquire::{Select, Confirm, Text},
+    inquire::{InquireError, Select, Confirm, Text},
     colored::*,
-    std::fmt,
-    solana_sdk::{pubkey::Pubkey, signature::Keypair, transaction::Transaction},
+    anyhow::{Result, anyhow},
+    solana_account_decoder::UiAccountData,
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_request::TokenAccountsFilter,
+    },
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        compute_budget::ComputeBudgetInstruction,
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        transaction::Transaction,
+    },
+    std::{path::Path, str::FromStr, sync::Arc},
+    tokio_util::sync::CancellationToken,
+    crate::{
+        backtest::run_backtest,
+        config::{config_path, TradingConfig},
+        error::BotError,
+        metrics::start_metrics_server,
+        monitoring::wallet::{FastCopyTrader, WalletTracker},
+        strategy::strategy_from_config,
+        token_registry::TokenRegistry,
+        TradingBot,
+        trading::{types::{Lamports, LatencyOp, PositionAction, Sol}, SubmitPolicy, TradingEngine},
+    },
 };
 
+const RECENT_WALLET_TRADES: usize = 10; // Shown when viewing a tracked wallet
+
+pub struct BotUI {
+    wallet: Arc<Keypair>,
+    config: TradingConfig,
+    engine: Arc<TradingEngine>,
+    wallet_tracker: WalletTracker,
+    rpc_client: Arc<RpcClient>,
+    tokens: TokenRegistry, // Symbols for display
+    running: bool,
+    copier: Option<Arc<FastCopyTrader>>, // Set while copy trading runs
+    signal_trading: Option<CancellationToken>, // Set while the TradingBot loop runs
+}
+
 impl BotUI {
-    pub fn new(wallet: Keypair, config: Config) -> Self {
-        Self {
+    pub fn new(wallet: Keypair, config: TradingConfig) -> Result<Self> {
+        let wallet = Arc::new(wallet);
+        let engine = Arc::new(TradingEngine::new(&config, wallet.clone())?);
+        engine.spawn_background_tasks();
+        engine.on_escalation(|error| println!("Retrying with a higher priority fee: {}", error));
+        if config.metrics_enabled {
+            let addr = config.metrics_addr.parse().map_err(|e| {
+                anyhow!("Invalid metrics_addr {}: {}", config.metrics_addr, e)
+            })?;
+            let server = start_metrics_server(addr, engine.metrics(), engine.shutdown_token());
+            tokio::spawn(async move {
+                if let Err(e) = server.await {
+                    println!("Metrics server stopped: {}", e);
+                }
+            });
+        }
+        let rpc_client = Arc::new(RpcClient::new_with_commitment(
+            config.rpc_endpoint(),
+            CommitmentConfig::confirmed(),
+        ));
+
+        let mut wallet_tracker = WalletTracker::new(&config, 0)?;
+        if Path::new(&config.wallet_state_path).exists() {
+            wallet_tracker.load_state(&config.wallet_state_path)?;
+        }
+
+        Ok(Self {
             wallet,
             config,
-            running: false
-        }
+            engine,
+            wallet_tracker,
+            tokens: TokenRegistry::new(rpc_client.clone()),
+            rpc_client,
+            running: false,
+            copier: None,
+            signal_trading: None,
+        })
     }
 
     pub async fn show_main_menu(&mut self) -> Result<()> {
@@ -22,14 +92,22 @@ impl BotUI {
         
         if self.running {
             println!("Copy Trading: ACTIVE");
-            println!("Target Wallet: {}", self.config.target_wallet);
+            println!("Target Wallets: {}", self.config.target_wallets.len());
         } else {
             println!("Copy Trading: INACTIVE");
         }
         
         if self.config.fixed_amount > 0.0 {
-            println!("Fixed Trading Amount: {} SOL", self.config.fixed_amount);
+            println!("Fixed Trading Amount: {} {}", self.config.fixed_amount, self.config.quote_token);
         }
+        println!("RPC Utilization: {:.0}%", self.engine.rpc_utilization() * 100.0);
+        println!("Circuit Breaker: {}", self.engine.breaker_state());
+        println!("Mempool Feed: {}", self.engine.mempool_state());
+        let (spent, loss) = self.engine.daily_totals();
+        println!(
+            "Today: spent {} of {}, lost {} of {}",
+            spent, self.config.max_daily_spend_sol, loss, Sol(self.config.daily_loss_cap)
+        );
         
         println!("\n");
 
@@ -39,20 +117,49 @@ impl BotUI {
                 "💰 Check Balance",
                 "🎯 Manual Trading",
                 "▶️ Start Copy Trading",
+                "📡 Start Signal Trading",
+                "📈 Performance",
+                "🧪 Backtest Strategy",
+                "👛 Tracked Wallets",
+                "🪙 Manage Tracked Tokens",
+                "📜 Export Trade History",
                 "⚙️ Settings",
+                "🛑 Emergency Stop",
                 "🚪 Exit"
             ];
 
-            let selection = Select::new("Select an option:", choices).prompt()?;
+            // Ctrl-C at the main prompt exits like "Exit" so main can shut down cleanly
+            let selection = match Select::new("Select an option:", choices).prompt() {
+                Ok(selection) => selection,
+                Err(InquireError::OperationInterrupted) => break,
+                Err(e) => return Err(e.into()),
+            };
 
-            match selection {
-                "💼 Wallet Info" => self.show_wallet_info().await?,
-                "💰 Check Balance" => self.show_balance().await?,
-                "🎯 Manual Trading" => self.show_manual_trading_menu().await?,
-                "▶️ Start Copy Trading" => self.start_bot().await?,
-                "⚙️ Settings" => self.show_settings().await?,
+            // A failed action is reported and the menu keeps running
+            let result = match selection {
+                "💼 Wallet Info" => self.show_wallet_info().await,
+                "💰 Check Balance" => self.show_balance().await,
+                "🎯 Manual Trading" => self.show_manual_trading_menu().await,
+                "▶️ Start Copy Trading" => self.start_bot().await,
+                "📡 Start Signal Trading" => self.start_signal_trading(),
+                "📈 Performance" => {
+                    self.show_performance();
+                    Ok(())
+                }
+                "🧪 Backtest Strategy" => self.show_backtest(),
+                "👛 Tracked Wallets" => self.show_tracked_wallets_menu().await,
+                "🪙 Manage Tracked Tokens" => self.show_tracked_tokens_menu(),
+                "📜 Export Trade History" => self.export_trade_history(),
+                "⚙️ Settings" => self.show_settings().await,
+                "🛑 Emergency Stop" => self.emergency_stop().await,
                 "🚪 Exit" => break,
-                _ => println!("Invalid option")
+                _ => {
+                    println!("Invalid option");
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                print_error(e);
             }
         }
         Ok(())
@@ -62,7 +169,6 @@ impl BotUI {
         loop {
             let settings = vec![
                 "Set Fixed Trading Amount",
-                "Target Wallet",
                 "RPC URL",
                 "Slippage %",
                 "Back"
@@ -72,13 +178,38 @@ impl BotUI {
             
             match selection {
                 "Set Fixed Trading Amount" => {
-                    let amount = Text::new("Enter fixed trading amount (SOL):").prompt()?;
+                    let amount = Text::new(&format!("Enter fixed trading amount ({}):", self.config.quote_token)).prompt()?;
                     self.config.fixed_amount = amount.parse::<f64>()?;
-                    println!("Fixed trading amount set to: {} SOL", self.config.fixed_amount);
+                    println!("Fixed trading amount set to: {} {}", self.config.fixed_amount, self.config.quote_token);
+                },
+                "Slippage %" => {
+                    let input = Text::new("Enter max slippage (%):").prompt()?;
+                    match parse_slippage_percent(&input) {
+                        Ok(slippage) => {
+                            self.config.max_slippage = slippage;
+                            self.save_config(|config| config.max_slippage = slippage)?;
+                            println!("Max slippage set to: {}%", slippage * 100.0);
+                        }
+                        Err(e) => println!("{}", e.to_string().red()),
+                    }
                 },
-                "Target Wallet" => {
-                    let wallet = Text::new("Enter target wallet:").prompt()?;
-                    self.config.target_wallet = Pubkey::from_str(&wallet)?;
+                "RPC URL" => {
+                    let input = Text::new("Enter RPC URL:")
+                        .with_default(&self.config.rpc_endpoint())
+                        .prompt()?;
+                    match parse_rpc_url(&input) {
+                        Ok(url) => {
+                            self.rpc_client = Arc::new(RpcClient::new_with_commitment(
+                                url.clone(),
+                                CommitmentConfig::confirmed(),
+                            ));
+                            self.engine.set_primary_rpc_endpoint(url.clone());
+                            self.config.rpc_url = url.clone();
+                            self.save_config(|config| config.rpc_url = url)?;
+                            println!("RPC URL set to: {}", self.config.rpc_url);
+                        }
+                        Err(e) => println!("{}", e.to_string().red()),
+                    }
                 },
                 "Back" => break,
                 _ => println!("Setting: {}", selection)
@@ -87,20 +218,175 @@ impl BotUI {
         Ok(())
     }
 
+    // Writes a change already made to self.config through to the config file,
+    // as `edit` on the file's own contents so env-sourced secrets stay off disk
+    fn save_config(&self, edit: impl FnOnce(&mut TradingConfig)) -> Result<()> {
+        let path = config_path();
+        TradingConfig::update_file(&path, edit)?;
+        println!("Settings saved to {}", path);
+        Ok(())
+    }
+
     // Add debug logging
     pub async fn start_bot(&mut self) -> Result<()> {
+        if self.copier.is_some() {
+            println!("Copy trading is already running");
+            return Ok(());
+        }
         println!("Starting bot with configuration:");
-        println!("RPC URL: {}", self.config.rpc_url);
-        println!("Target Wallet: {}", self.config.target_wallet);
-        println!("Fixed Amount: {} SOL", self.config.fixed_amount);
-        
+        println!("RPC URL: {}", self.config.rpc_endpoint());
+        let targets = self.config.target_wallets()?;
+        if targets.is_empty() {
+            println!("Target Wallets: none, add some under Tracked Wallets");
+        }
+        for (wallet, weight) in &targets {
+            println!("Target Wallet: {} ({:.2}x)", wallet, weight);
+        }
+        println!("Size Mode: Fixed ({} {} per trade)", self.config.fixed_amount, self.config.quote_token);
+        println!("Max Slippage: {}%", self.config.max_slippage * 100.0);
+
+        if !Confirm::new("Start copy trading with these settings?")
+            .with_default(false)
+            .prompt()?
+        {
+            println!("Copy trading not started");
+            return Ok(());
+        }
+
         self.test_rpc_connection().await?;
         self.verify_wallet_balance().await?;
-        
+
+        let copier = Arc::new(FastCopyTrader::new(
+            &self.config,
+            targets.into_iter().collect(),
+            Keypair::from_bytes(&self.wallet.to_bytes())?,
+            self.engine.clone(),
+        )?);
+        let running = copier.clone();
+        tokio::spawn(async move {
+            if let Err(e) = running.start_copying().await {
+                println!("Copy trading stopped: {}", e);
+            }
+        });
+        self.copier = Some(copier);
+
         self.running = true;
         Ok(())
     }
 
+    // Trades watch_tokens' volume signals and the strategy's reads of them,
+    // each gated by the RiskManager, until stopped or the engine shuts down
+    fn start_signal_trading(&mut self) -> Result<()> {
+        if self.signal_trading.is_some() {
+            println!("Signal trading is already running");
+            return Ok(());
+        }
+        if self.config.watch_tokens.is_empty() {
+            println!("No watch_tokens configured, nothing to trade signals on");
+            return Ok(());
+        }
+
+        let shutdown = self.engine.shutdown_token().child_token();
+        let bot = TradingBot::new(self.config.clone(), self.engine.clone());
+        let stop = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = bot.start(stop).await {
+                println!("Signal trading stopped: {}", e);
+            }
+        });
+        self.signal_trading = Some(shutdown);
+        println!("Signal trading started on {} tokens", self.config.watch_tokens.len());
+        Ok(())
+    }
+
+    fn stop_signal_trading(&mut self) {
+        if let Some(shutdown) = self.signal_trading.take() {
+            shutdown.cancel();
+        }
+    }
+
+    fn stop_copying(&mut self) {
+        if let Some(copier) = self.copier.take() {
+            copier.stop();
+        }
+        self.running = false;
+    }
+
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.engine.shutdown_token()
+    }
+
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.stop_copying();
+        self.stop_signal_trading();
+        self.engine
+            .graceful_shutdown(self.config.close_on_exit, &self.config.trade_history_path)
+            .await?;
+        println!("State saved to {} and {}", self.config.positions_path, self.config.trade_history_path);
+        Ok(())
+    }
+
+    async fn emergency_stop(&mut self) -> Result<()> {
+        if !Confirm::new("Stop all trading and sell every open position?")
+            .with_default(false)
+            .prompt()?
+        {
+            return Ok(());
+        }
+
+        self.stop_copying();
+        self.stop_signal_trading();
+        self.engine.trigger_emergency_stop().await?;
+        println!("{}", "Emergency stop complete, all positions closed".bright_red());
+        Ok(())
+    }
+
+    // RPC failures are shown inline so the menu keeps running
+    async fn show_wallet_info(&self) -> Result<()> {
+        let owner = self.wallet.pubkey();
+        println!("\n=== Wallet Info ===");
+        println!("Address: {}", owner);
+
+        match self.rpc_client.get_balance(&owner) {
+            Ok(lamports) => println!("SOL: {}", Lamports(lamports)),
+            Err(e) => println!("{}", format!("Failed to fetch SOL balance: {}", e).red()),
+        }
+
+        let accounts = match self.rpc_client
+            .get_token_accounts_by_owner(&owner, TokenAccountsFilter::ProgramId(spl_token::id()))
+        {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                println!("{}", format!("Failed to fetch token accounts: {}", e).red());
+                return Ok(());
+            }
+        };
+
+        let balances: Vec<(Pubkey, String)> = accounts
+            .iter()
+            .filter_map(|keyed| match &keyed.account.data {
+                UiAccountData::Json(parsed) => token_balance(&parsed.parsed),
+                _ => None,
+            })
+            .filter_map(|(mint, amount)| Some((Pubkey::from_str(&mint).ok()?, amount)))
+            .collect();
+
+        if balances.is_empty() {
+            println!("No SPL token balances");
+            return Ok(());
+        }
+
+        println!("\n{:<24}  {:>20}", "Token", "Balance");
+        for (mint, amount) in balances {
+            println!("{:<24}  {:>20}", self.tokens.label(&mint), amount);
+        }
+        Ok(())
+    }
+
+    async fn show_balance(&self) -> Result<()> {
+        self.verify_wallet_balance().await
+    }
+
     async fn test_rpc_connection(&self) -> Result<()> {
         self.rpc_client
             .get_latest_blockhash()
@@ -110,10 +396,8 @@ impl BotUI {
     }
 
     async fn verify_wallet_balance(&self) -> Result<()> {
-        let balance = self.rpc_client
-            .get_balance(&self.wallet.pubkey())
-            .await?;
-        println!("Wallet balance: {} SOL", balance as f64 / 1e9);
+        let balance = self.rpc_client.get_balance(&self.wallet.pubkey())?;
+        println!("Wallet balance: {}", Lamports(balance));
         Ok(())
     }
 
@@ -121,23 +405,73 @@ impl BotUI {
         loop {
             println!("\n=== Manual Trading ===");
             let action = Select::new("Select action:", vec![
+                "Quote",
                 "Buy Token",
                 "Sell Token",
                 "Back"
             ]).prompt()?;
 
-            match action {
-                "Buy Token" => {
+            let result = match action {
+                "Quote" => async {
+                    let address = Text::new("Enter token address:").prompt()?;
+                    let amount = Text::new(&format!("Enter amount ({}):", self.config.quote_token)).prompt()?.parse::<f64>()?;
+
+                    self.show_quote(Pubkey::from_str(&address)?, amount).await
+                }.await,
+                "Buy Token" => async {
                     let address = Text::new("Enter token address:").prompt()?;
                     
                     self.execute_manual_buy(
                         Pubkey::from_str(&address)?,
-                    ).await?;
-                },
+                    ).await
+                }.await,
+                "Sell Token" => async {
+                    let address = Text::new("Enter token address:").prompt()?;
+                    let percentage = Text::new("Enter percentage to sell (1-100):")
+                        .with_default("100")
+                        .prompt()?
+                        .parse::<f64>()?;
+
+                    self.execute_manual_sell(
+                        Pubkey::from_str(&address)?,
+                        percentage,
+                    ).await
+                }.await,
                 "Back" => break,
-                _ => println!("Invalid option")
+                _ => {
+                    println!("Invalid option");
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                print_error(e);
+            }
+        }
+        Ok(())
+    }
+
+    // Buy quote for `amount` of the quote token into `token`; nothing is submitted
+    async fn show_quote(&self, token: Pubkey, amount: f64) -> Result<()> {
+        let base_units = self.engine.quote_to_base_units(amount).await?;
+        let quote = self.engine.quote(&self.engine.quote_mint(), &token, base_units).await?;
+
+        println!("\n=== Quote: {} {} -> {} ===", amount, self.config.quote_token, token);
+        println!("Venue: {}", quote.venue);
+        println!("Expected Out: {}", self.engine.base_units_to_ui_amount(quote.expected_out, &token).await?);
+        println!(
+            "Min Out ({}% slippage): {}",
+            self.config.max_slippage * 100.0,
+            self.engine.base_units_to_ui_amount(quote.min_out, &token).await?
+        );
+        match quote.price_impact {
+            Some(impact) if impact > self.config.max_slippage => {
+                println!("{}", format!("Price Impact: {:.2}%", impact * 100.0).red());
             }
+            Some(impact) => println!("Price Impact: {:.2}%", impact * 100.0),
+            None => println!("Price Impact: unknown"),
         }
+        println!("Priority Fee: {} micro-lamports/CU", quote.priority_fee);
+        println!("Estimated Fee: {}", quote.estimated_fee);
         Ok(())
     }
 
@@ -145,44 +479,78 @@ impl BotUI {
         let amount = if self.config.fixed_amount > 0.0 {
             self.config.fixed_amount
         } else {
-            let input = Text::new("Enter amount (SOL):").prompt()?;
+            let input = Text::new(&format!("Enter amount ({}):", self.config.quote_token)).prompt()?;
             input.parse::<f64>()?
         };
 
+        if needs_confirmation(amount, self.config.confirm_threshold_sol)
+            && !Confirm::new(&format!("Buy {} with {} {}?", token, amount, self.config.quote_token))
+                .with_default(false)
+                .prompt()?
+        {
+            println!("Buy cancelled");
+            return Ok(());
+        }
+
         self.execute_trade(token, amount).await
     }
 
+    async fn execute_manual_sell(&self, token: Pubkey, percentage: f64) -> Result<()> {
+        if !(percentage > 0.0 && percentage <= 100.0) {
+            println!("{}", format!("Percentage must be between 0 and 100, got {}", percentage).red());
+            return Ok(());
+        }
+
+        let balance = self.engine.get_token_balance(&token).await?;
+        let amount = sell_amount(balance, percentage);
+        if amount == 0 {
+            println!("No position in {} to sell", token);
+            return Ok(());
+        }
+
+        let action = if percentage >= 100.0 {
+            PositionAction::SellAll
+        } else {
+            PositionAction::SellPartial(percentage / 100.0)
+        };
+
+        let ui_amount = self.engine.base_units_to_ui_amount(amount, &token).await?;
+        println!("Selling {} of {} ({}%)", ui_amount, token, percentage);
+        self.engine.manage_position_with(&token, action, SubmitPolicy::SAFE).await
+    }
+
+    // `amount` is in whole quote tokens
+    async fn execute_trade(&self, token: Pubkey, amount: f64) -> Result<()> {
+        let base_units = self.engine.quote_to_base_units(amount).await?;
+        self.engine
+            .manage_position_with(&token, PositionAction::Buy(base_units), SubmitPolicy::SAFE)
+            .await
+    }
+
     async fn execute_direct_swap(&self, token: Pubkey, amount: f64) -> Result<()> {
-        let ix = self.engine.create_privileged_swap(
-            &token,
-            amount_to_lamports(amount),
-            true // bypass checks flag
-        )?;
+        let base_units = self.engine.quote_to_base_units(amount).await?;
+        let swap_ixs = self.engine.create_privileged_swap(&token, base_units).await?;
 
         let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
         
+        let mut instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            ComputeBudgetInstruction::set_compute_unit_price(
+                self.engine.calculate_optimal_priority_fee().await?
+            ),
+        ];
+        instructions.extend(swap_ixs);
+
         let tx = Transaction::new_signed_with_payer(
-            &[
-                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
-                ComputeBudgetInstruction::set_compute_unit_price(
-                    self.engine.calculate_optimal_priority_fee()
-                ),
-                ix
-            ],
+            &instructions,
             Some(&self.wallet.pubkey()),
-            &[&self.wallet],
+            &[self.wallet.as_ref()],
             recent_blockhash
         );
 
         self.rpc_client.send_transaction_with_config(
             &tx,
-            RpcSendTransactionConfig {
-                skip_preflight: true,
-                preflight_commitment: None,
-                encoding: None,
-                max_retries: Some(0),
-                min_context_slot: None,
-            },
+            SubmitPolicy::SAFE.send_config(self.rpc_client.commitment(), None),
         )?;
 
         Ok(())
@@ -195,10 +563,25 @@ impl BotUI {
             println!("\n=== Active Positions ===");
             for pos in &positions {
                 println!(
-                    "Token: {} | Amount: {} | Entry: ${:.2} | Current: ${:.2} | PnL: ${:.2}",
-                    pos.token, pos.amount, pos.entry_price, pos.current_price, pos.pnl
+                    "Token: {} | Amount: {} | Entry: {} {} | Current: {} {} | Unrealized: {:+.4} | Realized: {:+.4}",
+                    self.tokens.label(&pos.token),
+                    pos.amount,
+                    pos.entry_price,
+                    self.config.quote_token,
+                    pos.current_price,
+                    self.config.quote_token,
+                    pos.pnl,
+                    pos.realized_pnl
                 );
             }
+            let unrealized: f64 = positions.iter().map(|pos| pos.pnl).sum();
+            println!(
+                "Total unrealized: {:+.4} {} | Total realized: {:+.4} {}",
+                unrealized,
+                self.config.quote_token,
+                self.engine.total_realized_pnl(),
+                self.config.quote_token
+            );
 
             let choices = vec![
                 "Buy More",
@@ -220,7 +603,12 @@ impl BotUI {
                         Text::new("Enter amount:").prompt()?.parse()?
                     };
 
-                    self.engine.execute_privileged_swap(&token, amount_to_lamports(amount)).await?;
+                    let base_units = self.engine.quote_to_base_units(amount).await?;
+                    self.engine.manage_position_with(
+                        &token,
+                        PositionAction::Buy(base_units),
+                        SubmitPolicy::SAFE,
+                    ).await?;
                 },
                 "Sell Partial" => {
                     let token = Select::new(
@@ -230,9 +618,10 @@ impl BotUI {
                     
                     let percentage = Text::new("Enter percentage to sell (1-100):").prompt()?.parse::<f64>()? / 100.0;
                     
-                    self.engine.manage_position(
+                    self.engine.manage_position_with(
                         &token,
-                        PositionAction::SellPartial(percentage)
+                        PositionAction::SellPartial(percentage),
+                        SubmitPolicy::SAFE,
                     ).await?;
                 },
                 "Sell All" => {
@@ -241,9 +630,10 @@ impl BotUI {
                         positions.iter().map(|p| p.token).collect()
                     ).prompt()?;
                     
-                    self.engine.manage_position(
+                    self.engine.manage_position_with(
                         &token,
-                        PositionAction::SellAll
+                        PositionAction::SellAll,
+                        SubmitPolicy::SAFE,
                     ).await?;
                 },
                 "Back" => break,
@@ -260,10 +650,11 @@ impl BotUI {
         for trade in history {
             let status = if trade.success { "✅" } else { "❌" };
             println!(
-                "{} {} | {} | Amount: {} | Price: ${:.2} | {}",
+                "{} {} | {:?} {} | Amount: {} | Price: {} SOL | {}",
                 status,
                 trade.timestamp.elapsed().as_secs(),
                 trade.trade_type,
+                self.tokens.label(&trade.token),
                 trade.amount,
                 trade.price,
                 trade.error.unwrap_or_default()
@@ -271,8 +662,368 @@ impl BotUI {
         }
         Ok(())
     }
+
+    fn show_performance(&self) {
+        println!("\n{}", "=== Performance ===".bright_green());
+        println!("Success Rate: {:.1}%", self.engine.get_success_rate() * 100.0);
+
+        for op in [LatencyOp::Build, LatencyOp::Submit, LatencyOp::Confirm] {
+            let stats = self.engine.get_latency_stats(op);
+            if stats.count == 0 {
+                println!("{:<8} no samples", op.to_string());
+                continue;
+            }
+            println!(
+                "{:<8} p50 {:>6}ms  p90 {:>6}ms  p99 {:>6}ms  ({} samples)",
+                op.to_string(),
+                stats.p50.as_millis(),
+                stats.p90.as_millis(),
+                stats.p99.as_millis(),
+                stats.count
+            );
+        }
+        println!();
+    }
+
+    // Replays the tracked wallets' recorded swaps through the configured strategy
+    fn show_backtest(&self) -> Result<()> {
+        let history = self.wallet_tracker.all_transactions();
+        if history.is_empty() {
+            println!("No recorded trades to replay, track some wallets first");
+            return Ok(());
+        }
+
+        let input = Text::new("Starting balance (SOL):").with_default("10").prompt()?;
+        let starting_balance: f64 = input
+            .trim()
+            .parse()
+            .map_err(|e| anyhow!("Invalid starting balance {}: {}", input, e))?;
+
+        let strategy = strategy_from_config(&self.config);
+        let swaps = history.len();
+        let report = run_backtest(history, strategy.as_ref(), starting_balance);
+
+        println!("\n{}", format!("=== Backtest: {} strategy, {} swaps ===", strategy.name(), swaps).bright_green());
+        println!("Final Balance: {:.4} SOL (from {:.4})", report.final_balance, report.starting_balance);
+        println!("Round Trips: {}", report.trades);
+        println!("Win Rate: {:.1}%", report.win_rate * 100.0);
+        println!("Max Drawdown: {:.1}%", report.max_drawdown * 100.0);
+        println!("Sharpe: {:.2}", report.sharpe);
+        println!();
+        Ok(())
+    }
+
+    // Wallets that would pass the copy-trading bar are highlighted
+    fn show_tracked_wallets(&self) {
+        let ranked = self.wallet_tracker.analyze_all();
+        if ranked.is_empty() {
+            println!("No tracked wallets with enough trades to rank");
+            return;
+        }
+
+        println!(
+            "\n{:<44}  {:>8}  {:>12}  {:>12}  {:>12}  {:>14}  {:>6}",
+            "Wallet", "Success", "Avg PnL SOL", "Realized", "Unrealized", "24h Volume", "Trades"
+        );
+        for (wallet, metrics) in ranked {
+            let row = format!(
+                "{:<44}  {:>7.1}%  {:>+12.4}  {:>+12.4}  {:>+12.4}  {:>14}  {:>6}",
+                wallet.to_string(),
+                metrics.success_rate * 100.0,
+                metrics.avg_profit,
+                metrics.realized_pnl,
+                metrics.unrealized_pnl,
+                metrics.total_volume,
+                metrics.trade_count
+            );
+            if metrics.meets_copy_threshold() {
+                println!("{}", row.bright_green());
+            } else {
+                println!("{}", row);
+            }
+        }
+        println!();
+    }
+
+    // Copy-trade targets and their size weights, saved to the config file
+    async fn show_tracked_wallets_menu(&mut self) -> Result<()> {
+        loop {
+            self.show_tracked_wallets();
+            let targets = self.config.target_wallets()?;
+            println!("=== Copy Targets ===");
+            if targets.is_empty() {
+                println!("No target wallets");
+            }
+            for (wallet, weight) in &targets {
+                println!("{}  {:.2}x", wallet, weight);
+            }
+
+            let actions = vec!["Add Wallet", "Remove Wallet", "Set Weight", "View Wallet", "Back"];
+            match Select::new("Select action:", actions).prompt()? {
+                "Add Wallet" => {
+                    let wallet = parse_wallet(&Text::new("Wallet address:").prompt()?)?;
+                    let weight = parse_weight(&Text::new("Size weight:").with_default("1.0").prompt()?)?;
+                    if !self.config.add_target_wallet(&wallet, weight) {
+                        println!("{} is already a target", wallet);
+                        continue;
+                    }
+                    self.save_config(|config| {
+                        config.add_target_wallet(&wallet, weight);
+                    })?;
+                    if let Some(copier) = &self.copier {
+                        copier.add_target(wallet, weight);
+                    }
+                    self.wallet_tracker.track_wallet(wallet).await?;
+                    println!("Copying {} at {:.2}x", wallet, weight);
+                }
+                "Remove Wallet" => {
+                    if targets.is_empty() {
+                        continue;
+                    }
+                    let wallet = Select::new("Select wallet:", targets.keys().copied().collect()).prompt()?;
+                    self.config.remove_target_wallet(&wallet);
+                    self.save_config(|config| {
+                        config.remove_target_wallet(&wallet);
+                    })?;
+                    if let Some(copier) = &self.copier {
+                        copier.remove_target(&wallet);
+                    }
+                    self.wallet_tracker.untrack_wallet(&wallet);
+                    println!("Stopped copying {}", wallet);
+                }
+                "Set Weight" => {
+                    if targets.is_empty() {
+                        continue;
+                    }
+                    let wallet = Select::new("Select wallet:", targets.keys().copied().collect()).prompt()?;
+                    let weight = parse_weight(&Text::new("Size weight:").with_default(&targets[&wallet].to_string()).prompt()?)?;
+                    self.config.set_target_weight(&wallet, weight);
+                    self.save_config(|config| {
+                        config.set_target_weight(&wallet, weight);
+                    })?;
+                    if let Some(copier) = &self.copier {
+                        copier.add_target(wallet, weight);
+                    }
+                    println!("{} now copied at {:.2}x", wallet, weight);
+                }
+                "View Wallet" => {
+                    if targets.is_empty() {
+                        continue;
+                    }
+                    let wallet = Select::new("Select wallet:", targets.keys().copied().collect()).prompt()?;
+                    self.show_wallet_trades(&wallet).await?;
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    // Metrics and the latest trades recorded for one wallet
+    async fn show_wallet_trades(&self, wallet: &Pubkey) -> Result<()> {
+        let Some(state) = self.wallet_tracker.wallet_state(wallet) else {
+            println!("No trades recorded for {} yet", wallet);
+            return Ok(());
+        };
+        let metrics = self.wallet_tracker.analyze_wallet(wallet).await?;
+
+        println!("\n=== {} ===", wallet);
+        println!(
+            "Success: {:.1}% | Realized: {:+.4} SOL | Unrealized: {:+.4} SOL | 24h Volume: {} | Trades: {}",
+            metrics.success_rate * 100.0,
+            metrics.realized_pnl,
+            metrics.unrealized_pnl,
+            metrics.total_volume,
+            metrics.trade_count
+        );
+        for trade in state.transaction_history.iter().rev().take(RECENT_WALLET_TRADES) {
+            let row = format!(
+                "{:>6}s ago | {:?} | {} {} -> {} {} | {}",
+                trade.timestamp.elapsed().as_secs(),
+                trade.trade_type,
+                trade.amount_in,
+                self.tokens.label(&trade.input_token),
+                trade.amount_out,
+                self.tokens.label(&trade.output_token),
+                trade.signature
+            );
+            if trade.success {
+                println!("{}", row);
+            } else {
+                println!("{}", row.red());
+            }
+        }
+        println!();
+        Ok(())
+    }
+
+    // Tokens bought are tracked automatically; this covers tokens bought elsewhere
+    fn show_tracked_tokens_menu(&self) -> Result<()> {
+        loop {
+            let tracked = self.engine.tracked_tokens();
+            println!("\n=== Tracked Tokens ===");
+            if tracked.is_empty() {
+                println!("No tracked tokens");
+            }
+            for token in &tracked {
+                println!("{}  {}", self.tokens.label(token), token);
+            }
+
+            match Select::new("Select action:", vec!["Track Token", "Untrack Token", "Back"]).prompt()? {
+                "Track Token" => {
+                    let input = Text::new("Token mint:").prompt()?;
+                    let mint = Pubkey::from_str(input.trim())
+                        .map_err(|e| anyhow!("Invalid mint '{}': {}", input, e))?;
+                    if self.engine.track_token(mint) {
+                        println!("Tracking {}", self.tokens.label(&mint));
+                    } else {
+                        println!("{} is already tracked", self.tokens.label(&mint));
+                    }
+                }
+                "Untrack Token" => {
+                    if tracked.is_empty() {
+                        continue;
+                    }
+                    let token = Select::new("Select token:", tracked).prompt()?;
+                    self.engine.untrack_token(&token);
+                    println!("Stopped tracking {}", self.tokens.label(&token));
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn export_trade_history(&self) -> Result<()> {
+        let path = Text::new("Export to:")
+            .with_default("trade_history.csv")
+            .prompt()?;
+        self.engine.export_trade_history_csv(&path)?;
+        println!("Trade history written to {}", path);
+        Ok(())
+    }
+}
+
+// Friendly, actionable version of a failed menu action
+fn print_error(error: anyhow::Error) {
+    println!("{}", BotError::from(error).user_message().red());
+}
+
+// "1.5" or "1.5%" -> 0.015
+fn parse_slippage_percent(input: &str) -> Result<f64> {
+    let percent = input
+        .trim()
+        .trim_end_matches('%')
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| anyhow!("Slippage must be a number, got '{}'", input))?;
+
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(anyhow!("Slippage must be between 0 and 100%, got {}", percent));
+    }
+    Ok(percent / 100.0)
+}
+
+fn parse_wallet(input: &str) -> Result<Pubkey> {
+    Pubkey::from_str(input.trim()).map_err(|e| anyhow!("Invalid wallet '{}': {}", input, e))
+}
+
+// "1.5" or "1.5x" -> 1.5; scales the copied size of a target's swaps
+fn parse_weight(input: &str) -> Result<f64> {
+    let weight = input
+        .trim()
+        .trim_end_matches(['x', 'X'])
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| anyhow!("Weight must be a number, got '{}'", input))?;
+
+    if !(weight > 0.0 && weight.is_finite()) {
+        return Err(anyhow!("Weight must be above 0, got {}", weight));
+    }
+    Ok(weight)
+}
+
+fn parse_rpc_url(input: &str) -> Result<String> {
+    let url = reqwest::Url::parse(input.trim())
+        .map_err(|e| anyhow!("Invalid RPC URL '{}': {}", input, e))?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(anyhow!("RPC URL must be http(s), got '{}'", url.scheme()));
+    }
+    Ok(url.to_string())
+}
+
+// (mint, UI amount) from a jsonParsed token account, None for empty accounts
+fn token_balance(parsed: &serde_json::Value) -> Option<(String, String)> {
+    let info = parsed.get("info")?;
+    let token_amount = info.get("tokenAmount")?;
+    if token_amount.get("amount")?.as_str()? == "0" {
+        return None;
+    }
+    Some((
+        info.get("mint")?.as_str()?.to_string(),
+        token_amount.get("uiAmountString")?.as_str()?.to_string(),
+    ))
+}
+
+// Manual buys above the threshold (SOL) must be confirmed; 0 confirms every buy
+fn needs_confirmation(amount: f64, threshold: f64) -> bool {
+    amount > threshold || threshold <= 0.0
 }
 
-fn amount_to_lamports(amount: f64) -> u64 {
-    (amount * 1_000_000_000.0) as u64
-}
\ No newline at end of file
+// Raw token amount for selling `percentage` (0-100) of `balance`
+fn sell_amount(balance: u64, percentage: f64) -> u64 {
+    if percentage >= 100.0 {
+        return balance;
+    }
+    // Rounded to whole basis points, so 0.29% is 29 bp rather than 28.999...
+    let basis_points = (percentage.max(0.0) * 100.0).round() as u128;
+    (balance as u128 * basis_points / 10_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_weights_with_or_without_suffix() {
+        assert_eq!(parse_weight("1.5").unwrap(), 1.5);
+        assert_eq!(parse_weight(" 2x ").unwrap(), 2.0);
+        assert_eq!(parse_weight("0.25X").unwrap(), 0.25);
+    }
+
+    #[test]
+    fn rejects_invalid_weights() {
+        for input in ["", "abc", "0", "-1", "inf", "NaN"] {
+            assert!(parse_weight(input).is_err(), "accepted {:?}", input);
+        }
+    }
+
+    #[test]
+    fn only_buys_over_the_threshold_need_confirming() {
+        assert!(!needs_confirmation(0.1, 0.5));
+        assert!(!needs_confirmation(0.5, 0.5));
+        assert!(needs_confirmation(0.51, 0.5));
+        assert!(needs_confirmation(10.0, 0.5));
+        // A zero (or negative) threshold confirms every buy
+        assert!(needs_confirmation(0.001, 0.0));
+        assert!(needs_confirmation(0.0, 0.0));
+        assert!(needs_confirmation(0.001, -1.0));
+    }
+
+    #[test]
+    fn sell_amount_takes_the_percentage_of_the_balance() {
+        assert_eq!(sell_amount(2_000_000, 50.0), 1_000_000);
+        assert_eq!(sell_amount(2_000_000, 12.5), 250_000);
+        assert_eq!(sell_amount(1_000_000, 0.29), 2_900);
+        // Partial sells round down to whole base units
+        assert_eq!(sell_amount(999, 33.33), 332);
+        // 100% sells everything, even where the basis-point math would leave dust
+        assert_eq!(sell_amount(u64::MAX, 100.0), u64::MAX);
+        assert_eq!(sell_amount(u64::MAX, 150.0), u64::MAX);
+        // Nothing held, or nothing asked for, sells nothing
+        assert_eq!(sell_amount(0, 50.0), 0);
+        assert_eq!(sell_amount(2_000_000, 0.0), 0);
+        assert_eq!(sell_amount(2_000_000, -5.0), 0);
+    }
+}
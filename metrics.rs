@@ -0,0 +1,210 @@
+use {
+    anyhow::Result,
+    hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Method, Request, Response, Server, StatusCode,
+    },
+    parking_lot::Mutex,
+    std::{
+        collections::BTreeMap,
+        convert::Infallible,
+        fmt::Write,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    tokio_util::sync::CancellationToken,
+    tracing::info,
+};
+
+// Counters and gauges TradingEngine updates as it trades, rendered in the
+// Prometheus text format by start_metrics_server
+#[derive(Default)]
+pub struct Metrics {
+    trades_total: AtomicU64,
+    trades_succeeded: AtomicU64,
+    open_positions: AtomicU64,
+    rpc_errors_total: AtomicU64,
+    priority_fee: AtomicU64, // Micro-lamports per compute unit, last computed
+    venue_fills: Mutex<BTreeMap<&'static str, (u64, f64)>>, // venue -> (fills, total seconds)
+}
+
+impl Metrics {
+    pub fn record_trade(&self, succeeded: bool) {
+        self.trades_total.fetch_add(1, Ordering::Relaxed);
+        if succeeded {
+            self.trades_succeeded.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn set_open_positions(&self, count: usize) {
+        self.open_positions.store(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_rpc_error(&self) {
+        self.rpc_errors_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_priority_fee(&self, fee: u64) {
+        self.priority_fee.store(fee, Ordering::Relaxed);
+    }
+
+    pub fn record_venue_fill(&self, venue: &'static str, latency: Duration) {
+        let mut fills = self.venue_fills.lock();
+        let entry = fills.entry(venue).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += latency.as_secs_f64();
+    }
+
+    pub fn render(&self) -> String {
+        let trades_total = self.trades_total.load(Ordering::Relaxed);
+        let trades_succeeded = self.trades_succeeded.load(Ordering::Relaxed);
+        let success_rate = if trades_total == 0 {
+            0.0
+        } else {
+            trades_succeeded as f64 / trades_total as f64
+        };
+
+        let mut out = String::new();
+        write_metric(&mut out, "bot_trades_total", "counter", "Transactions submitted", trades_total);
+        write_metric(&mut out, "bot_trades_succeeded_total", "counter", "Transactions accepted by the RPC", trades_succeeded);
+        write_metric(&mut out, "bot_trade_success_rate", "gauge", "Succeeded / submitted", success_rate);
+        write_metric(
+            &mut out,
+            "bot_open_positions",
+            "gauge",
+            "Positions with a non-zero balance",
+            self.open_positions.load(Ordering::Relaxed),
+        );
+        write_metric(
+            &mut out,
+            "bot_rpc_errors_total",
+            "counter",
+            "RPC errors that triggered a failover",
+            self.rpc_errors_total.load(Ordering::Relaxed),
+        );
+        write_metric(
+            &mut out,
+            "bot_priority_fee_micro_lamports",
+            "gauge",
+            "Current priority fee per compute unit",
+            self.priority_fee.load(Ordering::Relaxed),
+        );
+
+        let _ = writeln!(out, "# HELP bot_venue_fill_seconds Time from routing to a landed swap, per venue");
+        let _ = writeln!(out, "# TYPE bot_venue_fill_seconds summary");
+        for (venue, (count, total)) in self.venue_fills.lock().iter() {
+            let _ = writeln!(out, "bot_venue_fill_seconds_sum{{venue=\"{}\"}} {}", venue, total);
+            let _ = writeln!(out, "bot_venue_fill_seconds_count{{venue=\"{}\"}} {}", venue, count);
+        }
+        out
+    }
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+// Serves GET /metrics until `shutdown` is cancelled
+pub async fn start_metrics_server(
+    addr: SocketAddr,
+    metrics: Arc<Metrics>,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let metrics = metrics.clone();
+                async move { Ok::<_, Infallible>(respond(&metrics, &req)) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    info!(addr = %addr, "Metrics server listening");
+    server
+        .with_graceful_shutdown(async move { shutdown.cancelled().await })
+        .await?;
+    Ok(())
+}
+
+fn respond(metrics: &Metrics, req: &Request<Body>) -> Response<Body> {
+    if req.method() != Method::GET || req.uri().path() != "/metrics" {
+        let mut not_found = Response::new(Body::empty());
+        *not_found.status_mut() = StatusCode::NOT_FOUND;
+        return not_found;
+    }
+
+    let mut response = Response::new(Body::from(metrics.render()));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::net::TcpListener};
+
+    #[tokio::test]
+    async fn scrape_reports_the_recorded_counters() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.record_trade(true);
+        metrics.record_trade(true);
+        metrics.record_trade(false);
+        metrics.set_open_positions(2);
+        metrics.record_rpc_error();
+        metrics.set_priority_fee(12_500);
+        metrics.record_venue_fill("raydium", Duration::from_millis(250));
+        metrics.record_venue_fill("raydium", Duration::from_millis(750));
+
+        // Grab a free port; the server binds it again right after
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(start_metrics_server(addr, metrics, shutdown.clone()));
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/metrics", addr);
+        let mut response = None;
+        for _ in 0..50 {
+            match client.get(&url).send().await {
+                Ok(r) => {
+                    response = Some(r);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        let response = response.expect("metrics server never came up");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body = response.text().await.unwrap();
+
+        let lines: Vec<&str> = body.lines().filter(|line| !line.starts_with('#')).collect();
+        for expected in [
+            "bot_trades_total 3",
+            "bot_trades_succeeded_total 2",
+            "bot_open_positions 2",
+            "bot_rpc_errors_total 1",
+            "bot_priority_fee_micro_lamports 12500",
+            "bot_venue_fill_seconds_sum{venue=\"raydium\"} 1",
+            "bot_venue_fill_seconds_count{venue=\"raydium\"} 2",
+        ] {
+            assert!(lines.contains(&expected), "missing `{}` in\n{}", expected, body);
+        }
+        let rate = lines.iter().find_map(|line| line.strip_prefix("bot_trade_success_rate ")).unwrap();
+        assert!((rate.parse::<f64>().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+        let missing = client.get(format!("http://{}/other", addr)).send().await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+
+        shutdown.cancel();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -1,7 +1,26 @@
+pub mod jupiter;
+pub mod orca;
+pub mod raydium;
+
+use {
+    crate::{config::TradingConfig, error::BotError},
+    anyhow::Result,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, transaction::Transaction},
+    std::str::FromStr,
+};
+
 pub const RAYDIUM_V4_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
 
-pub fn get_raydium_program_id() -> Pubkey {
-    Pubkey::from_str(RAYDIUM_V4_PROGRAM_ID).unwrap()
+// An explicit `amm_program_id` wins, otherwise the deployment for the configured cluster
+pub fn resolve_amm_program_id(config: &TradingConfig) -> Result<Pubkey> {
+    let id = config.amm_program_id
+        .as_deref()
+        .unwrap_or_else(|| config.cluster.raydium_amm_program_id());
+
+    Pubkey::from_str(id).map_err(|e| BotError::ConfigError {
+        field: "amm_program_id".to_string(),
+        reason: e.to_string(),
+    }.into())
 }
 
 // Dex module placeholder
@@ -11,4 +30,39 @@ impl Dex {
     pub fn new() -> Self {
         Dex
     }
-}
\ No newline at end of file
+}
+
+// Common interface for anything that can price and build a swap. Venues are
+// shared with spawned tasks through Arc<TradingEngine>, hence Send + Sync.
+pub trait SwapVenue: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn program_id(&self) -> Pubkey;
+
+    // Bare swap instruction, for callers that assemble their own transaction
+    fn build_swap_ix(
+        &self,
+        token_in: &Pubkey,
+        token_out: &Pubkey,
+        amount_in: u64,
+        min_out: u64,
+    ) -> Result<Instruction>;
+
+    // Expected output amount, or None when the venue has no route for the pair
+    fn quote(&self, input: &Pubkey, output: &Pubkey, amount_in: u64) -> Result<Option<u64>>;
+
+    // Fractional price move the swap would cause, None when the venue can't tell
+    fn price_impact(&self, _input: &Pubkey, _output: &Pubkey, _amount_in: u64) -> Result<Option<f64>> {
+        Ok(None)
+    }
+
+    // Unsigned transaction paying from `payer`; the caller signs and submits
+    fn build_swap_transaction(
+        &self,
+        input: &Pubkey,
+        output: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+        payer: &Pubkey,
+    ) -> Result<Transaction>;
+}
@@ -0,0 +1,204 @@
+pub mod subscription;
+pub mod volume;
+pub mod wallet;
+
+use {
+    crate::config::TradingConfig,
+    anyhow::Result,
+    parking_lot::Mutex,
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        collections::{HashMap, VecDeque},
+        str::FromStr,
+        time::Instant,
+    },
+    tokio::sync::Mutex as AsyncMutex,
+    tracing::{info, warn},
+    volume::{MetricsSource, PriceApiSource, TokenMetrics, VolumeMonitor},
+    wallet::WalletTracker,
+};
+
+#[derive(Debug, Clone)]
+pub enum Signal {
+    BuySignal { token: Pubkey, confidence: f64 },
+    SellSignal { token: Pubkey, confidence: f64 }
+}
+
+impl Signal {
+    pub fn token(&self) -> Pubkey {
+        match self {
+            Signal::BuySignal { token, .. } | Signal::SellSignal { token, .. } => *token,
+        }
+    }
+
+    pub fn confidence(&self) -> f64 {
+        match self {
+            Signal::BuySignal { confidence, .. } | Signal::SellSignal { confidence, .. } => *confidence,
+        }
+    }
+}
+
+// Polls every signal source and queues what they produce for check_signals
+pub struct Monitor {
+    volume_monitors: Vec<AsyncMutex<VolumeMonitor>>,
+    wallet_trackers: Vec<AsyncMutex<WalletTracker>>,
+    watched_tokens: Vec<Pubkey>,
+    pending: Mutex<VecDeque<Signal>>,
+    latest_metrics: Mutex<HashMap<Pubkey, TokenMetrics>>,
+    last_wallet_check: Mutex<Instant>,
+}
+
+impl Monitor {
+    pub fn new(config: &TradingConfig) -> Self {
+        Self::with_metrics_source(config, Box::new(PriceApiSource::new(&config.price_api_url)))
+    }
+
+    // Volume signals for watch_tokens come from `metrics_source`
+    pub fn with_metrics_source(config: &TradingConfig, metrics_source: Box<dyn MetricsSource>) -> Self {
+        let watched_tokens = config.watch_tokens
+            .iter()
+            .filter_map(|token| match Pubkey::from_str(token) {
+                Ok(token) => Some(token),
+                Err(e) => {
+                    warn!(token = %token, error = %e, "Skipping invalid watch token");
+                    None
+                }
+            })
+            .collect();
+
+        let volume_monitor = VolumeMonitor::new(config, config.min_volume, metrics_source);
+
+        Self {
+            volume_monitors: vec![AsyncMutex::new(volume_monitor)],
+            wallet_trackers: Vec::new(),
+            watched_tokens,
+            pending: Mutex::new(VecDeque::new()),
+            latest_metrics: Mutex::new(HashMap::new()),
+            last_wallet_check: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn add_volume_monitor(&mut self, monitor: VolumeMonitor) {
+        self.volume_monitors.push(AsyncMutex::new(monitor));
+    }
+
+    pub fn add_wallet_tracker(&mut self, tracker: WalletTracker) {
+        self.wallet_trackers.push(AsyncMutex::new(tracker));
+    }
+
+    pub fn watch_token(&mut self, token: Pubkey) {
+        if !self.watched_tokens.contains(&token) {
+            self.watched_tokens.push(token);
+        }
+    }
+
+    // First poll only records baseline metrics; signals need a previous sample
+    pub async fn start_monitoring(&self) -> Result<()> {
+        self.poll_sources().await?;
+        info!(
+            tokens = self.watched_tokens.len(),
+            volume_monitors = self.volume_monitors.len(),
+            wallet_trackers = self.wallet_trackers.len(),
+            "Monitoring started"
+        );
+        Ok(())
+    }
+
+    // Next queued signal, polling the sources again once the queue is empty
+    pub async fn check_signals(&self) -> Result<Option<Signal>> {
+        if let Some(signal) = self.pending.lock().pop_front() {
+            return Ok(Some(signal));
+        }
+
+        self.poll_sources().await?;
+        Ok(self.pending.lock().pop_front())
+    }
+
+    // Latest metrics per watched token, for strategies
+    pub fn token_metrics(&self) -> Vec<(Pubkey, TokenMetrics)> {
+        self.latest_metrics
+            .lock()
+            .iter()
+            .map(|(token, metrics)| (*token, metrics.clone()))
+            .collect()
+    }
+
+    async fn poll_sources(&self) -> Result<()> {
+        let mut signals = Vec::new();
+
+        for monitor in &self.volume_monitors {
+            let mut monitor = monitor.lock().await;
+            for token in &self.watched_tokens {
+                signals.extend(monitor.check_token(*token).await?);
+                if let Some(metrics) = monitor.token_metrics(token) {
+                    self.latest_metrics.lock().insert(*token, metrics.clone());
+                }
+            }
+        }
+
+        let since = std::mem::replace(&mut *self.last_wallet_check.lock(), Instant::now());
+        for tracker in &self.wallet_trackers {
+            signals.extend(tracker.lock().await.signals_since(since));
+        }
+
+        self.pending.lock().extend(signals);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        async_trait::async_trait,
+        volume::MarketSnapshot,
+    };
+
+    // Serves the queued (price, volume) snapshots in order
+    struct ScriptedSource(Mutex<VecDeque<(f64, f64)>>);
+
+    #[async_trait]
+    impl MetricsSource for ScriptedSource {
+        async fn fetch(&self, _: &Pubkey) -> Result<MarketSnapshot> {
+            let (price, volume_24h) = self.0.lock().pop_front().ok_or_else(|| anyhow::anyhow!("script exhausted"))?;
+            Ok(MarketSnapshot { price, volume_24h, liquidity: 50_000.0 })
+        }
+    }
+
+    #[tokio::test]
+    async fn volume_spike_comes_out_as_a_buy_signal() {
+        let token = Pubkey::new_unique();
+        let config = TradingConfig {
+            watch_tokens: vec![token.to_string()],
+            signal_cooldown_secs: 0,
+            ..TradingConfig::default()
+        };
+        // Volume triples while the price rises 10%
+        let source = ScriptedSource(Mutex::new(VecDeque::from([(1.0, 10_000.0), (1.1, 30_000.0)])));
+        let monitor = Monitor::with_metrics_source(&config, Box::new(source));
+
+        monitor.start_monitoring().await.unwrap();
+        match monitor.check_signals().await.unwrap() {
+            Some(Signal::BuySignal { token: signalled, .. }) => assert_eq!(signalled, token),
+            other => panic!("expected a buy signal, got {:?}", other),
+        }
+
+        // Strategies see the same metrics the signal came from
+        let metrics = monitor.token_metrics();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].0, token);
+        assert_eq!(metrics[0].1.price(), 1.1);
+        assert_eq!(metrics[0].1.volume_spike_ratio(), Some(3.0));
+    }
+
+    #[tokio::test]
+    async fn baseline_poll_raises_no_signal() {
+        let token = Pubkey::new_unique();
+        let config = TradingConfig { watch_tokens: vec![token.to_string()], ..TradingConfig::default() };
+        let source = ScriptedSource(Mutex::new(VecDeque::from([(1.0, 10_000.0), (1.0, 10_000.0)])));
+        let monitor = Monitor::with_metrics_source(&config, Box::new(source));
+
+        monitor.start_monitoring().await.unwrap();
+        assert!(monitor.check_signals().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,251 @@
+use {
+    anyhow::{Result, anyhow},
+    async_trait::async_trait,
+    serde::{Deserialize, Serialize, Serializer},
+    serde_json::json,
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+    std::{sync::Arc, time::Duration},
+};
+
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Buy,
+    Sell,
+    StopLoss,
+    BreakerOpened,
+    DailyLimitHit,
+    HoneypotBlocked,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeEvent {
+    pub event: EventKind,
+    #[serde(serialize_with = "serialize_base58")]
+    pub token: Option<Pubkey>,
+    pub amount: Option<u64>, // Raw units of what was sold, lamports for buys
+    pub signature: Option<String>,
+    pub pnl: Option<f64>,    // SOL
+}
+
+// Webhook consumers want the mint as base58, not Pubkey's raw byte array
+fn serialize_base58<S: Serializer>(token: &Option<Pubkey>, serializer: S) -> Result<S::Ok, S::Error> {
+    match token {
+        Some(token) => serializer.serialize_some(&token.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl TradeEvent {
+    pub fn new(event: EventKind) -> Self {
+        Self {
+            event,
+            token: None,
+            amount: None,
+            signature: None,
+            pnl: None,
+        }
+    }
+
+    pub fn token(mut self, token: Pubkey) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn amount(mut self, amount: u64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn pnl(mut self, pnl: f64) -> Self {
+        self.pnl = Some(pnl);
+        self
+    }
+
+    pub fn signature(mut self, signature: &Signature) -> Self {
+        self.signature = Some(signature.to_string());
+        self
+    }
+
+    // One-line summary for chat notifiers
+    pub fn summary(&self) -> String {
+        let mut text = format!("{:?}", self.event);
+        if let Some(token) = &self.token {
+            text.push_str(&format!(" {}", token));
+        }
+        if let Some(amount) = self.amount {
+            text.push_str(&format!(" amount={}", amount));
+        }
+        if let Some(pnl) = self.pnl {
+            text.push_str(&format!(" pnl={:+.4} SOL", pnl));
+        }
+        if let Some(signature) = &self.signature {
+            text.push_str(&format!(" sig={}", signature));
+        }
+        text
+    }
+}
+
+// Called off the trading path; errors are logged by the caller and dropped
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, event: &TradeEvent) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub webhook_url: Option<String>,
+    pub telegram_bot_token: Option<String>,
+    pub telegram_chat_id: Option<String>,
+}
+
+// POSTs each event as JSON to an arbitrary endpoint
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(NOTIFY_TIMEOUT).build()?,
+            url,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, event: &TradeEvent) -> Result<()> {
+        let response = self.client.post(&self.url).json(event).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Webhook returned {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    bot_token: String,
+    chat_id: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(bot_token: String, chat_id: String) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(NOTIFY_TIMEOUT).build()?,
+            bot_token,
+            chat_id,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, event: &TradeEvent) -> Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+        let response = self.client
+            .post(url)
+            .json(&json!({
+                "chat_id": self.chat_id,
+                "text": event.summary(),
+            }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Telegram returned {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+// Every notifier the config has enough settings for
+pub fn notifiers_from_config(config: &NotificationConfig) -> Result<Vec<Arc<dyn Notifier>>> {
+    let mut notifiers: Vec<Arc<dyn Notifier>> = Vec::new();
+    if let Some(url) = &config.webhook_url {
+        notifiers.push(Arc::new(WebhookNotifier::new(url.clone())?));
+    }
+    match (&config.telegram_bot_token, &config.telegram_chat_id) {
+        (Some(bot_token), Some(chat_id)) => {
+            notifiers.push(Arc::new(TelegramNotifier::new(bot_token.clone(), chat_id.clone())?));
+        }
+        (None, None) => {}
+        _ => return Err(anyhow!("Telegram notifications need both telegram_bot_token and telegram_chat_id")),
+    }
+    Ok(notifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        serde_json::Value,
+        std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::TcpListener,
+            sync::mpsc,
+            thread,
+        },
+    };
+
+    // Accepts one POST, answers 200 and hands its JSON body back
+    fn capture_post() -> (String, mpsc::Receiver<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (body_tx, body_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            body_tx.send(serde_json::from_slice(&body).unwrap()).unwrap();
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+        });
+        (url, body_rx)
+    }
+
+    #[tokio::test]
+    async fn webhook_posts_the_event_as_json() {
+        let (url, body) = capture_post();
+        let token = Pubkey::new_unique();
+        let signature = Signature::from([7; 64]);
+        let event = TradeEvent::new(EventKind::StopLoss)
+            .token(token)
+            .amount(1_500)
+            .pnl(-0.25)
+            .signature(&signature);
+
+        WebhookNotifier::new(url).unwrap().notify(&event).await.unwrap();
+
+        let payload = body.recv().unwrap();
+        assert_eq!(payload["event"], "stop_loss");
+        assert_eq!(payload["token"], token.to_string());
+        assert_eq!(payload["amount"], 1_500);
+        assert_eq!(payload["pnl"], -0.25);
+        assert_eq!(payload["signature"], signature.to_string());
+    }
+
+    #[test]
+    fn summary_ends_with_the_signature() {
+        let signature = Signature::from([7; 64]);
+        let summary = TradeEvent::new(EventKind::Buy).amount(10).signature(&signature).summary();
+        assert_eq!(summary, format!("Buy amount=10 sig={}", signature));
+    }
+}
@@ -0,0 +1,483 @@
+use {
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        transaction::Transaction,
+    },
+    anyhow::{Result, anyhow},
+    spl_associated_token_account::get_associated_token_address,
+    crate::{
+        config::TradingConfig,
+        dex::SwapVenue,
+        error::BotError,
+    },
+    std::collections::HashMap,
+};
+
+pub const WHIRLPOOL_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+
+// Whirlpool account layout (Anchor, including the 8-byte discriminator)
+pub const WHIRLPOOL_SIZE: usize = 653;
+const TICK_SPACING_OFFSET: usize = 41;
+const FEE_RATE_OFFSET: usize = 45;
+const LIQUIDITY_OFFSET: usize = 49;
+const SQRT_PRICE_OFFSET: usize = 65;
+const TICK_CURRENT_INDEX_OFFSET: usize = 81;
+const TOKEN_MINT_A_OFFSET: usize = 101;
+const TOKEN_VAULT_A_OFFSET: usize = 133;
+const TOKEN_MINT_B_OFFSET: usize = 181;
+const TOKEN_VAULT_B_OFFSET: usize = 213;
+
+const TICK_ARRAY_SIZE: i32 = 88;
+const FEE_RATE_DENOMINATOR: u128 = 1_000_000; // fee_rate is in hundredths of a bp
+
+// sqrt_price bounds accepted by the program, i.e. no price limit
+const MIN_SQRT_PRICE: u128 = 4_295_048_016;
+const MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_055;
+const MAX_TICK_INDEX: i32 = 443_636; // Ticks at the two sqrt_price bounds are -/+ this
+
+const Q64: u128 = 1 << 64;
+const SQRT_TICK_BASE: u128 = 18_447_666_387_855_959_850; // sqrt(1.0001), the per-tick step, in Q64.64
+
+// sha256("global:swap")[..8]
+const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+
+#[derive(Debug, Clone)]
+pub struct WhirlpoolInfo {
+    pub tick_spacing: u16,
+    pub fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price: u128, // Q64.64
+    pub tick_current_index: i32,
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+}
+
+impl WhirlpoolInfo {
+    pub fn from_account(data: &[u8]) -> Result<WhirlpoolInfo> {
+        if data.len() != WHIRLPOOL_SIZE {
+            return Err(BotError::ParseError(format!(
+                "Whirlpool account must be {} bytes, got {}",
+                WHIRLPOOL_SIZE,
+                data.len()
+            )).into());
+        }
+
+        Ok(WhirlpoolInfo {
+            tick_spacing: u16::from_le_bytes([data[TICK_SPACING_OFFSET], data[TICK_SPACING_OFFSET + 1]]),
+            fee_rate: u16::from_le_bytes([data[FEE_RATE_OFFSET], data[FEE_RATE_OFFSET + 1]]),
+            liquidity: read_u128(data, LIQUIDITY_OFFSET),
+            sqrt_price: read_u128(data, SQRT_PRICE_OFFSET),
+            tick_current_index: read_i32(data, TICK_CURRENT_INDEX_OFFSET),
+            token_mint_a: read_pubkey(data, TOKEN_MINT_A_OFFSET),
+            token_vault_a: read_pubkey(data, TOKEN_VAULT_A_OFFSET),
+            token_mint_b: read_pubkey(data, TOKEN_MINT_B_OFFSET),
+            token_vault_b: read_pubkey(data, TOKEN_VAULT_B_OFFSET),
+        })
+    }
+
+    // Raw units of B per raw unit of A
+    pub fn price(&self) -> f64 {
+        let sqrt_price = self.sqrt_price as f64 / 2f64.powi(64);
+        sqrt_price * sqrt_price
+    }
+}
+
+// 1.0001^(tick / 2) in Q64.64, by squaring up SQRT_TICK_BASE. Clamped to the
+// program's tick range; within it the error is under 1e-13 relative.
+fn sqrt_price_at_tick(tick: i32) -> u128 {
+    let tick = tick.clamp(-MAX_TICK_INDEX, MAX_TICK_INDEX);
+    let mut step = SQRT_TICK_BASE;
+    let mut sqrt_price = Q64;
+    let mut bits = tick.unsigned_abs();
+    // Products stay under MAX_SQRT_PRICE < 2^96, so mul_div can't fail here
+    while bits > 0 {
+        if bits & 1 == 1 {
+            sqrt_price = mul_div(sqrt_price, step, Q64).unwrap_or(MAX_SQRT_PRICE);
+        }
+        bits >>= 1;
+        if bits > 0 {
+            step = mul_div(step, step, Q64).unwrap_or(MAX_SQRT_PRICE);
+        }
+    }
+    if tick < 0 {
+        mul_div(Q64, Q64, sqrt_price).unwrap_or(MIN_SQRT_PRICE)
+    } else {
+        sqrt_price
+    }
+}
+
+// a * b / denominator rounded down, through a 256-bit product. None when the
+// denominator is zero or the result doesn't fit in a u128.
+fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let (high, low) = full_mul(a, b);
+    if high == 0 {
+        return Some(low / denominator);
+    }
+    if high >= denominator {
+        return None;
+    }
+
+    // Long division of high:low, one bit at a time
+    let mut remainder = high;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+// (high, low) halves of the 256-bit product
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const LOW_64: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & LOW_64);
+    let (b_high, b_low) = (b >> 64, b & LOW_64);
+
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let middle = (low_low >> 64) + (low_high & LOW_64) + (high_low & LOW_64);
+
+    let low = (low_low & LOW_64) | (middle << 64);
+    let high = a_high * b_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+    (high, low)
+}
+
+// Callers check the account length up front, so these slices are in bounds
+fn read_u128(data: &[u8], offset: usize) -> u128 {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&data[offset..offset + 16]);
+    u128::from_le_bytes(bytes)
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    i32::from_le_bytes(bytes)
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&data[offset..offset + 32]);
+    Pubkey::new_from_array(bytes)
+}
+
+pub struct OrcaDex {
+    rpc_client: RpcClient,
+    program_id: Pubkey,
+    user: Pubkey, // Owner of the token accounts swaps pay from and into
+    pools: HashMap<Pubkey, WhirlpoolInfo>,
+}
+
+impl OrcaDex {
+    pub fn new(config: &TradingConfig, user: Pubkey) -> Self {
+        Self {
+            rpc_client: RpcClient::new(config.rpc_endpoint()),
+            program_id: WHIRLPOOL_PROGRAM_ID,
+            user,
+            pools: HashMap::new(),
+        }
+    }
+
+    pub fn get_pool_info(&self, pool_id: &Pubkey) -> Result<WhirlpoolInfo> {
+        let account = self.rpc_client.get_account(pool_id)?;
+        WhirlpoolInfo::from_account(&account.data)
+    }
+
+    // Quotes only cover tracked pools, same as RaydiumDex
+    pub fn track_pool(&mut self, pool_id: &Pubkey) -> Result<()> {
+        let info = self.get_pool_info(pool_id)?;
+        self.pools.insert(*pool_id, info);
+        Ok(())
+    }
+
+    // Tracked pool trading `input` against `output`, with whether the swap is A to B
+    fn find_pool(&self, input: &Pubkey, output: &Pubkey) -> Option<(Pubkey, &WhirlpoolInfo, bool)> {
+        self.pools.iter().find_map(|(pool_id, info)| {
+            if info.token_mint_a == *input && info.token_mint_b == *output {
+                Some((*pool_id, info, true))
+            } else if info.token_mint_b == *input && info.token_mint_a == *output {
+                Some((*pool_id, info, false))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Exact-in output from the current liquidity only, in Q64.64 integer math.
+    // The liquidity past the next initializable tick isn't known here, so the
+    // swap stops at that boundary and input beyond it earns nothing; the real
+    // fill can only be better, and min_out is still enforced on-chain.
+    pub fn calculate_amount_out(pool: &WhirlpoolInfo, amount_in: u64, a_to_b: bool) -> u64 {
+        if pool.liquidity == 0 || pool.sqrt_price == 0 {
+            return 0;
+        }
+
+        let fee = amount_in as u128 * pool.fee_rate as u128 / FEE_RATE_DENOMINATOR;
+        let amount_in = amount_in as u128 - fee;
+        let liquidity = pool.liquidity;
+        let sqrt_price = pool.sqrt_price;
+        let boundary = Self::next_boundary_sqrt_price(pool, a_to_b);
+
+        let amount_out = if a_to_b {
+            // Price falls: 1/next = 1/current + in/L, and out = L * (current - next)
+            let to_boundary = mul_div(liquidity, sqrt_price.saturating_sub(boundary), boundary)
+                .and_then(|scaled| mul_div(scaled, Q64, sqrt_price));
+            let next_sqrt_price = match to_boundary {
+                Some(max_in) if amount_in < max_in => mul_div(amount_in, sqrt_price, Q64)
+                    .and_then(|moved| liquidity.checked_add(moved))
+                    .and_then(|denominator| mul_div(liquidity, sqrt_price, denominator))
+                    .unwrap_or(boundary),
+                _ => boundary,
+            };
+            mul_div(liquidity, sqrt_price.saturating_sub(next_sqrt_price.max(boundary)), Q64)
+        } else {
+            // Price rises: next = current + in/L, and out = L * (1/current - 1/next)
+            let to_boundary = mul_div(liquidity, boundary.saturating_sub(sqrt_price), Q64);
+            let next_sqrt_price = match to_boundary {
+                Some(max_in) if amount_in < max_in => mul_div(amount_in, Q64, liquidity)
+                    .and_then(|moved| sqrt_price.checked_add(moved))
+                    .unwrap_or(boundary),
+                _ => boundary,
+            }
+            .min(boundary);
+            mul_div(liquidity, next_sqrt_price.saturating_sub(sqrt_price), sqrt_price)
+                .and_then(|scaled| mul_div(scaled, Q64, next_sqrt_price))
+        };
+        // An overflow quotes nothing rather than a fill the pool can't give
+        amount_out.map_or(0, |out| out.min(u64::MAX as u128) as u64)
+    }
+
+    // sqrt_price of the first initializable tick the swap would cross: the
+    // current tick's spacing-aligned floor going down, the next one going up
+    fn next_boundary_sqrt_price(pool: &WhirlpoolInfo, a_to_b: bool) -> u128 {
+        let spacing = pool.tick_spacing.max(1) as i32;
+        let floor = pool.tick_current_index.div_euclid(spacing) * spacing;
+        if a_to_b {
+            let lower = sqrt_price_at_tick(floor);
+            // Sitting exactly on the tick means the next one down is the limit
+            if lower < pool.sqrt_price { lower } else { sqrt_price_at_tick(floor - spacing) }
+        } else {
+            sqrt_price_at_tick(floor + spacing)
+        }
+    }
+
+    // The three tick arrays a swap may walk through, starting at the current one
+    pub fn tick_array_addresses(&self, pool_id: &Pubkey, pool: &WhirlpoolInfo, a_to_b: bool) -> [Pubkey; 3] {
+        let span = pool.tick_spacing as i32 * TICK_ARRAY_SIZE;
+        // B to A moves the price up, so start from the array one tick spacing ahead
+        let tick = if a_to_b {
+            pool.tick_current_index
+        } else {
+            pool.tick_current_index + pool.tick_spacing as i32
+        };
+        let start = tick.div_euclid(span) * span;
+        let step = if a_to_b { -span } else { span };
+
+        [0, 1, 2].map(|i| {
+            let start_tick_index = (start + step * i).to_string();
+            Pubkey::find_program_address(
+                &[b"tick_array", pool_id.as_ref(), start_tick_index.as_bytes()],
+                &self.program_id,
+            ).0
+        })
+    }
+
+    pub fn swap_instruction(
+        &self,
+        pool_id: &Pubkey,
+        pool: &WhirlpoolInfo,
+        amount_in: u64,
+        min_amount_out: u64,
+        a_to_b: bool,
+    ) -> Instruction {
+        let tick_arrays = self.tick_array_addresses(pool_id, pool, a_to_b);
+        let oracle = Pubkey::find_program_address(&[b"oracle", pool_id.as_ref()], &self.program_id).0;
+        let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+
+        let mut data = Vec::with_capacity(42);
+        data.extend_from_slice(&SWAP_DISCRIMINATOR);
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+        data.extend_from_slice(&sqrt_price_limit.to_le_bytes());
+        data.push(1); // amount_specified_is_input
+        data.push(a_to_b as u8);
+
+        Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(self.user, true),
+                AccountMeta::new(*pool_id, false),
+                AccountMeta::new(get_associated_token_address(&self.user, &pool.token_mint_a), false),
+                AccountMeta::new(pool.token_vault_a, false),
+                AccountMeta::new(get_associated_token_address(&self.user, &pool.token_mint_b), false),
+                AccountMeta::new(pool.token_vault_b, false),
+                AccountMeta::new(tick_arrays[0], false),
+                AccountMeta::new(tick_arrays[1], false),
+                AccountMeta::new(tick_arrays[2], false),
+                AccountMeta::new_readonly(oracle, false),
+            ],
+            data,
+        }
+    }
+}
+
+impl SwapVenue for OrcaDex {
+    fn name(&self) -> &'static str {
+        "orca"
+    }
+
+    fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    fn build_swap_ix(
+        &self,
+        token_in: &Pubkey,
+        token_out: &Pubkey,
+        amount_in: u64,
+        min_out: u64,
+    ) -> Result<Instruction> {
+        let (pool_id, pool, a_to_b) = self.find_pool(token_in, token_out)
+            .ok_or_else(|| anyhow!("No Orca whirlpool tracked for {} -> {}", token_in, token_out))?;
+
+        Ok(self.swap_instruction(&pool_id, pool, amount_in, min_out, a_to_b))
+    }
+
+    fn quote(&self, input: &Pubkey, output: &Pubkey, amount_in: u64) -> Result<Option<u64>> {
+        Ok(self.find_pool(input, output)
+            .map(|(_, pool, a_to_b)| Self::calculate_amount_out(pool, amount_in, a_to_b)))
+    }
+
+    fn build_swap_transaction(
+        &self,
+        input: &Pubkey,
+        output: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+        payer: &Pubkey,
+    ) -> Result<Transaction> {
+        let swap_ix = self.build_swap_ix(input, output, amount_in, min_amount_out)?;
+        Ok(Transaction::new_with_payer(&[swap_ix], Some(payer)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(liquidity: u128) -> WhirlpoolInfo {
+        WhirlpoolInfo {
+            tick_spacing: 64,
+            fee_rate: 3_000, // 0.3%
+            liquidity,
+            // A little above tick 10, so 10 ticks from the boundary below and 54 from the one above
+            sqrt_price: sqrt_price_at_tick(10) + 12_345,
+            tick_current_index: 10,
+            token_mint_a: Pubkey::new_unique(),
+            token_vault_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+            token_vault_b: Pubkey::new_unique(),
+        }
+    }
+
+    #[test]
+    fn tick_prices_span_the_program_bounds() {
+        assert_eq!(sqrt_price_at_tick(0), Q64);
+        assert_eq!(sqrt_price_at_tick(1), SQRT_TICK_BASE);
+        assert_eq!(sqrt_price_at_tick(-MAX_TICK_INDEX), MIN_SQRT_PRICE);
+        let top = sqrt_price_at_tick(MAX_TICK_INDEX);
+        assert!(MAX_SQRT_PRICE - top < MAX_SQRT_PRICE / 10_000_000_000_000);
+    }
+
+    #[test]
+    fn mul_div_carries_through_the_wide_product() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(Q64, Q64, 3), Some(u128::MAX / 3));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+    }
+
+    #[test]
+    fn small_swaps_quote_the_current_price_less_fees() {
+        let pool = pool(1_000_000_000_000);
+        assert_eq!(OrcaDex::calculate_amount_out(&pool, 1_000_000, true), 997_995);
+        assert_eq!(OrcaDex::calculate_amount_out(&pool, 1_000_000, false), 996_001);
+        assert_eq!(OrcaDex::calculate_amount_out(&self::pool(0), 1_000_000, true), 0);
+    }
+
+    #[test]
+    fn swaps_stop_at_the_tick_boundary() {
+        let pool = pool(1_000_000_000_000);
+        // Both sizes run past the boundary, so both get only what lies before it
+        for a_to_b in [true, false] {
+            let past = OrcaDex::calculate_amount_out(&pool, 10_000_000_000, a_to_b);
+            let far_past = OrcaDex::calculate_amount_out(&pool, 1_000_000_000_000, a_to_b);
+            assert_eq!(past, far_past);
+        }
+        assert_eq!(OrcaDex::calculate_amount_out(&pool, 10_000_000_000, true), 500_100_010);
+        assert_eq!(OrcaDex::calculate_amount_out(&pool, 10_000_000_000, false), 2_694_875_943);
+    }
+
+    fn tick_array(pool_id: &Pubkey, start_tick_index: i32) -> Pubkey {
+        let start = start_tick_index.to_string();
+        Pubkey::find_program_address(&[b"tick_array", pool_id.as_ref(), start.as_bytes()], &WHIRLPOOL_PROGRAM_ID).0
+    }
+
+    #[test]
+    fn swap_accounts_follow_the_whirlpool_layout() {
+        let user = Pubkey::new_unique();
+        let mut dex = OrcaDex::new(&TradingConfig::default(), user);
+        let pool_id = Pubkey::new_unique();
+        let info = pool(1_000_000_000_000);
+        dex.pools.insert(pool_id, info.clone());
+        let oracle = Pubkey::find_program_address(&[b"oracle", pool_id.as_ref()], &WHIRLPOOL_PROGRAM_ID).0;
+        let span = 64 * TICK_ARRAY_SIZE;
+
+        // Selling A walks the tick arrays down from the current one, selling B walks them up
+        for (input, output, a_to_b, tick_arrays) in [
+            (info.token_mint_a, info.token_mint_b, true, [0, -span, -2 * span]),
+            (info.token_mint_b, info.token_mint_a, false, [0, span, 2 * span]),
+        ] {
+            let ix = dex.build_swap_ix(&input, &output, 1_000, 900).unwrap();
+            assert_eq!(ix.program_id, WHIRLPOOL_PROGRAM_ID);
+
+            let expected = [
+                (spl_token::id(), false, false),
+                (user, true, false),
+                (pool_id, false, true),
+                (get_associated_token_address(&user, &info.token_mint_a), false, true),
+                (info.token_vault_a, false, true),
+                (get_associated_token_address(&user, &info.token_mint_b), false, true),
+                (info.token_vault_b, false, true),
+                (tick_array(&pool_id, tick_arrays[0]), false, true),
+                (tick_array(&pool_id, tick_arrays[1]), false, true),
+                (tick_array(&pool_id, tick_arrays[2]), false, true),
+                (oracle, false, false),
+            ];
+            let accounts: Vec<_> = ix.accounts.iter().map(|meta| (meta.pubkey, meta.is_signer, meta.is_writable)).collect();
+            assert_eq!(accounts, expected);
+
+            assert_eq!(ix.data[..8], SWAP_DISCRIMINATOR);
+            assert_eq!(ix.data[8..16], 1_000u64.to_le_bytes());
+            assert_eq!(ix.data[16..24], 900u64.to_le_bytes());
+            let limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+            assert_eq!(ix.data[24..40], limit.to_le_bytes());
+            assert_eq!(ix.data[40..], [1, a_to_b as u8]);
+        }
+        assert!(dex.build_swap_ix(&info.token_mint_a, &Pubkey::new_unique(), 1_000, 900).is_err());
+    }
+}
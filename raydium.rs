@@ -1,32 +1,437 @@
 use {
-    solana_client::rpc_client::RpcClient,
+    solana_account_decoder::UiAccountEncoding,
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    },
     solana_sdk::{
+        instruction::{AccountMeta, Instruction},
         pubkey::Pubkey,
         signature::{Keypair, Signature},
+        signer::Signer,
         transaction::Transaction,
-        sysvar::rent::Rent,
     },
     anyhow::{Result, anyhow},
-    raydium_contract_instructions::amm_instruction,
     serde::{Deserialize, Serialize},
-    std::collections::HashMap,
-    tokio::time::{Duration, Instant},
+    crate::{
+        config::TradingConfig,
+        dex::{resolve_amm_program_id, SwapVenue},
+        error::BotError,
+        monitoring::wallet::full_account_keys,
+        trading::types::Price,
+    },
+    solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta,
+    lru::LruCache,
+    spl_associated_token_account::get_associated_token_address,
+    spl_token::native_mint,
+    parking_lot::{Mutex, RwLock},
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+    },
+    tokio::{
+        sync::{mpsc, Semaphore},
+        task::JoinSet,
+        time::{Duration, Instant},
+    },
+    tracing::{error, info, warn},
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+const POOL_CACHE_CAPACITY: usize = 256;
+const POOL_CACHE_TTL: Duration = Duration::from_millis(500); // Reserves move every slot
+const MAX_CONCURRENT_POOL_UPDATES: usize = 8; // RPC reads in flight across monitor_pools
+const DRAINED_RESERVE_SHARE: f64 = 0.01; // Quote reserve under 1% of its peak means liquidity moved
+const FULL_CONFIDENCE_MOVE: f64 = 0.10; // Price moves this large or larger signal with full confidence
+
+// Raydium AMM v4 `AmmInfo` account layout
+pub const AMM_INFO_SIZE: usize = 752;
+const NONCE_OFFSET: usize = 8;
+const BASE_DECIMALS_OFFSET: usize = 32;
+const QUOTE_DECIMALS_OFFSET: usize = 40;
+const TRADE_FEE_NUMERATOR_OFFSET: usize = 144;
+const TRADE_FEE_DENOMINATOR_OFFSET: usize = 152;
+const NEED_TAKE_PNL_BASE_OFFSET: usize = 192;
+const NEED_TAKE_PNL_QUOTE_OFFSET: usize = 200;
+const POOL_OPEN_TIME_OFFSET: usize = 224;
+const BASE_VAULT_OFFSET: usize = 336;
+const QUOTE_VAULT_OFFSET: usize = 368;
+const BASE_MINT_OFFSET: usize = 400;
+const QUOTE_MINT_OFFSET: usize = 432;
+const LP_MINT_OFFSET: usize = 464;
+const OPEN_ORDERS_OFFSET: usize = 496;
+const MARKET_OFFSET: usize = 528;
+const MARKET_PROGRAM_OFFSET: usize = 560;
+const TARGET_ORDERS_OFFSET: usize = 592;
+const LP_AMOUNT_OFFSET: usize = 720;
+
+// OpenBook / Serum v3 market layout, including the 5-byte "serum" header
+pub const MARKET_STATE_SIZE: usize = 388;
+const VAULT_SIGNER_NONCE_OFFSET: usize = 45;
+const MARKET_COIN_VAULT_OFFSET: usize = 117;
+const MARKET_PC_VAULT_OFFSET: usize = 165;
+const MARKET_EVENT_QUEUE_OFFSET: usize = 253;
+const MARKET_BIDS_OFFSET: usize = 285;
+const MARKET_ASKS_OFFSET: usize = 317;
+
+const AMM_AUTHORITY_SEED: &[u8] = b"amm authority";
+const SWAP_BASE_IN_TAG: u8 = 9;
+
+// Initialize2: tag, nonce, open_time, init_pc_amount, init_coin_amount
+const INITIALIZE2_TAG: u8 = 1;
+const INITIALIZE2_DATA_LEN: usize = 26;
+pub const INITIALIZE2_LOG: &str = "initialize2"; // Raydium logs this when a pool is created
+// Positions in Initialize2's account list
+const INIT_AMM_INDEX: usize = 4;
+const INIT_LP_MINT_INDEX: usize = 7;
+const INIT_BASE_MINT_INDEX: usize = 8;
+const INIT_QUOTE_MINT_INDEX: usize = 9;
+const INIT_BASE_VAULT_INDEX: usize = 10;
+const INIT_QUOTE_VAULT_INDEX: usize = 11;
+const INIT_MARKET_INDEX: usize = 16;
+const INIT_ACCOUNT_COUNT: usize = 21;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolInfo {
     pub liquidity: u64,
     pub base_amount: u64,
     pub quote_amount: u64,
     pub fee_numerator: u64,
     pub fee_denominator: u64,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub need_take_pnl_base: u64,
+    pub need_take_pnl_quote: u64,
+    pub pool_open_time: u64,
+}
+
+impl PoolInfo {
+    // Decodes the static pool state; vault balances live in separate token
+    // accounts and are filled in by `PoolInfo::fetch`
+    pub fn from_raydium_account(data: &[u8]) -> Result<PoolInfo> {
+        if data.len() != AMM_INFO_SIZE {
+            return Err(BotError::ParseError(format!(
+                "Raydium AMM account must be {} bytes, got {}",
+                AMM_INFO_SIZE,
+                data.len()
+            )).into());
+        }
+
+        Ok(PoolInfo {
+            liquidity: read_u64(data, LP_AMOUNT_OFFSET),
+            base_amount: 0,
+            quote_amount: 0,
+            fee_numerator: read_u64(data, TRADE_FEE_NUMERATOR_OFFSET),
+            fee_denominator: read_u64(data, TRADE_FEE_DENOMINATOR_OFFSET),
+            base_decimals: read_u64(data, BASE_DECIMALS_OFFSET) as u8,
+            quote_decimals: read_u64(data, QUOTE_DECIMALS_OFFSET) as u8,
+            base_vault: read_pubkey(data, BASE_VAULT_OFFSET),
+            quote_vault: read_pubkey(data, QUOTE_VAULT_OFFSET),
+            base_mint: read_pubkey(data, BASE_MINT_OFFSET),
+            quote_mint: read_pubkey(data, QUOTE_MINT_OFFSET),
+            lp_mint: read_pubkey(data, LP_MINT_OFFSET),
+            need_take_pnl_base: read_u64(data, NEED_TAKE_PNL_BASE_OFFSET),
+            need_take_pnl_quote: read_u64(data, NEED_TAKE_PNL_QUOTE_OFFSET),
+            pool_open_time: read_u64(data, POOL_OPEN_TIME_OFFSET),
+        })
+    }
+
+    // Pool state plus current reserves
+    pub fn fetch(rpc_client: &RpcClient, pool_id: &Pubkey) -> Result<PoolInfo> {
+        let account = rpc_client.get_account(pool_id)?;
+        let mut pool_info = PoolInfo::from_raydium_account(&account.data)?;
+
+        // Reserves are the vault balances minus the PnL still owed to the pool owner
+        let base_vault = rpc_client.get_token_account_balance(&pool_info.base_vault)?;
+        let quote_vault = rpc_client.get_token_account_balance(&pool_info.quote_vault)?;
+
+        pool_info.base_amount = base_vault.amount.parse::<u64>()?
+            .saturating_sub(pool_info.need_take_pnl_base);
+        pool_info.quote_amount = quote_vault.amount.parse::<u64>()?
+            .saturating_sub(pool_info.need_take_pnl_quote);
+
+        Ok(pool_info)
+    }
+
+    // Quote per base at current reserves, None for an empty pool
+    pub fn price(&self) -> Option<Price> {
+        Price::from_reserves(self.base_amount, self.quote_amount)
+    }
+
+    // Whether `quote_mint` sits on the pool's quote side. Pools list their
+    // mints in either order, so a USDC/token pool has USDC as its base.
+    pub fn quote_is_pool_quote(&self, quote_mint: &Pubkey) -> Result<bool> {
+        if self.quote_mint == *quote_mint {
+            Ok(true)
+        } else if self.base_mint == *quote_mint {
+            Ok(false)
+        } else {
+            Err(anyhow!(
+                "Pool pairs {} with {}, not the quote token {}",
+                self.base_mint, self.quote_mint, quote_mint
+            ))
+        }
+    }
+
+    // Whole `quote_mint` tokens per whole unit of the other mint, decimals applied
+    pub fn ui_price_in(&self, quote_mint: &Pubkey) -> Result<f64> {
+        let (token_reserve, token_decimals, quote_reserve, quote_decimals) =
+            if self.quote_is_pool_quote(quote_mint)? {
+                (self.base_amount, self.base_decimals, self.quote_amount, self.quote_decimals)
+            } else {
+                (self.quote_amount, self.quote_decimals, self.base_amount, self.base_decimals)
+            };
+        if token_reserve == 0 {
+            return Err(anyhow!("Pool has no reserves of the token priced against {}", quote_mint));
+        }
+        let token = token_reserve as f64 / 10f64.powi(token_decimals as i32);
+        let quote = quote_reserve as f64 / 10f64.powi(quote_decimals as i32);
+        Ok(quote / token)
+    }
+}
+
+// Either side emptied, or the quote side down to a sliver of the most it held
+fn is_drained(pool: &PoolInfo, peak_quote: u64) -> bool {
+    pool.base_amount == 0
+        || pool.quote_amount == 0
+        || (pool.quote_amount as f64) < peak_quote as f64 * DRAINED_RESERVE_SHARE
+}
+
+// The cached pool info if it was fetched less than `ttl` before `now`
+fn fresh_pool_info(cached: Option<&(PoolInfo, Instant)>, ttl: Duration, now: Instant) -> Option<PoolInfo> {
+    let (info, fetched_at) = cached?;
+    (now.saturating_duration_since(*fetched_at) < ttl).then(|| info.clone())
+}
+
+// Callers check the account length up front, so these slices are in bounds
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&data[offset..offset + 32]);
+    Pubkey::new_from_array(bytes)
+}
+
+// A pool created by Initialize2, with the liquidity it opened with
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPoolEvent {
+    pub pool_id: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub lp_mint: Pubkey,
+    pub market_id: Pubkey,
+    pub base_reserve: u64,  // init_coin_amount
+    pub quote_reserve: u64, // init_pc_amount
+    pub open_time: u64,     // Unix seconds swaps are allowed from
+}
+
+impl NewPoolEvent {
+    // `accounts` are the instruction's accounts in order, already resolved to pubkeys
+    pub fn from_initialize2(data: &[u8], accounts: &[Pubkey]) -> Option<NewPoolEvent> {
+        if data.len() != INITIALIZE2_DATA_LEN || data[0] != INITIALIZE2_TAG || accounts.len() < INIT_ACCOUNT_COUNT {
+            return None;
+        }
+        Some(NewPoolEvent {
+            pool_id: accounts[INIT_AMM_INDEX],
+            base_mint: accounts[INIT_BASE_MINT_INDEX],
+            quote_mint: accounts[INIT_QUOTE_MINT_INDEX],
+            base_vault: accounts[INIT_BASE_VAULT_INDEX],
+            quote_vault: accounts[INIT_QUOTE_VAULT_INDEX],
+            lp_mint: accounts[INIT_LP_MINT_INDEX],
+            market_id: accounts[INIT_MARKET_INDEX],
+            open_time: read_u64(data, 2),
+            quote_reserve: read_u64(data, 10),
+            base_reserve: read_u64(data, 18),
+        })
+    }
+
+    pub fn involves(&self, mint: &Pubkey) -> bool {
+        self.base_mint == *mint || self.quote_mint == *mint
+    }
+}
+
+// Every pool a transaction created through top-level Initialize2 calls
+pub fn decode_new_pools(amm_program_id: &Pubkey, tx: &EncodedConfirmedTransactionWithStatusMeta) -> Vec<NewPoolEvent> {
+    let (Some(meta), Some(decoded)) = (tx.transaction.meta.as_ref(), tx.transaction.transaction.decode()) else {
+        return Vec::new();
+    };
+    if meta.err.is_some() {
+        return Vec::new();
+    }
+
+    let account_keys = full_account_keys(&decoded.message, meta);
+    decoded.message
+        .instructions()
+        .iter()
+        .filter(|ix| account_keys.get(ix.program_id_index as usize) == Some(amm_program_id))
+        .filter_map(|ix| {
+            let accounts: Option<Vec<Pubkey>> = ix.accounts
+                .iter()
+                .map(|&index| account_keys.get(index as usize).copied())
+                .collect();
+            NewPoolEvent::from_initialize2(&ix.data, &accounts?)
+        })
+        .collect()
+}
+
+// Every account a v4 swap touches, resolved from the AMM and its market
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolKeys {
+    pub amm_program_id: Pubkey,
+    pub amm_id: Pubkey,
+    pub authority: Pubkey,
+    pub open_orders: Pubkey,
+    pub target_orders: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub market_program_id: Pubkey,
+    pub market_id: Pubkey,
+    pub market_bids: Pubkey,
+    pub market_asks: Pubkey,
+    pub market_event_queue: Pubkey,
+    pub market_base_vault: Pubkey,
+    pub market_quote_vault: Pubkey,
+    pub market_authority: Pubkey,
+}
+
+impl PoolKeys {
+    pub fn from_accounts(
+        amm_program_id: &Pubkey,
+        amm_id: &Pubkey,
+        amm_data: &[u8],
+        market_data: &[u8],
+    ) -> Result<PoolKeys> {
+        if amm_data.len() != AMM_INFO_SIZE {
+            return Err(BotError::ParseError(format!(
+                "Raydium AMM account must be {} bytes, got {}",
+                AMM_INFO_SIZE,
+                amm_data.len()
+            )).into());
+        }
+        if market_data.len() < MARKET_STATE_SIZE {
+            return Err(BotError::ParseError(format!(
+                "Market account must be at least {} bytes, got {}",
+                MARKET_STATE_SIZE,
+                market_data.len()
+            )).into());
+        }
+
+        let market_id = read_pubkey(amm_data, MARKET_OFFSET);
+        let market_program_id = read_pubkey(amm_data, MARKET_PROGRAM_OFFSET);
+
+        let authority_nonce = read_u64(amm_data, NONCE_OFFSET) as u8;
+        let authority = Pubkey::create_program_address(
+            &[AMM_AUTHORITY_SEED, &[authority_nonce]],
+            amm_program_id,
+        ).map_err(|e| BotError::ParseError(format!("Invalid AMM authority nonce: {}", e)))?;
+
+        let vault_signer_nonce = read_u64(market_data, VAULT_SIGNER_NONCE_OFFSET);
+        let market_authority = Pubkey::create_program_address(
+            &[market_id.as_ref(), &vault_signer_nonce.to_le_bytes()],
+            &market_program_id,
+        ).map_err(|e| BotError::ParseError(format!("Invalid market vault signer nonce: {}", e)))?;
+
+        Ok(PoolKeys {
+            amm_program_id: *amm_program_id,
+            amm_id: *amm_id,
+            authority,
+            open_orders: read_pubkey(amm_data, OPEN_ORDERS_OFFSET),
+            target_orders: read_pubkey(amm_data, TARGET_ORDERS_OFFSET),
+            base_vault: read_pubkey(amm_data, BASE_VAULT_OFFSET),
+            quote_vault: read_pubkey(amm_data, QUOTE_VAULT_OFFSET),
+            base_mint: read_pubkey(amm_data, BASE_MINT_OFFSET),
+            quote_mint: read_pubkey(amm_data, QUOTE_MINT_OFFSET),
+            market_program_id,
+            market_id,
+            market_bids: read_pubkey(market_data, MARKET_BIDS_OFFSET),
+            market_asks: read_pubkey(market_data, MARKET_ASKS_OFFSET),
+            market_event_queue: read_pubkey(market_data, MARKET_EVENT_QUEUE_OFFSET),
+            market_base_vault: read_pubkey(market_data, MARKET_COIN_VAULT_OFFSET),
+            market_quote_vault: read_pubkey(market_data, MARKET_PC_VAULT_OFFSET),
+            market_authority,
+        })
+    }
+
+    // Two reads: the AMM account, then the market it points at
+    pub fn fetch(rpc_client: &RpcClient, amm_program_id: &Pubkey, amm_id: &Pubkey) -> Result<PoolKeys> {
+        let amm_account = rpc_client.get_account(amm_id)?;
+        let market_id = read_pubkey(&amm_account.data, MARKET_OFFSET);
+        let market_account = rpc_client.get_account(&market_id)?;
+        PoolKeys::from_accounts(amm_program_id, amm_id, &amm_account.data, &market_account.data)
+    }
+}
+
+// SwapBaseIn with the 18 accounts the v4 program expects, in order.
+// `user` owns the associated token accounts on both sides and signs.
+pub fn build_raydium_swap_ix(
+    pool: &PoolKeys,
+    user: &Pubkey,
+    amount_in: u64,
+    min_out: u64,
+    input_is_base: bool,
+) -> Result<Instruction> {
+    if amount_in == 0 {
+        return Err(anyhow!("Swap amount must be non-zero"));
+    }
+
+    let (source_mint, destination_mint) = if input_is_base {
+        (pool.base_mint, pool.quote_mint)
+    } else {
+        (pool.quote_mint, pool.base_mint)
+    };
+
+    let mut data = Vec::with_capacity(17);
+    data.push(SWAP_BASE_IN_TAG);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_out.to_le_bytes());
+
+    Ok(Instruction {
+        program_id: pool.amm_program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(pool.amm_id, false),
+            AccountMeta::new_readonly(pool.authority, false),
+            AccountMeta::new(pool.open_orders, false),
+            AccountMeta::new(pool.target_orders, false),
+            AccountMeta::new(pool.base_vault, false),
+            AccountMeta::new(pool.quote_vault, false),
+            AccountMeta::new_readonly(pool.market_program_id, false),
+            AccountMeta::new(pool.market_id, false),
+            AccountMeta::new(pool.market_bids, false),
+            AccountMeta::new(pool.market_asks, false),
+            AccountMeta::new(pool.market_event_queue, false),
+            AccountMeta::new(pool.market_base_vault, false),
+            AccountMeta::new(pool.market_quote_vault, false),
+            AccountMeta::new_readonly(pool.market_authority, false),
+            AccountMeta::new(get_associated_token_address(user, &source_mint), false),
+            AccountMeta::new(get_associated_token_address(user, &destination_mint), false),
+            AccountMeta::new_readonly(*user, true),
+        ],
+        data,
+    })
 }
 
 #[derive(Debug, Clone)]
 pub struct PoolState {
     pub info: PoolInfo,
     pub last_update: Instant,
-    pub price_history: Vec<(Instant, f64)>,
+    pub price_history: Vec<(Instant, Price)>,
 }
 
 #[derive(Debug)]
@@ -44,40 +449,79 @@ pub enum TradeDirection {
     Sell,
 }
 
+impl TradeSignal {
+    // Direction follows the move; confidence grows with its size, full at FULL_CONFIDENCE_MOVE
+    pub fn new(price_change: f64, volume_change: f64) -> Self {
+        Self {
+            direction: if price_change >= 0.0 { TradeDirection::Buy } else { TradeDirection::Sell },
+            price_change,
+            volume_change,
+            confidence: (price_change.abs() / FULL_CONFIDENCE_MOVE).min(1.0),
+            timestamp: Instant::now(),
+        }
+    }
+}
+
 pub struct RaydiumDex {
-    rpc_client: RpcClient,
+    rpc_client: Arc<RpcClient>, // Shared with spawn_blocking reads
     amm_program_id: Pubkey,
     min_liquidity: u64,
     max_slippage: f64,
+    min_confidence: f64,
     payer: Keypair,
-    pools: HashMap<Pubkey, PoolState>,
+    pools: RwLock<HashMap<Pubkey, PoolState>>,
     update_interval: Duration,
+    pool_cache: Mutex<LruCache<Pubkey, (PoolInfo, Instant)>>, // Pool -> info, fetched at
+    pool_cache_ttl: Duration,
+    pool_keys: Mutex<HashMap<Pubkey, PoolKeys>>, // Static per pool, loaded once
+    mint_pools: Mutex<HashMap<Pubkey, Pubkey>>,  // Mint -> its deepest pool, from find_pool_for_mint
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
 }
 
 impl RaydiumDex {
-    pub fn new(config: &Config) -> Self {
-        Self {
-            rpc_client: RpcClient::new(config.rpc_url.clone()),
-            amm_program_id: "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"
-                .parse()
-                .unwrap(),
-            min_liquidity: config.min_liquidity,
+    pub fn new(config: &TradingConfig, payer: Keypair) -> Result<Self> {
+        Ok(Self {
+            rpc_client: Arc::new(RpcClient::new(config.rpc_endpoint())),
+            amm_program_id: resolve_amm_program_id(config)?,
+            min_liquidity: config.min_liquidity as u64,
             max_slippage: config.max_slippage,
-            payer: config.payer.clone(),
-            pools: HashMap::new(),
+            min_confidence: config.min_confidence,
+            payer,
+            pools: RwLock::new(HashMap::new()),
             update_interval: Duration::from_secs(1),
-        }
+            pool_cache: Mutex::new(LruCache::new(POOL_CACHE_CAPACITY)),
+            pool_cache_ttl: POOL_CACHE_TTL,
+            pool_keys: Mutex::new(HashMap::new()),
+            mint_pools: Mutex::new(HashMap::new()),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+        })
     }
 
-    pub async fn get_pool_info(&self, pool_id: &Pubkey) -> Result<PoolInfo> {
-        let account = self.rpc_client.get_account(pool_id)?;
-        let pool_info = PoolInfo::deserialize(&account.data)?;
-        Ok(pool_info)
+    // Cached for pool_cache_ttl; `force` skips the cache for paths that need live reserves
+    pub async fn get_pool_info(&self, pool_id: &Pubkey, force: bool) -> Result<PoolInfo> {
+        self.fetch_pool_info(pool_id, force).await
+    }
+
+    // (hits, misses) of the pool info cache; forced reads count as misses
+    pub fn cache_stats(&self) -> (u64, u64) {
+        (self.cache_hits.load(Ordering::Relaxed), self.cache_misses.load(Ordering::Relaxed))
+    }
+
+    pub async fn validate_liquidity(&self, pool_id: &Pubkey) -> Result<bool> {
+        let pool = self.get_pool_info(pool_id, false).await?;
+        Ok(Self::has_min_liquidity(&pool, self.min_liquidity))
     }
 
-    pub async fn validate_liquidity(&self, token: &Pubkey) -> Result<bool> {
-        let pool = self.get_pool_info(token).await?;
-        Ok(pool.liquidity > self.min_liquidity)
+    // For callers that already hold the pool state
+    pub fn has_min_liquidity(pool: &PoolInfo, min_liquidity: u64) -> bool {
+        pool.liquidity > min_liquidity
+    }
+
+    // Seconds since the pool opened for trading, 0 if it hasn't opened yet
+    pub fn pool_age_secs(pool: &PoolInfo, now: u64) -> u64 {
+        now.saturating_sub(pool.pool_open_time)
     }
 
     pub async fn execute_swap(
@@ -86,20 +530,20 @@ impl RaydiumDex {
         amount_in: u64,
         min_amount_out: u64,
     ) -> Result<Signature> {
-        let pool = self.get_pool_info(pool_id).await?;
+        let pool = self.get_pool_info(pool_id, true).await?;
         
         // Calculate price impact
-        let price_impact = self.calculate_price_impact(&pool, amount_in)?;
+        let price_impact = Self::calculate_price_impact(&pool, amount_in, true)?;
         if price_impact > self.max_slippage {
-            return Err(anyhow!("Price impact too high: {}", price_impact));
+            return Err(BotError::SlippageError(format!(
+                "price impact {:.2}% exceeds max {:.2}%",
+                price_impact * 100.0,
+                self.max_slippage * 100.0
+            )).into());
         }
 
-        let swap_ix = amm_instruction::swap(
-            &self.amm_program_id,
-            pool_id,
-            amount_in,
-            min_amount_out,
-        )?;
+        let keys = self.get_pool_keys(pool_id)?;
+        let swap_ix = build_raydium_swap_ix(&keys, &self.payer.pubkey(), amount_in, min_amount_out, true)?;
 
         let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
         let tx = Transaction::new_signed_with_payer(
@@ -113,20 +557,30 @@ impl RaydiumDex {
             .map_err(|e| anyhow!("Swap failed: {}", e))
     }
 
-    fn calculate_price_impact(&self, pool: &PoolInfo, amount_in: u64) -> Result<f64> {
-        let price_before = pool.quote_amount as f64 / pool.base_amount as f64;
-        let new_base = pool.base_amount + amount_in;
-        let new_quote = (pool.base_amount * pool.quote_amount) / new_base;
-        let price_after = new_quote as f64 / new_base as f64;
-        
-        Ok((price_before - price_after).abs() / price_before)
+    // Fractional price move caused by swapping `amount_in` into the pool
+    pub fn calculate_price_impact(pool: &PoolInfo, amount_in: u64, input_is_base: bool) -> Result<f64> {
+        let price_before = pool.price().ok_or_else(|| anyhow!("Pool has no base reserves"))?;
+        let k = pool.base_amount as u128 * pool.quote_amount as u128;
+        let (new_base, new_quote) = if input_is_base {
+            let new_base = pool.base_amount as u128 + amount_in as u128;
+            (new_base, k / new_base)
+        } else {
+            let new_quote = pool.quote_amount as u128 + amount_in as u128;
+            (k / new_quote, new_quote)
+        };
+        let price_after = Price::from_ratio(new_quote, new_base)
+            .ok_or_else(|| anyhow!("Price impact out of range"))?;
+
+        let impact_bps = price_after.change_bps(price_before).unwrap_or(0).unsigned_abs();
+        Ok(impact_bps as f64 / 10_000.0)
     }
 
-    pub async fn update_pool(&mut self, pool_id: &Pubkey) -> Result<()> {
-        let pool_info = self.fetch_pool_info(pool_id).await?;
+    pub async fn update_pool(&self, pool_id: &Pubkey) -> Result<()> {
+        let pool_info = self.fetch_pool_info(pool_id, false).await?;
         let price = self.calculate_price(&pool_info)?;
         
-        let state = self.pools.entry(*pool_id).or_insert(PoolState {
+        let mut pools = self.pools.write();
+        let state = pools.entry(*pool_id).or_insert(PoolState {
             info: pool_info.clone(),
             last_update: Instant::now(),
             price_history: Vec::new(),
@@ -144,41 +598,246 @@ impl RaydiumDex {
         Ok(())
     }
 
-    async fn fetch_pool_info(&self, pool_id: &Pubkey) -> Result<PoolInfo> {
-        let account = self.rpc_client.get_account(pool_id)?;
-        PoolInfo::try_from_slice(&account.data)
-            .map_err(|e| anyhow!("Failed to deserialize pool info: {}", e))
+    async fn fetch_pool_info(&self, pool_id: &Pubkey, force: bool) -> Result<PoolInfo> {
+        if !force {
+            let cached = fresh_pool_info(self.pool_cache.lock().get(pool_id), self.pool_cache_ttl, Instant::now());
+            if let Some(info) = cached {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(info);
+            }
+        }
+
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        let (rpc_client, id) = (self.rpc_client.clone(), *pool_id);
+        let info = tokio::task::spawn_blocking(move || PoolInfo::fetch(&rpc_client, &id)).await??;
+        self.pool_cache.lock().put(*pool_id, (info.clone(), Instant::now()));
+        Ok(info)
+    }
+
+    pub fn get_pool_keys(&self, pool_id: &Pubkey) -> Result<PoolKeys> {
+        if let Some(keys) = self.pool_keys.lock().get(pool_id) {
+            return Ok(keys.clone());
+        }
+
+        let keys = PoolKeys::fetch(&self.rpc_client, &self.amm_program_id, pool_id)?;
+        self.pool_keys.lock().insert(*pool_id, keys.clone());
+        Ok(keys)
     }
 
-    fn calculate_price(&self, pool: &PoolInfo) -> Result<f64> {
-        Ok(pool.quote_amount as f64 / pool.base_amount as f64)
+    // The pool holding the most liquidity for `mint` on either side, or None if
+    // the AMM has no pool for it. Cached until forget_pool_for_mint.
+    pub fn find_pool_for_mint(&self, mint: &Pubkey) -> Result<Option<Pubkey>> {
+        if let Some(pool_id) = self.mint_pools.lock().get(mint) {
+            return Ok(Some(*pool_id));
+        }
+
+        // Filters are ANDed, so base and quote side are separate queries
+        let mut best: Option<(Pubkey, u64)> = None;
+        for offset in [BASE_MINT_OFFSET, QUOTE_MINT_OFFSET] {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::DataSize(AMM_INFO_SIZE as u64),
+                    RpcFilterType::Memcmp(Memcmp::new(offset, MemcmpEncodedBytes::Base58(mint.to_string()))),
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let accounts = self.rpc_client.get_program_accounts_with_config(&self.amm_program_id, config)?;
+            for (pool_id, account) in accounts {
+                let Ok(info) = PoolInfo::from_raydium_account(&account.data) else { continue };
+                if info.liquidity > 0 && best.is_none_or(|(_, liquidity)| info.liquidity > liquidity) {
+                    best = Some((pool_id, info.liquidity));
+                }
+            }
+        }
+
+        let pool_id = best.map(|(pool_id, _)| pool_id);
+        if let Some(pool_id) = pool_id {
+            self.mint_pools.lock().insert(*mint, pool_id);
+        }
+        Ok(pool_id)
+    }
+
+    pub fn forget_pool_for_mint(&self, mint: &Pubkey) {
+        self.mint_pools.lock().remove(mint);
+    }
+
+    // Where `pool`'s token trades now, if its liquidity moved to another pool
+    fn migrated_pool(&self, pool_id: &Pubkey, pool: &PoolInfo) -> Result<Option<Pubkey>> {
+        let mint = if pool.base_mint == native_mint::ID { pool.quote_mint } else { pool.base_mint };
+        self.forget_pool_for_mint(&mint);
+        Ok(self.find_pool_for_mint(&mint)?.filter(|found| found != pool_id))
+    }
+
+    // Pool trading `input` against `output`, with whether `input` is the base side.
+    // Pairs nothing tracks yet are looked up through find_pool_for_mint and tracked.
+    fn find_pool(&self, input: &Pubkey, output: &Pubkey) -> Result<Option<(Pubkey, PoolInfo, bool)>> {
+        if let Some(found) = self.tracked_pool(input, output) {
+            return Ok(Some(found));
+        }
+
+        for mint in [output, input] {
+            let Some(pool_id) = self.find_pool_for_mint(mint)? else { continue };
+            let info = PoolInfo::fetch(&self.rpc_client, &pool_id)?;
+            let pairs = (info.base_mint == *input && info.quote_mint == *output)
+                || (info.quote_mint == *input && info.base_mint == *output);
+            if pairs {
+                self.pools.write().entry(pool_id).or_insert(PoolState {
+                    info,
+                    last_update: Instant::now(),
+                    price_history: Vec::new(),
+                });
+                return Ok(self.tracked_pool(input, output));
+            }
+        }
+        Ok(None)
+    }
+
+    fn tracked_pool(&self, input: &Pubkey, output: &Pubkey) -> Option<(Pubkey, PoolInfo, bool)> {
+        self.pools.read().iter().find_map(|(pool_id, state)| {
+            let info = &state.info;
+            if info.base_mint == *input && info.quote_mint == *output {
+                Some((*pool_id, info.clone(), true))
+            } else if info.quote_mint == *input && info.base_mint == *output {
+                Some((*pool_id, info.clone(), false))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Constant-product output after the pool's trade fee
+    pub fn calculate_amount_out(pool: &PoolInfo, amount_in: u64, input_is_base: bool) -> u64 {
+        let (reserve_in, reserve_out) = if input_is_base {
+            (pool.base_amount as u128, pool.quote_amount as u128)
+        } else {
+            (pool.quote_amount as u128, pool.base_amount as u128)
+        };
+
+        let fee = if pool.fee_denominator == 0 {
+            0
+        } else {
+            amount_in as u128 * pool.fee_numerator as u128 / pool.fee_denominator as u128
+        };
+        let amount_in_after_fee = amount_in as u128 - fee;
+
+        let denominator = reserve_in + amount_in_after_fee;
+        if denominator == 0 {
+            return 0;
+        }
+        (reserve_out * amount_in_after_fee / denominator) as u64
+    }
+
+    fn calculate_price(&self, pool: &PoolInfo) -> Result<Price> {
+        pool.price().ok_or_else(|| anyhow!("Pool has no base reserves"))
+    }
+
+    // Watches every pool at once, one task each, with at most
+    // MAX_CONCURRENT_POOL_UPDATES RPC reads in flight. Validated signals go to
+    // `signals` for the trade executor; returns once the receiver is dropped.
+    pub async fn monitor_pools(
+        self: Arc<Self>,
+        pools: Vec<Pubkey>,
+        signals: mpsc::Sender<(Pubkey, TradeSignal)>,
+    ) -> Result<()> {
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_POOL_UPDATES));
+        let mut watchers = JoinSet::new();
+        for pool_id in pools {
+            watchers.spawn(self.clone().watch_pool(pool_id, permits.clone(), signals.clone()));
+        }
+        drop(signals);
+
+        // One pool's watcher ending never stops the others
+        while let Some(joined) = watchers.join_next().await {
+            match joined {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!(error = %e, "Pool watcher stopped"),
+                Err(e) => error!(error = %e, "Pool watcher panicked"),
+            }
+        }
+        Ok(())
     }
 
-    pub async fn monitor_pool(&mut self, pool_id: &Pubkey) -> Result<()> {
+    // One pool's loop for monitor_pools; a failed update or analysis is logged
+    // and retried on the next tick rather than ending the watcher
+    async fn watch_pool(
+        self: Arc<Self>,
+        pool_id: Pubkey,
+        permits: Arc<Semaphore>,
+        signals: mpsc::Sender<(Pubkey, TradeSignal)>,
+    ) -> Result<()> {
+        // Signals keep carrying `pool_id` after its liquidity has moved, so
+        // the receiver's pool -> token mapping stays valid
+        let mut current = pool_id;
+        let mut peak_quote = 0;
         loop {
-            let current_state = self.update_pool_state(pool_id).await?;
-            
-            if let Some(signal) = self.analyze_pool_state(&current_state).await? {
-                if self.validate_trade_conditions(pool_id, &signal).await? {
-                    self.execute_trade(pool_id, &signal).await?;
+            let state = {
+                let _permit = permits.acquire().await?;
+                self.update_pool_state(&current).await
+            };
+
+            let signal = match state {
+                Ok(state) if self.skip_drained(&mut current, &mut peak_quote, &state.info) => Ok(None),
+                Ok(state) => self.validated_signal(&current, &state).await,
+                Err(e) => Err(e),
+            };
+            match signal {
+                Ok(Some(signal)) => {
+                    if signals.send((pool_id, signal)).await.is_err() {
+                        return Ok(());
+                    }
                 }
+                Ok(None) => {}
+                Err(e) => warn!(pool = %pool_id, error = %e, "Pool update failed"),
+            }
+
+            if signals.is_closed() {
+                return Ok(());
             }
-            
             tokio::time::sleep(self.update_interval).await;
         }
     }
 
-    async fn update_pool_state(&mut self, pool_id: &Pubkey) -> Result<PoolState> {
-        let info = self.fetch_pool_info(pool_id).await?;
-        let price = self.calculate_current_price(&info);
-        let state = PoolState {
-            info,
-            last_update: Instant::now(),
-            price_history: vec![(Instant::now(), price)],
+    // Follows the token if its liquidity migrates: once the pool's reserves
+    // collapse, the mint's pool is looked up again and watched instead. True
+    // while `pool_id` is drained, so its signals are skipped.
+    fn skip_drained(&self, pool_id: &mut Pubkey, peak_quote: &mut u64, pool: &PoolInfo) -> bool {
+        *peak_quote = (*peak_quote).max(pool.quote_amount);
+        if !is_drained(pool, *peak_quote) {
+            return false;
+        }
+        match self.migrated_pool(pool_id, pool) {
+            Ok(Some(new_pool)) => {
+                info!(old_pool = %pool_id, new_pool = %new_pool, "Liquidity migrated, following the new pool");
+                self.pools.write().remove(pool_id);
+                *pool_id = new_pool;
+                *peak_quote = 0;
+            }
+            Ok(None) => warn!(pool = %pool_id, "Pool drained and no other pool found"),
+            Err(e) => warn!(pool = %pool_id, error = %e, "Pool drained, looking for its new pool failed"),
+        }
+        true
+    }
+
+    // Refreshes the pool and returns its state, price history included
+    async fn update_pool_state(&self, pool_id: &Pubkey) -> Result<PoolState> {
+        self.update_pool(pool_id).await?;
+        self.pools
+            .read()
+            .get(pool_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Pool {} not tracked", pool_id))
+    }
+
+    // The pool's signal, if it has one that passes validate_trade_conditions
+    async fn validated_signal(&self, pool_id: &Pubkey, state: &PoolState) -> Result<Option<TradeSignal>> {
+        let Some(signal) = self.analyze_pool_state(state).await? else {
+            return Ok(None);
         };
-        
-        self.pools.insert(*pool_id, state.clone());
-        Ok(state)
+        Ok(self.validate_trade_conditions(pool_id, &signal).await?.then_some(signal))
     }
 
     async fn analyze_pool_state(&self, state: &PoolState) -> Result<Option<TradeSignal>> {
@@ -192,7 +851,7 @@ impl RaydiumDex {
         }
     }
 
-    fn calculate_price_change(&self, price_history: &[(Instant, f64)]) -> Result<f64> {
+    fn calculate_price_change(&self, price_history: &[(Instant, Price)]) -> Result<f64> {
         if price_history.len() < 2 {
             return Ok(0.0);
         }
@@ -201,8 +860,10 @@ impl RaydiumDex {
             .ok_or_else(|| anyhow!("No price data"))?;
         let (_, previous_price) = price_history.first()
             .ok_or_else(|| anyhow!("No previous price"))?;
-            
-        Ok((current_price - previous_price) / previous_price)
+
+        let change_bps = current_price.change_bps(*previous_price)
+            .ok_or_else(|| anyhow!("Previous price is zero"))?;
+        Ok(change_bps as f64 / 10_000.0)
     }
 
     fn calculate_volume(&self, pool_info: &PoolInfo) -> Result<f64> {
@@ -223,11 +884,13 @@ impl RaydiumDex {
         pool_id: &Pubkey, 
         signal: &TradeSignal
     ) -> Result<bool> {
-        let pool_state = self.pools.get(pool_id)
+        let liquidity = self.pools.read()
+            .get(pool_id)
+            .map(|state| state.info.liquidity)
             .ok_or_else(|| anyhow!("Pool not found"))?;
             
         // Validate liquidity
-        if pool_state.info.liquidity < self.min_liquidity {
+        if liquidity < self.min_liquidity {
             return Ok(false);
         }
         
@@ -237,10 +900,509 @@ impl RaydiumDex {
         }
         
         // Validate confidence
-        if signal.confidence < 0.7 {
+        if signal.confidence < self.min_confidence {
             return Ok(false);
         }
         
         Ok(true)
     }
-}
\ No newline at end of file
+}
+
+impl SwapVenue for RaydiumDex {
+    fn name(&self) -> &'static str {
+        "raydium"
+    }
+
+    fn program_id(&self) -> Pubkey {
+        self.amm_program_id
+    }
+
+    fn build_swap_ix(
+        &self,
+        token_in: &Pubkey,
+        token_out: &Pubkey,
+        amount_in: u64,
+        min_out: u64,
+    ) -> Result<Instruction> {
+        let (pool_id, _, input_is_base) = self.find_pool(token_in, token_out)?
+            .ok_or_else(|| anyhow!("No Raydium pool tracked for {} -> {}", token_in, token_out))?;
+
+        let keys = self.get_pool_keys(&pool_id)?;
+        build_raydium_swap_ix(&keys, &self.payer.pubkey(), amount_in, min_out, input_is_base)
+    }
+
+    fn quote(&self, input: &Pubkey, output: &Pubkey, amount_in: u64) -> Result<Option<u64>> {
+        Ok(self.find_pool(input, output)?
+            .map(|(_, pool, input_is_base)| {
+                Self::calculate_amount_out(&pool, amount_in, input_is_base)
+            }))
+    }
+
+    fn price_impact(&self, input: &Pubkey, output: &Pubkey, amount_in: u64) -> Result<Option<f64>> {
+        self.find_pool(input, output)?
+            .map(|(_, pool, input_is_base)| {
+                Self::calculate_price_impact(&pool, amount_in, input_is_base)
+            })
+            .transpose()
+    }
+
+    fn build_swap_transaction(
+        &self,
+        input: &Pubkey,
+        output: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+        payer: &Pubkey,
+    ) -> Result<Transaction> {
+        let swap_ix = self.build_swap_ix(input, output, amount_in, min_amount_out)?;
+        Ok(Transaction::new_with_payer(&[swap_ix], Some(payer)))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use {super::*, std::collections::HashSet};
+
+    pub(crate) fn pool(base_mint: Pubkey, base_amount: u64, base_decimals: u8, quote_mint: Pubkey, quote_amount: u64, quote_decimals: u8) -> PoolInfo {
+        PoolInfo {
+            liquidity: 1_000_000,
+            base_amount,
+            quote_amount,
+            fee_numerator: 25,
+            fee_denominator: 10_000,
+            base_decimals,
+            quote_decimals,
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_mint,
+            quote_mint,
+            lp_mint: Pubkey::new_unique(),
+            need_take_pnl_base: 0,
+            need_take_pnl_quote: 0,
+            pool_open_time: 0,
+        }
+    }
+
+    // A dex that already tracks `info`, so quotes need no RPC
+    pub(crate) fn dex_tracking(info: PoolInfo) -> RaydiumDex {
+        let dex = RaydiumDex::new(&TradingConfig::default(), Keypair::new()).unwrap();
+        dex.pools.write().insert(Pubkey::new_unique(), PoolState {
+            info,
+            last_update: Instant::now(),
+            price_history: Vec::new(),
+        });
+        dex
+    }
+
+    #[test]
+    fn prices_a_usdc_quoted_pool() {
+        let (token, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // 1,000 tokens (9 decimals) against 250 USDC (6 decimals)
+        let pool = pool(token, 1_000_000_000_000, 9, usdc, 250_000_000, 6);
+
+        assert!(pool.quote_is_pool_quote(&usdc).unwrap());
+        assert!((pool.ui_price_in(&usdc).unwrap() - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn prices_a_pool_listing_usdc_as_base() {
+        let (token, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = pool(usdc, 250_000_000, 6, token, 1_000_000_000_000, 9);
+
+        assert!(!pool.quote_is_pool_quote(&usdc).unwrap());
+        assert!((pool.ui_price_in(&usdc).unwrap() - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_a_quote_token_the_pool_does_not_hold() {
+        let pool = pool(Pubkey::new_unique(), 1, 9, native_mint::ID, 1, 9);
+        assert!(pool.quote_is_pool_quote(&Pubkey::new_unique()).is_err());
+        assert!(pool.ui_price_in(&Pubkey::new_unique()).is_err());
+    }
+
+    #[tokio::test]
+    async fn watches_many_pools_at_once() {
+        const POOLS: usize = 12;
+        let mut dex = RaydiumDex::new(&TradingConfig::default(), Keypair::new()).unwrap();
+        dex.min_liquidity = 0;
+        dex.min_confidence = 0.5;
+        dex.pool_cache_ttl = Duration::from_secs(60);
+        dex.update_interval = Duration::from_secs(60);
+
+        // Cached reserves priced at twice what each pool was last seen at
+        let pool_ids: Vec<Pubkey> = (0..POOLS).map(|_| Pubkey::new_unique()).collect();
+        for pool_id in &pool_ids {
+            let info = pool(Pubkey::new_unique(), 1_000_000_000, 9, native_mint::ID, 2_000_000_000, 9);
+            let seen = Price::from_ratio(1_000_000_000, 1_000_000_000).unwrap();
+            dex.pool_cache.lock().put(*pool_id, (info.clone(), Instant::now()));
+            dex.pools.write().insert(*pool_id, PoolState {
+                info,
+                last_update: Instant::now(),
+                price_history: vec![(Instant::now(), seen)],
+            });
+        }
+
+        let (signals_tx, mut signals) = mpsc::channel(POOLS);
+        let monitor = tokio::spawn(Arc::new(dex).monitor_pools(pool_ids.clone(), signals_tx));
+
+        let mut signalled = HashSet::new();
+        while signalled.len() < POOLS {
+            let (pool_id, signal) = tokio::time::timeout(Duration::from_secs(5), signals.recv())
+                .await
+                .expect("every pool signals on its first update")
+                .unwrap();
+            assert!(matches!(signal.direction, TradeDirection::Buy));
+            assert_eq!(signal.confidence, 1.0);
+            signalled.insert(pool_id);
+        }
+        assert_eq!(signalled, pool_ids.into_iter().collect());
+
+        drop(signals);
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn confidence_floor_gates_trades() {
+        let config = TradingConfig { min_confidence: 0.6, ..TradingConfig::default() };
+        let mut dex = RaydiumDex::new(&config, Keypair::new()).unwrap();
+        dex.min_liquidity = 0;
+
+        let pool_id = Pubkey::new_unique();
+        dex.pools.write().insert(pool_id, PoolState {
+            info: pool(Pubkey::new_unique(), 1_000_000_000, 9, native_mint::ID, 2_000_000_000, 9),
+            last_update: Instant::now(),
+            price_history: Vec::new(),
+        });
+        let signal = |confidence| TradeSignal {
+            direction: TradeDirection::Buy,
+            price_change: 0.1,
+            volume_change: 0.5,
+            confidence,
+            timestamp: Instant::now(),
+        };
+
+        assert!(!dex.validate_trade_conditions(&pool_id, &signal(0.59)).await.unwrap());
+        assert!(dex.validate_trade_conditions(&pool_id, &signal(0.6)).await.unwrap());
+        assert!(dex.validate_trade_conditions(&pool_id, &signal(0.9)).await.unwrap());
+    }
+
+    // AMM account data for a `base_mint`/`quote_mint` pool with `lp_amount` LP supply
+    fn amm_account(base_mint: &Pubkey, quote_mint: &Pubkey, lp_amount: u64) -> Vec<u8> {
+        let mut data = vec![0u8; AMM_INFO_SIZE];
+        data[TRADE_FEE_NUMERATOR_OFFSET..][..8].copy_from_slice(&25u64.to_le_bytes());
+        data[TRADE_FEE_DENOMINATOR_OFFSET..][..8].copy_from_slice(&10_000u64.to_le_bytes());
+        data[BASE_MINT_OFFSET..][..32].copy_from_slice(base_mint.as_ref());
+        data[QUOTE_MINT_OFFSET..][..32].copy_from_slice(quote_mint.as_ref());
+        data[LP_AMOUNT_OFFSET..][..8].copy_from_slice(&lp_amount.to_le_bytes());
+        data
+    }
+
+    // Dex whose RPC calls are answered, per method in order, by `calls`. The
+    // client's one-off node version check is answered too.
+    fn dex_with_recording(mut calls: Vec<(&str, serde_json::Value)>) -> (RaydiumDex, String) {
+        use {
+            crate::rpc_record::{RecordedCall, RpcMode},
+            solana_sdk::commitment_config::CommitmentConfig,
+            std::io::Write,
+        };
+
+        let mut dex = RaydiumDex::new(&TradingConfig::default(), Keypair::new()).unwrap();
+        let path = std::env::temp_dir().join(format!("raydium-{}.jsonl", Pubkey::new_unique()));
+        let path = path.to_str().unwrap().to_string();
+        let mut file = std::fs::File::create(&path).unwrap();
+        calls.insert(0, ("getVersion", serde_json::json!({ "solana-core": "1.18.26", "feature-set": 3_469_865_029_u32 })));
+        for (method, result) in calls {
+            let call = RecordedCall {
+                method: method.to_string(),
+                params: serde_json::Value::Null,
+                result: Some(result),
+                error: None,
+            };
+            writeln!(file, "{}", serde_json::to_string(&call).unwrap()).unwrap();
+        }
+        let client = RpcMode::Replay(path.clone()).client(String::new(), CommitmentConfig::confirmed()).unwrap();
+        dex.rpc_client = Arc::new(client);
+        (dex, path)
+    }
+
+    fn account_json(data: &[u8], owner: &Pubkey) -> serde_json::Value {
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+        serde_json::json!({
+            "lamports": 6_124_800,
+            "data": [BASE64.encode(data), "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        })
+    }
+
+    // Dex whose getProgramAccounts calls are answered, in order, by `responses`
+    fn dex_with_program_accounts(responses: &[Vec<(Pubkey, Vec<u8>)>]) -> (RaydiumDex, String) {
+        let amm_program_id = resolve_amm_program_id(&TradingConfig::default()).unwrap();
+        let mut calls = Vec::new();
+        for accounts in responses {
+            let result = accounts
+                .iter()
+                .map(|(pubkey, data)| serde_json::json!({
+                    "pubkey": pubkey.to_string(),
+                    "account": account_json(data, &amm_program_id),
+                }))
+                .collect();
+            calls.push(("getProgramAccounts", serde_json::Value::Array(result)));
+        }
+        dex_with_recording(calls)
+    }
+
+    #[test]
+    fn cached_pool_info_is_served_until_the_ttl() {
+        let info = pool(Pubkey::new_unique(), 1, 9, native_mint::ID, 1, 9);
+        let fetched_at = Instant::now();
+        let cached = (info.clone(), fetched_at);
+
+        assert!(fresh_pool_info(None, POOL_CACHE_TTL, fetched_at).is_none());
+        let hit = fresh_pool_info(Some(&cached), POOL_CACHE_TTL, fetched_at + POOL_CACHE_TTL / 2);
+        assert_eq!(hit.map(|hit| hit.base_mint), Some(info.base_mint));
+        assert!(fresh_pool_info(Some(&cached), POOL_CACHE_TTL, fetched_at + POOL_CACHE_TTL).is_none());
+    }
+
+    #[tokio::test]
+    async fn pool_reads_within_the_ttl_share_one_fetch() {
+        let pool_id = Pubkey::new_unique();
+        let amm = amm_account(&Pubkey::new_unique(), &native_mint::ID, 1_000);
+        let context = |value| serde_json::json!({ "context": { "slot": 1 }, "value": value });
+        let balance = |amount: u64| context(serde_json::json!({
+            "amount": amount.to_string(),
+            "decimals": 9,
+            "uiAmount": null,
+            "uiAmountString": "",
+        }));
+        // Two fetches' worth: the AMM account, then its base and quote vaults
+        let mut calls = Vec::new();
+        for quote in [2_000, 3_000] {
+            calls.push(("getAccountInfo", context(account_json(&amm, &Pubkey::new_unique()))));
+            calls.push(("getTokenAccountBalance", balance(1_000)));
+            calls.push(("getTokenAccountBalance", balance(quote)));
+        }
+        let (dex, path) = dex_with_recording(calls);
+
+        assert_eq!(dex.get_pool_info(&pool_id, false).await.unwrap().quote_amount, 2_000);
+        assert_eq!(dex.get_pool_info(&pool_id, false).await.unwrap().quote_amount, 2_000);
+        assert_eq!(dex.cache_stats(), (1, 1));
+
+        // Age the entry past the TTL; the next read fetches again
+        dex.pool_cache.lock().get_mut(&pool_id).unwrap().1 -= POOL_CACHE_TTL;
+        assert_eq!(dex.get_pool_info(&pool_id, false).await.unwrap().quote_amount, 3_000);
+        assert_eq!(dex.cache_stats(), (1, 2));
+
+        // Forced reads skip the fresh entry; the recording held exactly two fetches
+        assert!(dex.get_pool_info(&pool_id, true).await.is_err());
+        assert_eq!(dex.cache_stats(), (1, 3));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn finds_the_deepest_pool_for_a_mint_and_caches_it() {
+        let mint = Pubkey::new_unique();
+        let (shallow, deep, empty) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        // Base-side query, then quote-side
+        let (dex, path) = dex_with_program_accounts(&[
+            vec![
+                (shallow, amm_account(&mint, &native_mint::ID, 1_000)),
+                (empty, amm_account(&mint, &native_mint::ID, 0)),
+            ],
+            vec![(deep, amm_account(&native_mint::ID, &mint, 5_000_000))],
+        ]);
+
+        assert_eq!(dex.find_pool_for_mint(&mint).unwrap(), Some(deep));
+        // The recording is used up, so this can only come from the cache
+        assert_eq!(dex.find_pool_for_mint(&mint).unwrap(), Some(deep));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn drained_pool_is_followed_to_where_its_liquidity_moved() {
+        let mint = Pubkey::new_unique();
+        let (old_pool, new_pool) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (dex, path) = dex_with_program_accounts(&[
+            vec![(old_pool, amm_account(&mint, &native_mint::ID, 10))],
+            vec![(new_pool, amm_account(&native_mint::ID, &mint, 8_000_000))],
+        ]);
+        dex.mint_pools.lock().insert(mint, old_pool);
+
+        let mut drained = pool(mint, 1_000_000_000, 9, native_mint::ID, 2_000_000_000, 9);
+        assert!(!is_drained(&drained, 2_000_000_000));
+        drained.quote_amount = 19_000_000;
+        assert!(is_drained(&drained, 2_000_000_000));
+        drained.quote_amount = 0;
+        assert!(is_drained(&drained, 0));
+
+        assert_eq!(dex.migrated_pool(&old_pool, &drained).unwrap(), Some(new_pool));
+        assert_eq!(dex.find_pool_for_mint(&mint).unwrap(), Some(new_pool));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // AMM and market accounts with every key PoolKeys reads set, and the
+    // market's vault signer nonce the first one that derives an address
+    fn pool_key_accounts(keys: &PoolKeys) -> (Vec<u8>, Vec<u8>) {
+        let mut amm = amm_account(&keys.base_mint, &keys.quote_mint, 1);
+        amm[NONCE_OFFSET..][..8].copy_from_slice(&254u64.to_le_bytes());
+        for (offset, key) in [
+            (BASE_VAULT_OFFSET, keys.base_vault),
+            (QUOTE_VAULT_OFFSET, keys.quote_vault),
+            (OPEN_ORDERS_OFFSET, keys.open_orders),
+            (MARKET_OFFSET, keys.market_id),
+            (MARKET_PROGRAM_OFFSET, keys.market_program_id),
+            (TARGET_ORDERS_OFFSET, keys.target_orders),
+        ] {
+            amm[offset..][..32].copy_from_slice(key.as_ref());
+        }
+
+        let mut market = vec![0u8; MARKET_STATE_SIZE];
+        let nonce = (0u64..)
+            .find(|nonce| {
+                Pubkey::create_program_address(&[keys.market_id.as_ref(), &nonce.to_le_bytes()], &keys.market_program_id).is_ok()
+            })
+            .unwrap();
+        market[VAULT_SIGNER_NONCE_OFFSET..][..8].copy_from_slice(&nonce.to_le_bytes());
+        for (offset, key) in [
+            (MARKET_COIN_VAULT_OFFSET, keys.market_base_vault),
+            (MARKET_PC_VAULT_OFFSET, keys.market_quote_vault),
+            (MARKET_EVENT_QUEUE_OFFSET, keys.market_event_queue),
+            (MARKET_BIDS_OFFSET, keys.market_bids),
+            (MARKET_ASKS_OFFSET, keys.market_asks),
+        ] {
+            market[offset..][..32].copy_from_slice(key.as_ref());
+        }
+        (amm, market)
+    }
+
+    #[test]
+    fn swap_accounts_follow_the_amm_v4_layout() {
+        let amm_program_id = crate::dex::RAYDIUM_V4_PROGRAM_ID.parse::<Pubkey>().unwrap();
+        let market_id = Pubkey::new_unique();
+        let market_program_id = Pubkey::new_unique();
+        let mut expected = PoolKeys {
+            amm_program_id,
+            amm_id: Pubkey::new_unique(),
+            authority: Pubkey::default(),
+            open_orders: Pubkey::new_unique(),
+            target_orders: Pubkey::new_unique(),
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_mint: Pubkey::new_unique(),
+            quote_mint: native_mint::ID,
+            market_program_id,
+            market_id,
+            market_bids: Pubkey::new_unique(),
+            market_asks: Pubkey::new_unique(),
+            market_event_queue: Pubkey::new_unique(),
+            market_base_vault: Pubkey::new_unique(),
+            market_quote_vault: Pubkey::new_unique(),
+            market_authority: Pubkey::default(),
+        };
+        let (amm, market) = pool_key_accounts(&expected);
+        let keys = PoolKeys::from_accounts(&amm_program_id, &expected.amm_id, &amm, &market).unwrap();
+
+        // Every mainnet v4 pool shares this authority, derived from nonce 254
+        assert_eq!(keys.authority.to_string(), "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1");
+        expected.authority = keys.authority;
+        expected.market_authority = keys.market_authority;
+        assert_eq!(keys, expected);
+        assert!(PoolKeys::from_accounts(&amm_program_id, &keys.amm_id, &amm[1..], &market).is_err());
+
+        let user = Pubkey::new_unique();
+        let ix = build_raydium_swap_ix(&keys, &user, 1_000, 900, false).unwrap();
+        assert_eq!(ix.program_id, amm_program_id);
+        let accounts: Vec<_> = ix.accounts.iter().map(|meta| (meta.pubkey, meta.is_signer, meta.is_writable)).collect();
+        assert_eq!(
+            accounts,
+            vec![
+                (spl_token::id(), false, false),
+                (keys.amm_id, false, true),
+                (keys.authority, false, false),
+                (keys.open_orders, false, true),
+                (keys.target_orders, false, true),
+                (keys.base_vault, false, true),
+                (keys.quote_vault, false, true),
+                (market_program_id, false, false),
+                (market_id, false, true),
+                (keys.market_bids, false, true),
+                (keys.market_asks, false, true),
+                (keys.market_event_queue, false, true),
+                (keys.market_base_vault, false, true),
+                (keys.market_quote_vault, false, true),
+                (keys.market_authority, false, false),
+                // Spending quote, so the quote account is the source
+                (get_associated_token_address(&user, &native_mint::ID), false, true),
+                (get_associated_token_address(&user, &keys.base_mint), false, true),
+                (user, true, false),
+            ]
+        );
+        assert_eq!(ix.data[0], SWAP_BASE_IN_TAG);
+        assert_eq!(ix.data[1..9], 1_000u64.to_le_bytes());
+        assert_eq!(ix.data[9..17], 900u64.to_le_bytes());
+        assert!(build_raydium_swap_ix(&keys, &user, 0, 900, false).is_err());
+    }
+
+    // AmmInfo of the mainnet SOL/USDC pool 58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2: its real
+    // keys, decimals and fees, with the counters set to a snapshot of round values
+    const SOL_USDC_AMM: &[u8] = include_bytes!("fixtures/raydium_sol_usdc_amm.bin");
+
+    #[test]
+    fn decodes_the_sol_usdc_pool_account() {
+        let pool = PoolInfo::from_raydium_account(SOL_USDC_AMM).unwrap();
+
+        assert_eq!(pool.base_mint, native_mint::ID);
+        assert_eq!(pool.quote_mint.to_string(), "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+        assert_eq!(pool.base_vault.to_string(), "DQyrAcCrDXQ7NeoqGgDCZwBvWDcYmFCjSb9JtteuvPpz");
+        assert_eq!(pool.quote_vault.to_string(), "HLmqeL62xR1QoZ1HKKbXRrdN1p3phKpxRMb2VVopvBBz");
+        assert_eq!(pool.lp_mint.to_string(), "8HoQnePLqPj4M7PUDzfw8e3Ymdwgc7NLGnaTUapubyvu");
+        assert_eq!((pool.base_decimals, pool.quote_decimals), (9, 6));
+        assert_eq!((pool.fee_numerator, pool.fee_denominator), (25, 10_000));
+        assert_eq!(pool.liquidity, 559_145_312_457);
+        assert_eq!((pool.need_take_pnl_base, pool.need_take_pnl_quote), (41_288_413, 2_716_030));
+        assert_eq!(pool.pool_open_time, 0);
+        // Reserves come from the vaults, not this account
+        assert_eq!((pool.base_amount, pool.quote_amount), (0, 0));
+
+        let error = PoolInfo::from_raydium_account(&SOL_USDC_AMM[..AMM_INFO_SIZE - 1]).unwrap_err();
+        assert!(matches!(error.downcast_ref::<BotError>(), Some(BotError::ParseError(_))));
+    }
+
+    fn pool_init_fixture() -> EncodedConfirmedTransactionWithStatusMeta {
+        serde_json::from_str(include_str!("fixtures/raydium_pool_init.json")).unwrap()
+    }
+
+    #[test]
+    fn decodes_a_pool_init_transaction() {
+        let amm_program_id = crate::dex::RAYDIUM_V4_PROGRAM_ID.parse::<Pubkey>().unwrap();
+        let tx = pool_init_fixture();
+        let logs: Option<Vec<String>> = tx.transaction.meta.as_ref().unwrap().log_messages.clone().into();
+        assert!(logs.unwrap().iter().any(|log| log.contains(INITIALIZE2_LOG)));
+
+        let events = decode_new_pools(&amm_program_id, &tx);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        let market_id = "BEuiRxodHbonvU9W6rkmrpCVbWV5UxZnKZukFGFSeeTy".parse::<Pubkey>().unwrap();
+        assert_eq!(event.market_id, market_id);
+        // Raydium derives the pool's accounts from its market
+        let derived = |seed: &[u8]| {
+            Pubkey::find_program_address(&[amm_program_id.as_ref(), market_id.as_ref(), seed], &amm_program_id).0
+        };
+        assert_eq!(event.pool_id, derived(b"amm_associated_seed"));
+        assert_eq!(event.lp_mint, derived(b"lp_mint_associated_seed"));
+        assert_eq!(event.base_vault, derived(b"coin_vault_associated_seed"));
+        assert_eq!(event.quote_vault, derived(b"pc_vault_associated_seed"));
+        assert_eq!(event.base_mint.to_string(), "9xkU49fBixt9jfgvsHquwoA6HBLriYgVsaqa6RaPKuQU");
+        assert_eq!(event.quote_mint, native_mint::ID);
+        assert_eq!(event.base_reserve, 206_900_000_000_000);
+        assert_eq!(event.quote_reserve, 79_005_359_123);
+        assert_eq!(event.open_time, 1_717_000_000);
+
+        // Not this program's pools, and nothing from a failed transaction
+        assert!(decode_new_pools(&Pubkey::new_unique(), &tx).is_empty());
+        let mut failed = tx;
+        failed.transaction.meta.as_mut().unwrap().err = Some(solana_sdk::transaction::TransactionError::AccountInUse);
+        assert!(decode_new_pools(&amm_program_id, &failed).is_empty());
+    }
+}
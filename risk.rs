@@ -0,0 +1,253 @@
+use {
+    crate::{
+        config::TradingConfig,
+        monitoring::Signal,
+        trading::TradingEngine,
+    },
+    anyhow::Result,
+    parking_lot::Mutex,
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        fmt,
+        sync::Arc,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    tracing::warn,
+};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Outcome of a risk check; everything but Approved says which limit was hit
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskDecision {
+    Approved,
+    PositionTooLarge { size: f64, max: f64 },
+    ExposureLimit { exposure: f64, max: f64 },
+    ConcentrationLimit { token: Pubkey, value: f64, max: f64 },
+    DailyLossLimit { loss: f64, max: f64 },
+}
+
+impl RiskDecision {
+    pub fn is_approved(&self) -> bool {
+        matches!(self, RiskDecision::Approved)
+    }
+}
+
+impl fmt::Display for RiskDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskDecision::Approved => write!(f, "approved"),
+            RiskDecision::PositionTooLarge { size, max } => {
+                write!(f, "position {:.4} SOL exceeds max {:.4} SOL", size, max)
+            }
+            RiskDecision::ExposureLimit { exposure, max } => {
+                write!(f, "total exposure {:.4} SOL would exceed {:.4} SOL", exposure, max)
+            }
+            RiskDecision::ConcentrationLimit { token, value, max } => {
+                write!(f, "{} would hold {:.4} SOL, over its {:.4} SOL limit", token, value, max)
+            }
+            RiskDecision::DailyLossLimit { loss, max } => {
+                write!(f, "daily loss {:.4} SOL reached cap {:.4} SOL", loss, max)
+            }
+        }
+    }
+}
+
+// All amounts in SOL
+pub struct RiskManager {
+    trade_size: f64,
+    max_position_size: f64,
+    max_total_exposure: f64,
+    max_token_concentration: f64, // Fraction of max_total_exposure one token may use
+    daily_loss_cap: f64,
+    daily_loss: Mutex<(u64, f64)>, // (UTC day, realized loss that day)
+    engine: Option<Arc<TradingEngine>>,
+}
+
+impl RiskManager {
+    pub fn new(config: &TradingConfig) -> Self {
+        Self {
+            trade_size: config.fixed_amount,
+            max_position_size: config.max_position_size,
+            max_total_exposure: config.max_total_exposure,
+            max_token_concentration: config.max_token_concentration,
+            daily_loss_cap: config.daily_loss_cap,
+            daily_loss: Mutex::new((current_day(), 0.0)),
+            engine: None,
+        }
+    }
+
+    // Without an engine, checks run as if there were no open positions
+    pub fn with_engine(mut self, engine: Arc<TradingEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    pub async fn validate_trade(&self, signal: &Signal) -> Result<bool> {
+        let decision = self.evaluate(signal).await?;
+        if !decision.is_approved() {
+            warn!(%decision, "Trade rejected by risk manager");
+        }
+        Ok(decision.is_approved())
+    }
+
+    pub async fn evaluate(&self, signal: &Signal) -> Result<RiskDecision> {
+        // Sells only reduce exposure
+        let token = match signal {
+            Signal::BuySignal { token, .. } => *token,
+            Signal::SellSignal { .. } => return Ok(RiskDecision::Approved),
+        };
+
+        let positions = match &self.engine {
+            Some(engine) => engine.get_active_positions().await?,
+            None => Vec::new(),
+        };
+
+        let exposure: f64 = positions.iter().map(|p| p.value()).sum();
+        let token_value: f64 = positions
+            .iter()
+            .filter(|p| p.token == token)
+            .map(|p| p.value())
+            .sum();
+        let unrealized_loss: f64 = positions.iter().map(|p| (-p.pnl).max(0.0)).sum();
+
+        Ok(self.check_limits(token, exposure, token_value, unrealized_loss))
+    }
+
+    fn check_limits(&self, token: Pubkey, exposure: f64, token_value: f64, unrealized_loss: f64) -> RiskDecision {
+        let loss = self.realized_loss_today() + unrealized_loss;
+        if loss >= self.daily_loss_cap {
+            return RiskDecision::DailyLossLimit { loss, max: self.daily_loss_cap };
+        }
+
+        if self.trade_size > self.max_position_size {
+            return RiskDecision::PositionTooLarge {
+                size: self.trade_size,
+                max: self.max_position_size,
+            };
+        }
+
+        let new_exposure = exposure + self.trade_size;
+        if new_exposure > self.max_total_exposure {
+            return RiskDecision::ExposureLimit {
+                exposure: new_exposure,
+                max: self.max_total_exposure,
+            };
+        }
+
+        let new_token_value = token_value + self.trade_size;
+        let token_limit = self.max_total_exposure * self.max_token_concentration;
+        if new_token_value > token_limit {
+            return RiskDecision::ConcentrationLimit {
+                token,
+                value: new_token_value,
+                max: token_limit,
+            };
+        }
+
+        RiskDecision::Approved
+    }
+
+    // Called when a position is closed; gains don't offset the cap
+    pub fn record_realized_pnl(&self, pnl: f64) {
+        let mut daily_loss = self.daily_loss.lock();
+        let today = current_day();
+        if daily_loss.0 != today {
+            *daily_loss = (today, 0.0);
+        }
+        if pnl < 0.0 {
+            daily_loss.1 -= pnl;
+        }
+    }
+
+    fn realized_loss_today(&self) -> f64 {
+        let daily_loss = self.daily_loss.lock();
+        if daily_loss.0 == current_day() {
+            daily_loss.1
+        } else {
+            0.0
+        }
+    }
+}
+
+fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECS_PER_DAY)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0.1 SOL trades, 1 SOL positions, 2 SOL total with 1 SOL per token, 0.5 SOL daily loss
+    fn risk_manager() -> RiskManager {
+        RiskManager::new(&TradingConfig {
+            fixed_amount: 0.1,
+            max_position_size: 1.0,
+            max_total_exposure: 2.0,
+            max_token_concentration: 0.5,
+            daily_loss_cap: 0.5,
+            ..TradingConfig::default()
+        })
+    }
+
+    fn buy(token: Pubkey) -> Signal {
+        Signal::BuySignal { token, confidence: 1.0 }
+    }
+
+    #[test]
+    fn trades_within_every_limit_are_approved() {
+        let token = Pubkey::new_unique();
+        assert_eq!(risk_manager().check_limits(token, 1.0, 0.5, 0.2), RiskDecision::Approved);
+    }
+
+    #[test]
+    fn oversized_trades_are_rejected() {
+        let mut risk = risk_manager();
+        risk.trade_size = 1.5;
+        assert_eq!(
+            risk.check_limits(Pubkey::new_unique(), 0.0, 0.0, 0.0),
+            RiskDecision::PositionTooLarge { size: 1.5, max: 1.0 },
+        );
+    }
+
+    #[test]
+    fn trades_past_total_exposure_are_rejected() {
+        let decision = risk_manager().check_limits(Pubkey::new_unique(), 1.95, 0.0, 0.0);
+        assert!(matches!(decision, RiskDecision::ExposureLimit { max, .. } if max == 2.0), "{:?}", decision);
+    }
+
+    #[test]
+    fn trades_past_token_concentration_are_rejected() {
+        let token = Pubkey::new_unique();
+        let decision = risk_manager().check_limits(token, 0.95, 0.95, 0.0);
+        assert!(
+            matches!(decision, RiskDecision::ConcentrationLimit { token: t, max, .. } if t == token && max == 1.0),
+            "{:?}",
+            decision
+        );
+    }
+
+    #[test]
+    fn realized_and_unrealized_losses_count_toward_the_daily_cap() {
+        let risk = risk_manager();
+        risk.record_realized_pnl(-0.3);
+        risk.record_realized_pnl(0.4); // Gains don't offset it
+        assert!(risk.check_limits(Pubkey::new_unique(), 0.0, 0.0, 0.1).is_approved());
+
+        let decision = risk.check_limits(Pubkey::new_unique(), 0.0, 0.0, 0.2);
+        assert!(matches!(decision, RiskDecision::DailyLossLimit { max, .. } if max == 0.5), "{:?}", decision);
+    }
+
+    #[tokio::test]
+    async fn sells_pass_even_past_the_limits() {
+        let risk = risk_manager();
+        risk.record_realized_pnl(-1.0);
+        let token = Pubkey::new_unique();
+
+        assert!(!risk.validate_trade(&buy(token)).await.unwrap());
+        assert!(risk.validate_trade(&Signal::SellSignal { token, confidence: 1.0 }).await.unwrap());
+    }
+}
@@ -0,0 +1,297 @@
+use {
+    anyhow::{Result, anyhow},
+    async_trait::async_trait,
+    parking_lot::Mutex,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    solana_client::{
+        client_error::{ClientErrorKind, Result as ClientResult},
+        nonblocking::rpc_client::RpcClient as NonblockingRpcClient,
+        rpc_client::{RpcClient, RpcClientConfig},
+        rpc_request::RpcRequest,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    },
+    solana_rpc_client::http_sender::HttpSender,
+    solana_sdk::commitment_config::CommitmentConfig,
+    std::{
+        collections::{HashMap, VecDeque},
+        fs::{self, File, OpenOptions},
+        io::Write,
+    },
+    tracing::warn,
+    crate::config::TradingConfig,
+};
+
+// One line of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub method: String,
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Forwards to a live endpoint and appends every request/response pair to a JSONL file
+pub struct RecordingRpc {
+    inner: HttpSender,
+    log: Mutex<File>,
+}
+
+impl RecordingRpc {
+    pub fn new(url: String, path: &str) -> Result<Self> {
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow!("Failed to open RPC recording {}: {}", path, e))?;
+        Ok(Self {
+            inner: HttpSender::new(url),
+            log: Mutex::new(log),
+        })
+    }
+
+    // A recording that can't be written must never fail the live call
+    fn record(&self, call: &RecordedCall) {
+        let mut line = match serde_json::to_string(call) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!(method = %call.method, error = %e, "Failed to serialize RPC call");
+                return;
+            }
+        };
+        line.push('\n');
+        // Single write so clients sharing the file don't interleave lines
+        if let Err(e) = self.log.lock().write_all(line.as_bytes()) {
+            warn!(method = %call.method, error = %e, "Failed to record RPC call");
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for RecordingRpc {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let response = self.inner.send(request, params.clone()).await;
+        self.record(&RecordedCall {
+            method: request.to_string(),
+            params,
+            result: response.as_ref().ok().cloned(),
+            error: response.as_ref().err().map(|e| e.to_string()),
+        });
+        response
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+// Serves a recording back in order. A call is answered by the next recorded
+// response with the same method and params; failing that, by the next one with
+// the same method, since blockhashes and signatures differ between runs.
+pub struct ReplayRpc {
+    path: String,
+    calls: Mutex<HashMap<String, VecDeque<RecordedCall>>>,
+}
+
+impl ReplayRpc {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read RPC recording {}: {}", path, e))?;
+
+        let mut calls: HashMap<String, VecDeque<RecordedCall>> = HashMap::new();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let call: RecordedCall = serde_json::from_str(line)
+                .map_err(|e| anyhow!("{}:{}: invalid recorded call: {}", path, index + 1, e))?;
+            calls.entry(call.method.clone()).or_default().push_back(call);
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            calls: Mutex::new(calls),
+        })
+    }
+
+    fn next_call(&self, method: &str, params: &Value) -> Option<RecordedCall> {
+        let mut calls = self.calls.lock();
+        let queue = calls.get_mut(method)?;
+        let index = queue.iter().position(|call| call.params == *params).unwrap_or(0);
+        queue.remove(index)
+    }
+}
+
+#[async_trait]
+impl RpcSender for ReplayRpc {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let method = request.to_string();
+        let call = self.next_call(&method, &params).ok_or_else(|| {
+            ClientErrorKind::Custom(format!("No recorded response left for {} {}", method, params))
+        })?;
+        match (call.result, call.error) {
+            (Some(result), _) => Ok(result),
+            (None, error) => Err(ClientErrorKind::Custom(
+                error.unwrap_or_else(|| format!("Recorded {} call has no result", method)),
+            ).into()),
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        format!("replay:{}", self.path)
+    }
+}
+
+// How RPC clients are built: straight to the endpoint, recording, or replaying
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum RpcMode {
+    #[default]
+    Live,
+    Record(String), // JSONL file appended to
+    Replay(String), // JSONL file served from; the endpoint URL is ignored
+}
+
+impl RpcMode {
+    // Replay wins if both paths are set, so a replay run can never hit the network
+    pub fn from_config(config: &TradingConfig) -> Self {
+        match (&config.rpc_replay_path, &config.rpc_record_path) {
+            (Some(path), _) => RpcMode::Replay(path.clone()),
+            (None, Some(path)) => RpcMode::Record(path.clone()),
+            (None, None) => RpcMode::Live,
+        }
+    }
+
+    pub fn client(&self, url: String, commitment: CommitmentConfig) -> Result<RpcClient> {
+        let client_config = RpcClientConfig::with_commitment(commitment);
+        Ok(match self {
+            RpcMode::Live => RpcClient::new_with_commitment(url, commitment),
+            RpcMode::Record(path) => RpcClient::new_sender(RecordingRpc::new(url, path)?, client_config),
+            RpcMode::Replay(path) => RpcClient::new_sender(ReplayRpc::from_file(path)?, client_config),
+        })
+    }
+
+    // The same for async callers, which must not block their runtime thread
+    pub fn nonblocking_client(&self, url: String, commitment: CommitmentConfig) -> Result<NonblockingRpcClient> {
+        let client_config = RpcClientConfig::with_commitment(commitment);
+        Ok(match self {
+            RpcMode::Live => NonblockingRpcClient::new_with_commitment(url, commitment),
+            RpcMode::Record(path) => NonblockingRpcClient::new_sender(RecordingRpc::new(url, path)?, client_config),
+            RpcMode::Replay(path) => NonblockingRpcClient::new_sender(ReplayRpc::from_file(path)?, client_config),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::monitoring::wallet::{decode_wallet_trade, TradeType},
+        serde_json::json,
+        solana_sdk::{pubkey::Pubkey, signature::Signature},
+        solana_transaction_status::UiTransactionEncoding,
+        std::{
+            io::{BufRead, BufReader, Read},
+            net::TcpListener,
+            str::FromStr,
+            thread,
+        },
+    };
+
+    const WALLET: &str = "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U";
+    const SIGNATURE: &str = "31Mfuu9kt7yXe9L83yjcw78Ws856VNUiyHR53TtS8hbDeSqv7koU4J7NwycrstToyg4EeHeTVWTzShVPnb24MPuM";
+
+    // JSON-RPC over HTTP on a local port, one request per connection, answering
+    // each method from `results`. Runs for the rest of the test process.
+    fn serve_rpc(results: HashMap<&'static str, Value>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let request: Value = serde_json::from_slice(&body).unwrap();
+
+                let response = match results.get(request["method"].as_str().unwrap_or_default()) {
+                    Some(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                    None => json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -32601, "message": "Method not found" },
+                    }),
+                };
+                let response = response.to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    // What the copy trader takes from the wallet's transaction; the
+    // timestamp is left out since it's relative to when it was decoded
+    fn trade_decision(client: &RpcClient) -> (String, TradeType, Pubkey, Pubkey, u64, u64) {
+        let signature = Signature::from_str(SIGNATURE).unwrap();
+        let tx = client.get_transaction(&signature, UiTransactionEncoding::Base64).unwrap();
+        let amm = Pubkey::from_str(crate::dex::RAYDIUM_V4_PROGRAM_ID).unwrap();
+        let trade = decode_wallet_trade(&amm, &Pubkey::from_str(WALLET).unwrap(), &tx).unwrap();
+        (trade.signature, trade.trade_type, trade.input_token, trade.output_token, trade.amount_in, trade.amount_out)
+    }
+
+    #[test]
+    fn replaying_a_recorded_session_reproduces_its_trade_decisions() {
+        let captured: Value = serde_json::from_str(include_str!("fixtures/wallet_routed_buy.json")).unwrap();
+        let url = serve_rpc(HashMap::from([
+            ("getTransaction", captured),
+            ("getVersion", json!({ "solana-core": "1.18.26", "feature-set": 3_469_865_029_u32 })),
+        ]));
+        let path = std::env::temp_dir().join(format!("rpc-{}.jsonl", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+
+        let recording = RpcMode::Record(path.to_string()).client(url, CommitmentConfig::confirmed()).unwrap();
+        let live = trade_decision(&recording);
+        assert_eq!(live.1, TradeType::SwapExactSOLForTokens);
+        assert_eq!(live.4, 1_000_000_000);
+
+        // The endpoint is never contacted on replay
+        let replay = RpcMode::Replay(path.to_string())
+            .client("http://127.0.0.1:9".to_string(), CommitmentConfig::confirmed())
+            .unwrap();
+        let replayed = trade_decision(&replay);
+        let signature = Signature::from_str(SIGNATURE).unwrap();
+        let exhausted = replay.get_transaction(&signature, UiTransactionEncoding::Base64);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(replayed, live);
+        assert!(exhausted.is_err(), "each recorded response is served once");
+    }
+}
@@ -0,0 +1,187 @@
+use {
+    crate::{
+        config::{StrategyKind, TradingConfig},
+        monitoring::{volume::{calculate_confidence, ConfidenceWeights, TokenMetrics}, Signal},
+    },
+    solana_sdk::pubkey::Pubkey,
+};
+
+// Momentum needs a few samples before a move means anything
+const MIN_MOMENTUM_SAMPLES: usize = 3;
+
+// Turns a token's latest metrics into a trade signal, if any
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn evaluate(&self, token: &Pubkey, metrics: &TokenMetrics) -> Option<Signal>;
+}
+
+// The strategy `config.strategy` names
+pub fn strategy_from_config(config: &TradingConfig) -> Box<dyn Strategy> {
+    match config.strategy {
+        StrategyKind::Volume => Box::new(VolumeStrategy::new(config)),
+        StrategyKind::Momentum => Box::new(MomentumStrategy::new(config)),
+    }
+}
+
+// Same rules as VolumeMonitor: volume above its EMA together with a price move
+pub struct VolumeStrategy {
+    volume_threshold: f64,
+    price_threshold: f64,
+    confidence_weights: ConfidenceWeights,
+}
+
+impl VolumeStrategy {
+    pub fn new(config: &TradingConfig) -> Self {
+        Self {
+            volume_threshold: config.volume_spike_threshold,
+            price_threshold: config.price_move_threshold,
+            confidence_weights: ConfidenceWeights::from_config(config),
+        }
+    }
+}
+
+impl Strategy for VolumeStrategy {
+    fn name(&self) -> &'static str {
+        "volume"
+    }
+
+    fn evaluate(&self, token: &Pubkey, metrics: &TokenMetrics) -> Option<Signal> {
+        let spike_ratio = metrics.volume_spike_ratio()?;
+        let (_, price_change) = metrics.latest_changes()?;
+        if spike_ratio <= self.volume_threshold {
+            return None;
+        }
+
+        let confidence = calculate_confidence(self.confidence_weights, spike_ratio - 1.0, price_change.abs());
+        if price_change > self.price_threshold {
+            Some(Signal::BuySignal { token: *token, confidence })
+        } else if price_change < -self.price_threshold {
+            Some(Signal::SellSignal { token: *token, confidence })
+        } else {
+            None
+        }
+    }
+}
+
+// Follows the price trend across the whole retained history
+pub struct MomentumStrategy {
+    threshold: f64,
+}
+
+impl MomentumStrategy {
+    pub fn new(config: &TradingConfig) -> Self {
+        Self {
+            threshold: config.momentum_threshold,
+        }
+    }
+}
+
+impl Strategy for MomentumStrategy {
+    fn name(&self) -> &'static str {
+        "momentum"
+    }
+
+    fn evaluate(&self, token: &Pubkey, metrics: &TokenMetrics) -> Option<Signal> {
+        let history = metrics.price_history();
+        if history.len() < MIN_MOMENTUM_SAMPLES {
+            return None;
+        }
+
+        let first = history.first()?.1;
+        let last = history.last()?.1;
+        if first <= 0.0 {
+            return None;
+        }
+
+        let change = (last - first) / first;
+        let confidence = (change.abs() / self.threshold / 2.0).min(1.0);
+        if change > self.threshold {
+            Some(Signal::BuySignal { token: *token, confidence })
+        } else if change < -self.threshold {
+            Some(Signal::SellSignal { token: *token, confidence })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One sample a minute at each (price, volume)
+    fn metrics(samples: &[(f64, f64)]) -> TokenMetrics {
+        let mut metrics = TokenMetrics::new();
+        for (i, &(price, volume)) in samples.iter().enumerate() {
+            metrics.update_metrics_at(i as i64 * 60, price, volume);
+        }
+        metrics
+    }
+
+    fn evaluate(strategy: &dyn Strategy, samples: &[(f64, f64)]) -> Option<Signal> {
+        strategy.evaluate(&Pubkey::new_unique(), &metrics(samples))
+    }
+
+    #[test]
+    fn volume_strategy_follows_the_price_on_a_spike() {
+        // Spike threshold 2x, price threshold 5%
+        let strategy = VolumeStrategy::new(&TradingConfig::default());
+
+        let rising = evaluate(&strategy, &[(1.0, 100.0), (1.1, 300.0)]);
+        assert!(matches!(rising, Some(Signal::BuySignal { .. })), "{:?}", rising);
+
+        let falling = evaluate(&strategy, &[(1.0, 100.0), (0.9, 300.0)]);
+        assert!(matches!(falling, Some(Signal::SellSignal { .. })), "{:?}", falling);
+
+        let flat = evaluate(&strategy, &[(1.0, 100.0), (1.0, 300.0)]);
+        assert!(flat.is_none(), "{:?}", flat);
+    }
+
+    #[test]
+    fn volume_strategy_needs_a_spike_and_a_baseline() {
+        let strategy = VolumeStrategy::new(&TradingConfig::default());
+
+        // Price moves without the volume behind it
+        assert!(evaluate(&strategy, &[(1.0, 100.0), (1.1, 150.0)]).is_none());
+        // A lone sample has nothing to spike against
+        assert!(evaluate(&strategy, &[(1.1, 300.0)]).is_none());
+    }
+
+    #[test]
+    fn momentum_strategy_follows_the_trend_across_the_history() {
+        // 10% threshold
+        let strategy = MomentumStrategy::new(&TradingConfig::default());
+
+        let rising = evaluate(&strategy, &[(1.0, 100.0), (1.05, 100.0), (1.25, 100.0)]);
+        assert!(matches!(rising, Some(Signal::BuySignal { confidence, .. }) if confidence == 1.0), "{:?}", rising);
+
+        let falling = evaluate(&strategy, &[(1.0, 100.0), (0.95, 100.0), (0.85, 100.0)]);
+        assert!(matches!(falling, Some(Signal::SellSignal { .. })), "{:?}", falling);
+
+        // Moves inside the window that end where they started
+        let flat = evaluate(&strategy, &[(1.0, 100.0), (1.3, 100.0), (1.02, 100.0)]);
+        assert!(flat.is_none(), "{:?}", flat);
+    }
+
+    #[test]
+    fn momentum_strategy_waits_for_enough_samples() {
+        let strategy = MomentumStrategy::new(&TradingConfig::default());
+        let mut samples = vec![(1.0, 100.0), (2.0, 100.0)];
+        assert_eq!(samples.len(), MIN_MOMENTUM_SAMPLES - 1);
+        assert!(evaluate(&strategy, &samples).is_none());
+
+        samples.push((2.0, 100.0));
+        assert!(matches!(evaluate(&strategy, &samples), Some(Signal::BuySignal { .. })));
+    }
+
+    #[test]
+    fn config_picks_the_strategy() {
+        assert_eq!(strategy_from_config(&TradingConfig::default()).name(), "volume");
+
+        let config = TradingConfig { strategy: StrategyKind::Momentum, ..TradingConfig::default() };
+        assert_eq!(strategy_from_config(&config).name(), "momentum");
+        assert_eq!("Momentum".parse::<StrategyKind>().unwrap(), StrategyKind::Momentum);
+        assert!("meanreversion".parse::<StrategyKind>().is_err());
+    }
+}
@@ -0,0 +1,442 @@
+use {
+    anyhow::Result,
+    futures::StreamExt,
+    parking_lot::RwLock,
+    solana_client::{
+        nonblocking::pubsub_client::PubsubClient,
+        rpc_client::RpcClient,
+        rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature},
+    solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding},
+    std::{
+        collections::{HashSet, VecDeque},
+        fmt,
+        future::Future,
+        str::FromStr,
+        sync::Arc,
+        time::Duration,
+    },
+    tokio::{sync::mpsc, time::sleep},
+    tokio_util::sync::CancellationToken,
+    tracing::{info, warn},
+};
+
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Reconnecting { attempt: u32 },
+    Stopped,
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Reconnecting { attempt } => write!(f, "reconnecting (attempt {})", attempt),
+            ConnectionState::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+// Exponential backoff after `attempt` consecutive failed connects
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+// Signatures already delivered, so a reconnect replaying recent logs doesn't
+// deliver them twice. Forgets the oldest past `capacity`.
+pub struct SeenSignatures {
+    capacity: usize,
+    order: VecDeque<Signature>,
+    seen: HashSet<Signature>,
+}
+
+impl SeenSignatures {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    // True the first time a signature is seen
+    pub fn insert(&mut self, signature: Signature) -> bool {
+        if !self.seen.insert(signature) {
+            return false;
+        }
+        self.order.push_back(signature);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+// Keeps a logs subscription to `program` alive until `shutdown` is cancelled or
+// the receiver is dropped, reconnecting with backoff whenever the socket drops.
+// With `log_contains`, only transactions logging that text are forwarded.
+// Each session starts fresh, so duplicates across reconnects are the caller's to filter.
+pub async fn supervise_program_logs(
+    ws_url: String,
+    program: Pubkey,
+    commitment: CommitmentConfig,
+    log_contains: Option<&'static str>,
+    sender: mpsc::Sender<Signature>,
+    state: Arc<RwLock<ConnectionState>>,
+    shutdown: CancellationToken,
+) {
+    supervise_sessions(program, &sender, &state, &shutdown, || {
+        program_logs_session(&ws_url, program, commitment, log_contains, &sender, &state, &shutdown)
+    })
+    .await
+}
+
+// Reconnect loop behind supervise_program_logs; each call of `session` is one
+// connection, Ok once it had connected
+async fn supervise_sessions<F, Fut>(
+    program: Pubkey,
+    sender: &mpsc::Sender<Signature>,
+    state: &RwLock<ConnectionState>,
+    shutdown: &CancellationToken,
+    mut session: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut attempt = 0;
+    loop {
+        *state.write() = if attempt == 0 {
+            ConnectionState::Connecting
+        } else {
+            ConnectionState::Reconnecting { attempt }
+        };
+
+        match session().await {
+            // Connected and later dropped: the backoff starts over
+            Ok(()) => attempt = 1,
+            Err(e) => {
+                warn!(program = %program, attempt, error = %e, "Logs subscription failed");
+                attempt += 1;
+            }
+        }
+        if shutdown.is_cancelled() || sender.is_closed() {
+            break;
+        }
+
+        let delay = reconnect_delay(attempt);
+        info!(program = %program, attempt, delay_ms = delay.as_millis() as u64, "Reconnecting logs subscription");
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = sleep(delay) => {}
+        }
+    }
+    *state.write() = ConnectionState::Stopped;
+}
+
+// One connection's worth of signatures; Ok once it had connected
+async fn program_logs_session(
+    ws_url: &str,
+    program: Pubkey,
+    commitment: CommitmentConfig,
+    log_contains: Option<&'static str>,
+    sender: &mpsc::Sender<Signature>,
+    state: &RwLock<ConnectionState>,
+    shutdown: &CancellationToken,
+) -> Result<()> {
+    let client = PubsubClient::new(ws_url).await?;
+    let (mut logs, unsubscribe) = client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![program.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(commitment),
+            },
+        )
+        .await?;
+    *state.write() = ConnectionState::Connected;
+
+    loop {
+        let log = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            log = logs.next() => match log {
+                Some(log) => log,
+                None => {
+                    warn!(program = %program, "Logs subscription dropped");
+                    break;
+                }
+            },
+        };
+        // Failed transactions can't be front-run or copied
+        if log.value.err.is_some() {
+            continue;
+        }
+        if log_contains.is_some_and(|text| !log.value.logs.iter().any(|line| line.contains(text))) {
+            continue;
+        }
+        let Ok(signature) = Signature::from_str(&log.value.signature) else {
+            continue;
+        };
+        if sender.send(signature).await.is_err() {
+            break;
+        }
+    }
+
+    unsubscribe().await;
+    Ok(())
+}
+
+// Subscribes to logs mentioning `wallet`, fetches each transaction in full and
+// forwards whatever `decode` makes of it. Runs until `shutdown` is cancelled,
+// the receiver is dropped or the socket closes.
+pub async fn subscribe_wallet<T, F>(
+    ws_url: String,
+    rpc_client: Arc<RpcClient>,
+    wallet: Pubkey,
+    decode: F,
+    sender: mpsc::Sender<T>,
+    shutdown: CancellationToken,
+) -> Result<()>
+where
+    T: Send + 'static,
+    F: Fn(&Pubkey, &EncodedConfirmedTransactionWithStatusMeta) -> Option<T> + Send + 'static,
+{
+    let client = PubsubClient::new(&ws_url).await?;
+
+    // getTransaction can't see processed transactions, so listen at confirmed
+    let (mut logs, unsubscribe) = client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![wallet.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await?;
+
+    loop {
+        let log = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            log = logs.next() => match log {
+                Some(log) => log,
+                None => break,
+            },
+        };
+
+        let signature = match Signature::from_str(&log.value.signature) {
+            Ok(signature) => signature,
+            Err(e) => {
+                println!("Skipping log with bad signature {}: {}", log.value.signature, e);
+                continue;
+            }
+        };
+
+        let rpc_client = rpc_client.clone();
+        let fetched = tokio::task::spawn_blocking(move || {
+            rpc_client.get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .map_err(Box::new)
+        })
+        .await?;
+
+        let tx = match fetched {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Failed to fetch {} for {}: {}", signature, wallet, e);
+                continue;
+            }
+        };
+
+        if let Some(item) = decode(&wallet, &tx) {
+            if sender.send(item).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    unsubscribe().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::sync::atomic::{AtomicU32, Ordering}};
+
+    #[tokio::test]
+    async fn resubscribes_after_a_drop_and_dedupes_the_replay() {
+        let (a, b, c) = (Signature::new_unique(), Signature::new_unique(), Signature::new_unique());
+        let (sender, mut receiver) = mpsc::channel(8);
+        let state = Arc::new(RwLock::new(ConnectionState::Stopped));
+        let shutdown = CancellationToken::new();
+        let sessions = AtomicU32::new(0);
+        let states_at_connect = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        // The first connection delivers two signatures and drops; the second
+        // replays the last of them before a new one, then stays up
+        let session = || {
+            let session = sessions.fetch_add(1, Ordering::SeqCst);
+            let (sender, state, shutdown, states_at_connect) =
+                (sender.clone(), state.clone(), shutdown.clone(), states_at_connect.clone());
+            async move {
+                states_at_connect.lock().push(*state.read());
+                *state.write() = ConnectionState::Connected;
+                let delivered = if session == 0 { [a, b] } else { [b, c] };
+                for signature in delivered {
+                    sender.send(signature).await?;
+                }
+                if session > 0 {
+                    shutdown.cancelled().await;
+                }
+                Ok::<_, anyhow::Error>(())
+            }
+        };
+        let supervisor = {
+            let (sender, state, shutdown) = (sender.clone(), state.clone(), shutdown.clone());
+            async move { supervise_sessions(Pubkey::new_unique(), &sender, &state, &shutdown, session).await }
+        };
+
+        let consumer = async {
+            let mut seen = SeenSignatures::new(16);
+            let mut delivered = Vec::new();
+            while delivered.len() < 3 {
+                let signature = receiver.recv().await.unwrap();
+                if seen.insert(signature) {
+                    delivered.push(signature);
+                }
+            }
+            assert_eq!(*state.read(), ConnectionState::Connected);
+            shutdown.cancel();
+            delivered
+        };
+        let (delivered, ()) = tokio::time::timeout(Duration::from_secs(5), async { tokio::join!(consumer, supervisor) })
+            .await
+            .expect("reconnects within the first backoff");
+
+        assert_eq!(delivered, vec![a, b, c]);
+        assert_eq!(sessions.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            *states_at_connect.lock(),
+            vec![ConnectionState::Connecting, ConnectionState::Reconnecting { attempt: 1 }]
+        );
+        assert_eq!(*state.read(), ConnectionState::Stopped);
+    }
+
+    // The wallet and signature of fixtures/wallet_routed_buy.json
+    const FIXTURE_WALLET: &str = "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U";
+    const FIXTURE_SIGNATURE: &str =
+        "31Mfuu9kt7yXe9L83yjcw78Ws856VNUiyHR53TtS8hbDeSqv7koU4J7NwycrstToyg4EeHeTVWTzShVPnb24MPuM";
+    const SUBSCRIPTION_ID: u64 = 42;
+
+    /// Serves one pubsub connection: acknowledges the logs subscription,
+    /// pushes a single notification for the fixture signature and answers
+    /// the unsubscribe. Every request is recorded for the test to inspect.
+    async fn mock_pubsub_server(
+        listener: tokio::net::TcpListener,
+        requests: Arc<parking_lot::Mutex<Vec<serde_json::Value>>>,
+    ) {
+        use {futures::SinkExt, serde_json::json, tokio_tungstenite::tungstenite::Message};
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+        while let Some(Ok(message)) = ws.next().await {
+            let text = match message {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+            requests.lock().push(request.clone());
+
+            let id = request["id"].clone();
+            let replies = match request["method"].as_str().unwrap() {
+                "getVersion" => vec![json!({"jsonrpc": "2.0", "result": {"solana-core": "1.18.26"}, "id": id})],
+                "logsSubscribe" => vec![
+                    json!({"jsonrpc": "2.0", "result": SUBSCRIPTION_ID, "id": id}),
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": "logsNotification",
+                        "params": {
+                            "result": {
+                                "context": {"slot": 1},
+                                "value": {"signature": FIXTURE_SIGNATURE, "err": null, "logs": []},
+                            },
+                            "subscription": SUBSCRIPTION_ID,
+                        },
+                    }),
+                ],
+                "logsUnsubscribe" => vec![json!({"jsonrpc": "2.0", "result": true, "id": id})],
+                method => panic!("unexpected pubsub request {}", method),
+            };
+            for reply in replies {
+                ws.send(Message::Text(reply.to_string())).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn wallet_logs_are_fetched_decoded_and_forwarded() {
+        use solana_client::{rpc_request::RpcRequest, rpc_client::Mocks};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let server = tokio::spawn(mock_pubsub_server(listener, requests.clone()));
+
+        let fixture: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/wallet_routed_buy.json")).unwrap();
+        let mut mocks = Mocks::new();
+        mocks.insert(RpcRequest::GetTransaction, fixture);
+        let rpc_client = Arc::new(RpcClient::new_mock_with_mocks("succeeds", mocks));
+
+        let wallet = Pubkey::from_str(FIXTURE_WALLET).unwrap();
+        let amm = Pubkey::from_str(crate::dex::RAYDIUM_V4_PROGRAM_ID).unwrap();
+        let (sender, mut receiver) = mpsc::channel(8);
+        let shutdown = CancellationToken::new();
+        let subscription = tokio::spawn(subscribe_wallet(
+            ws_url,
+            rpc_client,
+            wallet,
+            move |wallet, tx| crate::monitoring::wallet::decode_wallet_trade(&amm, wallet, tx),
+            sender,
+            shutdown.clone(),
+        ));
+
+        let trade = tokio::time::timeout(Duration::from_secs(10), receiver.recv())
+            .await
+            .expect("no trade forwarded")
+            .unwrap();
+        assert_eq!(trade.signature, FIXTURE_SIGNATURE);
+        assert_eq!(trade.amount_in, 1_000_000_000);
+        assert_eq!(trade.amount_out, 1_234_567_890);
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(10), subscription)
+            .await
+            .expect("subscription did not stop")
+            .unwrap()
+            .unwrap();
+        server.abort();
+
+        let requests = requests.lock().clone();
+        let subscribe = requests.iter().find(|r| r["method"] == "logsSubscribe").unwrap();
+        assert_eq!(subscribe["params"][0]["mentions"][0], FIXTURE_WALLET);
+        assert_eq!(subscribe["params"][1]["commitment"], "confirmed");
+        let unsubscribe = requests.iter().find(|r| r["method"] == "logsUnsubscribe").unwrap();
+        assert_eq!(unsubscribe["params"][0], SUBSCRIPTION_ID);
+    }
+}
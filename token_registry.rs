@@ -0,0 +1,215 @@
+use {
+    anyhow::{Result, anyhow},
+    dashmap::DashMap,
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{program_pack::Pack, pubkey::Pubkey},
+    spl_token::{native_mint, state::Mint},
+    std::{str::FromStr, sync::Arc},
+};
+
+pub const METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+// Metaplex metadata account: key (1) + update authority (32) + mint (32),
+// then borsh strings (u32 length + bytes, null padded) for name and symbol
+const METADATA_NAME_OFFSET: usize = 65;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+}
+
+// Mint -> symbol/name/decimals for display, cached for the life of the process
+pub struct TokenRegistry {
+    rpc_client: Arc<RpcClient>,
+    metadata_program: Pubkey,
+    tokens: DashMap<Pubkey, TokenInfo>,
+}
+
+impl TokenRegistry {
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        let tokens = DashMap::new();
+        tokens.insert(native_mint::ID, TokenInfo {
+            symbol: "SOL".to_string(),
+            name: "Wrapped SOL".to_string(),
+            decimals: native_mint::DECIMALS,
+        });
+        Self {
+            rpc_client,
+            metadata_program: Pubkey::from_str(METADATA_PROGRAM_ID).unwrap(),
+            tokens,
+        }
+    }
+
+    pub fn metadata_address(&self, mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"metadata", self.metadata_program.as_ref(), mint.as_ref()],
+            &self.metadata_program,
+        ).0
+    }
+
+    // Never fails: tokens without metadata, or an RPC that's down, get a
+    // shortened pubkey. Only successful lookups are cached.
+    pub fn resolve(&self, mint: &Pubkey) -> TokenInfo {
+        if let Some(info) = self.tokens.get(mint) {
+            return info.clone();
+        }
+        match self.fetch(mint) {
+            Ok(info) => {
+                self.tokens.insert(*mint, info.clone());
+                info
+            }
+            Err(_) => fallback_info(mint, 0),
+        }
+    }
+
+    // "BONK (DezX…B263)" style label for tables and prompts
+    pub fn label(&self, mint: &Pubkey) -> String {
+        let info = self.resolve(mint);
+        let short = short_pubkey(mint);
+        if info.symbol == short {
+            short
+        } else {
+            format!("{} ({})", info.symbol, short)
+        }
+    }
+
+    fn fetch(&self, mint: &Pubkey) -> Result<TokenInfo> {
+        let accounts = self.rpc_client.get_multiple_accounts(&[*mint, self.metadata_address(mint)])?;
+        let mint_account = accounts[0]
+            .as_ref()
+            .ok_or_else(|| anyhow!("Mint {} not found", mint))?;
+        let decimals = Mint::unpack(&mint_account.data)?.decimals;
+
+        match &accounts[1] {
+            Some(metadata) => {
+                let (name, symbol) = parse_metadata(&metadata.data)?;
+                if symbol.is_empty() {
+                    return Ok(fallback_info(mint, decimals));
+                }
+                Ok(TokenInfo { symbol, name, decimals })
+            }
+            None => Ok(fallback_info(mint, decimals)),
+        }
+    }
+}
+
+// (name, symbol) from a Metaplex metadata account
+pub fn parse_metadata(data: &[u8]) -> Result<(String, String)> {
+    let (name, offset) = read_borsh_string(data, METADATA_NAME_OFFSET)?;
+    let (symbol, _) = read_borsh_string(data, offset)?;
+    Ok((name, symbol))
+}
+
+// String with the null padding trimmed, and the offset just past it
+fn read_borsh_string(data: &[u8], offset: usize) -> Result<(String, usize)> {
+    let len_bytes = data
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("Metadata truncated at offset {}", offset))?;
+    let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    let start = offset + 4;
+    let bytes = data
+        .get(start..start + len)
+        .ok_or_else(|| anyhow!("Metadata string of {} bytes runs past the account", len))?;
+    let text = String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+    Ok((text, start + len))
+}
+
+fn fallback_info(mint: &Pubkey, decimals: u8) -> TokenInfo {
+    TokenInfo {
+        symbol: short_pubkey(mint),
+        name: mint.to_string(),
+        decimals,
+    }
+}
+
+// First 4 and last 4 characters, e.g. "DezX…B263"
+pub fn short_pubkey(pubkey: &Pubkey) -> String {
+    let text = pubkey.to_string();
+    format!("{}…{}", &text[..4], &text[text.len() - 4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        base64::{engine::general_purpose::STANDARD as BASE64, Engine},
+        serde_json::{json, Value},
+        solana_client::{rpc_client::Mocks, rpc_request::RpcRequest},
+        solana_sdk::program_option::COption,
+    };
+
+    const BONK_MINT: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+    // BONK's metadata account as returned by getAccountInfo
+    const BONK_METADATA: &[u8] = include_bytes!("fixtures/bonk_metadata.bin");
+
+    fn account(owner: &str, data: &[u8]) -> Value {
+        json!({
+            "lamports": 5_616_720,
+            "data": [BASE64.encode(data), "base64"],
+            "owner": owner,
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+
+    fn mint_data(decimals: u8) -> Vec<u8> {
+        let mint = Mint {
+            mint_authority: COption::None,
+            supply: 88_000_000_000_000_000,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(mint, &mut data).unwrap();
+        data
+    }
+
+    // Registry whose single getMultipleAccounts call returns `accounts`
+    fn registry(accounts: Vec<Value>) -> TokenRegistry {
+        let mocks = Mocks::from([(
+            RpcRequest::GetMultipleAccounts,
+            json!({ "context": { "slot": 1 }, "value": accounts }),
+        )]);
+        TokenRegistry::new(Arc::new(RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)))
+    }
+
+    #[test]
+    fn parses_a_captured_metadata_account() {
+        let (name, symbol) = parse_metadata(BONK_METADATA).unwrap();
+        assert_eq!(name, "Bonk");
+        assert_eq!(symbol, "Bonk");
+        assert!(parse_metadata(&BONK_METADATA[..METADATA_NAME_OFFSET + 10]).is_err());
+    }
+
+    #[test]
+    fn resolves_a_known_mint_from_its_metadata() {
+        let mint = Pubkey::from_str(BONK_MINT).unwrap();
+        let registry = registry(vec![
+            account(&spl_token::ID.to_string(), &mint_data(5)),
+            account(METADATA_PROGRAM_ID, BONK_METADATA),
+        ]);
+
+        let expected = TokenInfo { symbol: "Bonk".to_string(), name: "Bonk".to_string(), decimals: 5 };
+        assert_eq!(registry.resolve(&mint), expected);
+        // Served from the cache; the mock only answers once
+        assert_eq!(registry.label(&mint), "Bonk (DezX…B263)");
+    }
+
+    #[test]
+    fn mint_without_metadata_falls_back_to_its_short_pubkey() {
+        let mint = Pubkey::from_str(BONK_MINT).unwrap();
+        let registry = registry(vec![account(&spl_token::ID.to_string(), &mint_data(5)), Value::Null]);
+
+        let info = registry.resolve(&mint);
+        assert_eq!(info.symbol, "DezX…B263");
+        assert_eq!(info.decimals, 5);
+        assert_eq!(registry.label(&mint), "DezX…B263");
+    }
+}
@@ -1,23 +1,578 @@
+use {
+    crate::{
+        monitoring::wallet::TradeType,
+        notify::{EventKind, TradeEvent},
+        trading::{SubmitPolicy, TradingEngine},
+    },
+    anyhow::{Result, anyhow},
+    serde::{Deserialize, Serialize},
+    solana_account_decoder::parse_token::UiTokenAmount,
+    solana_sdk::{
+        instruction::Instruction,
+        native_token::LAMPORTS_PER_SOL,
+        pubkey::Pubkey,
+        signature::Signature,
+        signer::Signer,
+        system_instruction,
+    },
+    spl_associated_token_account::{
+        get_associated_token_address,
+        instruction::create_associated_token_account_idempotent,
+    },
+    spl_token::native_mint,
+    std::{
+        collections::HashMap,
+        fmt,
+        fs,
+        path::Path,
+        str::FromStr,
+        time::{Instant, SystemTime, UNIX_EPOCH},
+    },
+    tokio::time::Duration,
+    tokio_util::sync::CancellationToken,
+    tracing::{error, info, warn},
+};
+
+pub const PRICE_SCALE: u128 = 1_000_000_000;
+const BPS_SCALE: u64 = 10_000;
+const BASE_FEE_LAMPORTS: u64 = 5_000; // Per signature
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+const LATENCY_SAMPLES: usize = 1024; // Per operation; older samples are overwritten
+
+// Fixed-point price with 9 decimals, e.g. SOL per whole token. Integer math
+// keeps PnL and price-impact results identical from run to run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Price(u128);
+
+impl Price {
+    pub const ZERO: Price = Price(0);
+
+    pub fn from_raw(raw: u128) -> Self {
+        Price(raw)
+    }
+
+    pub fn raw(self) -> u128 {
+        self.0
+    }
+
+    // numerator / denominator; None for a zero denominator or on overflow
+    pub fn from_ratio(numerator: u128, denominator: u128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        numerator.checked_mul(PRICE_SCALE).map(|scaled| Price(scaled / denominator))
+    }
+
+    // Quote per base
+    pub fn from_reserves(base_amount: u64, quote_amount: u64) -> Option<Self> {
+        Self::from_ratio(quote_amount as u128, base_amount as u128)
+    }
+
+    // 10_000 bps leaves the price unchanged, 20_000 doubles it
+    pub fn mul_bps(self, bps: u64) -> Self {
+        Price(self.0.saturating_mul(bps as u128) / BPS_SCALE as u128)
+    }
+
+    // Signed move from `from` to self in bps; None when `from` is zero
+    pub fn change_bps(self, from: Price) -> Option<i64> {
+        if from.0 == 0 {
+            return None;
+        }
+        let diff = self.0 as i128 - from.0 as i128;
+        Some((diff * BPS_SCALE as i128 / from.0 as i128) as i64)
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    // Lossy, for display and f64 heuristics only
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / PRICE_SCALE as f64
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:09}", self.0 / PRICE_SCALE, self.0 % PRICE_SCALE)
+    }
+}
+
+// 2.0 -> 20_000 bps, for applying config multipliers to a Price
+pub fn multiple_to_bps(multiple: f64) -> u64 {
+    (multiple.max(0.0) * BPS_SCALE as f64).round() as u64
+}
+
+// Why `position` should be sold now, if it should. A ladder or trailing stop
+// replaces the fixed take-profit; the stop-loss still applies.
+pub fn exit_reason(
+    position: &Position,
+    trailed: bool,
+    has_exit_plan: bool,
+    profit_target: f64,
+    stop_loss: f64,
+) -> Option<&'static str> {
+    if trailed {
+        Some("trailing-stop")
+    } else if !has_exit_plan
+        && position.current_price >= position.entry_price.mul_bps(multiple_to_bps(profit_target))
+    {
+        Some("take-profit")
+    } else if position.current_price <= position.entry_price.mul_bps(multiple_to_bps(stop_loss)) {
+        Some("stop-loss")
+    } else {
+        None
+    }
+}
+
+// 1.5 of a 6-decimal mint -> 1_500_000; negative and NaN become 0, huge values saturate
+pub fn ui_to_base_units(amount: f64, decimals: u8) -> u64 {
+    (amount * 10f64.powi(decimals as i32)).round() as u64
+}
+
+// Saved positions, none when the file doesn't exist yet
+pub(crate) fn read_positions(path: &str) -> Result<Vec<PersistedPosition>> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+fn write_positions(path: &str, positions: &[PersistedPosition]) -> Result<()> {
+    fs::write(path, serde_json::to_vec_pretty(positions)?)?;
+    Ok(())
+}
+
+// Saved trailing stops by mint, none when the file doesn't exist yet
+pub(crate) fn read_trailing_stops(path: &str) -> Result<HashMap<Pubkey, TrailingStop>> {
+    if !Path::new(path).exists() {
+        return Ok(HashMap::new());
+    }
+    let stops: HashMap<String, TrailingStop> = serde_json::from_slice(&fs::read(path)?)?;
+    stops
+        .into_iter()
+        .map(|(token, stop)| Ok((Pubkey::from_str(&token)?, stop)))
+        .collect()
+}
+
+fn write_trailing_stops(path: &str, stops: &HashMap<Pubkey, TrailingStop>) -> Result<()> {
+    let stops: HashMap<String, &TrailingStop> = stops
+        .iter()
+        .map(|(token, stop)| (token.to_string(), stop))
+        .collect();
+    fs::write(path, serde_json::to_vec_pretty(&stops)?)?;
+    Ok(())
+}
+
+// Positions still held, keyed by mint, and the mints no longer held
+type Reconciled = (Vec<(Pubkey, PersistedPosition)>, Vec<Pubkey>);
+
+// Splits saved positions into those still held, amounts refreshed from the
+// on-chain `balances`, and the mints we no longer hold
+fn reconcile_positions(
+    saved: Vec<PersistedPosition>,
+    balances: &HashMap<Pubkey, u64>,
+) -> Result<Reconciled> {
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    for mut position in saved {
+        let token = Pubkey::from_str(&position.token)?;
+        let balance = balances.get(&token).copied().unwrap_or(0);
+        if balance == 0 {
+            dropped.push(token);
+            continue;
+        }
+        if balance != position.amount {
+            info!(token = %token, amount = balance, saved = position.amount, "Saved position size changed");
+            position.amount = balance;
+        }
+        kept.push((token, position));
+    }
+    Ok((kept, dropped))
+}
+
+// Least output a swap quoted at `expected_out` may fill for, after `slippage_bps`
+pub fn min_out_after_slippage(expected_out: u64, slippage_bps: u16) -> u64 {
+    let slippage_bps = slippage_bps.min(BPS_SCALE as u16) as u128;
+    (expected_out as u128 * (BPS_SCALE as u128 - slippage_bps) / BPS_SCALE as u128) as u64
+}
+
+// On-chain SOL amount, as returned by get_balance and passed to instructions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Lamports(pub u64);
+
+// SOL as users type and read it: config values, prompts, display
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Sol(pub f64);
+
+impl Lamports {
+    pub const ZERO: Lamports = Lamports(0);
+
+    pub fn to_sol(self) -> Sol {
+        Sol(self.0 as f64 / LAMPORTS_PER_SOL as f64)
+    }
+}
+
+impl Sol {
+    // Rounded to the nearest lamport; negative and NaN become 0, huge values saturate
+    pub fn to_lamports(self) -> Lamports {
+        let lamports = (self.0 * LAMPORTS_PER_SOL as f64).round();
+        if lamports.is_nan() || lamports <= 0.0 {
+            Lamports::ZERO
+        } else if lamports >= u64::MAX as f64 {
+            Lamports(u64::MAX)
+        } else {
+            Lamports(lamports as u64)
+        }
+    }
+}
+
+impl From<Sol> for Lamports {
+    fn from(sol: Sol) -> Self {
+        sol.to_lamports()
+    }
+}
+
+impl From<Lamports> for Sol {
+    fn from(lamports: Lamports) -> Self {
+        lamports.to_sol()
+    }
+}
+
+// 1.2345 SOL
+impl fmt::Display for Sol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.4} SOL", self.0)
+    }
+}
+
+impl fmt::Display for Lamports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_sol().fmt(f)
+    }
+}
+
+// What a swap would fill at right now; nothing is built or sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteResult {
+    pub venue: &'static str,
+    pub amount_in: u64,
+    pub expected_out: u64,
+    pub price_impact: Option<f64>, // 0.01 = 1%, None when the venue can't tell
+    pub min_out: u64,              // expected_out less the configured slippage
+    pub priority_fee: u64,         // Micro-lamports per compute unit
+    pub estimated_fee: Lamports,   // One signature plus the priority fee at the full compute limit
+}
+
+impl QuoteResult {
+    pub fn new(
+        venue: &'static str,
+        amount_in: u64,
+        expected_out: u64,
+        price_impact: Option<f64>,
+        slippage_bps: u16,
+        priority_fee: u64,
+        compute_units: u32,
+    ) -> Self {
+        let priority_lamports = (priority_fee as u128 * compute_units as u128).div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+
+        Self {
+            venue,
+            amount_in,
+            expected_out,
+            price_impact,
+            min_out: min_out_after_slippage(expected_out, slippage_bps),
+            priority_fee,
+            estimated_fee: Lamports(BASE_FEE_LAMPORTS.saturating_add(priority_lamports as u64)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Position {
     pub token: Pubkey,
     pub amount: u64,
-    pub entry_price: f64,
-    pub current_price: f64,
-    pub pnl: f64,
+    pub ui_amount: f64, // `amount` in whole tokens
+    pub entry_price: Price,
+    pub current_price: Price,
+    pub pnl: f64,          // Unrealized, SOL
+    pub realized_pnl: f64, // SOL booked by earlier sells of this token
+    pub high_watermark: Option<Price>, // Highest price seen above entry while a trailing stop is set
     pub timestamp: Instant,
 }
 
+impl Position {
+    // Current value in SOL
+    pub fn value(&self) -> f64 {
+        self.current_price.to_f64() * self.ui_amount
+    }
+}
+
+// Disk form of `Position`; `Instant` can't be serialized so the entry time is wall-clock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedPosition {
+    pub token: String,
+    pub amount: u64,
+    pub entry_price: Price,
+    pub entry_time: i64, // Unix seconds of the first buy we know of
+    pub ladder: Option<SellLadder>,
+    #[serde(default)]
+    pub dca: Option<DcaSchedule>, // Remaining tranches of a scaled-in buy
+}
+
 #[derive(Debug, Clone)]
 pub struct TradeHistory {
     pub signature: String,
     pub token: Pubkey,
     pub trade_type: TradeType,
     pub amount: u64,
-    pub price: f64,
+    pub price: Price,
     pub success: bool,
     pub error: Option<String>,
     pub timestamp: Instant,
+    pub recorded_at: i64,       // Unix seconds; `timestamp` is monotonic and not persistable
+    pub reason: Option<String>, // Why the bot traded, e.g. "take-profit"
+}
+
+// One row of the trade-history CSV; `timestamp` is TradeHistory::recorded_at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub signature: String,
+    pub token: String,
+    #[serde(rename = "type")]
+    pub trade_type: TradeType,
+    pub amount: u64,
+    pub price: String, // Price's 9-decimal display form
+    pub success: bool,
+    pub error: String,
+    pub timestamp: i64,
+    pub reason: String,
+}
+
+impl From<&TradeHistory> for TradeRecord {
+    fn from(trade: &TradeHistory) -> Self {
+        Self {
+            signature: trade.signature.clone(),
+            token: trade.token.to_string(),
+            trade_type: trade.trade_type.clone(),
+            amount: trade.amount,
+            price: trade.price.to_string(),
+            success: trade.success,
+            error: trade.error.clone().unwrap_or_default(),
+            timestamp: trade.recorded_at,
+            reason: trade.reason.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PositionAction {
+    Buy(u64),
+    SellPartial(f64),
+    SellAll,
+    SellLadder(Vec<(f64, f64)>), // (price multiple of entry, fraction of the position to sell)
+    TrailingStop { trail_percent: f64 },
+    DcaBuy { total: u64, tranches: u32, interval_secs: u64 }, // Quote base units spread over equal tranches
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrailingStop {
+    pub trail_percent: f64,
+    pub high_watermark: Option<Price>, // None until price first exceeds entry
+}
+
+impl TrailingStop {
+    // Raises the watermark once price is above entry; true when price has fallen through the trail
+    pub fn update(&mut self, entry_price: Price, current_price: Price) -> bool {
+        if current_price > entry_price {
+            self.high_watermark = Some(self.high_watermark.map_or(current_price, |high| high.max(current_price)));
+        }
+        let keep_bps = BPS_SCALE.saturating_sub(multiple_to_bps(self.trail_percent / 100.0));
+        match self.high_watermark {
+            Some(high) => current_price <= high.mul_bps(keep_bps),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderRung {
+    pub multiple: f64,
+    pub fraction: f64, // Of the position size when the ladder was registered
+    pub fired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SellLadder {
+    pub original_amount: u64,
+    pub rungs: Vec<LadderRung>, // Ascending by multiple
+}
+
+impl SellLadder {
+    // (multiple, amount) for each unfired rung `current` has crossed. Each
+    // sells its fraction of original_amount, capped at what is still held
+    // once the rungs before it have sold, so a gap through several rungs
+    // never sells more than `held`.
+    pub fn due_sells(&self, entry_price: Price, current_price: Price, held: u64) -> Vec<(f64, u64)> {
+        let mut remaining = held;
+        self.rungs
+            .iter()
+            .filter(|rung| !rung.fired)
+            .filter(|rung| current_price >= entry_price.mul_bps(multiple_to_bps(rung.multiple)))
+            .map(|rung| {
+                let amount = ((self.original_amount as f64 * rung.fraction) as u64).min(remaining);
+                remaining -= amount;
+                (rung.multiple, amount)
+            })
+            .collect()
+    }
+
+    // True once every rung has fired
+    pub fn mark_fired(&mut self, multiple: f64) -> bool {
+        for rung in self.rungs.iter_mut().filter(|rung| rung.multiple == multiple) {
+            rung.fired = true;
+        }
+        self.rungs.iter().all(|rung| rung.fired)
+    }
+}
+
+// Progress of a DcaBuy; `now` is passed in so the schedule doesn't read the clock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DcaSchedule {
+    pub total: u64, // Lamports
+    pub tranches: u32,
+    pub interval_secs: u64,
+    pub filled: u32,
+    pub spent: u64,
+    pub next_at: i64, // Unix seconds the next tranche is due
+}
+
+impl DcaSchedule {
+    pub fn new(total: u64, tranches: u32, interval_secs: u64, now: i64) -> Result<Self> {
+        if tranches == 0 || total / tranches as u64 == 0 {
+            return Err(anyhow!("DCA of {} lamports can't be split into {} tranches", total, tranches));
+        }
+        Ok(Self { total, tranches, interval_secs, filled: 0, spent: 0, next_at: now })
+    }
+
+    // Lamports to buy now, if a tranche is due. The last tranche takes the rounding remainder.
+    pub fn due(&self, now: i64) -> Option<u64> {
+        if self.is_complete() || now < self.next_at {
+            return None;
+        }
+        if self.filled + 1 == self.tranches {
+            Some(self.total - self.spent)
+        } else {
+            Some(self.total / self.tranches as u64)
+        }
+    }
+
+    pub fn record_fill(&mut self, amount: u64, now: i64) {
+        self.filled += 1;
+        self.spent += amount;
+        self.next_at = now + self.interval_secs as i64;
+    }
+
+    // A failed tranche is retried one interval later rather than every poll
+    pub fn defer(&mut self, now: i64) {
+        self.next_at = now + self.interval_secs as i64;
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.filled >= self.tranches
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LatencyOp {
+    Build,   // Instructions to signed transaction
+    Submit,  // sendTransaction round-trip
+    Confirm, // Submitted to requested commitment
+}
+
+impl fmt::Display for LatencyOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LatencyOp::Build => write!(f, "build"),
+            LatencyOp::Submit => write!(f, "submit"),
+            LatencyOp::Confirm => write!(f, "confirm"),
+        }
+    }
+}
+
+// Percentiles over the samples still in the ring
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyStats {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub count: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct LatencyRing {
+    samples: Vec<Duration>,
+    next: usize, // Slot the next sample overwrites once full
+}
+
+impl LatencyRing {
+    pub fn record(&mut self, latency: Duration) {
+        if self.samples.len() < LATENCY_SAMPLES {
+            self.samples.push(latency);
+        } else {
+            self.samples[self.next] = latency;
+        }
+        self.next = (self.next + 1) % LATENCY_SAMPLES;
+    }
+
+    pub fn stats(&self) -> LatencyStats {
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        LatencyStats {
+            p50: percentile(&sorted, 50),
+            p90: percentile(&sorted, 90),
+            p99: percentile(&sorted, 99),
+            count: sorted.len(),
+        }
+    }
+}
+
+// Nearest-rank percentile of an ascending slice
+fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() * pct).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+// Idempotent, so a racing create from another transaction can't fail the swap
+fn ata_create_ix(payer: &Pubkey, owner: &Pubkey, mint: &Pubkey, exists: bool) -> Option<Instruction> {
+    (!exists).then(|| create_associated_token_account_idempotent(payer, owner, mint, &spl_token::id()))
+}
+
+// Moves `lamports` into the owner's wSOL account, creating it if needed
+pub fn wrap_sol_ixs(owner: &Pubkey, lamports: u64) -> Result<Vec<Instruction>> {
+    let wsol_account = get_associated_token_address(owner, &native_mint::ID);
+    Ok(vec![
+        create_associated_token_account_idempotent(owner, owner, &native_mint::ID, &spl_token::id()),
+        system_instruction::transfer(owner, &wsol_account, lamports),
+        spl_token::instruction::sync_native(&spl_token::id(), &wsol_account)?,
+    ])
+}
+
+// Closes the owner's wSOL account, returning its whole balance as SOL
+pub fn unwrap_sol_ix(owner: &Pubkey) -> Result<Instruction> {
+    let wsol_account = get_associated_token_address(owner, &native_mint::ID);
+    Ok(spl_token::instruction::close_account(
+        &spl_token::id(),
+        &wsol_account,
+        owner,
+        owner,
+        &[],
+    )?)
 }
 
 // filepath: /src/trading/engine.rs
@@ -25,92 +580,1190 @@ impl TradingEngine {
     // Position Management
     pub async fn get_active_positions(&self) -> Result<Vec<Position>> {
         let mut positions = Vec::new();
-        for token in &self.tracked_tokens {
-            let amount = self.get_token_balance(token).await?;
+        for token in &self.tracked_tokens() {
+            let balance = match self.get_token_account_balance(token).await? {
+                Some(balance) => balance,
+                None => continue,
+            };
+            let amount: u64 = balance.amount.parse()?;
             if amount > 0 {
                 let current_price = self.get_token_price(token).await?;
+                let entry_price = self.get_entry_price(token)?;
+                let ui_amount = amount as f64 / 10f64.powi(balance.decimals as i32);
                 positions.push(Position {
                     token: *token,
                     amount,
-                    entry_price: self.get_entry_price(token)?,
+                    ui_amount,
+                    entry_price,
                     current_price,
-                    pnl: self.calculate_pnl(token)?,
+                    pnl: self.calculate_pnl(entry_price, current_price, ui_amount),
+                    realized_pnl: self.realized_pnl(token),
+                    high_watermark: self.trailing_stops
+                        .lock()
+                        .get(token)
+                        .and_then(|stop| stop.high_watermark),
                     timestamp: Instant::now(),
                 });
             }
         }
+        self.metrics.set_open_positions(positions.len());
         Ok(positions)
     }
 
+    pub async fn get_position(&self, token: &Pubkey) -> Result<Position> {
+        self.get_active_positions()
+            .await?
+            .into_iter()
+            .find(|position| position.token == *token)
+            .ok_or_else(|| anyhow!("No open position in {}", token))
+    }
+
+    // Raw token amount held in our ATA; zero when the ATA doesn't exist
+    pub async fn get_token_balance(&self, token: &Pubkey) -> Result<u64> {
+        match self.get_token_account_balance(token).await? {
+            Some(balance) => Ok(balance.amount.parse()?),
+            None => Ok(0),
+        }
+    }
+
+    async fn get_token_account_balance(&self, token: &Pubkey) -> Result<Option<UiTokenAmount>> {
+        let ata = get_associated_token_address(&self.payer.pubkey(), token);
+        let account = self.rpc_throttled()
+            .await
+            .get_account_with_commitment(&ata, self.commitment)
+            .await?
+            .value;
+
+        if account.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.rpc_throttled().await.get_token_account_balance(&ata).await?))
+    }
+
+    // Create for `owner`'s ATA of `mint`, paid by us, or None when it already exists
+    pub async fn ensure_ata_ix(&self, owner: &Pubkey, mint: &Pubkey) -> Result<Option<Instruction>> {
+        let ata = get_associated_token_address(owner, mint);
+        let exists = self.rpc_throttled()
+            .await
+            .get_account_with_commitment(&ata, self.commitment)
+            .await?
+            .value
+            .is_some();
+
+        Ok(ata_create_ix(&self.payer.pubkey(), owner, mint, exists))
+    }
+
+    // Everything `swap_ixs` needs around it in one transaction: SOL wrapped in
+    // when spending SOL, a create for each of our missing ATAs in `outputs`,
+    // and the wSOL account closed at the end whenever SOL was on either side
+    pub(crate) async fn with_swap_accounts(
+        &self,
+        token_in: &Pubkey,
+        amount_in: u64,
+        outputs: &[Pubkey],
+        swap_ixs: Vec<Instruction>,
+    ) -> Result<Vec<Instruction>> {
+        let owner = self.payer.pubkey();
+        let spends_sol = *token_in == native_mint::ID;
+        let mut ixs = Vec::with_capacity(outputs.len() + swap_ixs.len() + 4);
+
+        if spends_sol {
+            ixs.extend(wrap_sol_ixs(&owner, amount_in)?);
+        }
+        for mint in outputs {
+            // The wrap above already created the wSOL account
+            if spends_sol && *mint == native_mint::ID {
+                continue;
+            }
+            ixs.extend(self.ensure_ata_ix(&owner, mint).await?);
+        }
+        ixs.extend(swap_ixs);
+        if spends_sol || outputs.contains(&native_mint::ID) {
+            ixs.push(unwrap_sol_ix(&owner)?);
+        }
+        Ok(ixs)
+    }
+
+    // Decimals never change for a mint, so each is fetched once
+    pub async fn mint_decimals(&self, mint: &Pubkey) -> Result<u8> {
+        if *mint == native_mint::ID {
+            return Ok(native_mint::DECIMALS);
+        }
+        if let Some(decimals) = self.mint_decimals.get(mint) {
+            return Ok(*decimals);
+        }
+        let decimals = self.rpc_throttled().await.get_token_supply(mint).await?.decimals;
+        self.mint_decimals.insert(*mint, decimals);
+        Ok(decimals)
+    }
+
+    // 1.5 of a 6-decimal mint -> 1_500_000
+    pub async fn ui_amount_to_base_units(&self, amount: f64, mint: &Pubkey) -> Result<u64> {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(anyhow!("Invalid amount {}", amount));
+        }
+        let decimals = self.mint_decimals(mint).await?;
+        let base_units = (amount * 10f64.powi(decimals as i32)).round();
+        if base_units > u64::MAX as f64 {
+            return Err(anyhow!("Amount {} too large for {}", amount, mint));
+        }
+        Ok(base_units as u64)
+    }
+
+    pub async fn base_units_to_ui_amount(&self, base_units: u64, mint: &Pubkey) -> Result<f64> {
+        let decimals = self.mint_decimals(mint).await?;
+        Ok(base_units as f64 / 10f64.powi(decimals as i32))
+    }
+
+    // Whole quote tokens received for selling one whole token at current pool reserves
+    pub async fn get_token_price(&self, token: &Pubkey) -> Result<Price> {
+        let decimals = self.mint_decimals(token).await?;
+        let one_token = 10u64.pow(decimals as u32);
+        let (_, quote_out) = self.route_swap(token, &self.quote_mint, one_token)?;
+        let one_quote = 10u64.pow(self.mint_decimals(&self.quote_mint).await? as u32);
+        Price::from_ratio(quote_out as u128, one_quote as u128)
+            .ok_or_else(|| anyhow!("Price of {} out of range", token))
+    }
+
+    // 1.5 USDC with a USDC quote -> 1_500_000; what Buy and DcaBuy amounts are in
+    pub async fn quote_to_base_units(&self, amount: f64) -> Result<u64> {
+        self.ui_amount_to_base_units(amount, &self.quote_mint).await
+    }
+
+    // Amount-weighted average price of our successful buys, zero if none recorded
+    pub fn get_entry_price(&self, token: &Pubkey) -> Result<Price> {
+        let history = self.trade_history.lock();
+        let (cost, amount) = history
+            .iter()
+            .filter(|t| t.token == *token && t.success)
+            .filter(|t| t.trade_type == TradeType::SwapExactSOLForTokens)
+            .fold((0u128, 0u128), |(cost, amount), t| {
+                (cost + t.price.raw() * t.amount as u128, amount + t.amount as u128)
+            });
+
+        if amount == 0 {
+            // Buys from before a restart only survive as a restored entry
+            let restored = self.restored_entries.lock().get(token).map(|(price, _)| *price);
+            return Ok(restored.unwrap_or(Price::ZERO));
+        }
+        Ok(Price::from_raw(cost / amount))
+    }
+
+    // Unix seconds of the first successful buy, falling back to a restored entry
+    fn get_entry_time(&self, token: &Pubkey) -> Option<i64> {
+        let first_buy = self.trade_history
+            .lock()
+            .iter()
+            .filter(|t| t.token == *token && t.success)
+            .filter(|t| t.trade_type == TradeType::SwapExactSOLForTokens)
+            .map(|t| t.recorded_at)
+            .min();
+        let restored = self.restored_entries.lock().get(token).map(|(_, time)| *time);
+
+        match (first_buy, restored) {
+            (Some(first_buy), Some(restored)) => Some(first_buy.min(restored)),
+            (first_buy, restored) => first_buy.or(restored),
+        }
+    }
+
+    pub async fn save_positions(&self, path: &str) -> Result<()> {
+        let now = unix_timestamp();
+        let positions: Vec<PersistedPosition> = self.get_active_positions()
+            .await?
+            .into_iter()
+            .map(|position| PersistedPosition {
+                token: position.token.to_string(),
+                amount: position.amount,
+                entry_price: position.entry_price,
+                entry_time: self.get_entry_time(&position.token).unwrap_or(now),
+                ladder: self.ladders.lock().get(&position.token).cloned(),
+                dca: self.dca_schedules.lock().get(&position.token).cloned(),
+            })
+            .collect();
+
+        write_positions(path, &positions)
+    }
+
+    // Restores saved positions, trusting on-chain balances over the file: tokens
+    // we no longer hold are dropped and amounts are refreshed. Returns how many remain.
+    pub async fn load_positions(&self, path: &str) -> Result<usize> {
+        let saved = read_positions(path)?;
+        let mut balances = HashMap::with_capacity(saved.len());
+        for position in &saved {
+            let token = Pubkey::from_str(&position.token)?;
+            balances.insert(token, self.get_token_balance(&token).await?);
+        }
+
+        let (kept, dropped) = reconcile_positions(saved, &balances)?;
+        for token in dropped {
+            info!(token = %token, "Dropping saved position: no balance on-chain");
+            self.ladders.lock().remove(&token);
+            self.trailing_stops.lock().remove(&token);
+        }
+
+        let restored = kept.len();
+        for (token, position) in kept {
+            self.restored_entries
+                .lock()
+                .insert(token, (position.entry_price, position.entry_time));
+            if let Some(ladder) = position.ladder {
+                self.ladders.lock().entry(token).or_insert(ladder);
+            }
+            if let Some(dca) = position.dca {
+                self.dca_schedules.lock().entry(token).or_insert(dca);
+            }
+            self.track_token(token);
+        }
+
+        self.save_ladders()?;
+        self.save_dca_schedules()?;
+        self.save_trailing_stops()?;
+        Ok(restored)
+    }
+
+    // PnL in SOL; unknown entries (zero) report no PnL rather than the full value
+    pub fn calculate_pnl(&self, entry_price: Price, current_price: Price, ui_amount: f64) -> f64 {
+        if entry_price.is_zero() {
+            return 0.0;
+        }
+        let diff = current_price.raw() as i128 - entry_price.raw() as i128;
+        diff as f64 / PRICE_SCALE as f64 * ui_amount
+    }
+
     pub async fn manage_position(&self, token: &Pubkey, action: PositionAction) -> Result<()> {
+        self.manage_position_with(token, action, SubmitPolicy::FAST).await
+    }
+
+    // Buys go out under `policy`; the UI passes SubmitPolicy::SAFE for manual trades
+    pub async fn manage_position_with(&self, token: &Pubkey, action: PositionAction, policy: SubmitPolicy) -> Result<()> {
         match action {
             PositionAction::Buy(amount) => {
-                self.execute_privileged_swap(token, amount).await?;
+                self.ensure_running()?;
+                let signature = self
+                    .within_daily_spend(amount, async {
+                        self.ensure_sellable(token).await?;
+                        self.execute_privileged_swap(token, amount, policy).await
+                    })
+                    .await?;
+                self.track_token(*token);
+                self.notify(TradeEvent::new(EventKind::Buy).token(*token).amount(amount).signature(&signature));
             },
             PositionAction::SellPartial(percentage) => {
                 let position = self.get_position(token).await?;
                 let sell_amount = (position.amount as f64 * percentage) as u64;
                 self.execute_sell(token, sell_amount).await?;
+                self.book_realized_pnl(&position, sell_amount);
+                self.untrack_if_closed(token).await?;
             },
             PositionAction::SellAll => {
-                let position = self.get_position(token).await?;
-                self.execute_sell(token, position.amount).await?;
+                self.sell_all(token).await?;
+            }
+            PositionAction::SellLadder(rungs) => {
+                self.register_ladder(token, rungs).await?;
+            }
+            PositionAction::TrailingStop { trail_percent } => {
+                if trail_percent <= 0.0 || trail_percent >= 100.0 {
+                    return Err(anyhow!("Trail percent must be between 0 and 100, got {}", trail_percent));
+                }
+                self.trailing_stops
+                    .lock()
+                    .insert(*token, TrailingStop { trail_percent, high_watermark: None });
+                self.save_trailing_stops()?;
+            }
+            PositionAction::DcaBuy { total, tranches, interval_secs } => {
+                self.ensure_running()?;
+                self.ensure_sellable(token).await?;
+                let schedule = DcaSchedule::new(total, tranches, interval_secs, unix_timestamp())?;
+                self.dca_schedules.lock().insert(*token, schedule);
+                self.save_dca_schedules()?;
+                // First tranche goes out now; the rest fire from monitor_positions
+                self.fire_dca_tranches(unix_timestamp()).await?;
             }
         }
         Ok(())
     }
 
-    // Copy Trading Enhancement
-    pub async fn copy_trade(&self, tx: &Transaction) -> Result<()> {
-        let start = Instant::now();
-        let result = self.execute_copy_trade(tx).await;
-        
-        // Record trade history
-        let history = TradeHistory {
-            signature: tx.signatures[0].to_string(),
-            token: self.extract_token_from_tx(tx)?,
-            trade_type: self.determine_trade_type(tx)?,
-            amount: self.extract_amount_from_tx(tx)?,
-            price: self.get_execution_price(tx)?,
-            success: result.is_ok(),
-            error: result.err().map(|e| e.to_string()),
-            timestamp: start,
+    // Sells the whole position, returning the confirmed signature
+    pub(crate) async fn sell_all(&self, token: &Pubkey) -> Result<Signature> {
+        let position = self.get_position(token).await?;
+        let signature = self.execute_sell(token, position.amount).await?;
+        self.book_realized_pnl(&position, position.amount);
+        self.untrack_if_closed(token).await?;
+        Ok(signature)
+    }
+
+    // Moves the sold share of a position's unrealized PnL into its realized total
+    fn book_realized_pnl(&self, position: &Position, amount: u64) {
+        let pnl = position.pnl * amount.min(position.amount) as f64 / position.amount.max(1) as f64;
+        *self.realized_pnl.lock().entry(position.token).or_insert(0.0) += pnl;
+    }
+
+    pub fn realized_pnl(&self, token: &Pubkey) -> f64 {
+        self.realized_pnl.lock().get(token).copied().unwrap_or(0.0)
+    }
+
+    // Realized across every token traded this session, including closed ones
+    pub fn total_realized_pnl(&self) -> f64 {
+        self.realized_pnl.lock().values().sum()
+    }
+
+    // True if the token wasn't tracked yet
+    pub fn track_token(&self, mint: Pubkey) -> bool {
+        self.tracked_tokens.write().insert(mint)
+    }
+
+    // True if the token was tracked
+    pub fn untrack_token(&self, mint: &Pubkey) -> bool {
+        self.tracked_tokens.write().remove(mint)
+    }
+
+    pub fn tracked_tokens(&self) -> Vec<Pubkey> {
+        let mut tokens: Vec<Pubkey> = self.tracked_tokens.read().iter().copied().collect();
+        tokens.sort();
+        tokens
+    }
+
+    // A sell that emptied the account ends tracking, along with any exit plan
+    async fn untrack_if_closed(&self, token: &Pubkey) -> Result<()> {
+        if self.get_token_balance(token).await? > 0 {
+            return Ok(());
+        }
+        self.untrack_token(token);
+        if self.trailing_stops.lock().remove(token).is_some() {
+            self.save_trailing_stops()?;
+        }
+        if self.ladders.lock().remove(token).is_some() {
+            self.save_ladders()?;
+        }
+        Ok(())
+    }
+
+    // Buys every tranche that is due at `now`
+    pub(crate) async fn fire_dca_tranches(&self, now: i64) -> Result<()> {
+        let due: Vec<(Pubkey, u64)> = self.dca_schedules
+            .lock()
+            .iter()
+            .filter_map(|(token, schedule)| Some((*token, schedule.due(now)?)))
+            .collect();
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        for (token, amount) in due {
+            let result = Box::pin(self.manage_position(&token, PositionAction::Buy(amount))).await;
+            let mut schedules = self.dca_schedules.lock();
+            let Some(schedule) = schedules.get_mut(&token) else {
+                continue; // Cancelled while the buy was in flight
+            };
+            match result {
+                Ok(()) => {
+                    schedule.record_fill(amount, now);
+                    info!(token = %token, tranche = schedule.filled, tranches = schedule.tranches, amount, "DCA tranche filled");
+                    if schedule.is_complete() {
+                        schedules.remove(&token);
+                    }
+                }
+                Err(e) => {
+                    warn!(token = %token, amount, error = %e, "DCA tranche failed, retrying next interval");
+                    schedule.defer(now);
+                }
+            }
+        }
+        self.save_dca_schedules()
+    }
+
+    // Drops every pending tranche; returns how many schedules were cancelled
+    pub fn cancel_dca_schedules(&self) -> Result<usize> {
+        let cancelled = {
+            let mut schedules = self.dca_schedules.lock();
+            let count = schedules.len();
+            schedules.clear();
+            count
         };
-        
-        self.trade_history.push(history.clone());
-        
-        // Log errors for analysis
-        if let Err(e) = &result {
-            self.log_trade_error(e, tx).await?;
+        self.save_dca_schedules()?;
+        Ok(cancelled)
+    }
+
+    pub fn dca_schedules(&self) -> Vec<(Pubkey, DcaSchedule)> {
+        self.dca_schedules
+            .lock()
+            .iter()
+            .map(|(token, schedule)| (*token, schedule.clone()))
+            .collect()
+    }
+
+    fn save_dca_schedules(&self) -> Result<()> {
+        let schedules: HashMap<String, DcaSchedule> = self.dca_schedules
+            .lock()
+            .iter()
+            .map(|(token, schedule)| (token.to_string(), schedule.clone()))
+            .collect();
+        fs::write(&self.dca_state_path, serde_json::to_vec_pretty(&schedules)?)?;
+        Ok(())
+    }
+
+    pub(crate) fn load_dca_schedules(path: &str) -> Result<HashMap<Pubkey, DcaSchedule>> {
+        if !Path::new(path).exists() {
+            return Ok(HashMap::new());
+        }
+        let schedules: HashMap<String, DcaSchedule> = serde_json::from_slice(&fs::read(path)?)?;
+        schedules
+            .into_iter()
+            .map(|(token, schedule)| Ok((Pubkey::from_str(&token)?, schedule)))
+            .collect()
+    }
+
+    // Rungs fire from monitor_positions; registering again replaces the ladder
+    async fn register_ladder(&self, token: &Pubkey, rungs: Vec<(f64, f64)>) -> Result<()> {
+        if rungs.is_empty() {
+            return Err(anyhow!("Sell ladder needs at least one rung"));
+        }
+        for (multiple, fraction) in &rungs {
+            if *multiple <= 0.0 || *fraction <= 0.0 || *fraction > 1.0 {
+                return Err(anyhow!("Invalid ladder rung {}x / {}", multiple, fraction));
+            }
+        }
+
+        let original_amount = self.get_token_balance(token).await?;
+        if original_amount == 0 {
+            return Err(anyhow!("No position in {} to ladder", token));
         }
-        
-        result
+
+        let mut rungs: Vec<LadderRung> = rungs
+            .into_iter()
+            .map(|(multiple, fraction)| LadderRung { multiple, fraction, fired: false })
+            .collect();
+        rungs.sort_by(|a, b| a.multiple.total_cmp(&b.multiple));
+
+        self.ladders.lock().insert(*token, SellLadder { original_amount, rungs });
+        self.save_ladders()
     }
 
-    // Error Analysis
-    async fn log_trade_error(&self, error: &Error, tx: &Transaction) -> Result<()> {
-        let error_log = ErrorLog {
+    // Sells every rung the price has crossed; returns true if anything fired
+    async fn fire_ladder_rungs(&self, position: &Position) -> Result<bool> {
+        if position.entry_price.is_zero() {
+            return Ok(false);
+        }
+
+        let due = match self.ladders.lock().get(&position.token) {
+            Some(ladder) => ladder.due_sells(position.entry_price, position.current_price, position.amount),
+            None => return Ok(false),
+        };
+        if due.is_empty() {
+            return Ok(false);
+        }
+
+        for &(multiple, sell_amount) in &due {
+            // Nothing left for this rung once earlier ones sold the rest
+            if sell_amount > 0 {
+                let result = self.execute_sell(&position.token, sell_amount).await;
+                let sold = result.is_ok();
+                if sold {
+                    self.book_realized_pnl(position, sell_amount);
+                }
+                self.record_sell(position, sell_amount, result, format!("ladder-{}x", multiple));
+                if !sold {
+                    continue;
+                }
+            }
+            // Mark as fired before the next rung so a restart can't sell it twice
+            self.mark_rung_fired(&position.token, multiple)?;
+        }
+
+        self.untrack_if_closed(&position.token).await?;
+        Ok(true)
+    }
+
+    fn mark_rung_fired(&self, token: &Pubkey, multiple: f64) -> Result<()> {
+        {
+            let mut ladders = self.ladders.lock();
+            let done = ladders.get_mut(token).is_some_and(|ladder| ladder.mark_fired(multiple));
+            if done {
+                ladders.remove(token);
+            }
+        }
+        self.save_ladders()
+    }
+
+    fn save_trailing_stops(&self) -> Result<()> {
+        write_trailing_stops(&self.trailing_stop_state_path, &self.trailing_stops.lock())
+    }
+
+    fn save_ladders(&self) -> Result<()> {
+        let ladders: HashMap<String, SellLadder> = self.ladders
+            .lock()
+            .iter()
+            .map(|(token, ladder)| (token.to_string(), ladder.clone()))
+            .collect();
+        fs::write(&self.ladder_state_path, serde_json::to_vec_pretty(&ladders)?)?;
+        Ok(())
+    }
+
+    pub(crate) fn load_ladders(path: &str) -> Result<HashMap<Pubkey, SellLadder>> {
+        if !Path::new(path).exists() {
+            return Ok(HashMap::new());
+        }
+        let ladders: HashMap<String, SellLadder> = serde_json::from_slice(&fs::read(path)?)?;
+        ladders
+            .into_iter()
+            .map(|(token, ladder)| Ok((Pubkey::from_str(&token)?, ladder)))
+            .collect()
+    }
+
+    fn record_sell(&self, position: &Position, amount: u64, result: Result<Signature>, reason: String) {
+        if let Ok(signature) = &result {
+            let kind = if reason == "stop-loss" { EventKind::StopLoss } else { EventKind::Sell };
+            let pnl = position.pnl * amount as f64 / position.amount.max(1) as f64;
+            self.notify(TradeEvent::new(kind).token(position.token).amount(amount).pnl(pnl).signature(signature));
+            let pnl_sol = self.quote_pnl_in_sol(pnl).unwrap_or_else(|e| {
+                warn!(error = %e, pnl, "Failed to price realized PnL in SOL, counting it as is");
+                pnl
+            });
+            if self.daily_limits.record_realized(pnl_sol, unix_timestamp()) {
+                warn!(pnl = pnl, "Daily loss cap reached, halting trading until 00:00 UTC");
+                self.notify(TradeEvent::new(EventKind::DailyLimitHit).pnl(pnl));
+            }
+        }
+        self.trade_history.lock().push(TradeHistory {
+            signature: result.as_ref().map(|s| s.to_string()).unwrap_or_default(),
+            token: position.token,
+            trade_type: TradeType::SwapTokensForExactSOL,
+            amount,
+            price: position.current_price,
+            success: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
             timestamp: Instant::now(),
-            error_type: error.to_string(),
-            transaction: tx.clone(),
-            context: self.get_market_context().await?,
+            recorded_at: unix_timestamp(),
+            reason: Some(reason),
+        });
+    }
+
+    // Stop-loss / take-profit loop; runs until `shutdown` is cancelled. A failed
+    // pass is logged and the next one runs on schedule.
+    pub async fn monitor_positions(&self, shutdown: CancellationToken) -> Result<()> {
+        loop {
+            if let Err(e) = self.fire_dca_tranches(unix_timestamp()).await {
+                error!(error = %e, "Failed to run DCA tranches");
+            }
+
+            match self.get_active_positions().await {
+                Ok(positions) => {
+                    for position in positions {
+                        if let Err(e) = self.check_position_exits(&position).await {
+                            error!(token = %position.token, error = %e, "Failed to check position exits");
+                        }
+                    }
+                }
+                Err(e) => error!(error = %e, "Failed to load active positions"),
+            }
+
+            if let Err(e) = self.save_positions(&self.positions_path).await {
+                error!(error = %e, path = %self.positions_path, "Failed to save positions");
+            }
+
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(self.position_poll_interval) => {}
+            }
+        }
+    }
+
+    // Ladder rungs first, then the trailing stop, take-profit and stop-loss
+    async fn check_position_exits(&self, position: &Position) -> Result<()> {
+        if self.fire_ladder_rungs(position).await? {
+            return Ok(());
+        }
+
+        let (trailed, raised) = match self.trailing_stops.lock().get_mut(&position.token) {
+            Some(stop) => {
+                let high_before = stop.high_watermark;
+                let trailed = stop.update(position.entry_price, position.current_price);
+                (trailed, stop.high_watermark != high_before)
+            }
+            None => (false, false),
+        };
+        // A restart picks the trail up from the highest price seen, not from scratch
+        if raised {
+            self.save_trailing_stops()?;
+        }
+
+        let has_exit_plan = self.ladders.lock().contains_key(&position.token)
+            || self.trailing_stops.lock().contains_key(&position.token);
+        let Some(reason) = exit_reason(position, trailed, has_exit_plan, self.profit_target, self.stop_loss) else {
+            return Ok(());
         };
-        
-        self.error_logs.push(error_log);
-        self.analyze_error_patterns()?;
-        
+
+        let result = self.sell_all(&position.token).await;
+        // The position is gone, so its exit plan goes with it even if dust remains
+        let (mut dropped_stop, mut dropped_ladder) = (false, false);
+        if result.is_ok() {
+            dropped_stop = self.trailing_stops.lock().remove(&position.token).is_some();
+            dropped_ladder = self.ladders.lock().remove(&position.token).is_some();
+        }
+        self.record_sell(position, position.amount, result, reason.to_string());
+        if dropped_stop {
+            self.save_trailing_stops()?;
+        }
+        if dropped_ladder {
+            self.save_ladders()?;
+        }
         Ok(())
     }
 
     // Trade History Management
     pub fn get_trade_history(&self) -> Vec<TradeHistory> {
-        self.trade_history.clone()
+        self.trade_history.lock().clone()
+    }
+
+    // Header written by hand so an empty history still gets one
+    pub fn export_trade_history_csv(&self, path: &str) -> Result<()> {
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_path(path)?;
+        writer.write_record([
+            "signature", "token", "type", "amount", "price", "success", "error", "timestamp", "reason",
+        ])?;
+
+        for trade in self.trade_history.lock().iter() {
+            writer.serialize(TradeRecord::from(trade))?;
+        }
+
+        writer.flush()?;
+        Ok(())
     }
 
     pub fn get_failed_trades(&self) -> Vec<TradeHistory> {
-        self.trade_history.iter()
+        self.trade_history.lock().iter()
             .filter(|t| !t.success)
             .cloned()
             .collect()
     }
-}
\ No newline at end of file
+}
+
+pub(crate) fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    fn price(sol: f64) -> Price {
+        Price::from_raw((sol * PRICE_SCALE as f64) as u128)
+    }
+
+    fn position(entry: f64, current: f64) -> Position {
+        Position {
+            token: Pubkey::new_unique(),
+            amount: 1_000,
+            ui_amount: 1.0,
+            entry_price: price(entry),
+            current_price: price(current),
+            pnl: 0.0,
+            realized_pnl: 0.0,
+            high_watermark: None,
+            timestamp: Instant::now(),
+        }
+    }
+
+    fn persisted(token: Pubkey, amount: u64) -> PersistedPosition {
+        PersistedPosition {
+            token: token.to_string(),
+            amount,
+            entry_price: price(0.002),
+            entry_time: 1_700_000_000,
+            ladder: None,
+            dca: None,
+        }
+    }
+
+    #[test]
+    fn fixed_point_prices_match_f64_where_it_is_exact_and_stay_exact_where_it_is_not() {
+        // Ratios f64 represents exactly agree to the last digit
+        let even = Price::from_reserves(1_000_000_000, 2_000_000_000).unwrap();
+        assert_eq!(even.raw(), 2 * PRICE_SCALE);
+        assert_eq!(even.to_f64(), 2_000_000_000f64 / 1_000_000_000f64);
+
+        // A third truncates to nine places, within one unit of the f64
+        let third = Price::from_reserves(3_000_000, 1_000_000).unwrap();
+        assert_eq!(third.to_string(), "0.333333333");
+        assert!((third.to_f64() - 1_000_000f64 / 3_000_000f64).abs() < 1.0 / PRICE_SCALE as f64);
+
+        // A memecoin-sized base reserve keeps its digits
+        let tiny = Price::from_reserves(1_000_000_000_000_000, 2_500_000_000).unwrap();
+        assert_eq!(tiny.to_string(), "0.000002500");
+
+        // Reserves past 2^53 collapse to the same f64, so the naive price
+        // loses the difference the fixed-point one keeps
+        let (base, quote) = (u64::MAX, u64::MAX - 1);
+        assert_eq!(quote as f64 / base as f64, 1.0);
+        assert_eq!(Price::from_reserves(base, quote).unwrap().raw(), PRICE_SCALE - 1);
+
+        // Sums are exact: 0.1 + 0.2 is 0.3 in fixed point but not in f64
+        let tenth = |quote| Price::from_reserves(10, quote).unwrap().raw();
+        assert_eq!(tenth(1) + tenth(2), tenth(3));
+        assert_ne!(0.1f64 + 0.2f64, 0.3f64);
+
+        // Scaling truncates the same way every time: three thirds fall one unit short
+        assert_eq!(third.mul_bps(30_000).raw(), 999_999_999);
+        assert_eq!(Price::from_reserves(0, 1), None);
+    }
+
+    #[test]
+    fn latency_ring_reports_nearest_rank_percentiles() {
+        let ms = Duration::from_millis;
+        assert_eq!(LatencyRing::default().stats(), LatencyStats::default());
+
+        let mut single = LatencyRing::default();
+        single.record(ms(7));
+        assert_eq!(single.stats(), LatencyStats { p50: ms(7), p90: ms(7), p99: ms(7), count: 1 });
+
+        // 1..=100ms recorded out of order
+        let mut ring = LatencyRing::default();
+        for i in 0..100 {
+            ring.record(ms(i * 37 % 100 + 1));
+        }
+        assert_eq!(ring.stats(), LatencyStats { p50: ms(50), p90: ms(90), p99: ms(99), count: 100 });
+    }
+
+    #[test]
+    fn latency_ring_overwrites_its_oldest_samples_once_full() {
+        let mut ring = LatencyRing::default();
+        for _ in 0..LATENCY_SAMPLES {
+            ring.record(Duration::from_secs(1));
+        }
+        // Half the slow samples are replaced by fast ones; the count stays capped
+        for _ in 0..LATENCY_SAMPLES / 2 {
+            ring.record(Duration::from_millis(1));
+        }
+        let stats = ring.stats();
+        assert_eq!(stats.count, LATENCY_SAMPLES);
+        assert_eq!(stats.p50, Duration::from_millis(1));
+        assert_eq!(stats.p90, Duration::from_secs(1));
+
+        // A full lap more leaves only the fast samples
+        for _ in 0..LATENCY_SAMPLES {
+            ring.record(Duration::from_millis(1));
+        }
+        assert_eq!(ring.stats().p99, Duration::from_millis(1));
+    }
+
+    #[test]
+    fn lamports_display_as_sol_to_four_places() {
+        assert_eq!(Lamports(0).to_string(), "0.0000 SOL");
+        assert_eq!(Lamports(1).to_string(), "0.0000 SOL");
+        assert_eq!(Lamports(50_000).to_string(), "0.0001 SOL");
+        assert_eq!(Lamports(123_456_789).to_string(), "0.1235 SOL");
+        assert_eq!(Lamports(LAMPORTS_PER_SOL).to_string(), "1.0000 SOL");
+        assert_eq!(Lamports(1_500 * LAMPORTS_PER_SOL).to_string(), "1500.0000 SOL");
+        assert_eq!(Lamports(u64::MAX).to_string(), "18446744073.7096 SOL");
+    }
+
+    #[test]
+    fn positions_round_trip_through_disk() {
+        let path = std::env::temp_dir().join(format!("positions-{}.json", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        assert!(read_positions(path).unwrap().is_empty());
+
+        let token = Pubkey::new_unique();
+        write_positions(path, &[persisted(token, 5_000)]).unwrap();
+        let loaded = read_positions(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].token, token.to_string());
+        assert_eq!(loaded[0].amount, 5_000);
+        assert_eq!(loaded[0].entry_price, price(0.002));
+        assert_eq!(loaded[0].entry_time, 1_700_000_000);
+    }
+
+    #[test]
+    fn reconcile_drops_positions_with_no_balance() {
+        let (held, sold, resized) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let balances = HashMap::from([(held, 5_000), (sold, 0), (resized, 2_000)]);
+
+        let (kept, dropped) = reconcile_positions(
+            vec![persisted(held, 5_000), persisted(sold, 1_000), persisted(resized, 4_000)],
+            &balances,
+        )
+        .unwrap();
+
+        assert_eq!(dropped, vec![sold]);
+        let kept: HashMap<Pubkey, u64> = kept.into_iter().map(|(token, p)| (token, p.amount)).collect();
+        assert_eq!(kept, HashMap::from([(held, 5_000), (resized, 2_000)]));
+    }
+
+    #[test]
+    fn converts_quote_amounts_to_base_units() {
+        assert_eq!(ui_to_base_units(1.5, 6), 1_500_000);
+        assert_eq!(ui_to_base_units(1.5, 9), 1_500_000_000);
+        assert_eq!(ui_to_base_units(-1.0, 6), 0);
+    }
+
+    #[test]
+    fn price_feed_crosses_take_profit_and_stop_loss() {
+        // 2x take-profit, 0.5x stop-loss
+        let feed = [(1.0, None), (1.5, None), (2.0, Some("take-profit")), (0.8, None), (0.5, Some("stop-loss")), (0.3, Some("stop-loss"))];
+        for (current, expected) in feed {
+            assert_eq!(exit_reason(&position(1.0, current), false, false, 2.0, 0.5), expected, "price {}", current);
+        }
+    }
+
+    #[test]
+    fn exit_plan_replaces_take_profit_but_not_stop_loss() {
+        assert_eq!(exit_reason(&position(1.0, 3.0), false, true, 2.0, 0.5), None);
+        assert_eq!(exit_reason(&position(1.0, 0.4), false, true, 2.0, 0.5), Some("stop-loss"));
+        assert_eq!(exit_reason(&position(1.0, 1.2), true, true, 2.0, 0.5), Some("trailing-stop"));
+    }
+
+    fn ladder(rungs: &[(f64, f64)]) -> SellLadder {
+        SellLadder {
+            original_amount: 1_000,
+            rungs: rungs
+                .iter()
+                .map(|&(multiple, fraction)| LadderRung { multiple, fraction, fired: false })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn ladder_fires_rungs_as_the_price_climbs() {
+        let mut ladder = ladder(&[(2.0, 0.25), (3.0, 0.5), (4.0, 0.5)]);
+        let mut held = 1_000;
+        let feed = [
+            (1.5, vec![]),
+            (2.5, vec![(2.0, 250)]),
+            (2.8, vec![]),
+            // Gaps through two rungs; the last only gets what the one before left
+            (4.5, vec![(3.0, 500), (4.0, 250)]),
+            (5.0, vec![]),
+        ];
+
+        for (current, expected) in feed {
+            let due = ladder.due_sells(price(1.0), price(current), held);
+            assert_eq!(due, expected, "price {}", current);
+            for (multiple, amount) in due {
+                held -= amount;
+                ladder.mark_fired(multiple);
+            }
+        }
+        assert_eq!(held, 0);
+        assert!(ladder.rungs.iter().all(|rung| rung.fired));
+    }
+
+    #[test]
+    fn ladder_rungs_share_the_remaining_balance() {
+        let ladder = ladder(&[(2.0, 0.5), (3.0, 0.5), (4.0, 0.5)]);
+        // Every rung due at once against a position already partly sold elsewhere
+        assert_eq!(
+            ladder.due_sells(price(1.0), price(4.0), 800),
+            vec![(2.0, 500), (3.0, 300), (4.0, 0)]
+        );
+    }
+
+    #[test]
+    fn ladder_is_done_once_every_rung_fired() {
+        let mut ladder = ladder(&[(2.0, 0.5), (3.0, 0.5)]);
+        assert!(!ladder.mark_fired(2.0));
+        assert!(ladder.mark_fired(3.0));
+    }
+
+    #[test]
+    fn trailing_stops_round_trip_through_disk() {
+        let path = std::env::temp_dir().join(format!("trailing-stops-{}.json", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        assert!(read_trailing_stops(path).unwrap().is_empty());
+
+        // Raised past entry, so the watermark is what a restart has to keep
+        let mut stop = TrailingStop { trail_percent: 20.0, high_watermark: None };
+        stop.update(price(1.0), price(1.5));
+        let token = Pubkey::new_unique();
+        write_trailing_stops(path, &HashMap::from([(token, stop.clone())])).unwrap();
+        let mut loaded = read_trailing_stops(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, HashMap::from([(token, stop)]));
+        let restored = loaded.get_mut(&token).unwrap();
+        assert!(!restored.update(price(1.0), price(1.3)));
+        assert!(restored.update(price(1.0), price(1.2)));
+    }
+
+    #[test]
+    fn dca_tranches_follow_a_mock_clock() {
+        const START: i64 = 1_000;
+        let mut schedule = DcaSchedule::new(1_000_000_001, 3, 60, START).unwrap();
+        let mut fills = Vec::new();
+        let mut failed_once = false;
+
+        // Poll every 15s for ten minutes; the second tranche fails on its first try
+        for now in (START..=START + 600).step_by(15) {
+            let Some(amount) = schedule.due(now) else { continue };
+            if fills.len() == 1 && !failed_once {
+                failed_once = true;
+                schedule.defer(now);
+                continue;
+            }
+            schedule.record_fill(amount, now);
+            fills.push((now, amount));
+
+            // Restart after the second fill; the saved schedule resumes where it stopped
+            if fills.len() == 2 {
+                let saved = serde_json::to_string(&schedule).unwrap();
+                schedule = serde_json::from_str(&saved).unwrap();
+            }
+        }
+
+        // The last tranche picks up the rounding remainder
+        assert_eq!(fills, vec![(1_000, 333_333_333), (1_120, 333_333_333), (1_180, 333_333_335)]);
+        assert!(schedule.is_complete());
+        assert_eq!(schedule.spent, 1_000_000_001);
+        assert_eq!(schedule.due(START + 10_000), None);
+    }
+
+    #[test]
+    fn dca_rejects_tranches_smaller_than_a_lamport() {
+        assert!(DcaSchedule::new(2, 3, 60, 0).is_err());
+        assert!(DcaSchedule::new(100, 0, 60, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn trade_history_csv_reads_back_as_written() {
+        let engine = crate::trading::tests::replay_engine(crate::config::TradingConfig::default(), &[]);
+        let trade = |signature: &str, trade_type, success, error: Option<&str>, reason: Option<&str>| TradeHistory {
+            signature: signature.to_string(),
+            token: Pubkey::new_unique(),
+            trade_type,
+            amount: 1_500_000,
+            price: price(0.000123456),
+            success,
+            error: error.map(str::to_string),
+            timestamp: Instant::now(),
+            recorded_at: 1_700_000_000,
+            reason: reason.map(str::to_string),
+        };
+        let trades = [
+            trade("5sig", TradeType::SwapExactSOLForTokens, true, None, None),
+            trade("", TradeType::SwapTokensForExactSOL, false, Some("Slippage, \"exceeded\"\nretrying"), Some("stop-loss")),
+            trade("7sig", TradeType::SwapTokensForExactSOL, true, None, Some("ladder-2x")),
+        ];
+        engine.trade_history.lock().extend(trades.iter().cloned());
+
+        let path = std::env::temp_dir().join(format!("history-{}.csv", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        engine.export_trade_history_csv(path).unwrap();
+
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["signature", "token", "type", "amount", "price", "success", "error", "timestamp", "reason"],
+        );
+        let read: Vec<TradeRecord> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, trades.iter().map(TradeRecord::from).collect::<Vec<_>>());
+        assert_eq!(read[0].price, "0.000123456");
+    }
+
+    // Engine whose getAccountInfo finds every account or none, recording what was looked up
+    fn engine_with_accounts(exist: bool, lookups: Arc<Mutex<Vec<String>>>) -> TradingEngine {
+        use crate::trading::tests::{scripted_engine, some_account, with_context};
+        scripted_engine(crate::config::TradingConfig::default(), move |method, params| match method {
+            "getAccountInfo" => {
+                lookups.lock().push(params[0].as_str().unwrap().to_string());
+                Some(with_context(if exist { some_account() } else { serde_json::Value::Null }))
+            }
+            _ => None,
+        })
+    }
+
+    #[tokio::test]
+    async fn missing_ata_gets_a_create_before_the_swap() {
+        let mint = Pubkey::new_unique();
+        let lookups = Arc::new(Mutex::new(Vec::new()));
+        let engine = engine_with_accounts(false, lookups.clone());
+        let owner = engine.payer.pubkey();
+        let swap_ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[9], Vec::new());
+
+        let create = engine.ensure_ata_ix(&owner, &mint).await.unwrap().unwrap();
+        assert_eq!(create, create_associated_token_account_idempotent(&owner, &owner, &mint, &spl_token::id()));
+
+        let ixs = engine.with_swap_accounts(&Pubkey::new_unique(), 1_000, &[mint], vec![swap_ix.clone()]).await.unwrap();
+        assert_eq!(ixs, vec![create, swap_ix]);
+        let ata = get_associated_token_address(&owner, &mint).to_string();
+        assert_eq!(*lookups.lock(), vec![ata.clone(), ata]);
+    }
+
+    #[tokio::test]
+    async fn existing_ata_leaves_the_swap_alone() {
+        let mint = Pubkey::new_unique();
+        let lookups = Arc::new(Mutex::new(Vec::new()));
+        let engine = engine_with_accounts(true, lookups.clone());
+        let owner = engine.payer.pubkey();
+        let swap_ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[9], Vec::new());
+
+        assert!(engine.ensure_ata_ix(&owner, &mint).await.unwrap().is_none());
+        let ixs = engine.with_swap_accounts(&Pubkey::new_unique(), 1_000, &[mint], vec![swap_ix.clone()]).await.unwrap();
+        assert_eq!(ixs, vec![swap_ix]);
+        let ata = get_associated_token_address(&owner, &mint).to_string();
+        assert_eq!(*lookups.lock(), vec![ata.clone(), ata]);
+    }
+
+    #[test]
+    fn wrap_funds_the_wsol_account_then_syncs_it() {
+        let owner = Pubkey::new_unique();
+        let wsol = get_associated_token_address(&owner, &native_mint::ID);
+        let ixs = wrap_sol_ixs(&owner, 5_000).unwrap();
+
+        assert_eq!(
+            ixs,
+            vec![
+                create_associated_token_account_idempotent(&owner, &owner, &native_mint::ID, &spl_token::id()),
+                system_instruction::transfer(&owner, &wsol, 5_000),
+                spl_token::instruction::sync_native(&spl_token::id(), &wsol).unwrap(),
+            ]
+        );
+
+        let close = unwrap_sol_ix(&owner).unwrap();
+        assert_eq!(close.program_id, spl_token::id());
+        assert_eq!(close.accounts.iter().map(|meta| meta.pubkey).collect::<Vec<_>>(), vec![wsol, owner, owner]);
+        assert_eq!(close, spl_token::instruction::close_account(&spl_token::id(), &wsol, &owner, &owner, &[]).unwrap());
+    }
+
+    #[tokio::test]
+    async fn sol_buys_wrap_before_and_close_after_the_swap() {
+        let engine = engine_with_accounts(true, Arc::new(Mutex::new(Vec::new())));
+        let owner = engine.payer.pubkey();
+        let swap_ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[9], Vec::new());
+
+        let ixs = engine
+            .with_swap_accounts(&native_mint::ID, 5_000, &[Pubkey::new_unique()], vec![swap_ix.clone()])
+            .await
+            .unwrap();
+        let mut expected = wrap_sol_ixs(&owner, 5_000).unwrap();
+        expected.push(swap_ix);
+        expected.push(unwrap_sol_ix(&owner).unwrap());
+        assert_eq!(ixs, expected);
+    }
+
+    #[tokio::test]
+    async fn sol_sells_close_the_wsol_account_after_the_swap() {
+        let engine = engine_with_accounts(true, Arc::new(Mutex::new(Vec::new())));
+        let owner = engine.payer.pubkey();
+        let swap_ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[9], Vec::new());
+
+        let ixs = engine
+            .with_swap_accounts(&Pubkey::new_unique(), 5_000, &[native_mint::ID], vec![swap_ix.clone()])
+            .await
+            .unwrap();
+        assert_eq!(ixs, vec![swap_ix, unwrap_sol_ix(&owner).unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn positions_read_balances_and_prices_from_rpc() {
+        use crate::trading::tests::{scripted_engine, some_account, token_amount, with_context, MockVenue};
+        let (held, never_bought) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let held_ata = Arc::new(Mutex::new(None::<String>));
+        let ata = held_ata.clone();
+        let mut engine = scripted_engine(crate::config::TradingConfig::default(), move |method, params| match method {
+            // Only the held token has an ATA
+            "getAccountInfo" => {
+                let exists = ata.lock().as_deref() == params[0].as_str();
+                Some(with_context(if exists { some_account() } else { serde_json::Value::Null }))
+            }
+            "getTokenAccountBalance" => Some(with_context(token_amount(2_500_000, 6))),
+            "getTokenSupply" => Some(with_context(token_amount(1_000_000_000_000_000, 6))),
+            _ => None,
+        });
+        *held_ata.lock() = Some(get_associated_token_address(&engine.payer.pubkey(), &held).to_string());
+        // One whole token (1_000_000 base units) sells for 0.002 SOL
+        engine.venues = vec![MockVenue::boxed("mock", 999_000_000, 2_000_000_000)];
+
+        assert_eq!(engine.get_token_balance(&held).await.unwrap(), 2_500_000);
+        assert_eq!(engine.get_token_balance(&never_bought).await.unwrap(), 0);
+        assert_eq!(engine.get_token_price(&held).await.unwrap(), price(0.002));
+
+        // Entry is the amount-weighted average of the successful buys
+        let buy = |amount, sol: f64, success| TradeHistory {
+            signature: String::new(),
+            token: held,
+            trade_type: TradeType::SwapExactSOLForTokens,
+            amount,
+            price: price(sol),
+            success,
+            error: None,
+            timestamp: Instant::now(),
+            recorded_at: 1_700_000_000,
+            reason: None,
+        };
+        engine.trade_history.lock().extend([
+            buy(1_500_000, 0.0008, true),
+            buy(1_000_000, 0.0013, true),
+            buy(9_000_000, 0.01, false),
+        ]);
+        assert_eq!(engine.get_entry_price(&held).unwrap(), price(0.001));
+        assert_eq!(engine.get_entry_price(&never_bought).unwrap(), Price::ZERO);
+
+        engine.track_token(held);
+        engine.track_token(never_bought);
+        let positions = engine.get_active_positions().await.unwrap();
+        assert_eq!(positions.len(), 1);
+        let position = &positions[0];
+        assert_eq!((position.token, position.amount, position.ui_amount), (held, 2_500_000, 2.5));
+        assert_eq!((position.entry_price, position.current_price), (price(0.001), price(0.002)));
+        assert!((position.pnl - 0.0025).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn buys_start_tracking_and_only_a_full_exit_stops_it() {
+        use {
+            crate::config::{PriorityFeeStrategy, TradingConfig},
+            crate::trading::tests::{scripted_engine, sent_signature, some_account, token_amount, with_context, MockVenue},
+            solana_sdk::hash::Hash,
+            std::sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        let token = Pubkey::new_unique();
+        let config = TradingConfig {
+            priority_fee_strategy: PriorityFeeStrategy::Fixed { fee: 5_000 },
+            ..TradingConfig::default()
+        };
+        // What we hold after each send: nothing, bought 2.0, sold half, sold the rest
+        let sends = Arc::new(AtomicUsize::new(0));
+        let sent = sends.clone();
+        let mut engine = scripted_engine(config, move |method, params| match method {
+            "getAccountInfo" => Some(with_context(some_account())),
+            "getTokenAccountBalance" => {
+                let held = [0, 2_000_000, 1_000_000, 0][sent.load(Ordering::SeqCst).min(3)];
+                Some(with_context(token_amount(held, 6)))
+            }
+            "getTokenSupply" => Some(with_context(token_amount(1_000_000_000_000, 6))),
+            "getLatestBlockhash" => Some(with_context(serde_json::json!({
+                "blockhash": Hash::new_unique().to_string(),
+                "lastValidBlockHeight": 1_000,
+            }))),
+            "sendTransaction" => {
+                sent.fetch_add(1, Ordering::SeqCst);
+                Some(sent_signature(params))
+            }
+            _ => None,
+        });
+        engine.venues = vec![MockVenue::boxed("mock", 1_000_000_000_000, 1_000_000_000_000)];
+        // Already passed the honeypot check
+        engine.sell_checks.insert(token, (true, Instant::now()));
+
+        assert!(engine.tracked_tokens().is_empty());
+        engine.manage_position(&token, PositionAction::Buy(10_000_000)).await.unwrap();
+        assert_eq!(engine.tracked_tokens(), [token]);
+
+        engine.manage_position(&token, PositionAction::TrailingStop { trail_percent: 10.0 }).await.unwrap();
+        engine.manage_position(&token, PositionAction::SellPartial(0.5)).await.unwrap();
+        assert_eq!(engine.tracked_tokens(), [token], "half the position is still open");
+        assert!(engine.trailing_stops.lock().contains_key(&token));
+
+        engine.manage_position(&token, PositionAction::SellAll).await.unwrap();
+        assert_eq!(sends.load(Ordering::SeqCst), 3);
+        assert!(engine.tracked_tokens().is_empty());
+        assert!(!engine.trailing_stops.lock().contains_key(&token), "the exit plan goes with the position");
+    }
+}
@@ -4,14 +4,91 @@ use {
     std::collections::HashMap,
     std::time::{SystemTime, UNIX_EPOCH},
     anyhow::Result,
-    raydium_contract_instructions::amm_instruction,
+    async_trait::async_trait,
     serde::{Deserialize, Serialize},
+    crate::{config::TradingConfig, error::BotError, monitoring::Signal},
 };
 
-#[derive(Debug)]
-pub enum Signal {
-    BuySignal { token: Pubkey, confidence: f64 },
-    SellSignal { token: Pubkey, confidence: f64 }
+const DEFAULT_VOLUME_EMA_ALPHA: f64 = 0.2;
+
+// Point-in-time market data: price in SOL, volume and liquidity in USD
+#[derive(Debug, Clone, Copy)]
+pub struct MarketSnapshot {
+    pub price: f64,
+    pub volume_24h: f64,
+    pub liquidity: f64,
+}
+
+#[async_trait]
+pub trait MetricsSource: Send + Sync {
+    async fn fetch(&self, token: &Pubkey) -> Result<MarketSnapshot>;
+}
+
+// DexScreener-compatible token endpoint; uses the deepest pair for the token
+pub struct PriceApiSource {
+    http: reqwest::Client,
+    api_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceApiResponse {
+    pairs: Option<Vec<PriceApiPair>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PriceApiPair {
+    price_native: String,
+    volume: Option<PriceApiVolume>,
+    liquidity: Option<PriceApiLiquidity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceApiVolume {
+    h24: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceApiLiquidity {
+    usd: f64,
+}
+
+impl PriceApiSource {
+    pub fn new(api_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl MetricsSource for PriceApiSource {
+    async fn fetch(&self, token: &Pubkey) -> Result<MarketSnapshot> {
+        let response: PriceApiResponse = self.http
+            .get(format!("{}/{}", self.api_url, token))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let pair = response.pairs
+            .unwrap_or_default()
+            .into_iter()
+            .max_by(|a, b| {
+                let a = a.liquidity.as_ref().map_or(0.0, |l| l.usd);
+                let b = b.liquidity.as_ref().map_or(0.0, |l| l.usd);
+                a.total_cmp(&b)
+            })
+            .ok_or_else(|| anyhow::anyhow!("No market data for {}", token))?;
+
+        Ok(MarketSnapshot {
+            price: pair.price_native.parse()?,
+            volume_24h: pair.volume.map_or(0.0, |v| v.h24),
+            liquidity: pair.liquidity.map_or(0.0, |l| l.usd),
+        })
+    }
 }
 
 pub struct VolumeMonitor {
@@ -19,7 +96,12 @@ pub struct VolumeMonitor {
     min_volume: u64,
     tracked_tokens: HashMap<Pubkey, TokenMetrics>,
     volume_threshold: f64,
-    price_threshold: f64
+    price_threshold: f64,
+    volume_ema_alpha: f64,
+    signal_cooldown_secs: i64,
+    confidence_weights: ConfidenceWeights,
+    last_signal: HashMap<Pubkey, i64>, // Unix time of each token's last signal
+    metrics_source: Box<dyn MetricsSource>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,10 +112,24 @@ pub struct TokenMetrics {
     last_update: i64,
     price_history: Vec<(i64, f64)>, // timestamp, price
     volume_history: Vec<(i64, f64)>, // timestamp, volume
+    #[serde(default)]
+    volume_ema: f64,
+    #[serde(default)]
+    volume_baseline: f64, // volume_ema before the latest sample, 0 until there is one
+    #[serde(default = "default_volume_ema_alpha")]
+    ema_alpha: f64,       // Weight of each new sample, 0-1
+}
+
+fn default_volume_ema_alpha() -> f64 {
+    DEFAULT_VOLUME_EMA_ALPHA
 }
 
 impl TokenMetrics {
     pub fn new() -> Self {
+        Self::with_ema_alpha(DEFAULT_VOLUME_EMA_ALPHA)
+    }
+
+    pub fn with_ema_alpha(ema_alpha: f64) -> Self {
         Self {
             volume_24h: 0.0,
             price: 0.0,
@@ -44,6 +140,9 @@ impl TokenMetrics {
                 .as_secs() as i64,
             price_history: Vec::with_capacity(24), // 24 hour history
             volume_history: Vec::with_capacity(24),
+            volume_ema: 0.0,
+            volume_baseline: 0.0,
+            ema_alpha: ema_alpha.clamp(0.0, 1.0),
         }
     }
 
@@ -52,23 +151,81 @@ impl TokenMetrics {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
-            
+        self.update_metrics_at(now, price, volume);
+    }
+
+    // Same as update_metrics with an explicit unix timestamp, for replaying history
+    pub fn update_metrics_at(&mut self, now: i64, price: f64, volume: f64) {
         self.price = price;
         self.volume_24h = volume;
         self.last_update = now;
         
         self.price_history.push((now, price));
         self.volume_history.push((now, volume));
+        self.update_volume_ema(volume);
         
         // Maintain 24h window
         self.cleanup_history(now - 86400);
     }
 
+    // The first sample seeds the EMA; it has no baseline to spike against
+    fn update_volume_ema(&mut self, volume: f64) {
+        if self.volume_history.len() <= 1 {
+            self.volume_ema = volume;
+            self.volume_baseline = 0.0;
+            return;
+        }
+        self.volume_baseline = self.volume_ema;
+        self.volume_ema = self.ema_alpha * volume + (1.0 - self.ema_alpha) * self.volume_ema;
+    }
+
     fn cleanup_history(&mut self, cutoff: i64) {
         self.price_history.retain(|(ts, _)| *ts > cutoff);
         self.volume_history.retain(|(ts, _)| *ts > cutoff);
     }
 
+    pub fn price(&self) -> f64 {
+        self.price
+    }
+
+    pub fn volume_24h(&self) -> f64 {
+        self.volume_24h
+    }
+
+    pub fn liquidity(&self) -> f64 {
+        self.liquidity
+    }
+
+    pub fn price_history(&self) -> &[(i64, f64)] {
+        &self.price_history
+    }
+
+    pub fn volume_ema(&self) -> f64 {
+        self.volume_ema
+    }
+
+    // Latest volume over the EMA of the samples before it; 2.0 = twice the usual volume
+    pub fn volume_spike_ratio(&self) -> Option<f64> {
+        if self.volume_baseline <= 0.0 {
+            return None;
+        }
+        Some(self.volume_24h / self.volume_baseline)
+    }
+
+    // (volume change, price change) between the two most recent samples
+    pub fn latest_changes(&self) -> Option<(f64, f64)> {
+        let [.., (_, prev_volume), (_, volume)] = self.volume_history.as_slice() else {
+            return None;
+        };
+        let [.., (_, prev_price), (_, price)] = self.price_history.as_slice() else {
+            return None;
+        };
+        if *prev_volume <= 0.0 || *prev_price <= 0.0 {
+            return None;
+        }
+        Some(((volume - prev_volume) / prev_volume, (price - prev_price) / prev_price))
+    }
+
     pub fn calculate_volume_change(&self) -> Option<f64> {
         if self.volume_history.len() < 2 {
             return None;
@@ -82,54 +239,280 @@ impl TokenMetrics {
 }
 
 impl VolumeMonitor {
-    pub fn new(rpc_url: &str, min_volume: u64) -> Self {
+    pub fn new(
+        config: &TradingConfig,
+        min_volume: u64,
+        metrics_source: Box<dyn MetricsSource>,
+    ) -> Self {
         Self {
-            rpc_client: RpcClient::new(rpc_url.to_string()),
+            rpc_client: RpcClient::new(config.rpc_endpoint()),
             min_volume,
             tracked_tokens: HashMap::new(),
-            volume_threshold: 2.0,  // 200% volume increase
-            price_threshold: 0.05   // 5% price movement
+            volume_threshold: config.volume_spike_threshold,
+            price_threshold: config.price_move_threshold,
+            volume_ema_alpha: config.volume_ema_alpha,
+            signal_cooldown_secs: config.signal_cooldown_secs as i64,
+            confidence_weights: ConfidenceWeights::from_config(config),
+            last_signal: HashMap::new(),
+            metrics_source,
         }
     }
 
+    pub fn set_thresholds(&mut self, volume_threshold: f64, price_threshold: f64, cooldown_secs: u64) {
+        self.volume_threshold = volume_threshold;
+        self.price_threshold = price_threshold;
+        self.signal_cooldown_secs = cooldown_secs as i64;
+    }
+
+    pub fn token_metrics(&self, token: &Pubkey) -> Option<&TokenMetrics> {
+        self.tracked_tokens.get(token)
+    }
+
+    fn in_cooldown(&self, token: &Pubkey, now: i64) -> bool {
+        self.last_signal
+            .get(token)
+            .is_some_and(|last| now - last < self.signal_cooldown_secs)
+    }
+
     pub async fn check_token(&mut self, token: Pubkey) -> Result<Option<Signal>> {
         let current_metrics = self.fetch_token_metrics(&token).await?;
-        
-        if let Some(previous_metrics) = self.tracked_tokens.get(&token) {
-            // Volume spike detection
-            let volume_change = (current_metrics.volume_24h - previous_metrics.volume_24h) 
-                                / previous_metrics.volume_24h;
-            
+        let now = current_metrics.last_update;
+        let mut signal = None;
+
+        let spike_ratio = current_metrics.volume_spike_ratio();
+        if let (Some(previous_metrics), Some(spike_ratio)) = (self.tracked_tokens.get(&token), spike_ratio) {
             // Price movement detection
             let price_change = (current_metrics.price - previous_metrics.price) 
                               / previous_metrics.price;
 
-            if volume_change > self.volume_threshold && price_change > self.price_threshold {
-                let confidence = calculate_confidence(volume_change, price_change);
-                return Ok(Some(Signal::BuySignal { token, confidence }));
+            if spike_ratio > self.volume_threshold
+                && price_change > self.price_threshold
+                && !self.in_cooldown(&token, now)
+            {
+                let confidence = calculate_confidence(self.confidence_weights, spike_ratio - 1.0, price_change);
+                signal = Some(Signal::BuySignal { token, confidence });
             }
         }
 
+        if signal.is_some() {
+            self.last_signal.insert(token, now);
+        }
         self.tracked_tokens.insert(token, current_metrics);
-        Ok(None)
+        Ok(signal)
     }
 
+    // Fresh snapshot layered on top of the token's existing history
     async fn fetch_token_metrics(&self, token: &Pubkey) -> Result<TokenMetrics> {
-        // TODO: Implement actual metrics fetching from DEX
-        Ok(TokenMetrics {
-            volume_24h: 0.0,
-            price: 0.0,
-            liquidity: 0.0,
-            last_update: SystemTime::now()
-                .duration_since(UNIX_EPOCH)?
-                .as_secs() as i64,
-            price_history: Vec::with_capacity(24), // 24 hour history
-            volume_history: Vec::with_capacity(24),
-        })
+        let snapshot = self.metrics_source
+            .fetch(token)
+            .await
+            .map_err(|e| BotError::NetworkError(format!("Metrics fetch for {} failed: {}", token, e)))?;
+
+        let mut metrics = self.tracked_tokens
+            .get(token)
+            .cloned()
+            .unwrap_or_else(|| TokenMetrics::with_ema_alpha(self.volume_ema_alpha));
+        metrics.update_metrics(snapshot.price, snapshot.volume_24h);
+        metrics.liquidity = snapshot.liquidity;
+
+        Ok(metrics)
+    }
+}
+
+// How much volume and price each count toward a signal's confidence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceWeights {
+    volume: f64,
+    price: f64,
+}
+
+impl ConfidenceWeights {
+    // Scaled to sum to 1; TradingConfig::validate rejects negative or all-zero weights
+    pub fn new(volume: f64, price: f64) -> Self {
+        let total = volume + price;
+        Self {
+            volume: volume / total,
+            price: price / total,
+        }
     }
+
+    pub fn from_config(config: &TradingConfig) -> Self {
+        Self::new(config.confidence_volume_weight, config.confidence_price_weight)
+    }
+}
+
+pub(crate) fn calculate_confidence(weights: ConfidenceWeights, volume_change: f64, price_change: f64) -> f64 {
+    (volume_change * weights.volume + price_change * weights.price).min(1.0)
 }
 
-fn calculate_confidence(volume_change: f64, price_change: f64) -> f64 {
-    // Simple confidence calculation
-    (volume_change * 0.7 + price_change * 0.3).min(1.0)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use {super::*, parking_lot::Mutex, std::collections::VecDeque};
+
+    // Serves the queued snapshots in order
+    struct ScriptedSource(Mutex<VecDeque<MarketSnapshot>>);
+
+    #[async_trait]
+    impl MetricsSource for ScriptedSource {
+        async fn fetch(&self, _: &Pubkey) -> Result<MarketSnapshot> {
+            self.0.lock().pop_front().ok_or_else(|| anyhow::anyhow!("script exhausted"))
+        }
+    }
+
+    fn snapshot(price: f64, volume_24h: f64) -> MarketSnapshot {
+        MarketSnapshot { price, volume_24h, liquidity: 50_000.0 }
+    }
+
+    // Confidence of the buy signal a 30% volume spike with a 10% price move raises
+    async fn spike_confidence(volume_weight: f64, price_weight: f64) -> f64 {
+        let config = TradingConfig {
+            confidence_volume_weight: volume_weight,
+            confidence_price_weight: price_weight,
+            ..TradingConfig::default()
+        };
+        let source = ScriptedSource(Mutex::new(VecDeque::from([snapshot(1.0, 1_000.0), snapshot(1.1, 1_300.0)])));
+        let mut monitor = VolumeMonitor::new(&config, 0, Box::new(source));
+        monitor.set_thresholds(1.2, 0.05, 0);
+
+        let token = Pubkey::new_unique();
+        assert!(monitor.check_token(token).await.unwrap().is_none());
+        match monitor.check_token(token).await.unwrap() {
+            Some(Signal::BuySignal { confidence, .. }) => confidence,
+            other => panic!("expected a buy signal, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn weights_change_the_emitted_confidence() {
+        let volume_only = spike_confidence(1.0, 0.0).await;
+        let price_only = spike_confidence(0.0, 1.0).await;
+        let blended = spike_confidence(7.0, 3.0).await;
+
+        assert!((volume_only - 0.3).abs() < 1e-9);
+        assert!((price_only - 0.1).abs() < 1e-9);
+        // 7:3 normalizes to the old fixed 0.7/0.3 split
+        assert!((blended - 0.24).abs() < 1e-9);
+    }
+
+    // Monitor fed `snapshots` in order, signaling on a 1.2x spike with a 5% price rise
+    fn scripted_monitor(snapshots: Vec<MarketSnapshot>) -> VolumeMonitor {
+        let source = ScriptedSource(Mutex::new(VecDeque::from(snapshots)));
+        let mut monitor = VolumeMonitor::new(&TradingConfig::default(), 0, Box::new(source));
+        monitor.set_thresholds(1.2, 0.05, 0);
+        monitor
+    }
+
+    #[tokio::test]
+    async fn check_token_needs_both_a_volume_spike_and_a_price_rise() {
+        let token = Pubkey::new_unique();
+        let mut monitor = scripted_monitor(vec![
+            snapshot(1.0, 1_000.0),  // First sample only seeds the history
+            snapshot(1.0, 2_000.0),  // 2x volume, flat price
+            snapshot(1.2, 1_200.0),  // 20% price rise on usual volume
+            snapshot(1.32, 1_800.0), // 1.5x volume and a 10% rise
+        ]);
+
+        for _ in 0..3 {
+            assert!(monitor.check_token(token).await.unwrap().is_none());
+        }
+        let weights = ConfidenceWeights::from_config(&TradingConfig::default());
+        match monitor.check_token(token).await.unwrap() {
+            Some(Signal::BuySignal { token: signaled, confidence }) => {
+                assert_eq!(signaled, token);
+                assert!((confidence - calculate_confidence(weights, 0.5, 0.1)).abs() < 1e-9);
+            }
+            other => panic!("expected a buy signal, got {:?}", other),
+        }
+
+        let metrics = monitor.token_metrics(&token).unwrap();
+        assert_eq!(metrics.price_history().iter().map(|(_, price)| *price).collect::<Vec<_>>(), [1.0, 1.0, 1.2, 1.32]);
+        assert_eq!(metrics.volume_history.len(), 4);
+        assert_eq!((metrics.volume_24h(), metrics.liquidity()), (1_800.0, 50_000.0));
+
+        // A failed fetch is a network error and leaves the history alone
+        let error = monitor.check_token(token).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<BotError>(), Some(BotError::NetworkError(_))));
+        assert_eq!(monitor.token_metrics(&token).unwrap().price_history().len(), 4);
+    }
+
+    // Volume wobbling about 10% around 1000, a 2.6x spike at index 7, then back
+    const NOISY_VOLUMES: [f64; 10] = [1000.0, 1150.0, 880.0, 1100.0, 920.0, 1080.0, 950.0, 2600.0, 1000.0, 1050.0];
+
+    #[test]
+    fn ema_smooths_the_noise_and_flags_only_the_spike() {
+        let mut metrics = TokenMetrics::with_ema_alpha(0.2);
+        let mut emas = Vec::new();
+        let mut ratios = Vec::new();
+        for (i, volume) in NOISY_VOLUMES.into_iter().enumerate() {
+            metrics.update_metrics_at(1_700_000_000 + i as i64 * 60, 1.0, volume);
+            emas.push(metrics.volume_ema());
+            ratios.push(metrics.volume_spike_ratio());
+        }
+
+        // The first sample seeds the average and has nothing to spike against
+        assert_eq!(emas[0], 1000.0);
+        assert_eq!(ratios[0], None);
+
+        // Samples swing 880..1150 before the spike; the average stays within 1000..1030
+        for ema in &emas[..7] {
+            assert!((1000.0..=1030.0).contains(ema), "ema {} not smoothed", ema);
+        }
+
+        let spikes: Vec<usize> = ratios
+            .iter()
+            .enumerate()
+            .filter(|(_, ratio)| ratio.is_some_and(|ratio| ratio > 1.2))
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(spikes, [7]);
+        // Measured against the average before it, not the spike-inflated one after
+        assert!((ratios[7].unwrap() - 2600.0 / 1002.8).abs() < 1e-9);
+        assert!((emas[7] - 1322.24).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn noisy_volume_signals_once_at_the_spike() {
+        let prices = [1.0, 1.02, 0.99, 1.01, 1.0, 1.03, 1.01, 1.12, 1.1, 1.12];
+        let snapshots = prices.iter().zip(NOISY_VOLUMES).map(|(&price, volume)| snapshot(price, volume)).collect();
+        let mut monitor = scripted_monitor(snapshots);
+
+        let token = Pubkey::new_unique();
+        let mut signaled_at = Vec::new();
+        for i in 0..prices.len() {
+            if monitor.check_token(token).await.unwrap().is_some() {
+                signaled_at.push(i);
+            }
+        }
+        assert_eq!(signaled_at, [7]);
+    }
+
+    #[tokio::test]
+    async fn cooldown_holds_back_a_repeat_signal_until_it_passes() {
+        let token = Pubkey::new_unique();
+        // Every sample after the first is a 1.5x spike with a 10% rise
+        let mut monitor = scripted_monitor(vec![
+            snapshot(1.0, 1_000.0),
+            snapshot(1.1, 1_500.0),
+            snapshot(1.21, 1_650.0),
+            snapshot(1.331, 1_815.0),
+        ]);
+        monitor.set_thresholds(1.2, 0.05, 60);
+
+        assert!(monitor.check_token(token).await.unwrap().is_none());
+        assert!(matches!(monitor.check_token(token).await.unwrap(), Some(Signal::BuySignal { .. })));
+        let signaled_at = monitor.last_signal[&token];
+
+        // Same spike again inside the window
+        assert!(monitor.check_token(token).await.unwrap().is_none());
+        assert_eq!(monitor.last_signal[&token], signaled_at);
+
+        // Once the window has passed the spike signals again
+        monitor.last_signal.insert(token, signaled_at - 60);
+        assert!(matches!(monitor.check_token(token).await.unwrap(), Some(Signal::BuySignal { .. })));
+        let signaled_at = monitor.last_signal[&token];
+
+        assert!(monitor.in_cooldown(&token, signaled_at + 59));
+        assert!(!monitor.in_cooldown(&token, signaled_at + 60));
+        assert!(!monitor.in_cooldown(&Pubkey::new_unique(), signaled_at));
+    }
+}
@@ -1,28 +1,78 @@
 use {
-    solana_client::{
-        rpc_client::RpcClient,
-        rpc_config::{RpcTransactionConfig, RpcFilterType},
-        rpc_filter::{Memcmp, MemcmpEncodedBytes},
-        rpc_response::RpcResult,
-    },
+    solana_client::rpc_client::RpcClient,
     solana_sdk::{
         commitment_config::CommitmentConfig,
+        native_token::LAMPORTS_PER_SOL,
+        program_pack::Pack,
         instruction::Instruction,
+        message::VersionedMessage,
         pubkey::Pubkey,
+        signature::Keypair,
         signer::Signer,
-        transaction::Transaction,
     },
-    raydium_contract_instructions::amm_instruction,
-    std::sync::Arc,
-    tokio::sync::broadcast,
+    anyhow::{Result, anyhow},
+    dashmap::{mapref::entry::Entry, DashMap},
+    parking_lot::{Mutex, RwLock},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{HashMap, HashSet, VecDeque},
+        fs,
+        str::FromStr,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    solana_transaction_status::{
+        option_serializer::OptionSerializer,
+        EncodedConfirmedTransactionWithStatusMeta,
+        UiInnerInstructions,
+        UiInstruction,
+        UiLoadedAddresses,
+        UiParsedInstruction,
+        UiTransactionStatusMeta,
+        UiTransactionTokenBalance,
+    },
+    spl_associated_token_account::get_associated_token_address,
+    spl_token::native_mint,
+    tokio::sync::mpsc,
+    tokio_util::sync::CancellationToken,
+    tracing::{info, warn},
+    crate::{
+        monitoring::{subscription::subscribe_wallet, Signal},
+        config::TradingConfig,
+        dex::{raydium::{build_raydium_swap_ix, PoolInfo, PoolKeys, RaydiumDex}, resolve_amm_program_id},
+        error::BotError,
+        rpc_record::RpcMode,
+        trading::{types::{ui_to_base_units, unix_timestamp}, SubmitPolicy, TradingEngine},
+    },
 };
 
-#[derive(Debug)]
+const HISTORY_WINDOW_SECS: i64 = 24 * 60 * 60;
+const MIN_COPY_SUCCESS_RATE: f64 = 0.7;
+const MIN_COPY_TRADES: u32 = 10;
+const MIN_RANKED_TRADES: u32 = 3; // analyze_all leaves out wallets with fewer trades
+const FULL_HOLD_SECS: f64 = 30.0 * 60.0; // Average holds this long or longer earn the full hold-time score
+const COPY_DEDUPE_WINDOW: Duration = Duration::from_secs(5);
+const SUBSCRIPTION_BUFFER: usize = 256;
+
+// Raydium AMM v4 SwapBaseIn: tag byte, then amount_in and min_amount_out as u64 LE
+const RAYDIUM_SWAP_BASE_IN: u8 = 9;
+const RAYDIUM_SWAP_DATA_LEN: usize = 17;
+// 17 accounts, or 18 with target_orders; the user's source, destination and owner are always the last three
+const RAYDIUM_SWAP_MIN_ACCOUNTS: usize = 17;
+
 pub struct WalletTracker {
-    rpc_client: RpcClient,
+    rpc_client: Arc<RpcClient>,
+    ws_url: String,
+    amm_program_id: Pubkey,
     tracked_wallets: HashMap<Pubkey, WalletState>,
     min_transaction_amount: u64,
+    min_activity_score: f64,
+    max_trades_per_hour: f64,
     update_interval: Duration,
+    updates_tx: mpsc::Sender<(Pubkey, Transaction)>,
+    updates_rx: mpsc::Receiver<(Pubkey, Transaction)>, // Decoded trades from the subscriptions
+    shutdown: CancellationToken,
+    subscriptions: HashMap<Pubkey, CancellationToken>, // Per wallet, children of `shutdown`
 }
 
 #[derive(Debug)]
@@ -69,30 +119,98 @@ impl WalletState {
             avg_amount: 0,
             tokens_traded: HashMap::new(),
             preferred_dex: None,
-            avg_hold_time: Duration::from_secs(0),
+            avg_hold_time: Duration::from_secs_f64(self.avg_hold_secs()),
+            last_trade: self.last_transaction.as_ref().map(|tx| tx.timestamp),
         };
 
+        let mut total_amount: u64 = 0;
         for tx in &self.transaction_history {
             if tx.success {
                 pattern.success_count += 1;
             }
-            pattern.avg_amount += tx.amount_in;
+            total_amount = total_amount.saturating_add(tx.amount_in);
             pattern.tokens_traded
                 .entry(tx.input_token)
                 .and_modify(|e| *e += 1)
                 .or_insert(1);
         }
+        pattern.avg_amount = total_amount / pattern.total_trades as u64;
 
         Some(pattern)
     }
 
-    pub fn should_copy_trade(&self, transaction: &Transaction) -> bool {
-        let pattern = self.analyze_pattern()?;
-        
+    // Mean seconds between a buy and the sell that closes it, oldest buy first.
+    // Tokens never sold back don't count.
+    pub fn avg_hold_secs(&self) -> f64 {
+        let mut ordered: Vec<&Transaction> = self.transaction_history.iter().filter(|tx| tx.success).collect();
+        ordered.sort_by_key(|tx| tx.block_time);
+
+        let mut buys: HashMap<Pubkey, VecDeque<i64>> = HashMap::new();
+        let mut holds = Vec::new();
+        for tx in ordered {
+            if tx.input_token == native_mint::ID && tx.output_token != native_mint::ID {
+                buys.entry(tx.output_token).or_default().push_back(tx.block_time);
+            } else if tx.output_token == native_mint::ID {
+                if let Some(bought_at) = buys.get_mut(&tx.input_token).and_then(|open| open.pop_front()) {
+                    holds.push((tx.block_time - bought_at).max(0) as f64);
+                }
+            }
+        }
+
+        if holds.is_empty() {
+            return 0.0;
+        }
+        holds.iter().sum::<f64>() / holds.len() as f64
+    }
+
+    // Trades per hour across the recorded history; anything under an hour counts as one
+    pub fn trades_per_hour(&self) -> f64 {
+        let times = self.transaction_history.iter().map(|tx| tx.block_time);
+        let (Some(first), Some(last)) = (times.clone().min(), times.max()) else {
+            return 0.0;
+        };
+        let hours = ((last - first) as f64 / 3600.0).max(1.0);
+        self.transaction_history.len() as f64 / hours
+    }
+
+    // 0-1, higher looks more like a person trading on conviction. Bots and wash
+    // traders flip within seconds, hammer a handful of tokens and trade nonstop.
+    pub fn activity_score(&self, max_trades_per_hour: f64) -> f64 {
+        let Some(pattern) = self.analyze_pattern() else {
+            return 0.0;
+        };
+
+        let hold = (self.avg_hold_secs() / FULL_HOLD_SECS).min(1.0);
+        let tokens: HashSet<Pubkey> = self.transaction_history
+            .iter()
+            .flat_map(|tx| [tx.input_token, tx.output_token])
+            .filter(|token| *token != native_mint::ID)
+            .collect();
+        // A buy and a sell per token is all an honest round trip needs
+        let diversity = (tokens.len() as f64 * 2.0 / pattern.total_trades as f64).min(1.0);
+        let frequency = 1.0 - (self.trades_per_hour() / max_trades_per_hour).min(1.0);
+
+        0.4 * pattern.success_rate() + 0.2 * hold + 0.2 * diversity + 0.2 * frequency
+    }
+
+    pub fn should_copy_trade(
+        &self,
+        transaction: &Transaction,
+        min_transaction_amount: u64,
+        min_activity_score: f64,
+        max_trades_per_hour: f64,
+    ) -> bool {
+        let pattern = match self.analyze_pattern() {
+            Some(pattern) => pattern,
+            None => return false,
+        };
+
         // Minimum requirements for copy trading
-        pattern.success_rate() > 0.7 && // 70% success rate
-        pattern.total_trades > 10 && // Minimum trade history
-        transaction.amount_in >= self.min_transaction_amount
+        pattern.success_rate() > MIN_COPY_SUCCESS_RATE &&
+        pattern.total_trades > MIN_COPY_TRADES &&
+        transaction.amount_in >= min_transaction_amount &&
+        self.trades_per_hour() <= max_trades_per_hour &&
+        self.activity_score(max_trades_per_hour) >= min_activity_score
     }
 }
 
@@ -109,7 +227,54 @@ pub struct Transaction {
     pub success: bool,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Disk form of `Transaction`; `Instant` can't be serialized so only block_time is kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedTransaction {
+    pub signature: String,
+    pub trade_type: TradeType,
+    pub input_token: String,
+    pub output_token: String,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub block_time: i64,
+    pub success: bool,
+}
+
+impl From<&Transaction> for PersistedTransaction {
+    fn from(tx: &Transaction) -> Self {
+        Self {
+            signature: tx.signature.clone(),
+            trade_type: tx.trade_type.clone(),
+            input_token: tx.input_token.to_string(),
+            output_token: tx.output_token.to_string(),
+            amount_in: tx.amount_in,
+            amount_out: tx.amount_out,
+            block_time: tx.block_time,
+            success: tx.success,
+        }
+    }
+}
+
+impl PersistedTransaction {
+    // Rebuild the monotonic timestamp relative to `now` (unix seconds)
+    fn into_transaction(self, now: i64) -> Result<Transaction> {
+        let timestamp = instant_at(self.block_time, now);
+
+        Ok(Transaction {
+            signature: self.signature,
+            trade_type: self.trade_type,
+            input_token: Pubkey::from_str(&self.input_token)?,
+            output_token: Pubkey::from_str(&self.output_token)?,
+            amount_in: self.amount_in,
+            amount_out: self.amount_out,
+            timestamp,
+            block_time: self.block_time,
+            success: self.success,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TradeType {
     SwapExactTokensForTokens,
     SwapExactSOLForTokens,
@@ -132,19 +297,46 @@ pub struct TradeMetrics {
     pub avg_profit: f64,
     pub total_volume: u64,
     pub trade_count: u32,
+    pub realized_pnl: f64,   // SOL booked by closed round trips
+    pub unrealized_pnl: f64, // SOL on open lots at the wallet's last traded price
     pub last_updated: Instant,
 }
 
-#[derive(Debug)]
-pub struct TradePattern {
+impl TradeMetrics {
+    // Same bar should_copy_trade applies to a wallet's trading pattern
+    pub fn meets_copy_threshold(&self) -> bool {
+        self.success_rate > MIN_COPY_SUCCESS_RATE && self.trade_count > MIN_COPY_TRADES
+    }
+}
+
+// A sell matched FIFO against earlier buys of the same token; PnL in SOL
+#[derive(Debug, Clone)]
+pub struct RoundTrip {
     pub token: Pubkey,
-    pub avg_entry: f64,
-    pub avg_exit: f64,
-    pub success_rate: f64,
-    pub avg_profit: f64,
-    pub trade_count: u32,
+    pub amount: u64, // Tokens matched
+    pub cost: u64,   // Lamports paid for the matched tokens
+    pub proceeds: u64,
+    pub pnl: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TradePattern {
+    pub success_count: u32,
+    pub total_trades: u32,
+    pub avg_amount: u64,
+    pub tokens_traded: HashMap<Pubkey, u32>,
+    pub preferred_dex: Option<Pubkey>,
     pub avg_hold_time: Duration,
-    pub last_trade: Instant,
+    pub last_trade: Option<Instant>,
+}
+
+impl TradePattern {
+    pub fn success_rate(&self) -> f64 {
+        if self.total_trades == 0 {
+            return 0.0;
+        }
+        self.success_count as f64 / self.total_trades as f64
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -159,75 +351,157 @@ pub struct TradeInfo {
 }
 
 impl WalletTracker {
-    pub fn new(rpc_url: &str, min_amount: u64) -> Self {
-        Self {
-            rpc_client: RpcClient::new_with_commitment(
-                rpc_url.to_string(),
+    pub fn new(config: &TradingConfig, min_amount: u64) -> Result<Self> {
+        let (updates_tx, updates_rx) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        Ok(Self {
+            rpc_client: Arc::new(RpcMode::from_config(config).client(
+                config.rpc_endpoint(),
                 CommitmentConfig::confirmed(),
-            ),
+            )?),
+            ws_url: config.ws_endpoint(),
+            amm_program_id: resolve_amm_program_id(config)?,
             tracked_wallets: HashMap::new(),
             min_transaction_amount: min_amount,
+            min_activity_score: config.min_activity_score,
+            max_trades_per_hour: config.max_trades_per_hour,
             update_interval: Duration::from_secs(1),
-        }
+            updates_tx,
+            updates_rx,
+            shutdown: CancellationToken::new(),
+            subscriptions: HashMap::new(),
+        })
     }
 
-    pub async fn track_wallet(&mut self, wallet: Pubkey) -> Result<()> {
-        let config = solana_client::rpc_config::RpcTransactionConfig {
-            encoding: Some(UiTransactionEncoding::Json),
-            commitment: Some(CommitmentConfig::confirmed()),
-            max_supported_transaction_version: Some(0),
-        };
+    // Ends every wallet subscription
+    pub fn stop(&self) {
+        self.shutdown.cancel();
+    }
 
-        self.rpc_client.subscribe_transaction(
-            config,
-            Some(vec![
-                RpcFilterType::DataSize(165),
-                RpcFilterType::Memcmp(Memcmp {
-                    offset: 32,
-                    bytes: MemcmpEncodedBytes::Base58(wallet.to_string()),
-                    encoding: None,
-                }),
-            ]),
-            |tx| {
-                if let Some(trade) = self.parse_transaction(&tx) {
-                    if trade.amount_in >= self.min_transaction_amount {
-                        self.handle_trade(wallet, trade).await?;
+    // Copyable trades recorded after `since`, as buy/sell signals weighted by the wallet's success rate
+    pub fn signals_since(&self, since: Instant) -> Vec<Signal> {
+        let mut signals = Vec::new();
+        for state in self.tracked_wallets.values() {
+            let confidence = match state.analyze_pattern() {
+                Some(pattern) => pattern.success_rate(),
+                None => continue,
+            };
+
+            for tx in state.transaction_history.iter().filter(|tx| tx.timestamp > since) {
+                if !state.should_copy_trade(tx, self.min_transaction_amount, self.min_activity_score, self.max_trades_per_hour) {
+                    continue;
+                }
+                match tx.trade_type {
+                    TradeType::SwapExactSOLForTokens => {
+                        signals.push(Signal::BuySignal { token: tx.output_token, confidence });
                     }
+                    TradeType::SwapTokensForExactSOL => {
+                        signals.push(Signal::SellSignal { token: tx.input_token, confidence });
+                    }
+                    _ => {}
                 }
-                Ok(())
-            },
-        ).await?;
+            }
+        }
+        signals
+    }
 
+    pub fn save_state(&self, path: &str) -> Result<()> {
+        let state: HashMap<String, Vec<PersistedTransaction>> = self.tracked_wallets
+            .iter()
+            .map(|(wallet, state)| {
+                let history = state.transaction_history
+                    .iter()
+                    .map(PersistedTransaction::from)
+                    .collect();
+                (wallet.to_string(), history)
+            })
+            .collect();
+
+        fs::write(path, serde_json::to_vec_pretty(&state)?)?;
         Ok(())
     }
 
-    pub async fn monitor_wallet(&mut self, wallet: &Pubkey) -> Result<()> {
-        let subscribe_config = RpcTransactionConfig {
-            commitment: Some(CommitmentConfig::confirmed()),
-            encoding: None,
-            max_supported_transaction_version: Some(0),
-        };
+    // Restores tracked wallets, dropping trades that fell out of the 24h window
+    pub fn load_state(&mut self, path: &str) -> Result<()> {
+        let data = fs::read(path)?;
+        let state: HashMap<String, Vec<PersistedTransaction>> = serde_json::from_slice(&data)?;
 
-        let memcmp = Memcmp {
-            offset: 32,
-            bytes: MemcmpEncodedBytes::Base58(wallet.to_string()),
-            encoding: None,
-        };
+        let now = unix_timestamp();
+        let cutoff = now - HISTORY_WINDOW_SECS;
 
-        self.rpc_client.subscribe_transaction(
-            subscribe_config,
-            Some(vec![RpcFilterType::Memcmp(memcmp)]),
-            |transaction| {
-                if let Some(trade) = self.parse_transaction(transaction) {
-                    self.process_trade(wallet, trade)?;
-                }
-                Ok(())
-            },
-        ).await?;
+        for (wallet, history) in state {
+            let wallet = Pubkey::from_str(&wallet)
+                .map_err(|e| anyhow!("Invalid wallet {} in {}: {}", wallet, path, e))?;
+
+            let mut wallet_state = WalletState::new();
+            for persisted in history.into_iter().filter(|tx| tx.block_time >= cutoff) {
+                wallet_state.add_transaction(persisted.into_transaction(now)?);
+            }
+
+            self.tracked_wallets.insert(wallet, wallet_state);
+        }
 
         Ok(())
     }
 
+    // Starts a background subscription; its trades are picked up by process_updates
+    pub async fn track_wallet(&mut self, wallet: Pubkey) -> Result<()> {
+        self.tracked_wallets.entry(wallet).or_insert_with(WalletState::new);
+        if self.subscriptions.contains_key(&wallet) {
+            return Ok(());
+        }
+        let shutdown = self.shutdown.child_token();
+        self.subscriptions.insert(wallet, shutdown.clone());
+
+        let amm_program_id = self.amm_program_id;
+        let subscription = subscribe_wallet(
+            self.ws_url.clone(),
+            self.rpc_client.clone(),
+            wallet,
+            move |wallet, tx| decode_wallet_trade(&amm_program_id, wallet, tx).map(|trade| (*wallet, trade)),
+            self.updates_tx.clone(),
+            shutdown,
+        );
+
+        tokio::spawn(async move {
+            if let Err(e) = subscription.await {
+                warn!(wallet = %wallet, error = %e, "Subscription ended");
+            }
+        });
+
+        Ok(())
+    }
+
+    // Ends the wallet's subscription and drops its history
+    pub fn untrack_wallet(&mut self, wallet: &Pubkey) -> bool {
+        if let Some(subscription) = self.subscriptions.remove(wallet) {
+            subscription.cancel();
+        }
+        self.tracked_wallets.remove(wallet).is_some()
+    }
+
+    pub fn wallet_state(&self, wallet: &Pubkey) -> Option<&WalletState> {
+        self.tracked_wallets.get(wallet)
+    }
+
+    pub async fn monitor_wallet(&mut self, wallet: &Pubkey) -> Result<()> {
+        self.track_wallet(*wallet).await
+    }
+
+    // Records every trade the subscriptions have delivered so far
+    pub async fn process_updates(&mut self) -> Result<()> {
+        while let Ok((wallet, trade)) = self.updates_rx.try_recv() {
+            // Already queued when the wallet was untracked
+            if !self.tracked_wallets.contains_key(&wallet) {
+                continue;
+            }
+            // Failed swaps move nothing but still count toward the wallet's success rate
+            if !trade.success || trade.amount_in >= self.min_transaction_amount {
+                self.process_trade(&wallet, trade).await?;
+            }
+        }
+        Ok(())
+    }
+
     async fn handle_trade(&mut self, wallet: Pubkey, trade: Transaction) -> Result<()> {
         let state = self.tracked_wallets.entry(wallet)
             .or_insert_with(WalletState::new);
@@ -266,79 +540,130 @@ impl WalletTracker {
     pub async fn analyze_wallet(&self, wallet: &Pubkey) -> Result<TradeMetrics> {
         let state = self.tracked_wallets.get(wallet)
             .ok_or_else(|| anyhow!("Wallet not tracked"))?;
-            
+        self.trade_metrics(state)
+    }
+
+    // Every tracked wallet's recorded swaps, for replaying through a backtest
+    pub fn all_transactions(&self) -> Vec<Transaction> {
+        self.tracked_wallets
+            .values()
+            .flat_map(|state| state.transaction_history.iter().cloned())
+            .collect()
+    }
+
+    // Leaderboard of tracked wallets by success rate, then volume
+    pub fn analyze_all(&self) -> Vec<(Pubkey, TradeMetrics)> {
+        let mut ranked: Vec<(Pubkey, TradeMetrics)> = self.tracked_wallets
+            .iter()
+            .filter_map(|(wallet, state)| Some((*wallet, self.trade_metrics(state).ok()?)))
+            .filter(|(_, metrics)| metrics.trade_count >= MIN_RANKED_TRADES)
+            .collect();
+
+        ranked.sort_by(|(_, a), (_, b)| {
+            b.success_rate
+                .total_cmp(&a.success_rate)
+                .then(b.total_volume.cmp(&a.total_volume))
+        });
+        ranked
+    }
+
+    fn trade_metrics(&self, state: &WalletState) -> Result<TradeMetrics> {
         let trades = &state.transaction_history;
-        let successful_trades = trades.iter()
-            .filter(|tx| self.is_profitable_trade(tx))
+        let round_trips = match_round_trips(trades);
+        let profitable = round_trips.iter()
+            .filter(|trip| self.is_profitable_trade(trip))
             .count();
-            
+        let success_rate = if round_trips.is_empty() {
+            0.0
+        } else {
+            profitable as f64 / round_trips.len() as f64
+        };
+
         Ok(TradeMetrics {
-            success_rate: successful_trades as f64 / trades.len() as f64,
-            avg_profit: self.calculate_avg_profit(trades)?,
+            success_rate,
+            avg_profit: self.calculate_avg_profit(&round_trips)?,
             total_volume: state.total_volume_24h,
             trade_count: trades.len() as u32,
+            realized_pnl: round_trips.iter().map(|trip| trip.pnl).sum(),
+            unrealized_pnl: unrealized_pnl(trades),
             last_updated: Instant::now(),
         })
     }
 
-    fn is_profitable_trade(&self, tx: &Transaction) -> bool {
-        // Implement profit calculation logic
-        true // Placeholder
+    fn is_profitable_trade(&self, round_trip: &RoundTrip) -> bool {
+        round_trip.pnl > 0.0
     }
 
-    fn calculate_avg_profit(&self, trades: &[Transaction]) -> Result<f64> {
-        // Implement average profit calculation
-        Ok(0.0) // Placeholder
+    // Mean realized PnL in SOL per round trip
+    fn calculate_avg_profit(&self, round_trips: &[RoundTrip]) -> Result<f64> {
+        if round_trips.is_empty() {
+            return Ok(0.0);
+        }
+        Ok(round_trips.iter().map(|trip| trip.pnl).sum::<f64>() / round_trips.len() as f64)
     }
 
-    fn parse_transaction(&self, tx: &SolanaTransaction) -> Option<Transaction> {
-        // Transaction parsing logic here
-        None
+    fn parse_transaction(&self, wallet: &Pubkey, tx: &EncodedConfirmedTransactionWithStatusMeta) -> Option<Transaction> {
+        decode_wallet_trade(&self.amm_program_id, wallet, tx)
     }
+}
 
-    pub async fn start_monitoring(&mut self, target_wallets: Vec<Pubkey>) -> Result<()> {
-        for wallet in target_wallets {
-            self.track_wallet(wallet).await?;
-        }
-        
-        loop {
-            for (wallet, state) in self.tracked_wallets.iter_mut() {
-                if state.last_update.elapsed() > self.update_interval {
-                    self.update_wallet_state(wallet).await?;
-                }
-                
-                if let Some(pattern) = self.analyze_trading_pattern(wallet)? {
-                    if self.should_copy_trade(&pattern) {
-                        self.execute_copy_trade(&pattern).await?;
-                    }
-                }
-            }
-            tokio::time::sleep(Duration::from_secs(1)).await;
-        }
+// Copy targets and the size weight applied to each one's swaps. Shared with
+// the menu, so targets can change while copying runs.
+#[derive(Debug, Default)]
+pub struct CopyTargets(RwLock<HashMap<Pubkey, f64>>);
+
+impl CopyTargets {
+    pub fn new(weights: HashMap<Pubkey, f64>) -> Self {
+        Self(RwLock::new(weights))
     }
-    
-    async fn update_wallet_state(&mut self, wallet: &Pubkey) -> Result<()> {
-        let transactions = self.fetch_recent_transactions(wallet).await?;
-        let state = self.tracked_wallets.get_mut(wallet)
-            .ok_or_else(|| anyhow!("Wallet not found"))?;
-            
-        for tx in transactions {
-            if let Some(trade_info) = self.parse_transaction(&tx)? {
-                state.process_trade(trade_info);
-            }
-        }
-        
-        state.last_update = Instant::now();
-        Ok(())
+
+    // True if `wallet` wasn't a target yet; an existing one just takes the new weight
+    pub fn set(&self, wallet: Pubkey, weight: f64) -> bool {
+        self.0.write().insert(wallet, weight).is_none()
+    }
+
+    pub fn remove(&self, wallet: &Pubkey) -> bool {
+        self.0.write().remove(wallet).is_some()
+    }
+
+    pub fn weight(&self, wallet: &Pubkey) -> Option<f64> {
+        self.0.read().get(wallet).copied()
+    }
+
+    pub fn wallets(&self) -> Vec<Pubkey> {
+        self.0.read().keys().copied().collect()
     }
 }
 
-#[derive(Debug)]
 pub struct FastCopyTrader {
-    rpc_client: RpcClient,
-    target_wallet: Pubkey,
+    rpc_client: Arc<RpcClient>,
+    ws_url: String,
+    shutdown: CancellationToken, // Child of the engine's, so an emergency stop ends copying
+    target_wallets: CopyTargets,
+    subscriptions: DashMap<Pubkey, CancellationToken>, // Target -> ends its subscription
+    swaps_tx: Mutex<Option<mpsc::Sender<(Pubkey, SwapInfo)>>>, // Set while start_copying runs
     amm_program_id: Pubkey,
     our_wallet: Keypair,
+    recent_copies: DashMap<(Pubkey, Pubkey), Instant>, // (pool, token_in) -> last copy
+    slippage_bps: u16,
+    size_mode: SizeMode,
+    max_position_size: f64, // Whole quote tokens
+    min_liquidity: u64,
+    min_pool_age_secs: u64,
+    blacklist: HashSet<Pubkey>, // Mints we never copy into or out of
+    buy_cooldown: Duration,     // Minimum gap between two copied buys of one token
+    last_buy: DashMap<Pubkey, Instant>, // Token -> when we last copied a buy of it
+    mirror_exits: bool,         // Copied sells dump our whole holding when the target exits fully
+    mirror_partial_exits: bool, // ...and the same share of it when they sell part
+    engine: Arc<TradingEngine>, // Shared guards: sell check
+}
+
+// How much of the quote token to spend when copying a target's buy
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SizeMode {
+    Fixed(f64),                 // Always this many whole quote tokens
+    Proportional(f64),          // Fraction of the target's amount_in
+    MatchPercentOfBalance(f64), // Fraction of our own quote token balance
 }
 
 #[derive(Debug)]
@@ -348,127 +673,1323 @@ struct SwapInfo {
     min_amount_out: u64,
     token_in: Pubkey,
     token_out: Pubkey,
+    target_sold_fraction: Option<f64>, // Share of the target's token_in holding this swap sold
 }
 
 impl FastCopyTrader {
-    pub fn new(target_wallet: Pubkey, our_wallet: Keypair) -> Self {
-        Self {
-            rpc_client: RpcClient::new_with_commitment(
-                "https://api.mainnet-beta.solana.com".to_string(),
+    pub fn new(
+        config: &TradingConfig,
+        target_wallets: HashMap<Pubkey, f64>,
+        our_wallet: Keypair,
+        engine: Arc<TradingEngine>,
+    ) -> Result<Self> {
+        let blacklist = config.token_blacklist
+            .iter()
+            .map(|mint| Pubkey::from_str(mint))
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        Ok(Self {
+            rpc_client: Arc::new(RpcClient::new_with_commitment(
+                config.rpc_endpoint(),
                 CommitmentConfig::processed()
-            ),
-            target_wallet,
-            amm_program_id: "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"
-                .parse()
-                .unwrap(),
+            )),
+            ws_url: config.ws_endpoint(),
+            shutdown: engine.shutdown_token().child_token(),
+            target_wallets: CopyTargets::new(target_wallets),
+            subscriptions: DashMap::new(),
+            swaps_tx: Mutex::new(None),
+            amm_program_id: resolve_amm_program_id(config)?,
             our_wallet,
+            recent_copies: DashMap::new(),
+            slippage_bps: config.max_slippage_bps(),
+            size_mode: SizeMode::Fixed(config.fixed_amount),
+            max_position_size: config.max_position_size,
+            min_liquidity: config.min_liquidity as u64,
+            min_pool_age_secs: config.min_pool_age_secs,
+            blacklist,
+            buy_cooldown: Duration::from_secs(config.buy_cooldown_secs),
+            last_buy: DashMap::new(),
+            mirror_exits: config.mirror_exits,
+            mirror_partial_exits: config.mirror_partial_exits,
+            engine,
+        })
+    }
+
+    // Adds `wallet`, subscribing to it straight away if copying is running,
+    // or updates its weight if it's already a target
+    pub fn add_target(&self, wallet: Pubkey, weight: f64) {
+        if !self.target_wallets.set(wallet, weight) {
+            return;
+        }
+        let sender = self.swaps_tx.lock().clone();
+        if let Some(sender) = sender {
+            self.subscribe(wallet, sender);
         }
     }
 
-    pub async fn start_copying(&self) -> Result<()> {
-        let (tx_sender, _) = broadcast::channel(100);
-        
-        let config = RpcTransactionConfig {
-            encoding: None,
-            commitment: Some(CommitmentConfig::processed()),
-            max_supported_transaction_version: Some(0),
+    pub fn remove_target(&self, wallet: &Pubkey) -> bool {
+        if let Some((_, subscription)) = self.subscriptions.remove(wallet) {
+            subscription.cancel();
+        }
+        self.target_wallets.remove(wallet)
+    }
+
+    pub fn set_size_mode(&mut self, size_mode: SizeMode) {
+        self.size_mode = size_mode;
+    }
+
+    pub fn set_mirror_exits(&mut self, mirror_exits: bool, mirror_partial_exits: bool) {
+        self.mirror_exits = mirror_exits;
+        self.mirror_partial_exits = mirror_partial_exits;
+    }
+
+    // Our quote mint base units for a copied buy, capped at max_position_size
+    async fn copy_amount_in(&self, target_amount_in: u64) -> Result<u64> {
+        let quote_mint = self.engine.quote_mint();
+        let decimals = self.engine.mint_decimals(&quote_mint).await?;
+        let balance = match self.size_mode {
+            SizeMode::MatchPercentOfBalance(_) if quote_mint == native_mint::ID => {
+                self.rpc_client.get_balance(&self.our_wallet.pubkey())?
+            }
+            SizeMode::MatchPercentOfBalance(_) => self.our_token_balance(&quote_mint)?,
+            _ => 0,
         };
 
-        let filters = vec![
-            RpcFilterType::DataSize(165),
-            RpcFilterType::Memcmp(Memcmp {
-                offset: 32,
-                bytes: MemcmpEncodedBytes::Base58(self.target_wallet.to_string()),
-                encoding: None,
-            }),
-        ];
+        Ok(capped_copy_size(self.size_mode, target_amount_in, balance, decimals, self.max_position_size))
+    }
+
+    pub fn stop(&self) {
+        self.shutdown.cancel();
+    }
 
-        self.rpc_client.subscribe_transaction(
-            config,
-            Some(filters),
-            move |tx| {
-                if let Some(swap_info) = self.parse_raydium_swap(tx) {
-                    tokio::spawn(self.execute_copy_trade(swap_info));
+    // Copies target swaps as they arrive until stop() is called. Targets added
+    // meanwhile are subscribed to as they come; removed ones are dropped.
+    pub async fn start_copying(&self) -> Result<()> {
+        let (sender, mut swaps) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        *self.swaps_tx.lock() = Some(sender.clone());
+
+        for wallet in self.target_wallets.wallets() {
+            self.subscribe(wallet, sender.clone());
+        }
+        drop(sender);
+
+        loop {
+            let (source, swap_info) = tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                received = swaps.recv() => match received {
+                    Some(received) => received,
+                    None => break,
+                },
+            };
+            if let Some(swap_info) = self.prepare_copy(&source, swap_info) {
+                if let Err(e) = self.execute_copy_trade(swap_info).await {
+                    warn!(source = %source, error = %e, "Copy trade failed");
                 }
-                Ok(())
-            },
-        ).await?;
+            }
+        }
 
+        self.swaps_tx.lock().take();
         Ok(())
     }
 
-    async fn execute_copy_trade(&self, swap_info: SwapInfo) -> Result<()> {
-        let ix = amm_instruction::swap(
-            &self.amm_program_id,
-            &swap_info.pool_id,
-            swap_info.amount_in,
-            swap_info.min_amount_out,
-        )?;
+    // Streams `wallet`'s copyable swaps into `sender` until it is removed or copying stops
+    fn subscribe(&self, wallet: Pubkey, sender: mpsc::Sender<(Pubkey, SwapInfo)>) {
+        let shutdown = self.shutdown.child_token();
+        if let Some(previous) = self.subscriptions.insert(wallet, shutdown.clone()) {
+            previous.cancel();
+        }
 
-        let blockhash = self.rpc_client.get_latest_blockhash()?;
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&self.our_wallet.pubkey()),
-            &[&self.our_wallet],
-            blockhash,
+        let amm_program_id = self.amm_program_id;
+        let subscription = subscribe_wallet(
+            self.ws_url.clone(),
+            self.rpc_client.clone(),
+            wallet,
+            move |wallet, tx| decode_copyable_swap(&amm_program_id, wallet, tx).map(|swap| (*wallet, swap)),
+            sender,
+            shutdown,
         );
 
-        self.rpc_client.send_transaction_with_config(
-            &tx,
-            RpcTransactionConfig {
-                skip_preflight: true,
-                preflight_commitment: Some(CommitmentConfig::processed()),
-                encoding: None,
-                max_retries: Some(0),
-                ..Default::default()
-            },
-        )?;
+        tokio::spawn(async move {
+            if let Err(e) = subscription.await {
+                warn!(wallet = %wallet, error = %e, "Subscription ended");
+            }
+        });
+    }
+
+    // Applies the source wallet's weight and drops swaps another target
+    // already triggered within COPY_DEDUPE_WINDOW
+    fn prepare_copy(&self, source: &Pubkey, mut swap_info: SwapInfo) -> Option<SwapInfo> {
+        let weight = self.target_wallets.weight(source)?;
+
+        if !claim_copy(&self.recent_copies, (swap_info.pool_id, swap_info.token_in), Instant::now()) {
+            return None;
+        }
+
+        swap_info.amount_in = (swap_info.amount_in as f64 * weight) as u64;
+        Some(swap_info)
+    }
 
+    // Prices our own amount_in against current reserves; the target's min_out
+    // reflects their size and the pool as they saw it, so it isn't reused
+    fn compute_min_out(&self, pool: &PoolInfo, token_in: &Pubkey, amount_in: u64, slippage_bps: u16) -> Result<u64> {
+        let input_is_base = swaps_base_in(pool, token_in)?;
+        let expected_out = RaydiumDex::calculate_amount_out(pool, amount_in, input_is_base);
+        let slippage_bps = slippage_bps.min(10_000) as u128;
+
+        Ok((expected_out as u128 * (10_000 - slippage_bps) / 10_000) as u64)
+    }
+
+    // SlippageError when our size would move the pool further than max_slippage
+    fn check_price_impact(&self, pool: &PoolInfo, swap_info: &SwapInfo) -> Result<()> {
+        let input_is_base = swaps_base_in(pool, &swap_info.token_in)?;
+        let impact = RaydiumDex::calculate_price_impact(pool, swap_info.amount_in, input_is_base)?;
+        let max_slippage = self.slippage_bps as f64 / 10_000.0;
+        if impact > max_slippage {
+            return Err(BotError::SlippageError(format!(
+                "copying {} into pool {} would move the price {:.2}%, max is {:.2}%",
+                swap_info.amount_in,
+                swap_info.pool_id,
+                impact * 100.0,
+                max_slippage * 100.0
+            )).into());
+        }
         Ok(())
     }
 
-    async fn copy_swap(&self, swap_info: SwapInfo) -> Result<()> {
-        let swap_ix = amm_instruction::swap(
-            &self.amm_program_id,
-            &swap_info.pool_id,
+    // Our own swap through the same pool the target used
+    fn swap_ix(&self, pool: &PoolInfo, swap_info: &SwapInfo, min_amount_out: u64) -> Result<Instruction> {
+        let keys = PoolKeys::fetch(&self.rpc_client, &self.amm_program_id, &swap_info.pool_id)?;
+        build_raydium_swap_ix(
+            &keys,
+            &self.our_wallet.pubkey(),
             swap_info.amount_in,
-            swap_info.min_amount_out,
+            min_amount_out,
+            swaps_base_in(pool, &swap_info.token_in)?,
+        )
+    }
+
+    // Raw amount of `mint` in our associated token account; no account holds nothing
+    fn our_token_balance(&self, mint: &Pubkey) -> Result<u64> {
+        let token_account = get_associated_token_address(&self.our_wallet.pubkey(), mint);
+        match self.rpc_client.get_account_with_commitment(&token_account, CommitmentConfig::processed())?.value {
+            Some(account) => Ok(spl_token::state::Account::unpack(&account.data)?.amount),
+            None => Ok(0),
+        }
+    }
+
+    // Our side of a target's sell under mirror_exits: everything we hold when
+    // they sold everything, the same share of it on a partial exit if
+    // mirror_partial_exits is set. None means there's nothing to copy.
+    fn mirrored_exit_amount(&self, swap_info: &SwapInfo) -> Result<Option<u64>> {
+        let Some(fraction) = swap_info.target_sold_fraction else {
+            info!(token = %swap_info.token_in, "Skipping copy sell: target's holding unknown");
+            return Ok(None);
+        };
+        if fraction < 1.0 && !self.mirror_partial_exits {
+            info!(
+                token = %swap_info.token_in,
+                sold_pct = fraction * 100.0,
+                "Skipping copy sell: target only partially exited"
+            );
+            return Ok(None);
+        }
+
+        let held = self.our_token_balance(&swap_info.token_in)?;
+        Ok(mirrored_share(held, fraction, self.mirror_partial_exits))
+    }
+
+    // Time since our last buy of `token` while that is still inside the cooldown
+    fn in_buy_cooldown(&self, token: &Pubkey, now: Instant) -> Option<Duration> {
+        let since = now.duration_since(*self.last_buy.get(token)?);
+        (since < self.buy_cooldown).then_some(since)
+    }
+
+    // Why a swap shouldn't be copied, if it shouldn't. Liquidity and age only
+    // gate buys; selling out of a thin or young pool is still allowed.
+    fn skip_reason(&self, pool: &PoolInfo, swap_info: &SwapInfo, now: u64) -> Option<String> {
+        for mint in [&swap_info.token_in, &swap_info.token_out] {
+            if self.blacklist.contains(mint) {
+                return Some(format!("{} is blacklisted", mint));
+            }
+        }
+
+        if swap_info.token_in != self.engine.quote_mint() {
+            return None;
+        }
+
+        if !RaydiumDex::has_min_liquidity(pool, self.min_liquidity) {
+            return Some(format!(
+                "pool liquidity {} below minimum {}",
+                pool.liquidity, self.min_liquidity
+            ));
+        }
+
+        let age = RaydiumDex::pool_age_secs(pool, now);
+        if age < self.min_pool_age_secs {
+            return Some(format!(
+                "pool is {}s old, minimum is {}s",
+                age, self.min_pool_age_secs
+            ));
+        }
+
+        None
+    }
+
+    async fn execute_copy_trade(&self, mut swap_info: SwapInfo) -> Result<()> {
+        let pool = PoolInfo::fetch(&self.rpc_client, &swap_info.pool_id)?;
+        if let Some(reason) = self.skip_reason(&pool, &swap_info, unix_timestamp() as u64) {
+            info!(pool = %swap_info.pool_id, reason = %reason, "Skipping copy trade");
+            return Ok(());
+        }
+
+        // Sizes are in the quote token, so only buys are resized
+        let is_buy = swap_info.token_in == self.engine.quote_mint();
+        if is_buy {
+            if let Some(since) = self.in_buy_cooldown(&swap_info.token_out, Instant::now()) {
+                info!(
+                    token = %swap_info.token_out,
+                    since_last_buy_secs = since.as_secs(),
+                    "Skipping copy buy: token is in its buy cooldown"
+                );
+                return Ok(());
+            }
+            self.engine.ensure_sellable(&swap_info.token_out).await?;
+            swap_info.amount_in = self.copy_amount_in(swap_info.amount_in).await?;
+            // Copied buys count toward the engine's daily caps like its own
+            self.engine
+                .within_daily_spend(swap_info.amount_in, self.send_copy(&pool, &swap_info))
+                .await?;
+            self.last_buy.insert(swap_info.token_out, Instant::now());
+            return Ok(());
+        }
+        if self.mirror_exits {
+            match self.mirrored_exit_amount(&swap_info)? {
+                Some(amount) => {
+                    info!(token = %swap_info.token_in, amount, "Mirroring target exit");
+                    swap_info.amount_in = amount;
+                }
+                None => return Ok(()),
+            }
+        }
+        self.send_copy(&pool, &swap_info).await
+    }
+
+    // Our swap sized by `swap_info`, once its price impact is within bounds.
+    // Fast execution: no preflight, resent only if the blockhash expires.
+    async fn send_copy(&self, pool: &PoolInfo, swap_info: &SwapInfo) -> Result<()> {
+        self.check_price_impact(pool, swap_info)?;
+
+        let min_amount_out = self.compute_min_out(
+            pool,
+            &swap_info.token_in,
+            swap_info.amount_in,
+            self.slippage_bps,
         )?;
 
-        let blockhash = self.rpc_client.get_latest_blockhash()?;
-        
-        let tx = Transaction::new_signed_with_payer(
-            &[swap_ix],
-            Some(&self.our_wallet.pubkey()),
-            &[&self.our_wallet],
-            blockhash,
+        let ix = self.swap_ix(pool, swap_info, min_amount_out)?;
+        self.engine
+            .send_signed_with_fresh_blockhash(&[ix], &self.our_wallet, SubmitPolicy::FAST)
+            .await?;
+        Ok(())
+    }
+
+    async fn copy_swap(&self, swap_info: SwapInfo) -> Result<()> {
+        let pool = PoolInfo::fetch(&self.rpc_client, &swap_info.pool_id)?;
+        self.send_copy(&pool, &swap_info).await
+    }
+
+}
+
+// True when no target has triggered a copy of `key` (pool, token_in) within
+// COPY_DEDUPE_WINDOW, claiming it in the same step so two targets' swaps
+// racing through here copy once. Claims past the window are pruned first.
+fn claim_copy(recent: &DashMap<(Pubkey, Pubkey), Instant>, key: (Pubkey, Pubkey), now: Instant) -> bool {
+    recent.retain(|_, last_copy| now.duration_since(*last_copy) < COPY_DEDUPE_WINDOW);
+    match recent.entry(key) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(slot) => {
+            slot.insert(now);
+            true
+        }
+    }
+}
+
+// Trade made by `wallet` in a confirmed transaction that invokes the AMM, directly
+// or via CPI. Amounts come from the wallet's balance changes, not instruction data.
+pub(crate) fn decode_wallet_trade(
+    amm_program_id: &Pubkey,
+    wallet: &Pubkey,
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Option<Transaction> {
+    let meta = tx.transaction.meta.as_ref()?;
+    let versioned = tx.transaction.transaction.decode()?;
+    let account_keys = full_account_keys(&versioned.message, meta);
+    if !invokes_program(&versioned.message, meta, &account_keys, amm_program_id) {
+        return None;
+    }
+
+    let block_time = tx.block_time.unwrap_or_else(unix_timestamp);
+    let mut trade = Transaction {
+        signature: versioned.signatures.first()?.to_string(),
+        trade_type: TradeType::SwapExactTokensForTokens,
+        input_token: Pubkey::default(),
+        output_token: Pubkey::default(),
+        amount_in: 0,
+        amount_out: 0,
+        timestamp: instant_at(block_time, unix_timestamp()),
+        block_time,
+        success: meta.err.is_none(),
+    };
+    if !trade.success {
+        return Some(trade);
+    }
+
+    // Prefer SPL mints over SOL on each side, since SOL also moves for rent
+    let deltas = balance_deltas(wallet, &account_keys, meta);
+    let pick = |want_positive: bool| {
+        let side = deltas.iter().filter(|(_, delta)| (**delta > 0) == want_positive);
+        side.clone()
+            .find(|(mint, _)| **mint != native_mint::ID)
+            .or_else(|| side.clone().next())
+            .map(|(mint, delta)| (*mint, delta.unsigned_abs() as u64))
+    };
+    let (input_token, amount_in) = pick(false)?;
+    let (output_token, amount_out) = pick(true)?;
+
+    trade.trade_type = if input_token == native_mint::ID {
+        TradeType::SwapExactSOLForTokens
+    } else if output_token == native_mint::ID {
+        TradeType::SwapTokensForExactSOL
+    } else {
+        TradeType::SwapExactTokensForTokens
+    };
+    trade.input_token = input_token;
+    trade.output_token = output_token;
+    trade.amount_in = amount_in;
+    trade.amount_out = amount_out;
+    Some(trade)
+}
+
+// Buys spend SOL for a token, sells return it. Each sell consumes the oldest
+// open buys of that token; proceeds for tokens with no recorded buy are dropped.
+pub fn match_round_trips(trades: &[Transaction]) -> Vec<RoundTrip> {
+    fifo_match(trades).0
+}
+
+// Open lots marked at the last price the wallet traded each token at, in SOL.
+// Tokens never sold back are valued at their most recent buy.
+pub fn unrealized_pnl(trades: &[Transaction]) -> f64 {
+    let (_, lots) = fifo_match(trades);
+
+    let mut ordered: Vec<&Transaction> = trades.iter().filter(|tx| tx.success).collect();
+    ordered.sort_by_key(|tx| tx.block_time);
+    let mut last_price: HashMap<Pubkey, f64> = HashMap::new(); // Lamports per raw token unit
+    for tx in ordered {
+        if tx.input_token == native_mint::ID && tx.amount_out > 0 {
+            last_price.insert(tx.output_token, tx.amount_in as f64 / tx.amount_out as f64);
+        } else if tx.output_token == native_mint::ID && tx.amount_in > 0 {
+            last_price.insert(tx.input_token, tx.amount_out as f64 / tx.amount_in as f64);
+        }
+    }
+
+    lots.iter()
+        .filter_map(|(token, open)| {
+            let price = last_price.get(token)?;
+            let (tokens, cost) = open.iter().fold((0u64, 0u64), |(tokens, cost), lot| (tokens + lot.0, cost + lot.1));
+            Some((tokens as f64 * price - cost as f64) / LAMPORTS_PER_SOL as f64)
+        })
+        .sum()
+}
+
+// Lots still open per token, as (tokens, lamports paid)
+type OpenLots = HashMap<Pubkey, VecDeque<(u64, u64)>>;
+
+// Round trips plus the lots still open per token
+fn fifo_match(trades: &[Transaction]) -> (Vec<RoundTrip>, OpenLots) {
+    let mut ordered: Vec<&Transaction> = trades.iter().filter(|tx| tx.success).collect();
+    ordered.sort_by_key(|tx| tx.block_time);
+
+    let mut lots: HashMap<Pubkey, VecDeque<(u64, u64)>> = HashMap::new(); // token -> (tokens, lamports paid)
+    let mut round_trips = Vec::new();
+
+    for tx in ordered {
+        if tx.input_token == native_mint::ID && tx.output_token != native_mint::ID {
+            if tx.amount_out > 0 {
+                lots.entry(tx.output_token).or_default().push_back((tx.amount_out, tx.amount_in));
+            }
+            continue;
+        }
+        if tx.output_token != native_mint::ID || tx.input_token == native_mint::ID || tx.amount_in == 0 {
+            continue;
+        }
+
+        let Some(open) = lots.get_mut(&tx.input_token) else { continue };
+        let mut remaining = tx.amount_in;
+        let mut matched: u64 = 0;
+        let mut cost: u64 = 0;
+
+        while remaining > 0 {
+            let Some(lot) = open.front_mut() else { break };
+            let take = remaining.min(lot.0);
+            let lot_cost = (lot.1 as u128 * take as u128 / lot.0 as u128) as u64;
+
+            lot.0 -= take;
+            lot.1 -= lot_cost;
+            if lot.0 == 0 {
+                open.pop_front();
+            }
+
+            remaining -= take;
+            matched += take;
+            cost += lot_cost;
+        }
+
+        if matched == 0 {
+            continue;
+        }
+
+        let proceeds = (tx.amount_out as u128 * matched as u128 / tx.amount_in as u128) as u64;
+        round_trips.push(RoundTrip {
+            token: tx.input_token,
+            amount: matched,
+            cost,
+            proceeds,
+            pnl: (proceeds as f64 - cost as f64) / LAMPORTS_PER_SOL as f64,
+        });
+    }
+
+    (round_trips, lots)
+}
+
+// Static keys followed by those loaded from lookup tables, matching instruction indices
+pub(crate) fn full_account_keys(message: &VersionedMessage, meta: &UiTransactionStatusMeta) -> Vec<Pubkey> {
+    let mut keys = message.static_account_keys().to_vec();
+    if let OptionSerializer::Some(UiLoadedAddresses { writable, readonly }) = &meta.loaded_addresses {
+        keys.extend(
+            writable.iter()
+                .chain(readonly.iter())
+                .filter_map(|key| Pubkey::from_str(key).ok()),
         );
+    }
+    keys
+}
 
-        // Fast execution with processed commitment
-        self.rpc_client
-            .send_transaction_with_config(
-                &tx,
-                RpcTransactionConfig {
-                    skip_preflight: true,
-                    preflight_commitment: Some(CommitmentConfig::processed()),
-                    encoding: None,
-                    max_retries: Some(0),
-                    ..Default::default()
-                },
-            )?;
+fn invokes_program(
+    message: &VersionedMessage,
+    meta: &UiTransactionStatusMeta,
+    account_keys: &[Pubkey],
+    program_id: &Pubkey,
+) -> bool {
+    let is_program = |index: u8| account_keys.get(index as usize) == Some(program_id);
 
-        Ok(())
+    if message.instructions().iter().any(|ix| is_program(ix.program_id_index)) {
+        return true;
     }
 
-    fn parse_raydium_swap(&self, tx: &Transaction) -> Option<SwapInfo> {
-        tx.message.instructions.iter()
-            .find(|ix| ix.program_id == self.amm_program_id)
-            .map(|ix| SwapInfo {
-                pool_id: ix.accounts[1],
-                amount_in: ix.data[0..8].try_into().ok()?,
-                min_amount_out: ix.data[8..16].try_into().ok()?,
-                token_in: ix.accounts[3],
-                token_out: ix.accounts[4],
-            })
+    let inner: &[UiInnerInstructions] = match &meta.inner_instructions {
+        OptionSerializer::Some(inner) => inner,
+        _ => return false,
+    };
+    let program_id = program_id.to_string();
+    inner.iter().flat_map(|set| set.instructions.iter()).any(|ix| match ix {
+        UiInstruction::Compiled(ix) => is_program(ix.program_id_index),
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(ix)) => ix.program_id == program_id,
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(ix)) => ix.program_id == program_id,
+    })
+}
+
+// Net change per mint in accounts owned by `wallet`; lamports and wSOL both count as native_mint
+fn balance_deltas(
+    wallet: &Pubkey,
+    account_keys: &[Pubkey],
+    meta: &UiTransactionStatusMeta,
+) -> HashMap<Pubkey, i128> {
+    let mut deltas: HashMap<Pubkey, i128> = HashMap::new();
+
+    if let Some(index) = account_keys.iter().position(|key| key == wallet) {
+        let pre = meta.pre_balances.get(index).copied().unwrap_or(0) as i128;
+        let post = meta.post_balances.get(index).copied().unwrap_or(0) as i128;
+        // The fee payer is always index 0; the fee isn't part of the trade
+        let fee = if index == 0 { meta.fee as i128 } else { 0 };
+        *deltas.entry(native_mint::ID).or_default() += post - pre + fee;
+    }
+
+    let wallet = wallet.to_string();
+    let mut apply = |balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>, sign: i128| {
+        let balances = match balances {
+            OptionSerializer::Some(balances) => balances,
+            _ => return,
+        };
+        for balance in balances {
+            if !matches!(&balance.owner, OptionSerializer::Some(owner) if *owner == wallet) {
+                continue;
+            }
+            let (Ok(mint), Ok(amount)) = (
+                Pubkey::from_str(&balance.mint),
+                balance.ui_token_amount.amount.parse::<i128>(),
+            ) else {
+                continue;
+            };
+            *deltas.entry(mint).or_default() += sign * amount;
+        }
+    };
+    apply(&meta.pre_token_balances, -1);
+    apply(&meta.post_token_balances, 1);
+
+    deltas.retain(|_, delta| *delta != 0);
+    deltas
+}
+
+// Successful Raydium swap in a confirmed transaction
+fn decode_copyable_swap(
+    amm_program_id: &Pubkey,
+    wallet: &Pubkey,
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Option<SwapInfo> {
+    let meta = tx.transaction.meta.as_ref()?;
+    if meta.err.is_some() {
+        return None;
+    }
+    let versioned = tx.transaction.transaction.decode()?;
+    let mut swap = decode_raydium_swap(amm_program_id, &versioned.message, meta)?;
+    // Only read for sells, where token_in is the mint the target sold
+    swap.target_sold_fraction = sold_fraction(wallet, &swap.token_in, meta);
+    Some(swap)
+}
+
+// copy_size, but never more than `max_position_size` whole quote tokens
+fn capped_copy_size(size_mode: SizeMode, target_amount_in: u64, balance: u64, decimals: u8, max_position_size: f64) -> u64 {
+    let amount_in = copy_size(size_mode, target_amount_in, balance, decimals);
+    let cap = copy_size(SizeMode::Fixed(max_position_size), 0, 0, decimals);
+    if amount_in > cap {
+        info!(amount_in, cap, "Copy size capped at max_position_size");
+    }
+    amount_in.min(cap)
+}
+
+// Base units of a `decimals` quote mint that `size_mode` spends on a copied buy
+fn copy_size(size_mode: SizeMode, target_amount_in: u64, balance: u64, decimals: u8) -> u64 {
+    match size_mode {
+        SizeMode::Fixed(amount) => ui_to_base_units(amount, decimals),
+        SizeMode::Proportional(fraction) => (target_amount_in as f64 * fraction) as u64,
+        SizeMode::MatchPercentOfBalance(fraction) => (balance as f64 * fraction) as u64,
+    }
+}
+
+// Our matching sell for a target that sold `fraction` of their holding
+fn mirrored_share(held: u64, fraction: f64, mirror_partial_exits: bool) -> Option<u64> {
+    let amount = if fraction >= 1.0 {
+        held
+    } else if mirror_partial_exits {
+        (held as f64 * fraction) as u64
+    } else {
+        0
+    };
+    (amount > 0).then_some(amount)
+}
+
+// Share of `wallet`'s `mint` holding gone after the transaction; 1.0 is a full exit
+fn sold_fraction(wallet: &Pubkey, mint: &Pubkey, meta: &UiTransactionStatusMeta) -> Option<f64> {
+    let pre = owned_token_amount(wallet, mint, &meta.pre_token_balances)?;
+    let post = owned_token_amount(wallet, mint, &meta.post_token_balances).unwrap_or(0);
+    if pre == 0 {
+        return None;
+    }
+    Some(pre.saturating_sub(post) as f64 / pre as f64)
+}
+
+// Total `mint` across token accounts owned by `wallet`, None if the balances weren't reported
+fn owned_token_amount(
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>,
+) -> Option<u64> {
+    let OptionSerializer::Some(balances) = balances else {
+        return None;
+    };
+    let (wallet, mint) = (wallet.to_string(), mint.to_string());
+    Some(balances
+        .iter()
+        .filter(|balance| balance.mint == mint)
+        .filter(|balance| matches!(&balance.owner, OptionSerializer::Some(owner) if *owner == wallet))
+        .filter_map(|balance| balance.ui_token_amount.amount.parse::<u64>().ok())
+        .sum())
+}
+
+// First SwapBaseIn to `amm_program_id` in the message; anything short or malformed is skipped.
+// The mints come from the token balances of the user's source and destination accounts.
+fn decode_raydium_swap(
+    amm_program_id: &Pubkey,
+    message: &VersionedMessage,
+    meta: &UiTransactionStatusMeta,
+) -> Option<SwapInfo> {
+    let account_keys = full_account_keys(message, meta);
+    message.instructions().iter().find_map(|ix| {
+        let program_id = account_keys.get(ix.program_id_index as usize)?;
+        if program_id != amm_program_id
+            || ix.data.len() < RAYDIUM_SWAP_DATA_LEN
+            || ix.data[0] != RAYDIUM_SWAP_BASE_IN
+            || ix.accounts.len() < RAYDIUM_SWAP_MIN_ACCOUNTS
+        {
+            return None;
+        }
+
+        // Instruction accounts are indices into the message's account keys
+        let account = |position: usize| {
+            account_keys.get(*ix.accounts.get(position)? as usize).copied()
+        };
+
+        let source = *ix.accounts.get(ix.accounts.len() - 3)?;
+        let destination = *ix.accounts.get(ix.accounts.len() - 2)?;
+
+        Some(SwapInfo {
+            pool_id: account(1)?,
+            amount_in: u64::from_le_bytes(ix.data[1..9].try_into().ok()?),
+            min_amount_out: u64::from_le_bytes(ix.data[9..17].try_into().ok()?),
+            token_in: token_account_mint(source, meta)?,
+            token_out: token_account_mint(destination, meta)?,
+            target_sold_fraction: None,
+        })
+    })
+}
+
+// Whether a swap spending `token_in` sells the pool's base mint; errors for a mint the pool doesn't hold
+fn swaps_base_in(pool: &PoolInfo, token_in: &Pubkey) -> Result<bool> {
+    if *token_in == pool.base_mint {
+        Ok(true)
+    } else if *token_in == pool.quote_mint {
+        Ok(false)
+    } else {
+        Err(anyhow!(
+            "{} isn't traded by the pool pairing {} with {}",
+            token_in, pool.base_mint, pool.quote_mint
+        ))
+    }
+}
+
+// Mint of the token account at `account_index`, from whichever balance snapshot lists it
+fn token_account_mint(account_index: u8, meta: &UiTransactionStatusMeta) -> Option<Pubkey> {
+    [&meta.pre_token_balances, &meta.post_token_balances]
+        .into_iter()
+        .filter_map(|balances| match balances {
+            OptionSerializer::Some(balances) => Some(balances),
+            _ => None,
+        })
+        .flatten()
+        .find(|balance| balance.account_index == account_index)
+        .and_then(|balance| Pubkey::from_str(&balance.mint).ok())
+}
+
+// Monotonic timestamp for a unix time in the past, relative to `now` (unix seconds)
+fn instant_at(unix_secs: i64, now: i64) -> Instant {
+    let age = Duration::from_secs(now.saturating_sub(unix_secs).max(0) as u64);
+    Instant::now().checked_sub(age).unwrap_or_else(Instant::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        serde_json::json,
+        solana_sdk::{instruction::AccountMeta, message::Message, signature::Signature},
+    };
+
+    fn pool(base_mint: Pubkey, quote_mint: Pubkey) -> PoolInfo {
+        PoolInfo {
+            liquidity: 1_000_000,
+            base_amount: 1_000_000_000,
+            quote_amount: 10_000_000_000,
+            fee_numerator: 25,
+            fee_denominator: 10_000,
+            base_decimals: 6,
+            quote_decimals: 9,
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_mint,
+            quote_mint,
+            lp_mint: Pubkey::new_unique(),
+            need_take_pnl_base: 0,
+            need_take_pnl_quote: 0,
+            pool_open_time: 0,
+        }
+    }
+
+    fn token_balance(account_index: usize, mint: &Pubkey, owner: &Pubkey, amount: u64) -> serde_json::Value {
+        json!({
+            "accountIndex": account_index,
+            "mint": mint.to_string(),
+            "owner": owner.to_string(),
+            "uiTokenAmount": {
+                "uiAmount": null,
+                "decimals": 6,
+                "amount": amount.to_string(),
+                "uiAmountString": "",
+            },
+        })
+    }
+
+    fn status_meta(pre: Vec<serde_json::Value>, post: Vec<serde_json::Value>) -> UiTransactionStatusMeta {
+        serde_json::from_value(json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": [],
+            "preTokenBalances": pre,
+            "postTokenBalances": post,
+        }))
+        .unwrap()
+    }
+
+    struct Swap {
+        amm: Pubkey,
+        owner: Pubkey,
+        message: VersionedMessage,
+        source_index: usize,
+        destination_index: usize,
+    }
+
+    // A 17-account SwapBaseIn by `owner` from `source` to `destination`
+    fn swap_message(amount_in: u64, min_amount_out: u64) -> Swap {
+        let amm = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let (source, destination) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut accounts: Vec<AccountMeta> = (0..14)
+            .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+            .collect();
+        accounts.push(AccountMeta::new(source, false));
+        accounts.push(AccountMeta::new(destination, false));
+        accounts.push(AccountMeta::new_readonly(owner, true));
+
+        let mut data = vec![RAYDIUM_SWAP_BASE_IN];
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        let message = Message::new(&[Instruction { program_id: amm, accounts, data }], Some(&owner));
+        let index = |key: &Pubkey| message.account_keys.iter().position(|k| k == key).unwrap();
+        let (source_index, destination_index) = (index(&source), index(&destination));
+
+        Swap {
+            amm,
+            owner,
+            message: VersionedMessage::Legacy(message),
+            source_index,
+            destination_index,
+        }
+    }
+
+    #[test]
+    fn decodes_buy_direction_from_user_token_accounts() {
+        let token = Pubkey::new_unique();
+        let swap = swap_message(1_000_000, 10);
+        let meta = status_meta(
+            vec![token_balance(swap.source_index, &native_mint::ID, &swap.owner, 5_000_000)],
+            vec![
+                token_balance(swap.source_index, &native_mint::ID, &swap.owner, 4_000_000),
+                token_balance(swap.destination_index, &token, &swap.owner, 900),
+            ],
+        );
+
+        let info = decode_raydium_swap(&swap.amm, &swap.message, &meta).unwrap();
+        assert_eq!(info.token_in, native_mint::ID);
+        assert_eq!(info.token_out, token);
+        assert_eq!(info.amount_in, 1_000_000);
+        assert_eq!(info.min_amount_out, 10);
+
+        // wSOL is the pool's quote, so a buy spends quote
+        assert!(!swaps_base_in(&pool(token, native_mint::ID), &info.token_in).unwrap());
+    }
+
+    #[test]
+    fn decodes_sell_direction_from_user_token_accounts() {
+        let token = Pubkey::new_unique();
+        let swap = swap_message(900, 1);
+        let meta = status_meta(
+            vec![
+                token_balance(swap.source_index, &token, &swap.owner, 900),
+                token_balance(swap.destination_index, &native_mint::ID, &swap.owner, 0),
+            ],
+            vec![
+                token_balance(swap.source_index, &token, &swap.owner, 0),
+                token_balance(swap.destination_index, &native_mint::ID, &swap.owner, 1_000_000),
+            ],
+        );
+
+        let info = decode_raydium_swap(&swap.amm, &swap.message, &meta).unwrap();
+        assert_eq!(info.token_in, token);
+        assert_eq!(info.token_out, native_mint::ID);
+        assert!(swaps_base_in(&pool(token, native_mint::ID), &info.token_in).unwrap());
+    }
+
+    #[test]
+    fn decodes_the_full_amm_v4_account_layout() {
+        let token = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let keys = PoolKeys {
+            amm_program_id: Pubkey::new_unique(),
+            amm_id: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            open_orders: Pubkey::new_unique(),
+            target_orders: Pubkey::new_unique(),
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_mint: token,
+            quote_mint: native_mint::ID,
+            market_program_id: Pubkey::new_unique(),
+            market_id: Pubkey::new_unique(),
+            market_bids: Pubkey::new_unique(),
+            market_asks: Pubkey::new_unique(),
+            market_event_queue: Pubkey::new_unique(),
+            market_base_vault: Pubkey::new_unique(),
+            market_quote_vault: Pubkey::new_unique(),
+            market_authority: Pubkey::new_unique(),
+        };
+        // The 18-account sell our own copies send, target_orders included
+        let ix = build_raydium_swap_ix(&keys, &owner, 900, 1, true).unwrap();
+        assert_eq!(ix.accounts.len(), RAYDIUM_SWAP_MIN_ACCOUNTS + 1);
+        let message = Message::new(&[ix], Some(&owner));
+        let index = |mint: &Pubkey| {
+            let account = get_associated_token_address(&owner, mint);
+            message.account_keys.iter().position(|key| *key == account).unwrap()
+        };
+        let meta = status_meta(
+            vec![token_balance(index(&token), &token, &owner, 900)],
+            vec![token_balance(index(&native_mint::ID), &native_mint::ID, &owner, 1_000_000)],
+        );
+
+        let info = decode_raydium_swap(&keys.amm_program_id, &VersionedMessage::Legacy(message), &meta).unwrap();
+        assert_eq!(info.pool_id, keys.amm_id);
+        assert_eq!(info.token_in, token);
+        assert_eq!(info.token_out, native_mint::ID);
+        assert_eq!((info.amount_in, info.min_amount_out), (900, 1));
+        assert!(swaps_base_in(&pool(token, native_mint::ID), &info.token_in).unwrap());
+    }
+
+    #[test]
+    fn sold_fraction_tracks_the_sold_mint() {
+        let (wallet, token) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let full = status_meta(
+            vec![token_balance(3, &token, &wallet, 900)],
+            vec![token_balance(3, &token, &wallet, 0)],
+        );
+        assert_eq!(sold_fraction(&wallet, &token, &full), Some(1.0));
+
+        let partial = status_meta(
+            vec![token_balance(3, &token, &wallet, 1_000)],
+            vec![token_balance(3, &token, &wallet, 250)],
+        );
+        assert_eq!(sold_fraction(&wallet, &token, &partial), Some(0.75));
+
+        // Holdings of another mint say nothing about this one
+        assert_eq!(sold_fraction(&wallet, &native_mint::ID, &partial), None);
+    }
+
+    #[test]
+    fn mirrors_full_exits() {
+        assert_eq!(mirrored_share(5_000, 1.0, false), Some(5_000));
+        assert_eq!(mirrored_share(5_000, 1.0, true), Some(5_000));
+        assert_eq!(mirrored_share(0, 1.0, false), None);
+    }
+
+    #[test]
+    fn mirrors_partial_exits_only_when_enabled() {
+        assert_eq!(mirrored_share(5_000, 0.4, true), Some(2_000));
+        assert_eq!(mirrored_share(5_000, 0.4, false), None);
+    }
+
+    #[test]
+    fn sizes_copies_in_usdc_base_units() {
+        // USDC has 6 decimals
+        assert_eq!(copy_size(SizeMode::Fixed(25.0), 0, 0, 6), 25_000_000);
+        assert_eq!(copy_size(SizeMode::Proportional(0.5), 10_000_000, 0, 6), 5_000_000);
+        assert_eq!(copy_size(SizeMode::MatchPercentOfBalance(0.1), 0, 300_000_000, 6), 30_000_000);
+        // The same fixed size with a SOL quote is in lamports
+        assert_eq!(copy_size(SizeMode::Fixed(0.5), 0, 0, 9), 500_000_000);
+    }
+
+    #[test]
+    fn whale_buys_are_sized_by_each_mode_and_capped() {
+        // A whale spends 500 SOL; we hold 20 SOL and cap positions at 5 SOL
+        let whale_amount_in = 500 * LAMPORTS_PER_SOL;
+        let balance = 20 * LAMPORTS_PER_SOL;
+        let size = |mode| capped_copy_size(mode, whale_amount_in, balance, 9, 5.0);
+
+        assert_eq!(size(SizeMode::Fixed(0.5)), LAMPORTS_PER_SOL / 2);
+        assert_eq!(size(SizeMode::Proportional(0.001)), LAMPORTS_PER_SOL / 2);
+        assert_eq!(size(SizeMode::MatchPercentOfBalance(0.1)), 2 * LAMPORTS_PER_SOL);
+
+        // Anything past max_position_size is cut back to it
+        assert_eq!(size(SizeMode::Fixed(10.0)), 5 * LAMPORTS_PER_SOL);
+        assert_eq!(size(SizeMode::Proportional(0.5)), 5 * LAMPORTS_PER_SOL);
+        assert_eq!(size(SizeMode::MatchPercentOfBalance(1.0)), 5 * LAMPORTS_PER_SOL);
+    }
+
+    #[test]
+    fn rejects_swap_through_pool_without_the_mint() {
+        let pool = pool(Pubkey::new_unique(), native_mint::ID);
+        assert!(swaps_base_in(&pool, &Pubkey::new_unique()).is_err());
+    }
+
+    fn copy_trader(config: TradingConfig) -> FastCopyTrader {
+        let engine = Arc::new(crate::trading::tests::replay_engine(config.clone(), &[]));
+        FastCopyTrader::new(&config, HashMap::new(), Keypair::new(), engine).unwrap()
+    }
+
+    fn copied_swap(token_in: Pubkey, token_out: Pubkey) -> SwapInfo {
+        SwapInfo {
+            pool_id: Pubkey::new_unique(),
+            amount_in: 1_000_000,
+            min_amount_out: 0,
+            token_in,
+            token_out,
+            target_sold_fraction: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn buys_are_gated_on_blacklist_liquidity_and_pool_age() {
+        let (token, banned) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let trader = copy_trader(TradingConfig {
+            min_liquidity: 1_000.0,
+            min_pool_age_secs: 300,
+            token_blacklist: vec![banned.to_string()],
+            ..TradingConfig::default()
+        });
+        let now = 1_700_000_000;
+        let buy = copied_swap(native_mint::ID, token);
+        let mut pool = pool(token, native_mint::ID);
+        pool.liquidity = 1_001;
+        pool.pool_open_time = now - 300;
+
+        assert_eq!(trader.skip_reason(&pool, &buy, now), None);
+
+        // Liquidity has to clear the minimum, not just meet it
+        pool.liquidity = 1_000;
+        let reason = trader.skip_reason(&pool, &buy, now).unwrap();
+        assert_eq!(reason, "pool liquidity 1000 below minimum 1000");
+        pool.liquidity = 1_001;
+
+        pool.pool_open_time = now - 299;
+        let reason = trader.skip_reason(&pool, &buy, now).unwrap();
+        assert_eq!(reason, "pool is 299s old, minimum is 300s");
+        // A pool that hasn't opened yet counts as brand new
+        pool.pool_open_time = now + 60;
+        let reason = trader.skip_reason(&pool, &buy, now).unwrap();
+        assert_eq!(reason, "pool is 0s old, minimum is 300s");
+        pool.pool_open_time = now - 300;
+
+        let reason = trader.skip_reason(&pool, &copied_swap(native_mint::ID, banned), now).unwrap();
+        assert_eq!(reason, format!("{} is blacklisted", banned));
+    }
+
+    #[tokio::test]
+    async fn sells_skip_the_liquidity_and_age_gates_but_not_the_blacklist() {
+        let (token, banned) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let trader = copy_trader(TradingConfig {
+            min_liquidity: 1_000.0,
+            min_pool_age_secs: 300,
+            token_blacklist: vec![banned.to_string()],
+            ..TradingConfig::default()
+        });
+        let now = 1_700_000_000;
+        let mut pool = pool(token, native_mint::ID);
+        pool.liquidity = 10;
+        pool.pool_open_time = now;
+
+        assert_eq!(trader.skip_reason(&pool, &copied_swap(token, native_mint::ID), now), None);
+        let reason = trader.skip_reason(&pool, &copied_swap(banned, native_mint::ID), now).unwrap();
+        assert_eq!(reason, format!("{} is blacklisted", banned));
+    }
+
+    #[test]
+    fn copy_targets_add_reweight_and_remove() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let targets = CopyTargets::new(HashMap::from([(first, 1.0)]));
+
+        assert!(targets.set(second, 0.5));
+        assert!(!targets.set(first, 2.0));
+        assert_eq!(targets.weight(&first), Some(2.0));
+        assert_eq!(targets.weight(&second), Some(0.5));
+
+        assert!(targets.remove(&second));
+        assert!(!targets.remove(&second));
+        assert_eq!(targets.weight(&second), None);
+        assert_eq!(targets.wallets(), vec![first]);
+    }
+
+    #[test]
+    fn same_swap_from_two_targets_is_copied_once() {
+        let recent = DashMap::new();
+        let (pool, token_in) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let start = Instant::now();
+
+        // Both targets' swaps land at once; only the first is copied
+        let copied: Vec<bool> = std::thread::scope(|scope| {
+            let claims: Vec<_> = (0..2)
+                .map(|_| scope.spawn(|| claim_copy(&recent, (pool, token_in), start)))
+                .collect();
+            claims.into_iter().map(|claim| claim.join().unwrap()).collect()
+        });
+        assert_eq!(copied.iter().filter(|copied| **copied).count(), 1);
+
+        // Another pool isn't held back
+        assert!(claim_copy(&recent, (Pubkey::new_unique(), token_in), start));
+        assert!(!claim_copy(&recent, (pool, token_in), start + Duration::from_secs(1)));
+
+        // Past the window the swap copies again and stale claims are pruned
+        assert!(claim_copy(&recent, (pool, token_in), start + COPY_DEDUPE_WINDOW));
+        assert_eq!(recent.len(), 1);
+    }
+
+    // getTransaction responses for one wallet: a Jupiter-routed buy that
+    // reaches Raydium by CPI, a Raydium sell that failed on slippage, and a
+    // plain SOL transfer
+    const FIXTURE_WALLET: &str = "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U";
+    const BONK_MINT: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn decode_fixture(json: &str) -> Option<Transaction> {
+        let tx: EncodedConfirmedTransactionWithStatusMeta = serde_json::from_str(json).unwrap();
+        let amm = Pubkey::from_str(crate::dex::RAYDIUM_V4_PROGRAM_ID).unwrap();
+        decode_wallet_trade(&amm, &Pubkey::from_str(FIXTURE_WALLET).unwrap(), &tx)
+    }
+
+    #[test]
+    fn decodes_a_captured_routed_buy() {
+        let trade = decode_fixture(include_str!("fixtures/wallet_routed_buy.json")).unwrap();
+
+        assert!(trade.success);
+        assert_eq!(
+            trade.signature,
+            "31Mfuu9kt7yXe9L83yjcw78Ws856VNUiyHR53TtS8hbDeSqv7koU4J7NwycrstToyg4EeHeTVWTzShVPnb24MPuM"
+        );
+        assert_eq!(trade.trade_type, TradeType::SwapExactSOLForTokens);
+        assert_eq!(trade.input_token, native_mint::ID);
+        assert_eq!(trade.output_token, Pubkey::from_str(BONK_MINT).unwrap());
+        // The fee comes off the SOL side without counting as spent
+        assert_eq!(trade.amount_in, 1_000_000_000);
+        assert_eq!(trade.amount_out, 1_234_567_890);
+        assert_eq!(trade.block_time, 1_717_000_000);
+    }
+
+    #[test]
+    fn decodes_a_captured_failed_sell() {
+        let trade = decode_fixture(include_str!("fixtures/wallet_failed_sell.json")).unwrap();
+
+        assert!(!trade.success);
+        assert_eq!(trade.amount_in, 0);
+        assert_eq!(trade.block_time, 1_717_000_040);
+    }
+
+    #[test]
+    fn captured_transfer_is_not_a_trade() {
+        assert!(decode_fixture(include_str!("fixtures/wallet_sol_transfer.json")).is_none());
+    }
+
+    // A successful SOL <-> `token` swap at `block_time`
+    fn swap(token: Pubkey, buy: bool, block_time: i64) -> Transaction {
+        let (input_token, output_token) = if buy { (native_mint::ID, token) } else { (token, native_mint::ID) };
+        Transaction {
+            signature: Signature::new_unique().to_string(),
+            trade_type: if buy { TradeType::SwapExactSOLForTokens } else { TradeType::SwapTokensForExactSOL },
+            input_token,
+            output_token,
+            amount_in: 500_000_000,
+            amount_out: 500_000_000,
+            timestamp: Instant::now(),
+            block_time,
+            success: true,
+        }
+    }
+
+    #[test]
+    fn steady_wallet_outscores_a_washer() {
+        const START: i64 = 1_700_000_000;
+        let defaults = TradingConfig::default();
+
+        // Six tokens, each bought and sold two hours later, a few trades a day
+        let mut steady = WalletState::new();
+        for (index, token) in (0..6).map(|_| Pubkey::new_unique()).enumerate() {
+            let bought_at = START + index as i64 * 8 * 3600;
+            steady.add_transaction(swap(token, true, bought_at));
+            steady.add_transaction(swap(token, false, bought_at + 2 * 3600));
+        }
+
+        // One token flipped every 18 seconds for an hour
+        let mut washer = WalletState::new();
+        let token = Pubkey::new_unique();
+        for index in 0..200 {
+            washer.add_transaction(swap(token, index % 2 == 0, START + index * 18));
+        }
+
+        let steady_score = steady.activity_score(defaults.max_trades_per_hour);
+        let washer_score = washer.activity_score(defaults.max_trades_per_hour);
+        assert!(steady_score > 0.95, "steady wallet scored {}", steady_score);
+        assert!(washer_score < defaults.min_activity_score, "washer scored {}", washer_score);
+
+        let next = swap(Pubkey::new_unique(), true, START + 50 * 3600);
+        let copies = |wallet: &WalletState| {
+            wallet.should_copy_trade(&next, 0, defaults.min_activity_score, defaults.max_trades_per_hour)
+        };
+        assert!(copies(&steady));
+        assert!(!copies(&washer));
+    }
+
+    // A SOL <-> `token` swap at `block_time` moving `lamports` and `tokens`
+    fn priced_swap(token: Pubkey, buy: bool, block_time: i64, lamports: u64, tokens: u64) -> Transaction {
+        let (amount_in, amount_out) = if buy { (lamports, tokens) } else { (tokens, lamports) };
+        Transaction { amount_in, amount_out, ..swap(token, buy, block_time) }
+    }
+
+    #[test]
+    fn partial_exit_consumes_the_oldest_lots_first() {
+        let token = Pubkey::new_unique();
+        // Recorded out of order; matching goes by block time
+        let trades = [
+            priced_swap(token, false, 30, 3 * LAMPORTS_PER_SOL, 1_500), // Sell 1500 for 3 SOL
+            priced_swap(token, true, 10, LAMPORTS_PER_SOL, 1_000),      // 1000 at 1 SOL
+            priced_swap(token, true, 20, 2 * LAMPORTS_PER_SOL, 1_000),  // 1000 at 2 SOL
+            priced_swap(token, false, 40, LAMPORTS_PER_SOL / 2, 500),   // The last 500 for 0.5 SOL
+        ];
+
+        let round_trips = match_round_trips(&trades);
+        assert_eq!(round_trips.len(), 2);
+
+        // All of the first lot and half the second: 1 + 1 SOL cost for 3 SOL
+        let spanning = &round_trips[0];
+        assert_eq!((spanning.amount, spanning.cost, spanning.proceeds), (1_500, 2 * LAMPORTS_PER_SOL, 3 * LAMPORTS_PER_SOL));
+        assert_eq!(spanning.pnl, 1.0);
+
+        // The rest of the second lot, 1 SOL cost for 0.5 SOL
+        let rest = &round_trips[1];
+        assert_eq!((rest.amount, rest.cost, rest.proceeds), (500, LAMPORTS_PER_SOL, LAMPORTS_PER_SOL / 2));
+        assert_eq!(rest.pnl, -0.5);
+
+        assert_eq!(unrealized_pnl(&trades), 0.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn half_closed_position_splits_realized_and_unrealized_pnl() {
+        let wallet = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let mut state = WalletState::new();
+        // Buy 1000 for 1 SOL, then sell half at twice the price
+        state.add_transaction(priced_swap(token, true, 10, LAMPORTS_PER_SOL, 1_000));
+        state.add_transaction(priced_swap(token, false, 20, LAMPORTS_PER_SOL, 500));
+
+        let mut tracker = WalletTracker::new(&TradingConfig::default(), 0).unwrap();
+        tracker.tracked_wallets.insert(wallet, state);
+        let metrics = tracker.trade_metrics(tracker.wallet_state(&wallet).unwrap()).unwrap();
+
+        // 0.5 SOL booked on the half sold; the open half cost 0.5 SOL and is
+        // marked at the sell price, worth 1 SOL
+        assert_eq!(metrics.realized_pnl, 0.5);
+        assert_eq!(metrics.unrealized_pnl, 0.5);
+        assert_eq!(metrics.success_rate, 1.0);
+        assert_eq!(metrics.avg_profit, 0.5);
+    }
+
+    // `token` bought for `cost` lamports and sold an hour later for `proceeds`
+    fn round_trip(state: &mut WalletState, start: i64, cost: u64, proceeds: u64) {
+        let token = Pubkey::new_unique();
+        state.add_transaction(priced_swap(token, true, start, cost, 1_000));
+        state.add_transaction(priced_swap(token, false, start + 3600, proceeds, 1_000));
+    }
+
+    #[test]
+    fn leaderboard_ranks_by_success_rate_then_volume() {
+        const SOL: u64 = LAMPORTS_PER_SOL;
+        let now = unix_timestamp();
+        let wallets: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let (winner, big_mixed, small_mixed, loser, newcomer) =
+            (wallets[0], wallets[1], wallets[2], wallets[3], wallets[4]);
+
+        // (wallet, cost, proceeds), worst first so the order below comes from the sort
+        let trips = [
+            (loser, 2 * SOL, SOL),
+            (loser, 2 * SOL, SOL),
+            (small_mixed, SOL, 2 * SOL),
+            (small_mixed, SOL, SOL / 2),
+            (big_mixed, 10 * SOL, 20 * SOL),
+            (big_mixed, 10 * SOL, 5 * SOL),
+            (winner, SOL, 2 * SOL),
+            (winner, SOL, 3 * SOL),
+            // A perfect record, but one round trip is too few trades to rank
+            (newcomer, SOL, 10 * SOL),
+        ];
+        let mut tracker = WalletTracker::new(&TradingConfig::default(), 0).unwrap();
+        for (wallet, cost, proceeds) in trips {
+            let state = tracker.tracked_wallets.entry(wallet).or_insert_with(WalletState::new);
+            round_trip(state, now - 7200, cost, proceeds);
+        }
+
+        let ranked = tracker.analyze_all();
+        let order: Vec<Pubkey> = ranked.iter().map(|(wallet, _)| *wallet).collect();
+        assert_eq!(order, [winner, big_mixed, small_mixed, loser]);
+
+        let rates: Vec<f64> = ranked.iter().map(|(_, metrics)| metrics.success_rate).collect();
+        assert_eq!(rates, [1.0, 0.5, 0.5, 0.0]);
+        // The tie at 50% goes to the wallet that moved more
+        assert!(ranked[1].1.total_volume > ranked[2].1.total_volume);
+        assert!(ranked.iter().all(|(_, metrics)| metrics.trade_count == 4));
+    }
+
+    #[test]
+    fn tracker_state_round_trips_through_disk() {
+        let now = unix_timestamp();
+        let (steady, flipper) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (token, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let stale = swap(token, true, now - HISTORY_WINDOW_SECS - 60);
+        let trades = [
+            (steady, stale.clone()),
+            (steady, priced_swap(token, true, now - 3 * 3600, LAMPORTS_PER_SOL, 1_000)),
+            (steady, priced_swap(token, false, now - 3600, 2 * LAMPORTS_PER_SOL, 1_000)),
+            (flipper, swap(other, true, now - 600)),
+            (flipper, Transaction { success: false, ..swap(other, false, now - 300) }),
+            (flipper, swap(other, false, now - 60)),
+        ];
+
+        let mut tracker = WalletTracker::new(&TradingConfig::default(), 0).unwrap();
+        for (wallet, trade) in &trades {
+            tracker.tracked_wallets.entry(*wallet).or_insert_with(WalletState::new).add_transaction(trade.clone());
+        }
+        let path = std::env::temp_dir().join(format!("tracker-{}.json", Pubkey::new_unique()));
+        let path = path.to_str().unwrap();
+        tracker.save_state(path).unwrap();
+
+        let mut restored = WalletTracker::new(&TradingConfig::default(), 0).unwrap();
+        restored.load_state(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let fields = |tx: &Transaction| {
+            (
+                tx.signature.clone(),
+                tx.trade_type.clone(),
+                tx.input_token,
+                tx.output_token,
+                tx.amount_in,
+                tx.amount_out,
+                tx.block_time,
+                tx.success,
+            )
+        };
+        assert_eq!(restored.tracked_wallets.len(), 2);
+        for wallet in [steady, flipper] {
+            // Everything but the trade older than 24h comes back, in order
+            let kept: Vec<&Transaction> = trades
+                .iter()
+                .filter(|(owner, trade)| *owner == wallet && trade.signature != stale.signature)
+                .map(|(_, trade)| trade)
+                .collect();
+            let expected: Vec<_> = kept.iter().map(|trade| fields(trade)).collect();
+            let state = restored.wallet_state(&wallet).unwrap();
+            assert_eq!(state.transaction_history.iter().map(fields).collect::<Vec<_>>(), expected);
+            assert_eq!(state.last_transaction.as_ref().map(fields), expected.last().cloned());
+            assert_eq!(state.total_volume_24h, kept.iter().map(|trade| trade.amount_in).sum::<u64>());
+        }
+        // Restored trades keep their age on the monotonic clock
+        let oldest = &restored.wallet_state(&steady).unwrap().transaction_history[0];
+        assert!(oldest.timestamp.elapsed() >= Duration::from_secs(3 * 3600 - 5));
+    }
+}